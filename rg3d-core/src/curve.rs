@@ -0,0 +1,243 @@
+//! Curve is a set of keys with a value at some location and an interpolation mode
+//! between neighbour keys. It is used to describe law of change of some scalar value,
+//! for example size of a particle over its lifetime or some animated property.
+
+use crate::{math::lerpf, visitor::prelude::*};
+use std::cmp::Ordering;
+
+/// Defines how a curve will be interpolated between a key and the next one.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub enum CurveKeyKind {
+    /// Value of the key will be held until the next key.
+    Constant,
+    /// Value will be linearly interpolated to the value of the next key.
+    Linear,
+    /// Value will be interpolated using cubic Hermite spline, tangents are
+    /// slopes (change of value per unit of location) at the key.
+    Cubic {
+        /// Incoming slope.
+        left_tangent: f32,
+        /// Outgoing slope.
+        right_tangent: f32,
+    },
+}
+
+impl Default for CurveKeyKind {
+    fn default() -> Self {
+        Self::Linear
+    }
+}
+
+impl CurveKeyKind {
+    /// Creates new cubic key kind with same incoming and outgoing slope.
+    pub fn new_cubic(tangent: f32) -> Self {
+        Self::Cubic {
+            left_tangent: tangent,
+            right_tangent: tangent,
+        }
+    }
+}
+
+/// A single key of a curve.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Visit)]
+pub struct CurveKey {
+    location: f32,
+    /// Value of the curve at the location of the key.
+    pub value: f32,
+    /// Interpolation mode between this key and the next one.
+    pub kind: CurveKeyKind,
+}
+
+impl CurveKey {
+    /// Creates new curve key.
+    pub fn new(location: f32, value: f32, kind: CurveKeyKind) -> Self {
+        Self {
+            location,
+            value,
+            kind,
+        }
+    }
+
+    /// Returns location of the key.
+    pub fn location(&self) -> f32 {
+        self.location
+    }
+
+    fn interpolate(&self, other: &CurveKey, t: f32) -> f32 {
+        match self.kind {
+            CurveKeyKind::Constant => self.value,
+            CurveKeyKind::Linear => lerpf(self.value, other.value, t),
+            CurveKeyKind::Cubic { right_tangent, .. } => {
+                let span = other.location - self.location;
+                let m0 = right_tangent * span;
+                let m1 = match other.kind {
+                    CurveKeyKind::Cubic { left_tangent, .. } => left_tangent * span,
+                    _ => 0.0,
+                };
+
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                (2.0 * t3 - 3.0 * t2 + 1.0) * self.value
+                    + (t3 - 2.0 * t2 + t) * m0
+                    + (-2.0 * t3 + 3.0 * t2) * other.value
+                    + (t3 - t2) * m1
+            }
+        }
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct Curve {
+    keys: Vec<CurveKey>,
+}
+
+fn sort_keys(keys: &mut [CurveKey]) {
+    keys.sort_by(|a, b| {
+        a.location
+            .partial_cmp(&b.location)
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+impl From<Vec<CurveKey>> for Curve {
+    fn from(mut keys: Vec<CurveKey>) -> Self {
+        sort_keys(&mut keys);
+        Self { keys }
+    }
+}
+
+impl Curve {
+    /// Creates new empty curve.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates new curve that has constant value everywhere.
+    pub fn constant(value: f32) -> Self {
+        Self {
+            keys: vec![CurveKey::new(0.0, value, CurveKeyKind::Constant)],
+        }
+    }
+
+    /// Adds new key to the curve, keys are always kept sorted by their location.
+    pub fn add_key(&mut self, key: CurveKey) {
+        self.keys.push(key);
+        sort_keys(&mut self.keys);
+    }
+
+    /// Returns shared reference to sorted array of keys.
+    pub fn keys(&self) -> &[CurveKey] {
+        &self.keys
+    }
+
+    /// Returns mutable reference to a value and kind of a key at given index.
+    /// Location is not accessible this way, because it could break order of keys.
+    pub fn key_mut(&mut self, index: usize) -> Option<&mut CurveKey> {
+        self.keys.get_mut(index)
+    }
+
+    /// Removes a key at given index.
+    pub fn remove_key(&mut self, index: usize) -> Option<CurveKey> {
+        if index < self.keys.len() {
+            Some(self.keys.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Removes every key from the curve.
+    pub fn clear(&mut self) {
+        self.keys.clear()
+    }
+
+    /// Returns true if curve has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns location of the last key or 0.0 if there is no keys.
+    pub fn max_location(&self) -> f32 {
+        self.keys.last().map_or(0.0, |k| k.location)
+    }
+
+    /// Calculates value of the curve at given location. Curve is clamped at its
+    /// ends, so values out of range of locations of keys will be equal to first or
+    /// last key. Empty curve always returns zero.
+    pub fn fetch(&self, location: f32) -> f32 {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        if location <= first.location {
+            first.value
+        } else if location >= last.location {
+            last.value
+        } else {
+            // Index of first key that lies to the right of the location, it is always
+            // in [1; len - 1] range because of checks above.
+            let right_index = match self
+                .keys
+                .binary_search_by(|k| k.location.partial_cmp(&location).unwrap_or(Ordering::Equal))
+            {
+                Ok(index) => return self.keys[index].value,
+                Err(index) => index,
+            };
+
+            let left = &self.keys[right_index - 1];
+            let right = &self.keys[right_index];
+
+            let span = right.location - left.location;
+            let t = if span > 0.0 {
+                (location - left.location) / span
+            } else {
+                0.0
+            };
+
+            left.interpolate(right, t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::curve::{Curve, CurveKey, CurveKeyKind};
+
+    #[test]
+    fn curve_fetch() {
+        assert_eq!(Curve::new().fetch(0.5), 0.0);
+        assert_eq!(Curve::constant(2.0).fetch(-1.0), 2.0);
+
+        let curve = Curve::from(vec![
+            CurveKey::new(1.0, 2.0, CurveKeyKind::Constant),
+            CurveKey::new(0.0, 0.0, CurveKeyKind::Linear),
+            CurveKey::new(2.0, 4.0, CurveKeyKind::Linear),
+        ]);
+
+        // Clamped at ends.
+        assert_eq!(curve.fetch(-1.0), 0.0);
+        assert_eq!(curve.fetch(3.0), 4.0);
+        // Exact keys.
+        assert_eq!(curve.fetch(1.0), 2.0);
+        // Linear span.
+        assert_eq!(curve.fetch(0.5), 1.0);
+        // Constant span.
+        assert_eq!(curve.fetch(1.5), 2.0);
+    }
+
+    #[test]
+    fn curve_cubic() {
+        let curve = Curve::from(vec![
+            CurveKey::new(0.0, 0.0, CurveKeyKind::new_cubic(1.0)),
+            CurveKey::new(1.0, 1.0, CurveKeyKind::new_cubic(1.0)),
+        ]);
+
+        // Tangents are equal to the slope of a line, so cubic must degrade to linear.
+        for i in 0..10 {
+            let x = i as f32 / 10.0;
+            assert!((curve.fetch(x) - x).abs() < 0.0001);
+        }
+    }
+}
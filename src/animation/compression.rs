@@ -0,0 +1,279 @@
+//! Compact storage of key frames. Every track stores position, rotation and scale keys in
+//! separate channels, so a channel that does not change over time costs single key. Rotations
+//! are quantized to 16-bit integers per component. Key reduction removes keys that can be
+//! restored by interpolation of neighbour keys with given error tolerance, it is used when
+//! animations are loaded from FBX and can also be used offline on any animation.
+
+use crate::core::algebra::{Quaternion, UnitQuaternion, Vector3};
+use std::{cmp::Ordering, fmt::Debug};
+
+/// Value that can be stored in a key channel in packed form.
+pub trait KeyValue: Copy + Debug {
+    /// Unpacked value.
+    type Value: Copy;
+
+    /// Packs value into storage format.
+    fn pack(value: Self::Value) -> Self;
+
+    /// Unpacks value from storage format.
+    fn unpack(&self) -> Self::Value;
+
+    /// Interpolates two unpacked values.
+    fn interpolate(a: &Self::Value, b: &Self::Value, t: f32) -> Self::Value;
+
+    /// Returns magnitude of difference between two unpacked values.
+    fn error(a: &Self::Value, b: &Self::Value) -> f32;
+}
+
+impl KeyValue for Vector3<f32> {
+    type Value = Vector3<f32>;
+
+    fn pack(value: Self::Value) -> Self {
+        value
+    }
+
+    fn unpack(&self) -> Self::Value {
+        *self
+    }
+
+    fn interpolate(a: &Self::Value, b: &Self::Value, t: f32) -> Self::Value {
+        a.lerp(b, t)
+    }
+
+    fn error(a: &Self::Value, b: &Self::Value) -> f32 {
+        (a - b).norm()
+    }
+}
+
+/// Rotation quantized to four 16-bit integers, it takes half of the memory of a quaternion
+/// with precision of about 3e-5 per component.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QuantizedRotation {
+    components: [i16; 4],
+}
+
+impl QuantizedRotation {
+    const SCALE: f32 = std::i16::MAX as f32;
+}
+
+impl KeyValue for QuantizedRotation {
+    type Value = UnitQuaternion<f32>;
+
+    fn pack(value: Self::Value) -> Self {
+        let quantize = |v: f32| (v.max(-1.0).min(1.0) * Self::SCALE).round() as i16;
+        Self {
+            components: [
+                quantize(value.i),
+                quantize(value.j),
+                quantize(value.k),
+                quantize(value.w),
+            ],
+        }
+    }
+
+    fn unpack(&self) -> Self::Value {
+        let [i, j, k, w] = self.components;
+        UnitQuaternion::from_quaternion(Quaternion::new(
+            f32::from(w) / Self::SCALE,
+            f32::from(i) / Self::SCALE,
+            f32::from(j) / Self::SCALE,
+            f32::from(k) / Self::SCALE,
+        ))
+    }
+
+    fn interpolate(a: &Self::Value, b: &Self::Value, t: f32) -> Self::Value {
+        a.nlerp(b, t)
+    }
+
+    fn error(a: &Self::Value, b: &Self::Value) -> f32 {
+        a.angle_to(b)
+    }
+}
+
+/// Sorted set of keys of single component (position, rotation or scale) of a track.
+#[derive(Clone, Debug)]
+pub struct KeyChannel<K: KeyValue> {
+    times: Vec<f32>,
+    keys: Vec<K>,
+}
+
+impl<K: KeyValue> Default for KeyChannel<K> {
+    fn default() -> Self {
+        Self {
+            times: Default::default(),
+            keys: Default::default(),
+        }
+    }
+}
+
+impl<K: KeyValue> KeyChannel<K> {
+    /// Adds new key to the channel, keeping keys sorted by time.
+    pub fn add_key(&mut self, time: f32, value: K::Value) {
+        let index = self.times.iter().take_while(|&&t| t <= time).count();
+        self.times.insert(index, time);
+        self.keys.insert(index, K::pack(value));
+    }
+
+    pub fn clear(&mut self) {
+        self.times.clear();
+        self.keys.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns times of keys of the channel.
+    pub fn times(&self) -> &[f32] {
+        &self.times
+    }
+
+    /// Returns time and unpacked value of a key with given index.
+    pub fn key(&self, index: usize) -> (f32, K::Value) {
+        (self.times[index], self.keys[index].unpack())
+    }
+
+    /// Calculates value of the channel at given time. Returns `None` if channel has no keys.
+    pub fn sample(&self, time: f32) -> Option<K::Value> {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return None,
+        };
+
+        if time <= self.times[0] {
+            return Some(first.unpack());
+        }
+        if time >= self.times[self.times.len() - 1] {
+            return Some(last.unpack());
+        }
+
+        // Index of first key which time is greater or equal than given time.
+        let right = match self
+            .times
+            .binary_search_by(|t| t.partial_cmp(&time).unwrap_or(Ordering::Less))
+        {
+            Ok(index) => return Some(self.keys[index].unpack()),
+            Err(index) => index,
+        };
+        let left = right - 1;
+
+        let span = self.times[right] - self.times[left];
+        let t = if span > 0.0 {
+            (time - self.times[left]) / span
+        } else {
+            0.0
+        };
+
+        Some(K::interpolate(
+            &self.keys[left].unpack(),
+            &self.keys[right].unpack(),
+            t,
+        ))
+    }
+
+    /// Removes every key that can be restored by interpolation of neighbour keys with error
+    /// less than given tolerance. Channel with all keys within tolerance of the first key
+    /// will be reduced to a single key.
+    pub fn reduce(&mut self, tolerance: f32) {
+        if self.keys.len() < 2 {
+            return;
+        }
+
+        let values = self.keys.iter().map(|k| k.unpack()).collect::<Vec<_>>();
+
+        if values.iter().all(|v| K::error(&values[0], v) <= tolerance) {
+            self.times.truncate(1);
+            self.keys.truncate(1);
+            return;
+        }
+
+        let fits = |anchor: usize, next: usize| {
+            let span = self.times[next] - self.times[anchor];
+            (anchor + 1..next).all(|i| {
+                let t = if span > 0.0 {
+                    (self.times[i] - self.times[anchor]) / span
+                } else {
+                    0.0
+                };
+                let interpolated = K::interpolate(&values[anchor], &values[next], t);
+                K::error(&interpolated, &values[i]) <= tolerance
+            })
+        };
+
+        let last = values.len() - 1;
+        let mut kept = vec![0];
+        let mut anchor = 0;
+        for i in 1..last {
+            if !fits(anchor, i + 1) {
+                kept.push(i);
+                anchor = i;
+            }
+        }
+        kept.push(last);
+
+        self.times = kept.iter().map(|&i| self.times[i]).collect();
+        self.keys = kept.iter().map(|&i| self.keys[i]).collect();
+    }
+}
+
+/// Error tolerances for key reduction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KeyReductionOptions {
+    /// Max distance between original and restored position.
+    pub position_tolerance: f32,
+    /// Max angle (in radians) between original and restored rotation.
+    pub rotation_tolerance: f32,
+    /// Max difference between original and restored scale.
+    pub scale_tolerance: f32,
+}
+
+impl Default for KeyReductionOptions {
+    /// Default tolerances are small enough to be visually lossless.
+    fn default() -> Self {
+        Self {
+            position_tolerance: 0.0001,
+            rotation_tolerance: 0.0001,
+            scale_tolerance: 0.0001,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::compression::{KeyChannel, KeyValue, QuantizedRotation},
+        core::algebra::{UnitQuaternion, Vector3},
+    };
+
+    #[test]
+    fn test_key_reduction() {
+        let mut channel = KeyChannel::<Vector3<f32>>::default();
+        for i in 0..=10 {
+            channel.add_key(i as f32, Vector3::new(i as f32, 0.0, 0.0));
+        }
+        channel.add_key(12.0, Vector3::new(0.0, 0.0, 0.0));
+        channel.reduce(0.001);
+        assert_eq!(channel.times(), &[0.0, 10.0, 12.0]);
+        assert!((channel.sample(5.5).unwrap() - Vector3::new(5.5, 0.0, 0.0)).norm() < 1.0e-5);
+        assert_eq!(channel.sample(11.0), Some(Vector3::new(5.0, 0.0, 0.0)));
+
+        let mut constant = KeyChannel::<Vector3<f32>>::default();
+        for i in 0..10 {
+            constant.add_key(i as f32, Vector3::new(1.0, 1.0, 1.0));
+        }
+        constant.reduce(0.001);
+        assert_eq!(constant.len(), 1);
+        assert_eq!(constant.sample(5.0), Some(Vector3::new(1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_rotation_quantization() {
+        let rotation = UnitQuaternion::from_euler_angles(0.3, 1.2, -0.7);
+        let restored = QuantizedRotation::pack(rotation).unpack();
+        assert!(rotation.angle_to(&restored) < 0.001);
+    }
+}
@@ -0,0 +1,252 @@
+//! Scene-level queue of animation events.
+//!
+//! Every animation of a scene puts an event into [`Scene::animation_events`](crate::scene::Scene)
+//! when it passes a signal, so things like footstep sounds or hit frames can be handled in one
+//! place instead of draining events of every animation separately. Animation machines are not
+//! stored in a scene, so their events (state enter/leave) must be forwarded into the queue
+//! explicitly using [`Machine::forward_events`](crate::animation::machine::Machine::forward_events).
+//!
+//! ```no_run
+//! use rg3d::{animation::event::SceneAnimationEvent, scene::Scene};
+//!
+//! fn handle_animation_events(scene: &mut Scene) {
+//!     while let Some(event) = scene.animation_events.pop() {
+//!         match event {
+//!             SceneAnimationEvent::Signal { animation, event } => {
+//!                 // Name and payload are looked up by id of the signal.
+//!                 let animation = scene.animations.get(animation);
+//!                 if let Some(signal) = animation.find_signal(event.signal_id) {
+//!                     if signal.name() == "Footstep" {
+//!                         // Play footstep sound here.
+//!                     }
+//!                 }
+//!             }
+//!             SceneAnimationEvent::Machine { state_name, .. } => {
+//!                 println!("Machine event for state {}", state_name);
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::{
+    animation::{
+        machine::{self, State},
+        Animation, AnimationEvent,
+    },
+    core::pool::Handle,
+    scene::node::Node,
+};
+use std::collections::VecDeque;
+
+/// An event that was produced either by an animation or by an animation machine.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneAnimationEvent {
+    /// An animation has passed a signal.
+    Signal {
+        /// Handle of the animation that has passed the signal.
+        animation: Handle<Animation>,
+        /// Information about the signal.
+        event: AnimationEvent,
+    },
+
+    /// An animation machine has entered or left a state.
+    Machine {
+        /// A node that was passed to `Machine::forward_events`, usually it is root of a
+        /// character model animated by the machine.
+        owner: Handle<Node>,
+        /// Index of a layer of the machine that produced the event.
+        layer: usize,
+        /// Actual machine event.
+        event: machine::Event,
+        /// Name of the state from the event.
+        state_name: String,
+    },
+}
+
+impl SceneAnimationEvent {
+    /// Returns handle of a state of the machine event, `Handle::NONE` for signals.
+    pub fn state(&self) -> Handle<State> {
+        match self {
+            SceneAnimationEvent::Signal { .. } => Handle::NONE,
+            SceneAnimationEvent::Machine { event, .. } => match *event {
+                machine::Event::StateEnter(state)
+                | machine::Event::StateLeave(state)
+                | machine::Event::ActiveStateChanged(state) => state,
+            },
+        }
+    }
+}
+
+/// Limited queue of animation events. When the queue is full, the oldest event is discarded,
+/// so the queue never grows unbounded even if nobody reads it.
+#[derive(Clone, Debug)]
+pub struct AnimationEventQueue {
+    events: VecDeque<SceneAnimationEvent>,
+    limit: usize,
+}
+
+impl Default for AnimationEventQueue {
+    fn default() -> Self {
+        Self::new(2048)
+    }
+}
+
+impl AnimationEventQueue {
+    /// Creates new queue that can hold at most `limit` events.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            events: Default::default(),
+            limit,
+        }
+    }
+
+    /// Sets new limit of the queue, excessive oldest events will be discarded.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.events.len() > self.limit {
+            self.events.pop_front();
+        }
+    }
+
+    /// Returns maximum amount of events in the queue.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Puts new event at the end of the queue.
+    pub fn push(&mut self, event: SceneAnimationEvent) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.events.len() >= self.limit {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Extracts the oldest event from the queue.
+    pub fn pop(&mut self) -> Option<SceneAnimationEvent> {
+        self.events.pop_front()
+    }
+
+    /// Returns iterator over events in the queue, from the oldest to the newest.
+    pub fn iter(&self) -> impl Iterator<Item = &SceneAnimationEvent> {
+        self.events.iter()
+    }
+
+    /// Returns amount of events in the queue.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if there are no events in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Removes every event from the queue.
+    pub fn clear(&mut self) {
+        self.events.clear()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{
+            event::{AnimationEventQueue, SceneAnimationEvent},
+            Animation, AnimationContainer, AnimationSignal, KeyFrame, SignalPayload, Track,
+        },
+        core::{
+            algebra::{UnitQuaternion, Vector3},
+            pool::Handle,
+        },
+    };
+
+    #[test]
+    fn test_signal_events() {
+        let mut track = Track::new();
+        track.set_node(Handle::new(1, 1));
+        for time in [0.0, 1.0].iter() {
+            track.add_key_frame(KeyFrame::new(
+                *time,
+                Vector3::default(),
+                Vector3::new(1.0, 1.0, 1.0),
+                UnitQuaternion::identity(),
+            ));
+        }
+
+        let mut animation = Animation::default();
+        animation.add_track(track);
+        animation.add_signal(
+            AnimationSignal::new(1, 0.5)
+                .with_name("Footstep")
+                .with_payload(SignalPayload::String("Left".to_owned())),
+        );
+
+        let mut container = AnimationContainer::new();
+        let handle = container.add(animation);
+
+        let mut queue = AnimationEventQueue::new(1);
+        container.update_animations_with_events(0.4, &mut queue);
+        assert!(queue.is_empty());
+        container.update_animations_with_events(0.2, &mut queue);
+        match queue.pop() {
+            Some(SceneAnimationEvent::Signal { animation, event }) => {
+                assert_eq!(animation, handle);
+                assert_eq!(event.signal_id, 1);
+                let signal = container
+                    .get(animation)
+                    .find_signal(event.signal_id)
+                    .unwrap();
+                assert_eq!(signal.name(), "Footstep");
+                assert_eq!(signal.payload(), &SignalPayload::String("Left".to_owned()));
+            }
+            _ => unreachable!(),
+        }
+        assert!(queue.is_empty());
+
+        // Per-animation queue still works.
+        assert_eq!(container.get_mut(handle).pop_event().unwrap().signal_id, 1);
+        assert!(container.get_mut(handle).pop_event().is_none());
+
+        // The oldest events are discarded when the queue is full.
+        container.get_mut(handle).set_time_position(0.0);
+        container.update_animations_with_events(0.6, &mut queue);
+        let newest = SceneAnimationEvent::Signal {
+            animation: Handle::NONE,
+            event: AnimationSignal::new(3, 0.0).make_event(),
+        };
+        queue.push(newest.clone());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.pop(), Some(newest));
+    }
+
+    #[test]
+    fn test_disabled_signal() {
+        let mut track = Track::new();
+        track.set_node(Handle::new(1, 1));
+        track.add_key_frame(KeyFrame::new(
+            1.0,
+            Vector3::default(),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::identity(),
+        ));
+
+        let mut signal = AnimationSignal::new(1, 0.5);
+        signal.set_enabled(false);
+
+        let mut animation = Animation::default();
+        animation.add_track(track);
+        animation.add_signal(signal);
+
+        let mut container = AnimationContainer::new();
+        let handle = container.add(animation);
+
+        let mut queue = AnimationEventQueue::new(4);
+        container.update_animations_with_events(0.6, &mut queue);
+        assert!(queue.is_empty());
+        assert!(container.get_mut(handle).pop_event().is_none());
+    }
+}
@@ -0,0 +1,551 @@
+//! Inverse kinematics (IK) solvers. Solvers modify local rotations of nodes of a skeleton
+//! to make end of a chain of bones reach a target, so they must be applied after animation
+//! pose was applied to a graph, see
+//! [`AnimationPose::apply_with_ik`](crate::animation::AnimationPose::apply_with_ik).
+//!
+//! There are three kinds of solvers:
+//!
+//! - [`TwoBoneIk`] - analytical solver for arms and legs with optional pole target which
+//! defines bending direction of a middle joint (elbow or knee).
+//! - [`ChainIk`] - iterative solver (FABRIK or CCD) for chains of any length, like tails or
+//! spines.
+//! - [`LookAt`] - rotates a single node to face a target, like head tracking something.
+//!
+//! All targets are in world coordinates. Typical foot placement is done by casting a ray
+//! down from a foot, and setting intersection point as a target of two-bone solver of a leg.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Unit, UnitQuaternion, Vector3},
+        math::clampf,
+        pool::Handle,
+    },
+    scene::{graph::Graph, node::Node},
+};
+
+/// Analytical solver for chains of two bones (three joints), like arms or legs.
+#[derive(Clone, Debug)]
+pub struct TwoBoneIk {
+    /// First joint of the chain (shoulder, hip).
+    pub root: Handle<Node>,
+    /// Middle joint of the chain (elbow, knee). Must be a descendant of the root.
+    pub middle: Handle<Node>,
+    /// End of the chain (wrist, ankle). Must be a descendant of the middle joint.
+    pub effector: Handle<Node>,
+    /// Position in world coordinates that effector should reach.
+    pub target: Vector3<f32>,
+    /// Position in world coordinates towards which middle joint will bend. If not set,
+    /// current bending direction will be preserved.
+    pub pole: Option<Vector3<f32>>,
+    /// Weight of the solver in [0; 1] range, zero means that animation pose stays intact.
+    pub weight: f32,
+}
+
+impl TwoBoneIk {
+    /// Creates new two-bone solver with full weight and without pole target.
+    pub fn new(
+        root: Handle<Node>,
+        middle: Handle<Node>,
+        effector: Handle<Node>,
+        target: Vector3<f32>,
+    ) -> Self {
+        Self {
+            root,
+            middle,
+            effector,
+            target,
+            pole: None,
+            weight: 1.0,
+        }
+    }
+
+    /// Sets pole target.
+    pub fn with_pole(mut self, pole: Vector3<f32>) -> Self {
+        self.pole = Some(pole);
+        self
+    }
+
+    /// Sets weight of the solver.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Modifies rotations of root and middle joints so effector reaches target.
+    pub fn solve(&self, graph: &mut Graph) {
+        solve_weighted(graph, &[self.root, self.middle], self.weight, |graph| {
+            self.solve_full(graph)
+        });
+    }
+
+    fn solve_full(&self, graph: &mut Graph) {
+        let a = global_position(graph, self.root);
+        let b = global_position(graph, self.middle);
+        let c = global_position(graph, self.effector);
+        let t = self.target;
+
+        let lab = (b - a).norm();
+        let lcb = (c - b).norm();
+        let ac = c - a;
+        if lab <= f32::EPSILON || lcb <= f32::EPSILON || ac.norm() <= f32::EPSILON {
+            return;
+        }
+        let lat = clampf(
+            (t - a).norm(),
+            (lab - lcb).abs() + IK_EPSILON,
+            lab + lcb - IK_EPSILON,
+        );
+
+        let ac_dir = ac.normalize();
+        let ab_dir = (b - a).normalize();
+        let ba_dir = -ab_dir;
+        let bc_dir = (c - b).normalize();
+
+        // Current and desired angles of the triangle formed by the joints.
+        let ac_ab_0 = clampf(ac_dir.dot(&ab_dir), -1.0, 1.0).acos();
+        let ba_bc_0 = clampf(ba_dir.dot(&bc_dir), -1.0, 1.0).acos();
+        let ac_ab_1 = clampf(
+            (lcb * lcb - lab * lab - lat * lat) / (-2.0 * lab * lat),
+            -1.0,
+            1.0,
+        )
+        .acos();
+        let ba_bc_1 = clampf(
+            (lat * lat - lab * lab - lcb * lcb) / (-2.0 * lab * lcb),
+            -1.0,
+            1.0,
+        )
+        .acos();
+
+        // Axis of bending, if chain is straight, try to bend towards pole.
+        let bend_axis = ac_dir
+            .cross(&ab_dir)
+            .try_normalize(f32::EPSILON)
+            .or_else(|| {
+                self.pole
+                    .and_then(|pole| ac_dir.cross(&(pole - a)).try_normalize(f32::EPSILON))
+            })
+            .unwrap_or_else(|| any_perpendicular(&ac_dir));
+        let bend_axis = Unit::new_unchecked(bend_axis);
+
+        rotate_in_world(
+            graph,
+            self.root,
+            UnitQuaternion::from_axis_angle(&bend_axis, ac_ab_1 - ac_ab_0),
+        );
+        rotate_in_world(
+            graph,
+            self.middle,
+            UnitQuaternion::from_axis_angle(&bend_axis, ba_bc_1 - ba_bc_0),
+        );
+
+        // Bending keeps direction from root to effector, so now rotate whole chain to target.
+        if let Some(rotation) = UnitQuaternion::rotation_between(&ac, &(t - a)) {
+            rotate_in_world(graph, self.root, rotation);
+        }
+
+        if let Some(pole) = self.pole {
+            // Twist chain around root-effector axis to make middle joint point to the pole.
+            let c = global_position(graph, self.effector);
+            let b = global_position(graph, self.middle);
+            if let Some(axis) = (c - a).try_normalize(f32::EPSILON) {
+                let project = |v: Vector3<f32>| v - axis.scale(v.dot(&axis));
+                if let Some(rotation) =
+                    UnitQuaternion::rotation_between(&project(b - a), &project(pole - a))
+                {
+                    rotate_in_world(graph, self.root, rotation);
+                }
+            }
+        }
+    }
+}
+
+/// Algorithm of iterative chain solver.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChainIkAlgorithm {
+    /// Forward And Backward Reaching Inverse Kinematics. Gives natural looking results
+    /// and converges fast, good for spines and tails.
+    Fabrik,
+    /// Cyclic Coordinate Descent. Tends to curl chains, but is very cheap.
+    Ccd,
+}
+
+impl Default for ChainIkAlgorithm {
+    fn default() -> Self {
+        Self::Fabrik
+    }
+}
+
+/// Iterative solver for chains of any length.
+#[derive(Clone, Debug)]
+pub struct ChainIk {
+    /// Joints of the chain starting from the root, last joint is effector. Every joint
+    /// must be a descendant of a previous one.
+    pub joints: Vec<Handle<Node>>,
+    /// Position in world coordinates that effector should reach.
+    pub target: Vector3<f32>,
+    /// Algorithm of the solver.
+    pub algorithm: ChainIkAlgorithm,
+    /// Max amount of iterations.
+    pub iterations: u32,
+    /// Distance between effector and target at which solver stops.
+    pub tolerance: f32,
+    /// Weight of the solver in [0; 1] range, zero means that animation pose stays intact.
+    pub weight: f32,
+}
+
+impl ChainIk {
+    /// Creates new chain solver with default parameters.
+    pub fn new(joints: Vec<Handle<Node>>, target: Vector3<f32>) -> Self {
+        Self {
+            joints,
+            target,
+            algorithm: Default::default(),
+            iterations: 10,
+            tolerance: 0.001,
+            weight: 1.0,
+        }
+    }
+
+    /// Sets algorithm of the solver.
+    pub fn with_algorithm(mut self, algorithm: ChainIkAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets max amount of iterations.
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets distance between effector and target at which solver stops.
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets weight of the solver.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Modifies rotations of joints of the chain so its end reaches target.
+    pub fn solve(&self, graph: &mut Graph) {
+        if self.joints.len() < 2 {
+            return;
+        }
+        let rotated = &self.joints[..self.joints.len() - 1];
+        solve_weighted(graph, rotated, self.weight, |graph| match self.algorithm {
+            ChainIkAlgorithm::Fabrik => self.solve_fabrik(graph),
+            ChainIkAlgorithm::Ccd => self.solve_ccd(graph),
+        });
+    }
+
+    fn effector(&self) -> Handle<Node> {
+        *self.joints.last().unwrap()
+    }
+
+    fn solve_ccd(&self, graph: &mut Graph) {
+        let effector = self.effector();
+        for _ in 0..self.iterations {
+            for &joint in self.joints.iter().rev().skip(1) {
+                let joint_position = global_position(graph, joint);
+                let effector_position = global_position(graph, effector);
+                if let Some(rotation) = UnitQuaternion::rotation_between(
+                    &(effector_position - joint_position),
+                    &(self.target - joint_position),
+                ) {
+                    rotate_in_world(graph, joint, rotation);
+                }
+            }
+            if (global_position(graph, effector) - self.target).norm() <= self.tolerance {
+                break;
+            }
+        }
+    }
+
+    fn solve_fabrik(&self, graph: &mut Graph) {
+        let mut positions = self
+            .joints
+            .iter()
+            .map(|&joint| global_position(graph, joint))
+            .collect::<Vec<_>>();
+        let lengths = positions
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).norm())
+            .collect::<Vec<_>>();
+        let root = positions[0];
+        let last = positions.len() - 1;
+
+        if (self.target - root).norm() >= lengths.iter().sum::<f32>() {
+            // Target is unreachable - stretch chain towards it.
+            for i in 0..last {
+                let dir = (self.target - positions[i])
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_default();
+                positions[i + 1] = positions[i] + dir.scale(lengths[i]);
+            }
+        } else {
+            for _ in 0..self.iterations {
+                if (positions[last] - self.target).norm() <= self.tolerance {
+                    break;
+                }
+
+                // Backward pass - from effector to root.
+                positions[last] = self.target;
+                for i in (0..last).rev() {
+                    let dir = (positions[i] - positions[i + 1])
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or_default();
+                    positions[i] = positions[i + 1] + dir.scale(lengths[i]);
+                }
+
+                // Forward pass - from root to effector.
+                positions[0] = root;
+                for i in 0..last {
+                    let dir = (positions[i + 1] - positions[i])
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or_default();
+                    positions[i + 1] = positions[i] + dir.scale(lengths[i]);
+                }
+            }
+        }
+
+        // Convert positions into rotations of joints.
+        for i in 0..last {
+            let joint_position = global_position(graph, self.joints[i]);
+            let child_position = global_position(graph, self.joints[i + 1]);
+            if let Some(rotation) = UnitQuaternion::rotation_between(
+                &(child_position - joint_position),
+                &(positions[i + 1] - joint_position),
+            ) {
+                rotate_in_world(graph, self.joints[i], rotation);
+            }
+        }
+    }
+}
+
+/// Look-at constraint rotates a node to make its axis point to a target.
+#[derive(Clone, Debug)]
+pub struct LookAt {
+    /// Node to rotate.
+    pub node: Handle<Node>,
+    /// Position in world coordinates to look at.
+    pub target: Vector3<f32>,
+    /// Axis in local coordinates of the node, that should point to the target.
+    pub axis: Vector3<f32>,
+    /// Max angle (in radians) by which node can be rotated from its animated pose.
+    pub max_angle: f32,
+    /// Weight of the solver in [0; 1] range, zero means that animation pose stays intact.
+    pub weight: f32,
+}
+
+impl LookAt {
+    /// Creates new look-at constraint which makes Z axis of a node point to the target.
+    pub fn new(node: Handle<Node>, target: Vector3<f32>) -> Self {
+        Self {
+            node,
+            target,
+            axis: Vector3::z(),
+            max_angle: std::f32::consts::PI,
+            weight: 1.0,
+        }
+    }
+
+    /// Sets axis in local coordinates of the node, that should point to the target.
+    pub fn with_axis(mut self, axis: Vector3<f32>) -> Self {
+        self.axis = axis;
+        self
+    }
+
+    /// Sets max angle (in radians) by which node can be rotated from its animated pose.
+    pub fn with_max_angle(mut self, max_angle: f32) -> Self {
+        self.max_angle = max_angle;
+        self
+    }
+
+    /// Sets weight of the solver.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Rotates node to make its axis point to the target.
+    pub fn solve(&self, graph: &mut Graph) {
+        solve_weighted(graph, &[self.node], self.weight, |graph| {
+            let position = global_position(graph, self.node);
+            let axis = graph.global_rotation(self.node) * self.axis;
+            if let Some(rotation) =
+                UnitQuaternion::rotation_between(&axis, &(self.target - position))
+            {
+                let rotation = match rotation.axis() {
+                    Some(rotation_axis) if rotation.angle() > self.max_angle => {
+                        UnitQuaternion::from_axis_angle(&rotation_axis, self.max_angle)
+                    }
+                    _ => rotation,
+                };
+                rotate_in_world(graph, self.node, rotation);
+            }
+        });
+    }
+}
+
+/// Generic IK solver.
+#[derive(Clone, Debug)]
+pub enum IkSolver {
+    /// See [`TwoBoneIk`] docs.
+    TwoBone(TwoBoneIk),
+    /// See [`ChainIk`] docs.
+    Chain(ChainIk),
+    /// See [`LookAt`] docs.
+    LookAt(LookAt),
+}
+
+impl IkSolver {
+    /// Modifies rotations of nodes of the graph according to the solver.
+    pub fn solve(&self, graph: &mut Graph) {
+        match self {
+            IkSolver::TwoBone(solver) => solver.solve(graph),
+            IkSolver::Chain(solver) => solver.solve(graph),
+            IkSolver::LookAt(solver) => solver.solve(graph),
+        }
+    }
+}
+
+impl From<TwoBoneIk> for IkSolver {
+    fn from(solver: TwoBoneIk) -> Self {
+        IkSolver::TwoBone(solver)
+    }
+}
+
+impl From<ChainIk> for IkSolver {
+    fn from(solver: ChainIk) -> Self {
+        IkSolver::Chain(solver)
+    }
+}
+
+impl From<LookAt> for IkSolver {
+    fn from(solver: LookAt) -> Self {
+        IkSolver::LookAt(solver)
+    }
+}
+
+const IK_EPSILON: f32 = 0.0001;
+
+// Global transforms of the graph are calculated once per frame and could be outdated after
+// animation pose was applied, so they are calculated from local transforms.
+fn global_transform(graph: &Graph, node: Handle<Node>) -> Matrix4<f32> {
+    let node_ref = &graph[node];
+    let local_transform = node_ref.local_transform().matrix();
+    if node_ref.parent().is_some() {
+        global_transform(graph, node_ref.parent()) * local_transform
+    } else {
+        local_transform
+    }
+}
+
+fn global_position(graph: &Graph, node: Handle<Node>) -> Vector3<f32> {
+    let m = global_transform(graph, node);
+    Vector3::new(m[12], m[13], m[14])
+}
+
+fn rotate_in_world(graph: &mut Graph, node: Handle<Node>, rotation: UnitQuaternion<f32>) {
+    let parent = graph[node].parent();
+    let parent_rotation = if parent.is_some() {
+        graph.global_rotation(parent)
+    } else {
+        UnitQuaternion::identity()
+    };
+    let transform = graph[node].local_transform_mut();
+    let basis = parent_rotation * **transform.pre_rotation();
+    let local_rotation = basis.inverse() * rotation * basis * **transform.rotation();
+    transform.set_rotation(local_rotation);
+}
+
+fn solve_weighted<F>(graph: &mut Graph, nodes: &[Handle<Node>], weight: f32, solver: F)
+where
+    F: FnOnce(&mut Graph),
+{
+    let weight = clampf(weight, 0.0, 1.0);
+    if weight <= 0.0 {
+        return;
+    }
+    let initial_rotations = nodes
+        .iter()
+        .map(|&node| **graph[node].local_transform().rotation())
+        .collect::<Vec<_>>();
+    solver(graph);
+    if weight < 1.0 {
+        for (&node, initial_rotation) in nodes.iter().zip(initial_rotations) {
+            let transform = graph[node].local_transform_mut();
+            let solved_rotation = **transform.rotation();
+            transform.set_rotation(initial_rotation.nlerp(&solved_rotation, weight));
+        }
+    }
+}
+
+fn any_perpendicular(v: &Vector3<f32>) -> Vector3<f32> {
+    let other = if v.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    v.cross(&other).normalize()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::ik::{global_position, ChainIk, ChainIkAlgorithm, TwoBoneIk},
+        core::{algebra::Vector3, pool::Handle},
+        scene::{base::BaseBuilder, graph::Graph, node::Node, transform::TransformBuilder},
+    };
+
+    fn make_chain(graph: &mut Graph, count: usize) -> Vec<Handle<Node>> {
+        let mut joints = Vec::new();
+        let mut parent = Handle::NONE;
+        for i in 0..count {
+            let offset = if i == 0 { 0.0 } else { 1.0 };
+            let joint = BaseBuilder::new()
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vector3::new(0.0, offset, 0.0))
+                        .build(),
+                )
+                .build(graph);
+            if parent.is_some() {
+                graph.link_nodes(joint, parent);
+            }
+            joints.push(joint);
+            parent = joint;
+        }
+        joints
+    }
+
+    #[test]
+    fn test_two_bone_ik() {
+        let mut graph = Graph::new();
+        let joints = make_chain(&mut graph, 3);
+        let target = Vector3::new(1.0, 1.0, 0.0);
+        TwoBoneIk::new(joints[0], joints[1], joints[2], target)
+            .with_pole(Vector3::new(0.0, 0.0, 1.0))
+            .solve(&mut graph);
+        assert!((global_position(&graph, joints[2]) - target).norm() < 0.001);
+    }
+
+    #[test]
+    fn test_chain_ik() {
+        for algorithm in [ChainIkAlgorithm::Fabrik, ChainIkAlgorithm::Ccd].iter() {
+            let mut graph = Graph::new();
+            let joints = make_chain(&mut graph, 5);
+            let target = Vector3::new(2.0, 1.5, 0.5);
+            ChainIk::new(joints.clone(), target)
+                .with_algorithm(*algorithm)
+                .with_iterations(50)
+                .solve(&mut graph);
+            assert!((global_position(&graph, joints[4]) - target).norm() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,592 @@
+//! Blend spaces are pose nodes that blend a set of animations placed at some coordinates
+//! in 1D or 2D space. Weights of the animations are calculated automatically from current
+//! position in the space, which is taken from Weight parameters of the machine. Typical use
+//! case is locomotion: 1D space can blend idle, walk and run animations by speed of a
+//! character, 2D space can blend forward, backward and strafe animations by its velocity.
+//!
+//! Animations of different lengths can be synchronized by phase, in this case every animation
+//! is sampled at the same normalized time, so cycles of animations (foot steps for example)
+//! line up with each other.
+
+use crate::{
+    animation::{
+        machine::{EvaluatePose, Parameter, ParameterContainer, PoseNode},
+        Animation, AnimationContainer, AnimationPose,
+    },
+    core::{
+        algebra::Vector2,
+        math::get_barycentric_coords_2d,
+        pool::{Handle, Pool},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+};
+use std::{
+    cell::{Cell, Ref, RefCell},
+    cmp::Ordering,
+};
+
+fn fetch_parameter(params: &ParameterContainer, id: &str) -> f32 {
+    if let Some(Parameter::Weight(value)) = params.get(id) {
+        *value
+    } else {
+        0.0
+    }
+}
+
+#[derive(Default)]
+struct SampleBlender {
+    sync_phase: bool,
+    phase: Cell<f32>,
+    sample_pose: RefCell<AnimationPose>,
+    output_pose: RefCell<AnimationPose>,
+}
+
+impl SampleBlender {
+    fn blend(
+        &self,
+        samples: &[(Handle<Animation>, f32)],
+        animations: &AnimationContainer,
+        dt: f32,
+    ) -> Ref<AnimationPose> {
+        let mut output_pose = self.output_pose.borrow_mut();
+        output_pose.reset();
+
+        if self.sync_phase {
+            // Duration of a cycle of the space is weighted sum of durations of animations.
+            let mut cycle_duration = 0.0;
+            for &(animation, weight) in samples {
+                let animation = animations.get(animation);
+                let speed = animation.get_speed().abs();
+                if speed > std::f32::EPSILON {
+                    cycle_duration += weight * animation.length() / speed;
+                }
+            }
+            if cycle_duration > std::f32::EPSILON {
+                self.phase
+                    .set((self.phase.get() + dt / cycle_duration).fract());
+            }
+
+            let mut sample_pose = self.sample_pose.borrow_mut();
+            for &(animation, weight) in samples {
+                let animation = animations.get(animation);
+                animation.sample_pose(self.phase.get() * animation.length(), &mut sample_pose);
+                output_pose.blend_with(&sample_pose, weight);
+            }
+        } else {
+            for &(animation, weight) in samples {
+                output_pose.blend_with(animations.get(animation).get_pose(), weight);
+            }
+        }
+
+        drop(output_pose);
+        self.output_pose.borrow()
+    }
+
+    fn visit(&mut self, visitor: &mut Visitor) -> VisitResult {
+        self.sync_phase.visit("SyncPhase", visitor)?;
+        self.phase.visit("Phase", visitor)
+    }
+}
+
+/// Animation placed at some value in 1D blend space.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct BlendSpace1DPoint {
+    pub value: f32,
+    pub animation: Handle<Animation>,
+}
+
+impl BlendSpace1DPoint {
+    pub fn new(value: f32, animation: Handle<Animation>) -> Self {
+        Self { value, animation }
+    }
+}
+
+impl Visit for BlendSpace1DPoint {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.value.visit("Value", visitor)?;
+        self.animation.visit("Animation", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// One-dimensional blend space. Position in the space is taken from a Weight parameter,
+/// pose is a linear blend of two animations which values surround the position. Position
+/// outside of the range of values is clamped.
+#[derive(Default)]
+pub struct BlendSpace1D {
+    points: Vec<BlendSpace1DPoint>,
+    parameter: String,
+    blender: SampleBlender,
+}
+
+impl BlendSpace1D {
+    /// Creates new 1D blend space with given points and name of Weight parameter which
+    /// defines position in the space.
+    pub fn new(parameter: &str, points: Vec<BlendSpace1DPoint>) -> Self {
+        let mut space = Self {
+            points,
+            parameter: parameter.to_owned(),
+            blender: Default::default(),
+        };
+        space.sort_points();
+        space
+    }
+
+    /// Enables or disables phase synchronization of animations.
+    pub fn with_phase_sync(mut self, sync_phase: bool) -> Self {
+        self.set_phase_sync(sync_phase);
+        self
+    }
+
+    /// Enables or disables phase synchronization of animations. When it is enabled, time
+    /// positions of animations are ignored and every animation is sampled at the same
+    /// normalized time of the space.
+    pub fn set_phase_sync(&mut self, sync_phase: bool) {
+        self.blender.sync_phase = sync_phase;
+    }
+
+    pub fn is_phase_synced(&self) -> bool {
+        self.blender.sync_phase
+    }
+
+    pub fn add_point(&mut self, point: BlendSpace1DPoint) {
+        self.points.push(point);
+        self.sort_points();
+    }
+
+    /// Returns points of the space sorted by their values.
+    pub fn points(&self) -> &[BlendSpace1DPoint] {
+        &self.points
+    }
+
+    pub(in crate) fn points_mut(&mut self) -> &mut [BlendSpace1DPoint] {
+        &mut self.points
+    }
+
+    pub fn parameter(&self) -> &str {
+        &self.parameter
+    }
+
+    fn sort_points(&mut self) {
+        self.points
+            .sort_by(|a, b| a.value.partial_cmp(&b.value).unwrap_or(Ordering::Equal));
+    }
+
+    /// Calculates weights of points at given position in the space. Returns pairs of
+    /// index of a point and its weight, sum of weights is always one.
+    pub fn weights(&self, value: f32) -> Vec<(usize, f32)> {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Vec::new(),
+        };
+
+        if value <= first.value {
+            return vec![(0, 1.0)];
+        }
+        if value >= last.value {
+            return vec![(self.points.len() - 1, 1.0)];
+        }
+
+        for (i, pair) in self.points.windows(2).enumerate() {
+            let (left, right) = (&pair[0], &pair[1]);
+            if value >= left.value && value <= right.value {
+                let range = right.value - left.value;
+                let t = if range > std::f32::EPSILON {
+                    (value - left.value) / range
+                } else {
+                    0.0
+                };
+                return vec![(i, 1.0 - t), (i + 1, t)];
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+impl EvaluatePose for BlendSpace1D {
+    fn eval_pose(
+        &self,
+        _nodes: &Pool<PoseNode>,
+        params: &ParameterContainer,
+        animations: &AnimationContainer,
+        dt: f32,
+    ) -> Ref<AnimationPose> {
+        let samples = self
+            .weights(fetch_parameter(params, &self.parameter))
+            .into_iter()
+            .filter(|(_, weight)| *weight > std::f32::EPSILON)
+            .map(|(index, weight)| (self.points[index].animation, weight))
+            .collect::<Vec<_>>();
+        self.blender.blend(&samples, animations, dt)
+    }
+}
+
+impl Visit for BlendSpace1D {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.points.visit("Points", visitor)?;
+        self.parameter.visit("Parameter", visitor)?;
+        self.blender.visit(visitor)?;
+
+        if visitor.is_reading() {
+            self.sort_points();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Animation placed at some position in 2D blend space.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct BlendSpace2DPoint {
+    pub position: Vector2<f32>,
+    pub animation: Handle<Animation>,
+}
+
+impl BlendSpace2DPoint {
+    pub fn new(position: Vector2<f32>, animation: Handle<Animation>) -> Self {
+        Self {
+            position,
+            animation,
+        }
+    }
+}
+
+impl Visit for BlendSpace2DPoint {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.position.visit("Position", visitor)?;
+        self.animation.visit("Animation", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Two-dimensional blend space. Position in the space is taken from two Weight parameters.
+/// Points of the space are triangulated (Delaunay triangulation), pose is a blend of three
+/// animations of a triangle that contains the position with barycentric weights. If the
+/// position is outside of the triangulation, it is projected on the closest edge.
+#[derive(Default)]
+pub struct BlendSpace2D {
+    points: Vec<BlendSpace2DPoint>,
+    triangles: Vec<[usize; 3]>,
+    x_parameter: String,
+    y_parameter: String,
+    blender: SampleBlender,
+}
+
+impl BlendSpace2D {
+    /// Creates new 2D blend space with given points and names of Weight parameters which
+    /// define position in the space.
+    pub fn new(x_parameter: &str, y_parameter: &str, points: Vec<BlendSpace2DPoint>) -> Self {
+        let mut space = Self {
+            points,
+            triangles: Default::default(),
+            x_parameter: x_parameter.to_owned(),
+            y_parameter: y_parameter.to_owned(),
+            blender: Default::default(),
+        };
+        space.triangulate();
+        space
+    }
+
+    /// Enables or disables phase synchronization of animations.
+    pub fn with_phase_sync(mut self, sync_phase: bool) -> Self {
+        self.set_phase_sync(sync_phase);
+        self
+    }
+
+    /// Enables or disables phase synchronization of animations. When it is enabled, time
+    /// positions of animations are ignored and every animation is sampled at the same
+    /// normalized time of the space.
+    pub fn set_phase_sync(&mut self, sync_phase: bool) {
+        self.blender.sync_phase = sync_phase;
+    }
+
+    pub fn is_phase_synced(&self) -> bool {
+        self.blender.sync_phase
+    }
+
+    pub fn add_point(&mut self, point: BlendSpace2DPoint) {
+        self.points.push(point);
+        self.triangulate();
+    }
+
+    pub fn points(&self) -> &[BlendSpace2DPoint] {
+        &self.points
+    }
+
+    pub(in crate) fn points_mut(&mut self) -> &mut [BlendSpace2DPoint] {
+        &mut self.points
+    }
+
+    /// Returns triangles of the space, each triangle is a triple of indices of points.
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    pub fn x_parameter(&self) -> &str {
+        &self.x_parameter
+    }
+
+    pub fn y_parameter(&self) -> &str {
+        &self.y_parameter
+    }
+
+    fn triangulate(&mut self) {
+        let positions = self
+            .points
+            .iter()
+            .map(|point| point.position)
+            .collect::<Vec<_>>();
+        self.triangles = triangulate(&positions);
+    }
+
+    /// Calculates weights of points at given position in the space. Returns pairs of
+    /// index of a point and its weight, sum of weights is always one.
+    pub fn weights(&self, position: Vector2<f32>) -> Vec<(usize, f32)> {
+        match self.points.len() {
+            0 => return Vec::new(),
+            1 => return vec![(0, 1.0)],
+            _ => (),
+        }
+
+        for triangle in self.triangles.iter() {
+            let (u, v, w) = get_barycentric_coords_2d(
+                position,
+                self.points[triangle[0]].position,
+                self.points[triangle[1]].position,
+                self.points[triangle[2]].position,
+            );
+            let eps = -1.0e-5;
+            if u >= eps && v >= eps && w >= eps {
+                let (u, v, w) = (u.max(0.0), v.max(0.0), w.max(0.0));
+                let sum = u + v + w;
+                return vec![
+                    (triangle[0], u / sum),
+                    (triangle[1], v / sum),
+                    (triangle[2], w / sum),
+                ];
+            }
+        }
+
+        // Position is outside of triangulation (or there are no triangles because points
+        // are collinear) - project it on the closest edge.
+        let mut edges = Vec::new();
+        if self.triangles.is_empty() {
+            for i in 0..self.points.len() {
+                for j in (i + 1)..self.points.len() {
+                    edges.push((i, j));
+                }
+            }
+        } else {
+            for triangle in self.triangles.iter() {
+                edges.push((triangle[0], triangle[1]));
+                edges.push((triangle[1], triangle[2]));
+                edges.push((triangle[2], triangle[0]));
+            }
+        }
+
+        let mut closest = None;
+        let mut closest_distance = std::f32::MAX;
+        for (i, j) in edges {
+            let a = self.points[i].position;
+            let b = self.points[j].position;
+            let edge = b - a;
+            let length_sqr = edge.norm_squared();
+            let t = if length_sqr > std::f32::EPSILON {
+                ((position - a).dot(&edge) / length_sqr).max(0.0).min(1.0)
+            } else {
+                0.0
+            };
+            let distance = (a + edge.scale(t) - position).norm_squared();
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest = Some((i, j, t));
+            }
+        }
+
+        match closest {
+            Some((i, j, t)) => vec![(i, 1.0 - t), (j, t)],
+            None => Vec::new(),
+        }
+    }
+}
+
+impl EvaluatePose for BlendSpace2D {
+    fn eval_pose(
+        &self,
+        _nodes: &Pool<PoseNode>,
+        params: &ParameterContainer,
+        animations: &AnimationContainer,
+        dt: f32,
+    ) -> Ref<AnimationPose> {
+        let position = Vector2::new(
+            fetch_parameter(params, &self.x_parameter),
+            fetch_parameter(params, &self.y_parameter),
+        );
+        let samples = self
+            .weights(position)
+            .into_iter()
+            .filter(|(_, weight)| *weight > std::f32::EPSILON)
+            .map(|(index, weight)| (self.points[index].animation, weight))
+            .collect::<Vec<_>>();
+        self.blender.blend(&samples, animations, dt)
+    }
+}
+
+impl Visit for BlendSpace2D {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.points.visit("Points", visitor)?;
+        self.x_parameter.visit("XParameter", visitor)?;
+        self.y_parameter.visit("YParameter", visitor)?;
+        self.blender.visit(visitor)?;
+
+        if visitor.is_reading() {
+            self.triangulate();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+fn is_in_circumcircle(p: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> bool {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() <= std::f32::EPSILON {
+        return false;
+    }
+    let (a2, b2, c2) = (a.norm_squared(), b.norm_squared(), c.norm_squared());
+    let center = Vector2::new(
+        (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+        (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+    );
+    (p - center).norm_squared() < (a - center).norm_squared()
+}
+
+/// Bowyer-Watson Delaunay triangulation of a set of points. Degenerate triangles are
+/// discarded, so collinear points produce no triangles.
+fn triangulate(points: &[Vector2<f32>]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    let mut min = Vector2::new(std::f32::MAX, std::f32::MAX);
+    let mut max = Vector2::new(-std::f32::MAX, -std::f32::MAX);
+    for point in points {
+        min = min.inf(point);
+        max = max.sup(point);
+    }
+    let size = (max - min).max().max(1.0);
+    let center = (min + max).scale(0.5);
+
+    // Super triangle that contains every point.
+    let count = points.len();
+    let mut vertices = points.to_vec();
+    vertices.push(center + Vector2::new(-20.0 * size, -size));
+    vertices.push(center + Vector2::new(0.0, 20.0 * size));
+    vertices.push(center + Vector2::new(20.0 * size, -size));
+
+    let mut triangles = vec![[count, count + 1, count + 2]];
+    for (i, &point) in points.iter().enumerate() {
+        let mut edges = Vec::new();
+        triangles.retain(|triangle| {
+            let bad = is_in_circumcircle(
+                point,
+                vertices[triangle[0]],
+                vertices[triangle[1]],
+                vertices[triangle[2]],
+            );
+            if bad {
+                edges.push((triangle[0], triangle[1]));
+                edges.push((triangle[1], triangle[2]));
+                edges.push((triangle[2], triangle[0]));
+            }
+            !bad
+        });
+
+        // Boundary of the hole is formed by edges that are not shared by bad triangles.
+        for &(a, b) in edges.iter() {
+            let shared = edges
+                .iter()
+                .filter(|&&(c, d)| (a == c && b == d) || (a == d && b == c))
+                .count()
+                > 1;
+            if !shared {
+                triangles.push([a, b, i]);
+            }
+        }
+    }
+
+    triangles.retain(|triangle| {
+        let (a, b, c) = (
+            vertices[triangle[0]],
+            vertices[triangle[1]],
+            vertices[triangle[2]],
+        );
+        let area = (b - a).perp(&(c - a)).abs();
+        triangle.iter().all(|&index| index < count) && area > std::f32::EPSILON
+    });
+
+    triangles
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::machine::blend_space::{
+            BlendSpace1D, BlendSpace1DPoint, BlendSpace2D, BlendSpace2DPoint,
+        },
+        core::{algebra::Vector2, pool::Handle},
+    };
+
+    #[test]
+    fn test_blend_space_1d_weights() {
+        let space = BlendSpace1D::new(
+            "Speed",
+            vec![
+                BlendSpace1DPoint::new(4.0, Handle::NONE),
+                BlendSpace1DPoint::new(0.0, Handle::NONE),
+                BlendSpace1DPoint::new(1.0, Handle::NONE),
+            ],
+        );
+        assert_eq!(space.weights(-1.0), vec![(0, 1.0)]);
+        assert_eq!(space.weights(2.5), vec![(1, 0.5), (2, 0.5)]);
+        assert_eq!(space.weights(5.0), vec![(2, 1.0)]);
+    }
+
+    #[test]
+    fn test_blend_space_2d_weights() {
+        let space = BlendSpace2D::new(
+            "X",
+            "Y",
+            vec![
+                BlendSpace2DPoint::new(Vector2::new(0.0, 0.0), Handle::NONE),
+                BlendSpace2DPoint::new(Vector2::new(1.0, 0.0), Handle::NONE),
+                BlendSpace2DPoint::new(Vector2::new(0.0, 1.0), Handle::NONE),
+                BlendSpace2DPoint::new(Vector2::new(1.0, 1.0), Handle::NONE),
+            ],
+        );
+        assert_eq!(space.triangles().len(), 2);
+
+        let total = |weights: Vec<(usize, f32)>| weights.iter().map(|(_, w)| w).sum::<f32>();
+
+        let weights = space.weights(Vector2::new(1.0, 1.0));
+        assert!(weights
+            .iter()
+            .any(|&(i, w)| i == 3 && (w - 1.0).abs() < 1.0e-5));
+        assert!((total(space.weights(Vector2::new(0.3, 0.6))) - 1.0).abs() < 1.0e-5);
+
+        // Outside of the space - projected on the closest edge.
+        let weights = space.weights(Vector2::new(0.5, -1.0));
+        assert_eq!(weights.len(), 2);
+        assert!(weights.iter().all(|(_, w)| (w - 0.5).abs() < 1.0e-5));
+    }
+}
@@ -0,0 +1,204 @@
+//! Machine definition is a standalone asset which stores animation machine in a file. Handles
+//! of animations make sense only within a scene they were created in, so definition stores
+//! name of every animation used by the machine and resolves them against animations of other
+//! scene when loaded.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use rg3d::{animation::machine::definition::MachineDefinition, scene::Scene};
+//!
+//! async fn load_machine(scene: &Scene) -> rg3d::animation::machine::Machine {
+//!     MachineDefinition::from_file("data/character.absm")
+//!         .await
+//!         .unwrap()
+//!         .resolve_in(&scene.animations)
+//! }
+//! ```
+
+use crate::{
+    animation::{machine::Machine, Animation, AnimationContainer},
+    core::{
+        pool::Handle,
+        visitor::{Visit, VisitError, VisitResult, Visitor},
+    },
+    utils::log::{Log, MessageKind},
+};
+use std::{collections::HashMap, path::Path};
+
+/// Animation machine with names of animations it uses. See module docs.
+#[derive(Default)]
+pub struct MachineDefinition {
+    machine: Machine,
+    animation_names: HashMap<Handle<Animation>, String>,
+}
+
+impl MachineDefinition {
+    /// Creates new definition from given machine, names of animations are taken from given
+    /// container, which must be the container the machine works with. Unset and invalid
+    /// handles are skipped, they will stay as is in the machine.
+    pub fn new(mut machine: Machine, animations: &AnimationContainer) -> Self {
+        let mut animation_names = HashMap::new();
+        machine.for_each_animation_mut(|animation| {
+            if animation.is_none() {
+                return;
+            }
+            if let Some(instance) = animations.try_get(*animation) {
+                animation_names.insert(*animation, instance.name().to_owned());
+            } else {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!("Machine uses invalid animation handle {:?}!", animation),
+                );
+            }
+        });
+        Self {
+            machine,
+            animation_names,
+        }
+    }
+
+    /// Loads definition from a file.
+    pub async fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, VisitError> {
+        let mut definition = Self::default();
+        let mut visitor = Visitor::load_binary(path).await?;
+        definition.visit("MachineDefinition", &mut visitor)?;
+        Ok(definition)
+    }
+
+    /// Saves definition to a file.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::new();
+        self.visit("MachineDefinition", &mut visitor)?;
+        visitor.save_binary(path)
+    }
+
+    /// Returns names of animations used by the machine.
+    pub fn animation_names(&self) -> impl Iterator<Item = &str> {
+        self.animation_names.values().map(|name| name.as_str())
+    }
+
+    /// Replaces every animation handle of the machine with a handle returned by given
+    /// resolver for name of the animation and returns the machine ready to use. Animations
+    /// that failed to resolve (resolver returned `Handle::NONE`) are reported to the log.
+    pub fn resolve<F>(mut self, mut resolver: F) -> Machine
+    where
+        F: FnMut(&str) -> Handle<Animation>,
+    {
+        let animation_names = &self.animation_names;
+        self.machine.for_each_animation_mut(|animation| {
+            if let Some(name) = animation_names.get(animation) {
+                *animation = resolver(name);
+                if animation.is_none() {
+                    Log::writeln(
+                        MessageKind::Error,
+                        format!("Unable to resolve animation {} of a machine!", name),
+                    );
+                }
+            }
+        });
+        self.machine
+    }
+
+    /// Resolves animations of the machine by names of animations in given container. If
+    /// there are multiple animations with the same name (for example there are multiple
+    /// instances of a model), use [`MachineDefinition::resolve`] with your own rules.
+    pub fn resolve_in(self, animations: &AnimationContainer) -> Machine {
+        self.resolve(|name| animations.find_by_name(name))
+    }
+}
+
+impl Visit for MachineDefinition {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.machine.visit("Machine", visitor)?;
+        self.animation_names.visit("AnimationNames", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{
+            machine::{definition::MachineDefinition, Machine, PlayAnimation, PoseNode, State},
+            Animation, AnimationContainer,
+        },
+        core::{futures::executor::block_on, pool::Handle},
+    };
+
+    fn play_animation_handles(machine: &Machine) -> Vec<Handle<Animation>> {
+        machine
+            .nodes()
+            .filter_map(|node| match node {
+                PoseNode::PlayAnimation(play_animation) => Some(play_animation.animation),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_definition_resolve() {
+        let mut animations = AnimationContainer::new();
+        let mut animation = Animation::default();
+        animation.set_name("Run");
+        let old_run = animations.add(animation);
+        let new_run = Handle::new(7, 3);
+
+        let mut machine = Machine::new();
+        let node = machine.add_node(PoseNode::PlayAnimation(PlayAnimation::new(old_run)));
+        machine.add_state(State::new("Run", node));
+        // Unset handles must not break the definition.
+        machine.add_node(PoseNode::PlayAnimation(PlayAnimation::new(Handle::NONE)));
+
+        let definition = MachineDefinition::new(machine, &animations);
+        assert_eq!(
+            definition.animation_names().collect::<Vec<_>>(),
+            vec!["Run"]
+        );
+
+        let machine = definition.resolve(|name| {
+            assert_eq!(name, "Run");
+            new_run
+        });
+        assert_eq!(
+            play_animation_handles(&machine),
+            vec![new_run, Handle::NONE]
+        );
+    }
+
+    #[test]
+    fn test_definition_save_load() {
+        let mut animations = AnimationContainer::new();
+        let mut animation = Animation::default();
+        animation.set_name("Idle");
+        let idle = animations.add(animation);
+
+        let mut machine = Machine::new();
+        let node = machine.add_node(PoseNode::PlayAnimation(PlayAnimation::new(idle)));
+        machine.add_state(State::new("Idle", node));
+
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_test_machine_definition_save_load_{}.absm",
+            std::process::id()
+        ));
+        MachineDefinition::new(machine, &animations)
+            .save(&path)
+            .unwrap();
+        let definition = block_on(MachineDefinition::from_file(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Load into another container, where animation has different handle.
+        let mut other_animations = AnimationContainer::new();
+        other_animations.add(Animation::default());
+        let mut animation = Animation::default();
+        animation.set_name("Idle");
+        let other_idle = other_animations.add(animation);
+        assert_ne!(idle, other_idle);
+
+        let machine = definition.resolve_in(&other_animations);
+        assert_eq!(play_animation_handles(&machine), vec![other_idle]);
+    }
+}
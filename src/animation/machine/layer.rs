@@ -0,0 +1,714 @@
+//! Machine layers allow to animate different parts of a model by separate state graphs. For
+//! example base layer can be used for locomotion of whole body, while second layer with a mask
+//! built from upper body can be used for combat animations. Each layer has its own weight and
+//! blend mode, layers are combined one by one in the order they were added to the machine.
+
+use crate::{
+    animation::{
+        machine::{
+            Event, LimitedEventQueue, Parameter, ParameterContainer, PoseNode, PoseWeight, State,
+            Transition,
+        },
+        Animation, AnimationContainer, AnimationPose,
+    },
+    core::{
+        algebra::UnitQuaternion,
+        pool::{Handle, Pool, PoolIterator},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{graph::Graph, node::Node},
+    utils::log::{Log, MessageKind},
+};
+use std::collections::HashSet;
+
+/// Defines how pose of a layer is combined with the pose produced by previous layers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LayerBlendMode {
+    /// Pose of the layer replaces pose of previous layers, weight of the layer is used to
+    /// interpolate between them.
+    Override,
+
+    /// Pose of the layer is added on top of pose of previous layers, weight of the layer is
+    /// used to scale the offset. Offset is calculated relative to pose of additive reference
+    /// animation of the layer (if any), otherwise pose of the layer is treated as offset.
+    Additive,
+}
+
+impl Default for LayerBlendMode {
+    fn default() -> Self {
+        Self::Override
+    }
+}
+
+impl LayerBlendMode {
+    fn from_id(id: i32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Override),
+            1 => Ok(Self::Additive),
+            _ => Err(format!("Invalid layer blend mode id {}", id)),
+        }
+    }
+
+    fn id(self) -> i32 {
+        match self {
+            Self::Override => 0,
+            Self::Additive => 1,
+        }
+    }
+}
+
+impl Visit for LayerBlendMode {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut id = self.id();
+        id.visit(name, visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Layer mask defines a set of nodes affected by a layer. Empty mask means that layer
+/// affects every node.
+#[derive(Default, Clone, Debug)]
+pub struct LayerMask {
+    nodes: HashSet<Handle<Node>>,
+}
+
+impl LayerMask {
+    /// Creates new mask that includes given node and all its descendants. For example you
+    /// can pass upper body bone of a character to get mask for upper body.
+    pub fn from_hierarchy(graph: &Graph, root: Handle<Node>) -> Self {
+        let mut mask = Self::default();
+        mask.add_hierarchy(graph, root);
+        mask
+    }
+
+    /// Adds given node and all its descendants to the mask.
+    pub fn add_hierarchy(&mut self, graph: &Graph, root: Handle<Node>) {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            self.nodes.insert(node);
+            stack.extend_from_slice(graph[node].children());
+        }
+    }
+
+    /// Removes given node and all its descendants from the mask.
+    pub fn remove_hierarchy(&mut self, graph: &Graph, root: Handle<Node>) {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            self.nodes.remove(&node);
+            stack.extend_from_slice(graph[node].children());
+        }
+    }
+
+    /// Adds single node to the mask.
+    pub fn add(&mut self, node: Handle<Node>) {
+        self.nodes.insert(node);
+    }
+
+    /// Removes single node from the mask.
+    pub fn remove(&mut self, node: Handle<Node>) {
+        self.nodes.remove(&node);
+    }
+
+    /// Returns true if mask has no nodes, such mask does not filter anything.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns true if given node is affected by a layer with the mask.
+    pub fn affects(&self, node: Handle<Node>) -> bool {
+        self.nodes.is_empty() || self.nodes.contains(&node)
+    }
+
+    /// Returns iterator over nodes of the mask.
+    pub fn nodes(&self) -> impl Iterator<Item = &Handle<Node>> {
+        self.nodes.iter()
+    }
+}
+
+impl Visit for LayerMask {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut nodes = self.nodes.iter().copied().collect::<Vec<_>>();
+        nodes.visit("Nodes", visitor)?;
+        if visitor.is_reading() {
+            self.nodes = nodes.into_iter().collect();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Layer of animation machine. Every layer has its own graph of states and transitions
+/// and produces its own pose, which is then combined with poses of other layers. See
+/// module docs for more info.
+pub struct MachineLayer {
+    name: String,
+    weight: PoseWeight,
+    blend_mode: LayerBlendMode,
+    mask: LayerMask,
+    additive_reference: Handle<Animation>,
+    nodes: Pool<PoseNode>,
+    states: Pool<State>,
+    transitions: Pool<Transition>,
+    final_pose: AnimationPose,
+    active_state: Handle<State>,
+    entry_state: Handle<State>,
+    active_transition: Handle<Transition>,
+}
+
+impl Default for MachineLayer {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl MachineLayer {
+    /// Creates new empty layer with override blend mode, full weight and empty mask.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            weight: PoseWeight::Constant(1.0),
+            blend_mode: Default::default(),
+            mask: Default::default(),
+            additive_reference: Default::default(),
+            nodes: Default::default(),
+            states: Default::default(),
+            transitions: Default::default(),
+            final_pose: Default::default(),
+            active_state: Default::default(),
+            entry_state: Default::default(),
+            active_transition: Default::default(),
+        }
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_owned();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets new weight of the layer, weight can be either constant or taken from Weight
+    /// parameter of the machine. Weight of the first layer of a machine is ignored.
+    pub fn set_weight(&mut self, weight: PoseWeight) {
+        self.weight = weight;
+    }
+
+    pub fn weight(&self) -> &PoseWeight {
+        &self.weight
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: LayerBlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn blend_mode(&self) -> LayerBlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_mask(&mut self, mask: LayerMask) {
+        self.mask = mask;
+    }
+
+    pub fn mask(&self) -> &LayerMask {
+        &self.mask
+    }
+
+    pub fn mask_mut(&mut self) -> &mut LayerMask {
+        &mut self.mask
+    }
+
+    /// Sets animation which pose will be used as a reference to calculate offsets for
+    /// additive blending. Usually it is a single-frame animation with neutral pose or
+    /// disabled animation which time position is set to the first frame. Pass
+    /// `Handle::NONE` to treat pose of the layer as offset.
+    pub fn set_additive_reference(&mut self, animation: Handle<Animation>) {
+        self.additive_reference = animation;
+    }
+
+    pub fn additive_reference(&self) -> Handle<Animation> {
+        self.additive_reference
+    }
+
+    pub fn add_node(&mut self, node: PoseNode) -> Handle<PoseNode> {
+        self.nodes.spawn(node)
+    }
+
+    pub fn set_entry_state(&mut self, entry_state: Handle<State>) {
+        self.active_state = entry_state;
+        self.entry_state = entry_state;
+    }
+
+    pub fn add_state(&mut self, state: State) -> Handle<State> {
+        let state = self.states.spawn(state);
+        if self.active_state.is_none() {
+            self.active_state = state;
+        }
+        state
+    }
+
+    pub fn add_transition(&mut self, transition: Transition) -> Handle<Transition> {
+        self.transitions.spawn(transition)
+    }
+
+    pub fn get_state(&self, state: Handle<State>) -> &State {
+        &self.states[state]
+    }
+
+    pub fn get_transition(&self, transition: Handle<Transition>) -> &Transition {
+        &self.transitions[transition]
+    }
+
+    pub fn reset(&mut self) {
+        for transition in self.transitions.iter_mut() {
+            transition.reset();
+        }
+
+        self.active_state = self.entry_state;
+    }
+
+    pub fn nodes(&self) -> PoolIterator<PoseNode> {
+        self.nodes.iter()
+    }
+
+    pub fn active_state(&self) -> Handle<State> {
+        self.active_state
+    }
+
+    pub fn active_transition(&self) -> Handle<Transition> {
+        self.active_transition
+    }
+
+    pub fn transitions(&self) -> &Pool<Transition> {
+        &self.transitions
+    }
+
+    pub fn states(&self) -> &Pool<State> {
+        &self.states
+    }
+
+    pub(in crate::animation::machine) fn for_each_animation_mut(
+        &mut self,
+        func: &mut dyn FnMut(&mut Handle<Animation>),
+    ) {
+        for node in self.nodes.iter_mut() {
+            node.for_each_animation_mut(func);
+        }
+        if self.additive_reference.is_some() {
+            func(&mut self.additive_reference);
+        }
+    }
+
+    /// Returns pose of the layer calculated at last update of the machine, the pose is not
+    /// masked and not weighted.
+    pub fn pose(&self) -> &AnimationPose {
+        &self.final_pose
+    }
+
+    pub(in crate::animation::machine) fn evaluate_pose(
+        &mut self,
+        index: usize,
+        params: &ParameterContainer,
+        events: &mut LimitedEventQueue,
+        debug: bool,
+        animations: &AnimationContainer,
+        dt: f32,
+    ) {
+        self.final_pose.reset();
+
+        if self.active_state.is_some() || self.active_transition.is_some() {
+            // Gather actual poses for each state.
+            for state in self.states.iter_mut() {
+                state.update(&self.nodes, params, animations, dt);
+            }
+
+            if self.active_transition.is_none() {
+                // Find transition.
+                for (handle, transition) in self.transitions.pair_iter_mut() {
+                    if transition.dest == self.active_state
+                        || transition.source != self.active_state
+                    {
+                        continue;
+                    }
+                    if let Some(Parameter::Rule(active)) = params.get(&transition.rule) {
+                        if *active {
+                            events.push(index, Event::StateLeave(self.active_state));
+                            if debug {
+                                Log::writeln(
+                                    MessageKind::Information,
+                                    format!(
+                                        "Leaving state: {}",
+                                        self.states[self.active_state].name
+                                    ),
+                                );
+                            }
+
+                            events.push(index, Event::StateEnter(transition.dest));
+                            if debug {
+                                Log::writeln(
+                                    MessageKind::Information,
+                                    format!(
+                                        "Entering state: {}",
+                                        self.states[transition.dest].name
+                                    ),
+                                );
+                            }
+
+                            self.active_state = Handle::NONE;
+                            self.active_transition = handle;
+
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // Double check for active transition because we can have empty machine.
+            if self.active_transition.is_some() {
+                let transition = &mut self.transitions[self.active_transition];
+
+                // Blend between source and dest states.
+                self.final_pose.blend_with(
+                    &self.states[transition.source].pose,
+                    1.0 - transition.blend_factor,
+                );
+                self.final_pose
+                    .blend_with(&self.states[transition.dest].pose, transition.blend_factor);
+
+                transition.update(dt);
+
+                if transition.is_done() {
+                    transition.reset();
+                    self.active_transition = Handle::NONE;
+                    self.active_state = transition.dest;
+                    events.push(index, Event::ActiveStateChanged(self.active_state));
+
+                    if debug {
+                        Log::writeln(
+                            MessageKind::Information,
+                            format!(
+                                "Active state changed: {}",
+                                self.states[self.active_state].name
+                            ),
+                        );
+                    }
+                }
+            } else {
+                // We must have active state all the time when we do not have any active transition.
+                // Just get pose from active state.
+                self.states[self.active_state]
+                    .pose
+                    .clone_into(&mut self.final_pose);
+            }
+        }
+    }
+
+    /// Combines pose of the layer with given pose (result of previous layers) using
+    /// blend mode, weight and mask of the layer. Weight of the base layer is ignored.
+    pub(in crate::animation::machine) fn blend_into(
+        &self,
+        dest: &mut AnimationPose,
+        params: &ParameterContainer,
+        animations: &AnimationContainer,
+        base: bool,
+    ) {
+        let weight = match self.weight {
+            // There is nothing to blend base layer with.
+            _ if base => 1.0,
+            PoseWeight::Constant(value) => value,
+            PoseWeight::Parameter(ref param_id) => {
+                if let Some(Parameter::Weight(weight)) = params.get(param_id) {
+                    *weight
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        let reference = if self.additive_reference.is_some() {
+            Some(animations.get(self.additive_reference).get_pose())
+        } else {
+            None
+        };
+
+        blend_layer_pose(
+            dest,
+            &self.final_pose,
+            reference,
+            &self.mask,
+            self.blend_mode,
+            weight,
+        );
+    }
+
+    /// Visits contents of the layer without entering a region, this is used to keep
+    /// first layer of a machine compatible with machines saved before layers were added.
+    pub(in crate::animation::machine) fn visit_data(
+        &mut self,
+        visitor: &mut Visitor,
+    ) -> VisitResult {
+        self.nodes.visit("Nodes", visitor)?;
+        self.transitions.visit("Transitions", visitor)?;
+        self.states.visit("States", visitor)?;
+        self.active_state.visit("ActiveState", visitor)?;
+        self.entry_state.visit("EntryState", visitor)?;
+        self.active_transition.visit("ActiveTransition", visitor)?;
+
+        // Backward compatibility - layer settings are missing in old machines.
+        if let Err(e) = self.name.visit("LayerName", visitor) {
+            // Name of the base layer of old machines is set by the machine.
+            if !visitor.is_reading() {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.weight.visit("LayerWeight", visitor) {
+            if visitor.is_reading() {
+                self.weight = PoseWeight::Constant(1.0);
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.blend_mode.visit("LayerBlendMode", visitor) {
+            if visitor.is_reading() {
+                self.blend_mode = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.mask.visit("LayerMask", visitor) {
+            if visitor.is_reading() {
+                self.mask = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.additive_reference.visit("AdditiveReference", visitor) {
+            if visitor.is_reading() {
+                self.additive_reference = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Visit for MachineLayer {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.visit_data(visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+fn blend_layer_pose(
+    dest: &mut AnimationPose,
+    source: &AnimationPose,
+    reference: Option<&AnimationPose>,
+    mask: &LayerMask,
+    blend_mode: LayerBlendMode,
+    weight: f32,
+) {
+    let weight = weight.max(0.0).min(1.0);
+
+    for (handle, local_pose) in source.local_poses.iter() {
+        if !mask.affects(*handle) {
+            continue;
+        }
+
+        match blend_mode {
+            LayerBlendMode::Override => {
+                if let Some(dest_pose) = dest.local_poses.get_mut(handle) {
+                    dest_pose.position = dest_pose.position.lerp(&local_pose.position, weight);
+                    dest_pose.rotation = dest_pose.rotation.nlerp(&local_pose.rotation, weight);
+                    dest_pose.scale = dest_pose.scale.lerp(&local_pose.scale, weight);
+                } else {
+                    dest.local_poses.insert(*handle, local_pose.clone());
+                }
+            }
+            LayerBlendMode::Additive => {
+                // Offsets can only be added to nodes animated by previous layers.
+                if let Some(dest_pose) = dest.local_poses.get_mut(handle) {
+                    let (position_offset, rotation_offset) =
+                        match reference.and_then(|r| r.local_poses.get(handle)) {
+                            Some(reference_pose) => (
+                                local_pose.position - reference_pose.position,
+                                reference_pose.rotation.inverse() * local_pose.rotation,
+                            ),
+                            None => (local_pose.position, local_pose.rotation),
+                        };
+
+                    dest_pose.position += position_offset.scale(weight);
+                    dest_pose.rotation = dest_pose.rotation
+                        * UnitQuaternion::identity().nlerp(&rotation_offset, weight);
+                }
+            }
+        }
+    }
+
+    if let Some(root_motion) = source.root_motion.as_ref() {
+        match (dest.root_motion.as_mut(), blend_mode) {
+            (Some(dest_motion), LayerBlendMode::Override) => dest_motion.lerp(root_motion, weight),
+            (Some(dest_motion), LayerBlendMode::Additive) => {
+                dest_motion.blend_with(root_motion, weight)
+            }
+            (None, LayerBlendMode::Override) => dest.root_motion = Some(*root_motion),
+            (None, LayerBlendMode::Additive) => {
+                dest.root_motion = Some(root_motion.weighted(weight))
+            }
+        }
+    }
+
+    for (key, value) in source.properties.iter() {
+        if !mask.affects(key.0) {
+            continue;
+        }
+
+        if let Some(dest_value) = dest.properties.get_mut(key) {
+            if blend_mode == LayerBlendMode::Override {
+                *dest_value = dest_value.weighted(1.0 - weight);
+            }
+            dest_value.blend_with(value, weight);
+        } else if blend_mode == LayerBlendMode::Override {
+            dest.properties.insert(*key, *value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{
+            machine::{
+                layer::{blend_layer_pose, LayerBlendMode, LayerMask, MachineLayer},
+                Event, Machine, Parameter, PlayAnimation, PoseNode, PoseWeight, State, Transition,
+            },
+            root_motion::RootMotion,
+            Animation, AnimationContainer, AnimationPose, LocalPose,
+        },
+        core::{
+            algebra::{UnitQuaternion, Vector3},
+            pool::Handle,
+        },
+    };
+
+    fn make_pose(nodes: &[(u32, Vector3<f32>)]) -> AnimationPose {
+        let mut pose = AnimationPose::default();
+        for (index, position) in nodes {
+            let node = Handle::new(*index, 1);
+            pose.local_poses.insert(
+                node,
+                LocalPose {
+                    node,
+                    position: *position,
+                    scale: Vector3::new(1.0, 1.0, 1.0),
+                    rotation: UnitQuaternion::identity(),
+                },
+            );
+        }
+        pose
+    }
+
+    #[test]
+    fn test_layer_blending() {
+        let position =
+            |pose: &AnimationPose, index| pose.local_poses[&Handle::new(index, 1)].position;
+
+        let mut mask = LayerMask::default();
+        mask.add(Handle::new(1, 1));
+
+        let mut dest = make_pose(&[(1, Vector3::new(0.0, 0.0, 0.0)), (2, Vector3::default())]);
+        let source = make_pose(&[
+            (1, Vector3::new(2.0, 0.0, 0.0)),
+            (2, Vector3::new(2.0, 0.0, 0.0)),
+        ]);
+        blend_layer_pose(
+            &mut dest,
+            &source,
+            None,
+            &mask,
+            LayerBlendMode::Override,
+            0.5,
+        );
+        assert_eq!(position(&dest, 1), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(position(&dest, 2), Vector3::new(0.0, 0.0, 0.0));
+
+        let reference = make_pose(&[(1, Vector3::new(1.0, 0.0, 0.0))]);
+        blend_layer_pose(
+            &mut dest,
+            &source,
+            Some(&reference),
+            &LayerMask::default(),
+            LayerBlendMode::Additive,
+            1.0,
+        );
+        assert_eq!(position(&dest, 1), Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(position(&dest, 2), Vector3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_transition_events() {
+        let mut animations = AnimationContainer::new();
+        let idle = animations.add(Animation::default());
+        let walk = animations.add(Animation::default());
+
+        let mut machine = Machine::new();
+        let idle_node = machine.add_node(PoseNode::PlayAnimation(PlayAnimation::new(idle)));
+        let idle_state = machine.add_state(State::new("Idle", idle_node));
+        let walk_node = machine.add_node(PoseNode::PlayAnimation(PlayAnimation::new(walk)));
+        let walk_state = machine.add_state(State::new("Walk", walk_node));
+        machine.add_transition(Transition::new(
+            "IdleToWalk",
+            idle_state,
+            walk_state,
+            0.5,
+            "Walk",
+        ));
+        machine.set_entry_state(idle_state);
+        machine.set_parameter("Walk", Parameter::Rule(true));
+
+        machine.evaluate_pose(&animations, 0.1);
+        assert_eq!(machine.pop_event(), Some(Event::StateLeave(idle_state)));
+        assert_eq!(machine.pop_event(), Some(Event::StateEnter(walk_state)));
+        assert_eq!(machine.pop_event(), None);
+
+        machine.evaluate_pose(&animations, 1.0);
+        assert_eq!(
+            machine.pop_event(),
+            Some(Event::ActiveStateChanged(walk_state))
+        );
+    }
+
+    #[test]
+    fn test_base_layer_weight() {
+        let mut layer = MachineLayer::new("Base");
+        layer.set_weight(PoseWeight::Constant(0.0));
+        layer.set_blend_mode(LayerBlendMode::Additive);
+        layer.final_pose.root_motion = Some(RootMotion {
+            delta_position: Vector3::new(1.0, 0.0, 0.0),
+            ..Default::default()
+        });
+
+        let params = Default::default();
+        let animations = AnimationContainer::new();
+
+        let mut dest = AnimationPose::default();
+        layer.blend_into(&mut dest, &params, &animations, true);
+        assert_eq!(
+            dest.root_motion.unwrap().delta_position,
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+
+        let mut dest = AnimationPose::default();
+        layer.blend_into(&mut dest, &params, &animations, false);
+        assert_eq!(dest.root_motion.unwrap().delta_position, Vector3::default());
+    }
+}
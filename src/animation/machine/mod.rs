@@ -84,18 +84,62 @@
 //! You can use multiple machines to animation single model - for example one machine can be for
 //! locomotion and other is for combat. This means that locomotion machine will take control over
 //! lower body and combat machine will control upper body.
+//!
+//! # Layers
+//!
+//! Same can be done with a single machine using layers. Every machine has base layer, which
+//! is used by `add_node`, `add_state`, etc. methods of the machine, and can have any amount
+//! of additional layers (see [`layer::MachineLayer`]). Each layer has its own states and
+//! transitions, weight, blend mode and mask. Layers are combined in the order they were
+//! added: override layer replaces pose of previous layers for nodes in its mask, additive
+//! layer adds its offset on top of it (useful for breathing, leaning, recoil, etc.).
+//!
+//! ```no_run
+//! use rg3d::{
+//!     animation::machine::{
+//!         layer::{LayerMask, MachineLayer}, Machine, PoseNode, PlayAnimation, PoseWeight, State,
+//!     },
+//!     core::pool::Handle,
+//!     scene::graph::Graph,
+//! };
+//!
+//! fn add_upper_body_layer(machine: &mut Machine, graph: &Graph) {
+//!     // Assume that these are correct handles.
+//!     let aim_animation = Handle::default();
+//!     let spine = Handle::default();
+//!
+//!     let mut layer = MachineLayer::new("UpperBody");
+//!     layer.set_mask(LayerMask::from_hierarchy(graph, spine));
+//!     layer.set_weight(PoseWeight::Parameter("AimWeight".to_owned()));
+//!     let aim = layer.add_node(PoseNode::PlayAnimation(PlayAnimation::new(aim_animation)));
+//!     layer.add_state(State::new("Aim", aim));
+//!     machine.add_layer(layer);
+//! }
+//! ```
+//!
+//! # Saving and loading
+//!
+//! Machine implements `Visit` so it is saved together with the rest of game state. Machine
+//! can also be saved as a standalone asset using [`definition::MachineDefinition`], in this
+//! case animations are stored by their names and resolved against animations of a scene
+//! when the definition is loaded.
 
 use crate::animation::machine::blend_nodes::IndexedBlendInput;
 use crate::{
     animation::{
-        machine::blend_nodes::{BlendAnimations, BlendAnimationsByIndex, BlendPose},
+        event::{AnimationEventQueue, SceneAnimationEvent},
+        machine::{
+            blend_nodes::{BlendAnimations, BlendAnimationsByIndex, BlendPose},
+            blend_space::{BlendSpace1D, BlendSpace1DPoint, BlendSpace2D, BlendSpace2DPoint},
+            layer::MachineLayer,
+        },
         Animation, AnimationContainer, AnimationPose,
     },
     core::{
         pool::{Handle, Pool, PoolIterator},
         visitor::{Visit, VisitResult, Visitor},
     },
-    utils::log::{Log, MessageKind},
+    scene::node::Node,
 };
 use std::{
     cell::{Ref, RefCell},
@@ -103,8 +147,12 @@ use std::{
 };
 
 pub mod blend_nodes;
+pub mod blend_space;
+pub mod definition;
+pub mod layer;
 
 /// Specific machine event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Event {
     /// Occurs when enter some state. See module docs for example.
     StateEnter(Handle<State>),
@@ -265,6 +313,12 @@ pub enum PoseNode {
 
     /// See docs for `BlendAnimationsByIndex`.
     BlendAnimationsByIndex(BlendAnimationsByIndex),
+
+    /// See docs for `BlendSpace1D`.
+    BlendSpace1D(BlendSpace1D),
+
+    /// See docs for `BlendSpace2D`.
+    BlendSpace2D(BlendSpace2D),
 }
 
 impl Default for PoseNode {
@@ -292,11 +346,45 @@ impl PoseNode {
         Self::BlendAnimationsByIndex(BlendAnimationsByIndex::new(index_parameter, inputs))
     }
 
+    /// Creates new node that blends animations placed on a line by value of Weight parameter.
+    pub fn make_blend_space_1d(parameter: &str, points: Vec<BlendSpace1DPoint>) -> Self {
+        Self::BlendSpace1D(BlendSpace1D::new(parameter, points))
+    }
+
+    /// Creates new node that blends animations placed on a plane by values of two Weight
+    /// parameters.
+    pub fn make_blend_space_2d(
+        x_parameter: &str,
+        y_parameter: &str,
+        points: Vec<BlendSpace2DPoint>,
+    ) -> Self {
+        Self::BlendSpace2D(BlendSpace2D::new(x_parameter, y_parameter, points))
+    }
+
+    fn for_each_animation_mut(&mut self, func: &mut dyn FnMut(&mut Handle<Animation>)) {
+        match self {
+            Self::PlayAnimation(play_animation) => func(&mut play_animation.animation),
+            Self::BlendSpace1D(blend_space) => {
+                for point in blend_space.points_mut() {
+                    func(&mut point.animation);
+                }
+            }
+            Self::BlendSpace2D(blend_space) => {
+                for point in blend_space.points_mut() {
+                    func(&mut point.animation);
+                }
+            }
+            Self::BlendAnimations(_) | Self::BlendAnimationsByIndex(_) => (),
+        }
+    }
+
     fn from_id(id: i32) -> Result<Self, String> {
         match id {
             0 => Ok(Self::PlayAnimation(Default::default())),
             1 => Ok(Self::BlendAnimations(Default::default())),
             2 => Ok(Self::BlendAnimationsByIndex(Default::default())),
+            3 => Ok(Self::BlendSpace1D(Default::default())),
+            4 => Ok(Self::BlendSpace2D(Default::default())),
             _ => Err(format!("Invalid pose node id {}", id)),
         }
     }
@@ -306,6 +394,8 @@ impl PoseNode {
             Self::PlayAnimation(_) => 0,
             Self::BlendAnimations(_) => 1,
             Self::BlendAnimationsByIndex(_) => 2,
+            Self::BlendSpace1D(_) => 3,
+            Self::BlendSpace2D(_) => 4,
         }
     }
 }
@@ -316,6 +406,8 @@ macro_rules! static_dispatch {
             PoseNode::PlayAnimation(v) => v.$func($($args),*),
             PoseNode::BlendAnimations(v) => v.$func($($args),*),
             PoseNode::BlendAnimationsByIndex(v) => v.$func($($args),*),
+            PoseNode::BlendSpace1D(v) => v.$func($($args),*),
+            PoseNode::BlendSpace2D(v) => v.$func($($args),*),
         }
     };
 }
@@ -509,22 +601,29 @@ impl Transition {
     }
 }
 
-#[derive(Default)]
 pub struct Machine {
-    nodes: Pool<PoseNode>,
-    states: Pool<State>,
-    transitions: Pool<Transition>,
+    layers: Vec<MachineLayer>,
     final_pose: AnimationPose,
-    active_state: Handle<State>,
-    entry_state: Handle<State>,
-    active_transition: Handle<Transition>,
     parameters: ParameterContainer,
     events: LimitedEventQueue,
     debug: bool,
 }
 
+impl Default for Machine {
+    fn default() -> Self {
+        Self {
+            layers: vec![MachineLayer::new("Base")],
+            final_pose: Default::default(),
+            parameters: Default::default(),
+            events: Default::default(),
+            debug: false,
+        }
+    }
+}
+
 struct LimitedEventQueue {
-    queue: VecDeque<Event>,
+    // Events are stored with index of a layer that produced them.
+    queue: VecDeque<(usize, Event)>,
     limit: u32,
 }
 
@@ -545,13 +644,13 @@ impl LimitedEventQueue {
         }
     }
 
-    fn push(&mut self, event: Event) {
+    fn push(&mut self, layer: usize, event: Event) {
         if self.queue.len() < (self.limit as usize) {
-            self.queue.push_back(event);
+            self.queue.push_back((layer, event));
         }
     }
 
-    fn pop(&mut self) -> Option<Event> {
+    fn pop(&mut self) -> Option<(usize, Event)> {
         self.queue.pop_front()
     }
 }
@@ -559,21 +658,13 @@ impl LimitedEventQueue {
 impl Machine {
     pub fn new() -> Self {
         Self {
-            nodes: Default::default(),
-            states: Default::default(),
-            transitions: Default::default(),
-            final_pose: Default::default(),
-            active_state: Default::default(),
-            entry_state: Default::default(),
-            active_transition: Default::default(),
-            parameters: Default::default(),
             events: LimitedEventQueue::new(2048),
-            debug: false,
+            ..Default::default()
         }
     }
 
     pub fn add_node(&mut self, node: PoseNode) -> Handle<PoseNode> {
-        self.nodes.spawn(node)
+        self.base_layer_mut().add_node(node)
     }
 
     pub fn set_parameter(&mut self, id: &str, new_value: Parameter) -> &mut Self {
@@ -590,8 +681,7 @@ impl Machine {
     }
 
     pub fn set_entry_state(&mut self, entry_state: Handle<State>) {
-        self.active_state = entry_state;
-        self.entry_state = entry_state;
+        self.base_layer_mut().set_entry_state(entry_state)
     }
 
     pub fn debug(&mut self, state: bool) {
@@ -599,141 +689,141 @@ impl Machine {
     }
 
     pub fn add_state(&mut self, state: State) -> Handle<State> {
-        let state = self.states.spawn(state);
-        if self.active_state.is_none() {
-            self.active_state = state;
-        }
-        state
+        self.base_layer_mut().add_state(state)
     }
 
     pub fn add_transition(&mut self, transition: Transition) -> Handle<Transition> {
-        self.transitions.spawn(transition)
+        self.base_layer_mut().add_transition(transition)
     }
 
     pub fn get_state(&self, state: Handle<State>) -> &State {
-        &self.states[state]
+        self.base_layer().get_state(state)
     }
 
     pub fn get_transition(&self, transition: Handle<Transition>) -> &Transition {
-        &self.transitions[transition]
+        self.base_layer().get_transition(transition)
     }
 
     pub fn pop_event(&mut self) -> Option<Event> {
-        self.events.pop()
+        self.events.pop().map(|(_, event)| event)
+    }
+
+    /// Moves every pending event of the machine into the given scene-level queue, `owner` is
+    /// used to distinguish machines, usually it is root of a model animated by the machine.
+    /// Events extracted this way are no longer available via [`Self::pop_event`].
+    pub fn forward_events(&mut self, owner: Handle<Node>, queue: &mut AnimationEventQueue) {
+        while let Some((layer, event)) = self.events.pop() {
+            let state = match event {
+                Event::StateEnter(state)
+                | Event::StateLeave(state)
+                | Event::ActiveStateChanged(state) => state,
+            };
+            let state_name = self
+                .layers
+                .get(layer)
+                .and_then(|l| l.states().try_borrow(state))
+                .map(|s| s.name().to_owned())
+                .unwrap_or_default();
+            queue.push(SceneAnimationEvent::Machine {
+                owner,
+                layer,
+                event,
+                state_name,
+            });
+        }
     }
 
     pub fn reset(&mut self) {
-        for transition in self.transitions.iter_mut() {
-            transition.reset();
+        for layer in self.layers.iter_mut() {
+            layer.reset();
         }
-
-        self.active_state = self.entry_state;
     }
 
     pub fn nodes(&self) -> PoolIterator<PoseNode> {
-        self.nodes.iter()
+        self.base_layer().nodes()
     }
 
     pub fn active_state(&self) -> Handle<State> {
-        self.active_state
+        self.base_layer().active_state()
     }
 
     pub fn active_transition(&self) -> Handle<Transition> {
-        self.active_transition
+        self.base_layer().active_transition()
     }
 
     pub fn transitions(&self) -> &Pool<Transition> {
-        &self.transitions
+        self.base_layer().transitions()
     }
 
-    pub fn evaluate_pose(&mut self, animations: &AnimationContainer, dt: f32) -> &AnimationPose {
-        self.final_pose.reset();
+    /// Returns first layer of the machine. Methods of the machine that work with states,
+    /// transitions and nodes are shortcuts for respective methods of the base layer.
+    pub fn base_layer(&self) -> &MachineLayer {
+        &self.layers[0]
+    }
 
-        if self.active_state.is_some() || self.active_transition.is_some() {
-            // Gather actual poses for each state.
-            for state in self.states.iter_mut() {
-                state.update(&self.nodes, &self.parameters, animations, dt);
-            }
+    /// Returns first layer of the machine.
+    pub fn base_layer_mut(&mut self) -> &mut MachineLayer {
+        &mut self.layers[0]
+    }
 
-            if self.active_transition.is_none() {
-                // Find transition.
-                for (handle, transition) in self.transitions.pair_iter_mut() {
-                    if transition.dest == self.active_state
-                        || transition.source != self.active_state
-                    {
-                        continue;
-                    }
-                    if let Some(Parameter::Rule(active)) = self.parameters.get(&transition.rule) {
-                        if *active {
-                            self.events.push(Event::StateLeave(self.active_state));
-                            if self.debug {
-                                Log::writeln(
-                                    MessageKind::Information,
-                                    format!(
-                                        "Leaving state: {}",
-                                        self.states[self.active_state].name
-                                    ),
-                                );
-                            }
-
-                            self.events.push(Event::StateEnter(transition.source));
-                            if self.debug {
-                                Log::writeln(
-                                    MessageKind::Information,
-                                    format!(
-                                        "Entering state: {}",
-                                        self.states[transition.source].name
-                                    ),
-                                );
-                            }
-
-                            self.active_state = Handle::NONE;
-                            self.active_transition = handle;
-
-                            break;
-                        }
-                    }
-                }
-            }
+    /// Adds new layer on top of existing ones and returns its index. Layers are combined
+    /// in the order they were added.
+    pub fn add_layer(&mut self, layer: MachineLayer) -> usize {
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
 
-            // Double check for active transition because we can have empty machine.
-            if self.active_transition.is_some() {
-                let transition = &mut self.transitions[self.active_transition];
-
-                // Blend between source and dest states.
-                self.final_pose.blend_with(
-                    &self.states[transition.source].pose,
-                    1.0 - transition.blend_factor,
-                );
-                self.final_pose
-                    .blend_with(&self.states[transition.dest].pose, transition.blend_factor);
-
-                transition.update(dt);
-
-                if transition.is_done() {
-                    transition.reset();
-                    self.active_transition = Handle::NONE;
-                    self.active_state = transition.dest;
-                    self.events
-                        .push(Event::ActiveStateChanged(self.active_state));
-
-                    if self.debug {
-                        Log::writeln(
-                            MessageKind::Information,
-                            format!(
-                                "Active state changed: {}",
-                                self.states[self.active_state].name
-                            ),
-                        );
-                    }
-                }
-            } else {
-                // We must have active state all the time when we do not have any active transition.
-                // Just get pose from active state.
-                self.states[self.active_state]
-                    .pose
-                    .clone_into(&mut self.final_pose);
-            }
+    /// Returns all layers of the machine, first layer is the base layer.
+    pub fn layers(&self) -> &[MachineLayer] {
+        &self.layers
+    }
+
+    /// Returns all layers of the machine, first layer is the base layer.
+    pub fn layers_mut(&mut self) -> &mut [MachineLayer] {
+        &mut self.layers
+    }
+
+    /// Tries to find a layer by its name.
+    pub fn find_layer(&self, name: &str) -> Option<&MachineLayer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    /// Tries to find a layer by its name.
+    pub fn find_layer_mut(&mut self, name: &str) -> Option<&mut MachineLayer> {
+        self.layers.iter_mut().find(|layer| layer.name() == name)
+    }
+
+    /// Calls given function for every animation handle used by the machine.
+    pub(in crate) fn for_each_animation_mut(
+        &mut self,
+        mut func: impl FnMut(&mut Handle<Animation>),
+    ) {
+        for layer in self.layers.iter_mut() {
+            layer.for_each_animation_mut(&mut func);
+        }
+    }
+
+    /// Evaluates pose of every layer and combines them into the final pose. Layers are
+    /// combined one by one starting from the base layer, every next layer is blended with
+    /// result of previous layers using its blend mode, weight and mask.
+    pub fn evaluate_pose(&mut self, animations: &AnimationContainer, dt: f32) -> &AnimationPose {
+        self.final_pose.reset();
+
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            layer.evaluate_pose(
+                index,
+                &self.parameters,
+                &mut self.events,
+                self.debug,
+                animations,
+                dt,
+            );
+            layer.blend_into(
+                &mut self.final_pose,
+                &self.parameters,
+                animations,
+                index == 0,
+            );
         }
 
         &self.final_pose
@@ -745,12 +835,23 @@ impl Visit for Machine {
         visitor.enter_region(name)?;
 
         self.parameters.visit("Parameters", visitor)?;
-        self.nodes.visit("Nodes", visitor)?;
-        self.transitions.visit("Transitions", visitor)?;
-        self.states.visit("States", visitor)?;
-        self.active_state.visit("ActiveState", visitor)?;
-        self.entry_state.visit("EntryState", visitor)?;
-        self.active_transition.visit("ActiveTransition", visitor)?;
+
+        // Base layer is stored inline to keep compatibility with machines without layers.
+        if self.layers.is_empty() {
+            self.layers.push(MachineLayer::new("Base"));
+        }
+        self.layers[0].visit_data(visitor)?;
+
+        // Backward compatibility - old machines do not have additional layers.
+        let mut layers = self.layers.split_off(1);
+        if let Err(e) = layers.visit("Layers", visitor) {
+            if visitor.is_reading() {
+                layers = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        self.layers.extend(layers);
 
         visitor.leave_region()
     }
@@ -1,6 +1,18 @@
+pub mod compression;
+pub mod event;
+pub mod ik;
 pub mod machine;
+pub mod property;
+pub mod root_motion;
 
 use crate::{
+    animation::{
+        compression::{KeyChannel, KeyReductionOptions, QuantizedRotation},
+        event::{AnimationEventQueue, SceneAnimationEvent},
+        ik::IkSolver,
+        property::{NodeProperty, PropertyTrack, PropertyValue},
+        root_motion::{RootMotion, RootMotionSettings},
+    },
     core::{
         algebra::{UnitQuaternion, Vector3},
         math::{clampf, wrapf},
@@ -8,13 +20,14 @@ use crate::{
             Handle, Pool, PoolIterator, PoolIteratorMut, PoolPairIterator, PoolPairIteratorMut,
             Ticket,
         },
-        visitor::{Visit, VisitResult, Visitor},
+        visitor::prelude::*,
     },
     resource::{model::Model, ResourceState},
     scene::{graph::Graph, node::Node},
     utils::log::{Log, MessageKind},
 };
 use std::{
+    cmp::Ordering,
     collections::{HashMap, VecDeque},
     ops::{Index, IndexMut},
 };
@@ -88,9 +101,11 @@ impl Visit for PoseEvaluationFlags {
 
 #[derive(Debug)]
 pub struct Track {
-    // Frames are not serialized, because it makes no sense to store them in save file,
+    // Keys are not serialized, because it makes no sense to store them in save file,
     // they will be taken from resource on Resolve stage.
-    frames: Vec<KeyFrame>,
+    position: KeyChannel<Vector3<f32>>,
+    rotation: KeyChannel<QuantizedRotation>,
+    scale: KeyChannel<Vector3<f32>>,
     enabled: bool,
     max_time: f32,
     node: Handle<Node>,
@@ -100,7 +115,9 @@ pub struct Track {
 impl Clone for Track {
     fn clone(&self) -> Self {
         Self {
-            frames: self.frames.clone(),
+            position: self.position.clone(),
+            rotation: self.rotation.clone(),
+            scale: self.scale.clone(),
             enabled: self.enabled,
             max_time: self.max_time,
             node: self.node,
@@ -112,7 +129,9 @@ impl Clone for Track {
 impl Default for Track {
     fn default() -> Self {
         Self {
-            frames: Vec::new(),
+            position: Default::default(),
+            rotation: Default::default(),
+            scale: Default::default(),
             enabled: true,
             max_time: 0.0,
             node: Default::default(),
@@ -147,22 +166,15 @@ impl Track {
         self.node
     }
 
+    /// Adds new key frame to the track. Position, rotation and scale of the key frame are
+    /// stored in separate channels.
     pub fn add_key_frame(&mut self, key_frame: KeyFrame) {
-        if key_frame.time > self.max_time {
-            self.frames.push(key_frame);
+        self.position.add_key(key_frame.time, key_frame.position);
+        self.rotation.add_key(key_frame.time, key_frame.rotation);
+        self.scale.add_key(key_frame.time, key_frame.scale);
 
+        if key_frame.time > self.max_time {
             self.max_time = key_frame.time;
-        } else {
-            // Find a place to insert
-            let mut index = 0;
-            for (i, other_key_frame) in self.frames.iter().enumerate() {
-                if key_frame.time < other_key_frame.time {
-                    index = i;
-                    break;
-                }
-            }
-
-            self.frames.insert(index, key_frame)
         }
     }
 
@@ -175,75 +187,105 @@ impl Track {
     }
 
     pub fn set_key_frames(&mut self, key_frames: &[KeyFrame]) {
-        self.frames = key_frames.to_vec();
+        self.position.clear();
+        self.rotation.clear();
+        self.scale.clear();
         self.max_time = 0.0;
 
-        for key_frame in self.frames.iter() {
-            if key_frame.time > self.max_time {
-                self.max_time = key_frame.time;
-            }
+        for key_frame in key_frames {
+            self.add_key_frame(*key_frame);
         }
     }
 
-    pub fn get_key_frames(&self) -> &[KeyFrame] {
-        &self.frames
+    /// Returns key frames of the track. Since channels of the track can have keys at
+    /// different times, key frames are sampled at every time when any of channels has key.
+    pub fn get_key_frames(&self) -> Vec<KeyFrame> {
+        let mut times = self
+            .position
+            .times()
+            .iter()
+            .chain(self.rotation.times())
+            .chain(self.scale.times())
+            .copied()
+            .collect::<Vec<_>>();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        times.dedup();
+
+        times
+            .into_iter()
+            .map(|time| {
+                KeyFrame::new(
+                    time,
+                    self.position.sample(time).unwrap_or_default(),
+                    self.scale
+                        .sample(time)
+                        .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0)),
+                    self.rotation.sample(time).unwrap_or_default(),
+                )
+            })
+            .collect()
     }
 
-    pub fn get_local_pose(&self, mut time: f32) -> Option<LocalPose> {
-        if self.frames.is_empty() {
-            return None;
-        }
+    /// Copies keys of other track as is, without unpacking them.
+    pub fn copy_keys_from(&mut self, other: &Track) {
+        self.position = other.position.clone();
+        self.rotation = other.rotation.clone();
+        self.scale = other.scale.clone();
+        self.max_time = other.max_time;
+    }
 
-        if time >= self.max_time {
-            return self.frames.last().map(|k| LocalPose {
-                node: self.node,
-                position: k.position,
-                scale: k.scale,
-                rotation: k.rotation,
-            });
-        }
+    pub fn position_channel(&self) -> &KeyChannel<Vector3<f32>> {
+        &self.position
+    }
+
+    pub fn rotation_channel(&self) -> &KeyChannel<QuantizedRotation> {
+        &self.rotation
+    }
 
-        time = clampf(time, 0.0, self.max_time);
+    pub fn scale_channel(&self) -> &KeyChannel<Vector3<f32>> {
+        &self.scale
+    }
 
-        let mut right_index = 0;
-        for (i, keyframe) in self.frames.iter().enumerate() {
-            if keyframe.time >= time {
-                right_index = i;
-                break;
-            }
-        }
+    /// Returns total amount of keys in every channel of the track.
+    pub fn key_count(&self) -> usize {
+        self.position.len() + self.rotation.len() + self.scale.len()
+    }
 
-        if right_index == 0 {
-            self.frames.first().map(|k| LocalPose {
-                node: self.node,
-                position: k.position,
-                scale: k.scale,
-                rotation: k.rotation,
-            })
-        } else {
-            let left = &self.frames[right_index - 1];
-            let right = &self.frames[right_index];
-            let interpolator = (time - left.time) / (right.time - left.time);
-
-            Some(LocalPose {
-                node: self.node,
-                position: if self.flags.ignore_position {
-                    Vector3::new(0.0, 0.0, 0.0)
-                } else {
-                    left.position.lerp(&right.position, interpolator)
-                },
-                scale: if self.flags.ignore_scale {
-                    Vector3::new(1.0, 1.0, 1.0)
-                } else {
-                    left.scale.lerp(&right.scale, interpolator)
-                },
-                rotation: if self.flags.ignore_rotation {
-                    UnitQuaternion::default()
-                } else {
-                    left.rotation.nlerp(&right.rotation, interpolator)
-                },
-            })
+    /// Removes keys that can be restored by interpolation of neighbour keys within given
+    /// tolerances. Constant channels are reduced to a single key.
+    pub fn reduce_keys(&mut self, options: &KeyReductionOptions) {
+        self.position.reduce(options.position_tolerance);
+        self.rotation.reduce(options.rotation_tolerance);
+        self.scale.reduce(options.scale_tolerance);
+    }
+
+    pub fn get_local_pose(&self, time: f32) -> Option<LocalPose> {
+        if self.position.is_empty() && self.rotation.is_empty() && self.scale.is_empty() {
+            return None;
         }
+
+        let time = clampf(time, 0.0, self.max_time);
+
+        Some(LocalPose {
+            node: self.node,
+            position: if self.flags.ignore_position {
+                Vector3::new(0.0, 0.0, 0.0)
+            } else {
+                self.position.sample(time).unwrap_or_default()
+            },
+            scale: if self.flags.ignore_scale {
+                Vector3::new(1.0, 1.0, 1.0)
+            } else {
+                self.scale
+                    .sample(time)
+                    .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0))
+            },
+            rotation: if self.flags.ignore_rotation {
+                UnitQuaternion::default()
+            } else {
+                self.rotation.sample(time).unwrap_or_default()
+            },
+        })
     }
 
     pub fn flags(&self) -> PoseEvaluationFlags {
@@ -255,6 +297,23 @@ impl Track {
     }
 }
 
+/// Small piece of data attached to a signal, for example name of a sound to play or amount
+/// of damage of a hit frame.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub enum SignalPayload {
+    None,
+    Number(f32),
+    String(String),
+}
+
+impl Default for SignalPayload {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// An event that is produced when an animation passes a signal. Name and payload of the
+/// signal can be obtained using [`Animation::find_signal`].
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct AnimationEvent {
     pub signal_id: u64,
@@ -263,6 +322,8 @@ pub struct AnimationEvent {
 #[derive(Clone, Debug)]
 pub struct AnimationSignal {
     id: u64,
+    name: String,
+    payload: SignalPayload,
     time: f32,
     enabled: bool,
 }
@@ -271,11 +332,37 @@ impl AnimationSignal {
     pub fn new(id: u64, time: f32) -> Self {
         Self {
             id,
+            name: Default::default(),
+            payload: Default::default(),
             time,
             enabled: true,
         }
     }
 
+    /// Sets name of the signal, it is passed to every event of the signal.
+    pub fn with_name<N: AsRef<str>>(mut self, name: N) -> Self {
+        self.name = name.as_ref().to_owned();
+        self
+    }
+
+    /// Sets payload of the signal, it is passed to every event of the signal.
+    pub fn with_payload(mut self, payload: SignalPayload) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn payload(&self) -> &SignalPayload {
+        &self.payload
+    }
+
+    fn make_event(&self) -> AnimationEvent {
+        AnimationEvent { signal_id: self.id }
+    }
+
     pub fn set_enabled(&mut self, value: bool) {
         self.enabled = value;
     }
@@ -297,6 +384,8 @@ impl Default for AnimationSignal {
     fn default() -> Self {
         Self {
             id: 0,
+            name: Default::default(),
+            payload: Default::default(),
             time: 0.0,
             enabled: true,
         }
@@ -311,14 +400,32 @@ impl Visit for AnimationSignal {
         self.time.visit("Time", visitor)?;
         self.enabled.visit("Enabled", visitor)?;
 
+        // Backward compatibility - name and payload may be missing in old files.
+        if let Err(e) = self.name.visit("Name", visitor) {
+            if visitor.is_reading() {
+                self.name = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.payload.visit("Payload", visitor) {
+            if visitor.is_reading() {
+                self.payload = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
         visitor.leave_region()
     }
 }
 
 #[derive(Debug)]
 pub struct Animation {
+    name: String,
     // TODO: Extract into separate struct AnimationTimeline
     tracks: Vec<Track>,
+    property_tracks: Vec<PropertyTrack>,
     length: f32,
     time_position: f32,
     ///////////////////////////////////////////////////////
@@ -329,6 +436,7 @@ pub struct Animation {
     pose: AnimationPose,
     signals: Vec<AnimationSignal>,
     events: VecDeque<AnimationEvent>,
+    root_motion_settings: Option<RootMotionSettings>,
 }
 
 /// Snapshot of scene node local transform state.
@@ -383,6 +491,8 @@ impl LocalPose {
 #[derive(Default, Debug)]
 pub struct AnimationPose {
     local_poses: HashMap<Handle<Node>, LocalPose>,
+    properties: HashMap<(Handle<Node>, NodeProperty), PropertyValue>,
+    root_motion: Option<RootMotion>,
 }
 
 impl AnimationPose {
@@ -391,6 +501,8 @@ impl AnimationPose {
         for (handle, local_pose) in self.local_poses.iter() {
             dest.local_poses.insert(*handle, local_pose.clone());
         }
+        dest.properties.extend(self.properties.iter());
+        dest.root_motion = self.root_motion;
     }
 
     pub fn blend_with(&mut self, other: &AnimationPose, weight: f32) {
@@ -403,6 +515,19 @@ impl AnimationPose {
                 self.add_local_pose(other_pose.weighted_clone(weight));
             }
         }
+        for (key, other_value) in other.properties.iter() {
+            if let Some(current_value) = self.properties.get_mut(key) {
+                current_value.blend_with(other_value, weight);
+            } else {
+                self.properties.insert(*key, other_value.weighted(weight));
+            }
+        }
+        if let Some(other_motion) = other.root_motion.as_ref() {
+            match self.root_motion.as_mut() {
+                Some(root_motion) => root_motion.blend_with(other_motion, weight),
+                None => self.root_motion = Some(other_motion.weighted(weight)),
+            }
+        }
     }
 
     fn add_local_pose(&mut self, local_pose: LocalPose) {
@@ -411,6 +536,34 @@ impl AnimationPose {
 
     pub fn reset(&mut self) {
         self.local_poses.clear();
+        self.properties.clear();
+        self.root_motion = None;
+    }
+
+    /// Returns motion of root node extracted from animations of the pose, if any. See
+    /// [`root_motion`] module docs for more info.
+    pub fn root_motion(&self) -> Option<RootMotion> {
+        self.root_motion
+    }
+
+    /// Returns value of animated property of a node, if any.
+    pub fn property_value(
+        &self,
+        node: Handle<Node>,
+        property: NodeProperty,
+    ) -> Option<PropertyValue> {
+        self.properties.get(&(node, property)).copied()
+    }
+
+    /// Sets animated values of properties to respective nodes. It is called automatically
+    /// by [`AnimationPose::apply`], but must be called manually if you're using
+    /// [`AnimationPose::apply_with`].
+    pub fn apply_properties(&self, graph: &mut Graph) {
+        for (&(node, property), &value) in self.properties.iter() {
+            if graph.is_valid_handle(node) {
+                property.apply(&mut graph[node], value);
+            }
+        }
     }
 
     pub fn apply(&self, graph: &mut Graph) {
@@ -425,6 +578,16 @@ impl AnimationPose {
                     .set_scale(local_pose.scale);
             }
         }
+        self.apply_properties(graph);
+    }
+
+    /// Applies pose to the graph and then applies given IK solvers on top of it, in the order
+    /// they are specified.
+    pub fn apply_with_ik(&self, graph: &mut Graph, solvers: &[IkSolver]) {
+        self.apply(graph);
+        for solver in solvers {
+            solver.solve(graph);
+        }
     }
 
     /// Calls given callback function for each node and allows you to apply pose with your own
@@ -446,7 +609,9 @@ impl AnimationPose {
 impl Clone for Animation {
     fn clone(&self) -> Self {
         Self {
+            name: self.name.clone(),
             tracks: self.tracks.clone(),
+            property_tracks: self.property_tracks.clone(),
             speed: self.speed,
             length: self.length,
             time_position: self.time_position,
@@ -456,11 +621,24 @@ impl Clone for Animation {
             pose: Default::default(),
             signals: self.signals.clone(),
             events: Default::default(),
+            root_motion_settings: self.root_motion_settings,
         }
     }
 }
 
 impl Animation {
+    /// Sets new name of the animation. Name is used to distinguish animation clips of a
+    /// model resource, so it is better to keep it unique among animations of a resource.
+    pub fn set_name<N: AsRef<str>>(&mut self, name: N) -> &mut Self {
+        self.name = name.as_ref().to_owned();
+        self
+    }
+
+    /// Returns name of the animation.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
     pub fn add_track(&mut self, track: Track) {
         self.tracks.push(track);
 
@@ -475,6 +653,31 @@ impl Animation {
         &self.tracks
     }
 
+    /// Adds new property track to the animation, length of the animation will be extended
+    /// if the track is longer.
+    pub fn add_property_track(&mut self, track: PropertyTrack) {
+        self.length = self.length.max(track.max_time());
+        self.property_tracks.push(track);
+    }
+
+    /// Returns property tracks of the animation.
+    pub fn property_tracks(&self) -> &[PropertyTrack] {
+        &self.property_tracks
+    }
+
+    /// Returns property tracks of the animation.
+    pub fn property_tracks_mut(&mut self) -> &mut [PropertyTrack] {
+        &mut self.property_tracks
+    }
+
+    /// Removes all property tracks that do not satisfy the predicate.
+    pub fn retain_property_tracks<F>(&mut self, filter: F)
+    where
+        F: FnMut(&PropertyTrack) -> bool,
+    {
+        self.property_tracks.retain(filter)
+    }
+
     pub fn set_time_position(&mut self, time: f32) -> &mut Self {
         if self.looped {
             self.time_position = wrapf(time, 0.0, self.length);
@@ -493,12 +696,22 @@ impl Animation {
     }
 
     fn tick(&mut self, dt: f32) {
+        self.tick_and_emit(dt, None)
+    }
+
+    /// Advances the animation and puts an event into the per-animation queue for every passed
+    /// signal, the event is also put into the given scene-level queue (if any).
+    fn tick_and_emit(
+        &mut self,
+        dt: f32,
+        mut scene_events: Option<(Handle<Animation>, &mut AnimationEventQueue)>,
+    ) {
         self.update_pose();
 
         let current_time_position = self.get_time_position();
         let new_time_position = current_time_position + dt * self.get_speed();
 
-        for signal in self.signals.iter_mut() {
+        for signal in self.signals.iter().filter(|s| s.enabled) {
             if self.speed >= 0.0
                 && (current_time_position < signal.time && new_time_position >= signal.time)
                 || self.speed < 0.0
@@ -506,14 +719,94 @@ impl Animation {
             {
                 // TODO: Make this configurable.
                 if self.events.len() < 32 {
-                    self.events.push_back(AnimationEvent {
-                        signal_id: signal.id,
+                    self.events.push_back(signal.make_event());
+                }
+                if let Some((animation, queue)) = scene_events.as_mut() {
+                    queue.push(SceneAnimationEvent::Signal {
+                        animation: *animation,
+                        event: signal.make_event(),
                     });
                 }
             }
         }
 
         self.set_time_position(new_time_position);
+
+        if let Some(settings) = self.root_motion_settings {
+            self.extract_root_motion(settings, current_time_position, new_time_position);
+        }
+    }
+
+    fn extract_root_motion(&mut self, settings: RootMotionSettings, from: f32, to: f32) {
+        let track = match self
+            .tracks
+            .iter()
+            .find(|track| track.is_enabled() && track.node == settings.node)
+        {
+            Some(track) => track,
+            None => return,
+        };
+
+        let (from_pose, start_pose, end_pose) = match (
+            track.get_local_pose(from),
+            track.get_local_pose(0.0),
+            track.get_local_pose(self.length),
+        ) {
+            (Some(from_pose), Some(start_pose), Some(end_pose)) => {
+                (from_pose, start_pose, end_pose)
+            }
+            _ => return,
+        };
+
+        let motion = |a: &LocalPose, b: &LocalPose| RootMotion {
+            delta_position: settings.extracted(b.position - a.position),
+            delta_rotation: if settings.ignore_rotations {
+                UnitQuaternion::identity()
+            } else {
+                a.rotation.inverse() * b.rotation
+            },
+        };
+
+        // Looped animation can wrap around during the step, in this case motion is a sum
+        // of motion to the end (or beginning) of the animation and motion after wrapping.
+        let root_motion = if self.looped && to > self.length && self.length > 0.0 {
+            let to_pose = track.get_local_pose(wrapf(to, 0.0, self.length)).unwrap();
+            motion(&from_pose, &end_pose).then(&motion(&start_pose, &to_pose))
+        } else if self.looped && to < 0.0 && self.length > 0.0 {
+            let to_pose = track.get_local_pose(wrapf(to, 0.0, self.length)).unwrap();
+            motion(&from_pose, &start_pose).then(&motion(&end_pose, &to_pose))
+        } else {
+            let to_pose = track.get_local_pose(clampf(to, 0.0, self.length)).unwrap();
+            motion(&from_pose, &to_pose)
+        };
+
+        // Strip extracted motion from the pose, so root node will stay at its initial
+        // position and rotation.
+        if let Some(root_pose) = self.pose.local_poses.get_mut(&settings.node) {
+            root_pose.position =
+                settings.extracted(start_pose.position) + settings.kept(root_pose.position);
+            if !settings.ignore_rotations {
+                root_pose.rotation = start_pose.rotation;
+            }
+        }
+
+        self.pose.root_motion = Some(root_motion);
+    }
+
+    /// Enables root motion extraction with given settings, or disables it if `None` is
+    /// passed. See [`root_motion`] module docs for more info.
+    pub fn set_root_motion_settings(&mut self, settings: Option<RootMotionSettings>) {
+        self.root_motion_settings = settings;
+    }
+
+    pub fn root_motion_settings(&self) -> Option<&RootMotionSettings> {
+        self.root_motion_settings.as_ref()
+    }
+
+    /// Returns motion of root node during last update of the animation, if root motion
+    /// extraction is enabled.
+    pub fn root_motion(&self) -> Option<RootMotion> {
+        self.pose.root_motion
     }
 
     pub fn pop_event(&mut self) -> Option<AnimationEvent> {
@@ -555,6 +848,14 @@ impl Animation {
         self
     }
 
+    /// Removes redundant keys from every track of the animation, see
+    /// [`Track::reduce_keys`] for more info.
+    pub fn reduce_keys(&mut self, options: &KeyReductionOptions) {
+        for track in self.tracks.iter_mut() {
+            track.reduce_keys(options);
+        }
+    }
+
     pub fn get_tracks_mut(&mut self) -> &mut [Track] {
         &mut self.tracks
     }
@@ -567,6 +868,12 @@ impl Animation {
         &self.signals
     }
 
+    /// Tries to find a signal by its id, it is used to get name and payload of a signal from
+    /// an [`AnimationEvent`].
+    pub fn find_signal(&self, id: u64) -> Option<&AnimationSignal> {
+        self.signals.iter().find(|signal| signal.id == id)
+    }
+
     pub fn retain_tracks<F>(&mut self, filter: F)
     where
         F: FnMut(&Track) -> bool,
@@ -645,8 +952,25 @@ impl Animation {
         if let Some(resource) = self.resource.clone() {
             let resource = resource.state();
             if let ResourceState::Ok(ref data) = *resource {
-                // TODO: Here we assume that resource contains only *one* animation.
-                if let Some(ref_animation) = data.get_scene().animations.pool.at(0) {
+                // Find the clip this animation was created from. Old save files do not
+                // have names of animations, in this case first animation will be used.
+                let ref_animations = &data.get_scene().animations;
+                let ref_animation = ref_animations
+                    .iter()
+                    .find(|ref_animation| ref_animation.name == self.name)
+                    .or_else(|| {
+                        if !self.name.is_empty() {
+                            Log::writeln(
+                                MessageKind::Error,
+                                format!(
+                                    "Failed to find animation clip {} in resource {:?}!",
+                                    self.name, data.path
+                                ),
+                            );
+                        }
+                        ref_animations.pool.at(0)
+                    });
+                if let Some(ref_animation) = ref_animation {
                     for track in self.get_tracks_mut() {
                         // This may panic if animation has track that refers to a deleted node,
                         // it can happen if you deleted a node but forgot to remove animation
@@ -668,7 +992,7 @@ impl Animation {
                             if track_node.name()
                                 == data.get_scene().graph[ref_track.get_node()].name()
                             {
-                                track.set_key_frames(ref_track.get_key_frames());
+                                track.copy_keys_from(ref_track);
                                 found = true;
                                 break;
                             }
@@ -691,11 +1015,28 @@ impl Animation {
     }
 
     fn update_pose(&mut self) {
-        self.pose.reset();
+        let mut pose = std::mem::take(&mut self.pose);
+        self.sample_pose(self.time_position, &mut pose);
+        self.pose = pose;
+    }
+
+    /// Calculates pose of the animation at given time and writes it to `pose`. Unlike
+    /// [`Animation::get_pose`], it does not depend on current time position of the animation,
+    /// so it can be used to sample animation at arbitrary time without changing it.
+    pub fn sample_pose(&self, time: f32, pose: &mut AnimationPose) {
+        pose.reset();
         for track in self.tracks.iter() {
             if track.is_enabled() {
-                if let Some(local_pose) = track.get_local_pose(self.time_position) {
-                    self.pose.add_local_pose(local_pose);
+                if let Some(local_pose) = track.get_local_pose(time) {
+                    pose.add_local_pose(local_pose);
+                }
+            }
+        }
+        for track in self.property_tracks.iter() {
+            if track.is_enabled() {
+                if let Some(value) = track.fetch(time) {
+                    pose.properties
+                        .insert((track.node(), track.property()), value);
                 }
             }
         }
@@ -709,7 +1050,9 @@ impl Animation {
 impl Default for Animation {
     fn default() -> Self {
         Self {
+            name: Default::default(),
             tracks: Vec::new(),
+            property_tracks: Vec::new(),
             speed: 1.0,
             length: 0.0,
             time_position: 0.0,
@@ -719,6 +1062,7 @@ impl Default for Animation {
             pose: Default::default(),
             signals: Default::default(),
             events: Default::default(),
+            root_motion_settings: None,
         }
     }
 }
@@ -736,6 +1080,32 @@ impl Visit for Animation {
         self.enabled.visit("Enabled", visitor)?;
         self.signals.visit("Signals", visitor)?;
 
+        // Backward compatibility - name may be missing in old files.
+        if let Err(e) = self.name.visit("Name", visitor) {
+            if visitor.is_reading() {
+                self.name = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.property_tracks.visit("PropertyTracks", visitor) {
+            if visitor.is_reading() {
+                self.property_tracks = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self
+            .root_motion_settings
+            .visit("RootMotionSettings", visitor)
+        {
+            if visitor.is_reading() {
+                self.root_motion_settings = None;
+            } else {
+                return Err(e);
+            }
+        }
+
         visitor.leave_region()
     }
 }
@@ -821,6 +1191,23 @@ impl AnimationContainer {
         self.pool.borrow_mut(handle)
     }
 
+    /// Tries to borrow an animation, returns `None` if handle is invalid.
+    #[inline]
+    pub fn try_get(&self, handle: Handle<Animation>) -> Option<&Animation> {
+        self.pool.try_borrow(handle)
+    }
+
+    /// Tries to find an animation by its name. Returns `Handle::NONE` if there is no such
+    /// animation.
+    #[inline]
+    pub fn find_by_name<N: AsRef<str>>(&self, name: N) -> Handle<Animation> {
+        self.pool
+            .pair_iter()
+            .find(|(_, animation)| animation.name == name.as_ref())
+            .map(|(handle, _)| handle)
+            .unwrap_or_default()
+    }
+
     #[inline]
     pub fn retain<P>(&mut self, pred: P)
     where
@@ -848,6 +1235,14 @@ impl AnimationContainer {
             animation.tick(dt);
         }
     }
+
+    /// Same as [`Self::update_animations`], but also puts events of every passed signal into
+    /// the given queue. Events still can be extracted per animation using `pop_event`.
+    pub fn update_animations_with_events(&mut self, dt: f32, events: &mut AnimationEventQueue) {
+        for (handle, animation) in self.pool.pair_iter_mut().filter(|(_, anim)| anim.enabled) {
+            animation.tick_and_emit(dt, Some((handle, &mut *events)));
+        }
+    }
 }
 
 impl Visit for AnimationContainer {
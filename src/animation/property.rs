@@ -0,0 +1,343 @@
+//! Property tracks allow to animate arbitrary properties of scene nodes (color of a light,
+//! field of view of a camera, visibility, etc.) using curves. Unlike key frame tracks,
+//! property tracks store their curves in save files, because usually they are created from
+//! code or in editor and are not taken from model resources.
+
+use crate::{
+    core::{
+        algebra::Vector4,
+        color::Color,
+        curve::{Curve, CurveKey, CurveKeyKind},
+        pool::Handle,
+        visitor::prelude::*,
+    },
+    scene::{light::Light, node::Node},
+};
+
+/// Kind of value of a property.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PropertyValueKind {
+    /// Single floating point number.
+    Float,
+    /// Boolean value, curve value >= 0.5 means `true`.
+    Bool,
+    /// RGBA color, every component of the color is in [0; 1] range.
+    Color,
+}
+
+impl PropertyValueKind {
+    /// Returns amount of curves required to describe a value of the kind.
+    pub fn component_count(self) -> usize {
+        match self {
+            PropertyValueKind::Float | PropertyValueKind::Bool => 1,
+            PropertyValueKind::Color => 4,
+        }
+    }
+}
+
+/// Value of an animated property.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// See [`PropertyValueKind::Float`].
+    Float(f32),
+    /// See [`PropertyValueKind::Bool`].
+    Bool(bool),
+    /// See [`PropertyValueKind::Color`].
+    Color(Color),
+}
+
+impl PropertyValue {
+    fn components(self) -> Vector4<f32> {
+        match self {
+            PropertyValue::Float(value) => Vector4::new(value, 0.0, 0.0, 0.0),
+            PropertyValue::Bool(value) => {
+                Vector4::new(if value { 1.0 } else { 0.0 }, 0.0, 0.0, 0.0)
+            }
+            PropertyValue::Color(color) => color.as_frgba(),
+        }
+    }
+
+    pub(in crate) fn weighted(self, weight: f32) -> Self {
+        match self {
+            PropertyValue::Float(value) => PropertyValue::Float(value * weight),
+            PropertyValue::Bool(value) => PropertyValue::Bool(value),
+            PropertyValue::Color(color) => {
+                PropertyValue::Color(Color::from(color.as_frgba().scale(weight)))
+            }
+        }
+    }
+
+    pub(in crate) fn blend_with(&mut self, other: &PropertyValue, weight: f32) {
+        match (self, other) {
+            (PropertyValue::Float(value), PropertyValue::Float(other)) => *value += *other * weight,
+            (PropertyValue::Bool(value), PropertyValue::Bool(other)) => {
+                if weight >= 0.5 {
+                    *value = *other;
+                }
+            }
+            (PropertyValue::Color(color), PropertyValue::Color(other)) => {
+                *color = Color::from(color.as_frgba() + other.as_frgba().scale(weight));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Property of a scene node that can be animated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Visit)]
+pub enum NodeProperty {
+    /// Visibility of any node.
+    Visibility,
+    /// Color of any light.
+    LightColor,
+    /// Radius of a point light or distance of a spot light.
+    LightRadius,
+    /// Hotspot cone angle of a spot light (in radians).
+    SpotLightHotspotAngle,
+    /// Falloff angle delta of a spot light (in radians).
+    SpotLightFalloffAngleDelta,
+    /// Field of view of a camera (in radians).
+    CameraFov,
+    /// Color of a surface of a mesh with given index.
+    SurfaceColor(u32),
+    /// Color of a sprite.
+    SpriteColor,
+    /// Size of a sprite.
+    SpriteSize,
+    /// Rotation of a sprite (in radians).
+    SpriteRotation,
+    /// User-defined property with given id, it won't be applied to a node automatically,
+    /// but its value can be fetched from animation pose (see
+    /// [`AnimationPose::property_value`](crate::animation::AnimationPose::property_value))
+    /// and applied to anything, for example gain of a sound source.
+    Custom(u32),
+    /// Weight of a blend shape of a mesh with given index.
+    BlendShapeWeight(u32),
+}
+
+impl Default for NodeProperty {
+    fn default() -> Self {
+        Self::Visibility
+    }
+}
+
+impl NodeProperty {
+    /// Returns kind of value of the property.
+    pub fn value_kind(self) -> PropertyValueKind {
+        match self {
+            NodeProperty::Visibility => PropertyValueKind::Bool,
+            NodeProperty::LightColor
+            | NodeProperty::SurfaceColor(_)
+            | NodeProperty::SpriteColor => PropertyValueKind::Color,
+            NodeProperty::LightRadius
+            | NodeProperty::SpotLightHotspotAngle
+            | NodeProperty::SpotLightFalloffAngleDelta
+            | NodeProperty::CameraFov
+            | NodeProperty::SpriteSize
+            | NodeProperty::SpriteRotation
+            | NodeProperty::Custom(_)
+            | NodeProperty::BlendShapeWeight(_) => PropertyValueKind::Float,
+        }
+    }
+
+    /// Sets new value of the property to a node. Does nothing if node does not have such
+    /// property or value has wrong kind.
+    pub fn apply(self, node: &mut Node, value: PropertyValue) {
+        match (self, value) {
+            (NodeProperty::Visibility, PropertyValue::Bool(visibility)) => {
+                node.set_visibility(visibility);
+            }
+            (NodeProperty::LightColor, PropertyValue::Color(color)) => {
+                if let Node::Light(light) = node {
+                    light.set_color(color);
+                }
+            }
+            (NodeProperty::LightRadius, PropertyValue::Float(radius)) => match node {
+                Node::Light(Light::Point(point_light)) => point_light.set_radius(radius),
+                Node::Light(Light::Spot(spot_light)) => {
+                    spot_light.set_distance(radius);
+                }
+                _ => (),
+            },
+            (NodeProperty::SpotLightHotspotAngle, PropertyValue::Float(angle)) => {
+                if let Node::Light(Light::Spot(spot_light)) = node {
+                    spot_light.set_hotspot_cone_angle(angle);
+                }
+            }
+            (NodeProperty::SpotLightFalloffAngleDelta, PropertyValue::Float(delta)) => {
+                if let Node::Light(Light::Spot(spot_light)) = node {
+                    spot_light.set_falloff_angle_delta(delta);
+                }
+            }
+            (NodeProperty::CameraFov, PropertyValue::Float(fov)) => {
+                if let Node::Camera(camera) = node {
+                    camera.set_fov(fov);
+                }
+            }
+            (NodeProperty::SurfaceColor(index), PropertyValue::Color(color)) => {
+                if let Node::Mesh(mesh) = node {
+                    if let Some(surface) = mesh.surfaces_mut().get_mut(index as usize) {
+                        surface.set_color(color);
+                    }
+                }
+            }
+            (NodeProperty::SpriteColor, PropertyValue::Color(color)) => {
+                if let Node::Sprite(sprite) = node {
+                    sprite.set_color(color);
+                }
+            }
+            (NodeProperty::SpriteSize, PropertyValue::Float(size)) => {
+                if let Node::Sprite(sprite) = node {
+                    sprite.set_size(size);
+                }
+            }
+            (NodeProperty::SpriteRotation, PropertyValue::Float(rotation)) => {
+                if let Node::Sprite(sprite) = node {
+                    sprite.set_rotation(rotation);
+                }
+            }
+            (NodeProperty::BlendShapeWeight(index), PropertyValue::Float(weight)) => {
+                if let Node::Mesh(mesh) = node {
+                    mesh.set_blend_shape_weight(index as usize, weight);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Property track animates single property of a node using a curve per component of the
+/// value of the property.
+#[derive(Clone, Debug, Visit)]
+pub struct PropertyTrack {
+    node: Handle<Node>,
+    property: NodeProperty,
+    curves: Vec<Curve>,
+    enabled: bool,
+}
+
+impl Default for PropertyTrack {
+    fn default() -> Self {
+        Self::new(Handle::NONE, NodeProperty::default())
+    }
+}
+
+impl PropertyTrack {
+    /// Creates new property track without keys.
+    pub fn new(node: Handle<Node>, property: NodeProperty) -> Self {
+        Self {
+            node,
+            property,
+            curves: vec![Curve::default(); property.value_kind().component_count()],
+            enabled: true,
+        }
+    }
+
+    /// Adds new key to the track. Components of the value will be added to respective
+    /// curves, boolean values are always added as constant keys.
+    pub fn add_key(&mut self, time: f32, value: PropertyValue, kind: CurveKeyKind) {
+        let kind = match value {
+            PropertyValue::Bool(_) => CurveKeyKind::Constant,
+            _ => kind,
+        };
+        let components = value.components();
+        for (i, curve) in self.curves.iter_mut().enumerate() {
+            curve.add_key(CurveKey::new(time, components[i], kind));
+        }
+    }
+
+    /// Returns curves of the track, there is one curve per component of the value.
+    pub fn curves(&self) -> &[Curve] {
+        &self.curves
+    }
+
+    /// Returns curves of the track, there is one curve per component of the value.
+    pub fn curves_mut(&mut self) -> &mut [Curve] {
+        &mut self.curves
+    }
+
+    /// Sets new node the track is bound to.
+    pub fn set_node(&mut self, node: Handle<Node>) {
+        self.node = node;
+    }
+
+    /// Returns node the track is bound to.
+    pub fn node(&self) -> Handle<Node> {
+        self.node
+    }
+
+    /// Returns animated property.
+    pub fn property(&self) -> NodeProperty {
+        self.property
+    }
+
+    /// Enables or disables the track.
+    pub fn enable(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns true if the track is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns time of the last key of the track.
+    pub fn max_time(&self) -> f32 {
+        self.curves
+            .iter()
+            .map(|curve| curve.max_location())
+            .fold(0.0, f32::max)
+    }
+
+    /// Calculates value of the property at given time. Returns `None` if track has no keys.
+    pub fn fetch(&self, time: f32) -> Option<PropertyValue> {
+        if self.curves.iter().all(|curve| curve.is_empty()) {
+            return None;
+        }
+
+        let component = |i: usize| self.curves.get(i).map_or(0.0, |curve| curve.fetch(time));
+
+        Some(match self.property.value_kind() {
+            PropertyValueKind::Float => PropertyValue::Float(component(0)),
+            PropertyValueKind::Bool => PropertyValue::Bool(component(0) >= 0.5),
+            PropertyValueKind::Color => PropertyValue::Color(Color::from(Vector4::new(
+                component(0),
+                component(1),
+                component(2),
+                component(3),
+            ))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::property::{NodeProperty, PropertyTrack, PropertyValue},
+        core::{color::Color, curve::CurveKeyKind, pool::Handle},
+    };
+
+    #[test]
+    fn test_property_track_fetch() {
+        let mut track = PropertyTrack::new(Handle::NONE, NodeProperty::CameraFov);
+        assert_eq!(track.fetch(0.0), None);
+        track.add_key(0.0, PropertyValue::Float(1.0), CurveKeyKind::Linear);
+        track.add_key(2.0, PropertyValue::Float(2.0), CurveKeyKind::Linear);
+        assert_eq!(track.fetch(1.0), Some(PropertyValue::Float(1.5)));
+        assert_eq!(track.max_time(), 2.0);
+
+        let mut track = PropertyTrack::new(Handle::NONE, NodeProperty::Visibility);
+        track.add_key(0.0, PropertyValue::Bool(true), CurveKeyKind::Linear);
+        track.add_key(1.0, PropertyValue::Bool(false), CurveKeyKind::Linear);
+        assert_eq!(track.fetch(0.9), Some(PropertyValue::Bool(true)));
+        assert_eq!(track.fetch(1.0), Some(PropertyValue::Bool(false)));
+
+        let mut track = PropertyTrack::new(Handle::NONE, NodeProperty::SpriteColor);
+        track.add_key(
+            0.0,
+            PropertyValue::Color(Color::WHITE),
+            CurveKeyKind::Constant,
+        );
+        assert_eq!(track.fetch(1.0), Some(PropertyValue::Color(Color::WHITE)));
+    }
+}
@@ -0,0 +1,194 @@
+//! Root motion allows to extract movement of root node of a character from its animations,
+//! so it can be applied to a rigid body (or anything else) by gameplay code instead of
+//! sliding root node in the scene graph.
+//!
+//! When root motion is enabled for an animation (see
+//! [`Animation::set_root_motion_settings`](crate::animation::Animation::set_root_motion_settings)),
+//! translation and rotation of root node are stripped from the pose of the animation on every
+//! update, and the difference between previous and current state of the root node is stored
+//! in the pose as [`RootMotion`]. Root motion is blended together with poses, so final pose of
+//! animation machine contains root motion blended from every active animation.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use rg3d::{
+//!     animation::machine::Machine,
+//!     core::pool::Handle,
+//!     scene::{node::Node, Scene},
+//! };
+//!
+//! fn update_character(scene: &mut Scene, machine: &mut Machine, model: Handle<Node>, dt: f32) {
+//!     let pose = machine.evaluate_pose(&scene.animations, dt);
+//!     pose.apply(&mut scene.graph);
+//!
+//!     if let Some(root_motion) = pose.root_motion() {
+//!         // Delta position is in local coordinates of parent of root node, which is usually
+//!         // the model itself.
+//!         let velocity = scene.graph[model]
+//!             .global_transform()
+//!             .transform_vector(&root_motion.delta_position)
+//!             .scale(1.0 / dt);
+//!
+//!         if let Some(body) = scene.physics_binder.body_of(model) {
+//!             if let Some(body) = scene.physics.body_mut(body) {
+//!                 body.set_linvel(velocity, true);
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        pool::Handle,
+        visitor::prelude::*,
+    },
+    scene::node::Node,
+};
+
+/// Defines which node of an animation is root node and which parts of its motion should be
+/// extracted. Ignored parts are left in the pose of the animation.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub struct RootMotionSettings {
+    /// Root node of a character, usually it is hips bone.
+    pub node: Handle<Node>,
+    /// Keep movement along X axis in the pose.
+    pub ignore_x_movement: bool,
+    /// Keep movement along Y axis in the pose.
+    pub ignore_y_movement: bool,
+    /// Keep movement along Z axis in the pose.
+    pub ignore_z_movement: bool,
+    /// Keep rotation in the pose.
+    pub ignore_rotations: bool,
+}
+
+impl Default for RootMotionSettings {
+    fn default() -> Self {
+        Self::new(Handle::NONE)
+    }
+}
+
+impl RootMotionSettings {
+    /// Creates new settings that extract horizontal movement and rotation of given node.
+    /// Vertical movement is kept in the pose, because it is usually just a bobbing of a
+    /// character.
+    pub fn new(node: Handle<Node>) -> Self {
+        Self {
+            node,
+            ignore_x_movement: false,
+            ignore_y_movement: true,
+            ignore_z_movement: false,
+            ignore_rotations: false,
+        }
+    }
+
+    fn mask(&self) -> Vector3<f32> {
+        let axis = |ignore: bool| if ignore { 0.0 } else { 1.0 };
+        Vector3::new(
+            axis(self.ignore_x_movement),
+            axis(self.ignore_y_movement),
+            axis(self.ignore_z_movement),
+        )
+    }
+
+    /// Returns a part of given offset that should be extracted.
+    pub(in crate) fn extracted(&self, offset: Vector3<f32>) -> Vector3<f32> {
+        offset.component_mul(&self.mask())
+    }
+
+    /// Returns a part of given offset that should be kept in the pose.
+    pub(in crate) fn kept(&self, offset: Vector3<f32>) -> Vector3<f32> {
+        offset - self.extracted(offset)
+    }
+}
+
+/// Motion of root node between two updates of an animation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RootMotion {
+    /// Movement of root node in local coordinates of its parent.
+    pub delta_position: Vector3<f32>,
+    /// Rotation of root node relative to its previous rotation.
+    pub delta_rotation: UnitQuaternion<f32>,
+}
+
+impl Default for RootMotion {
+    fn default() -> Self {
+        Self {
+            delta_position: Default::default(),
+            delta_rotation: UnitQuaternion::identity(),
+        }
+    }
+}
+
+impl RootMotion {
+    /// Combines two consequent motions.
+    pub fn then(&self, other: &RootMotion) -> Self {
+        Self {
+            delta_position: self.delta_position + other.delta_position,
+            delta_rotation: self.delta_rotation * other.delta_rotation,
+        }
+    }
+
+    pub(in crate) fn weighted(&self, weight: f32) -> Self {
+        Self {
+            delta_position: self.delta_position.scale(weight),
+            delta_rotation: UnitQuaternion::identity().nlerp(&self.delta_rotation, weight),
+        }
+    }
+
+    pub(in crate) fn blend_with(&mut self, other: &RootMotion, weight: f32) {
+        self.delta_position += other.delta_position.scale(weight);
+        self.delta_rotation = self.delta_rotation.nlerp(&other.delta_rotation, weight);
+    }
+
+    pub(in crate) fn lerp(&mut self, other: &RootMotion, t: f32) {
+        self.delta_position = self.delta_position.lerp(&other.delta_position, t);
+        self.delta_rotation = self.delta_rotation.nlerp(&other.delta_rotation, t);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{root_motion::RootMotionSettings, Animation, KeyFrame, Track},
+        core::{
+            algebra::{UnitQuaternion, Vector3},
+            pool::Handle,
+        },
+    };
+
+    #[test]
+    fn test_root_motion_extraction() {
+        let node = Handle::new(1, 1);
+        let mut track = Track::new();
+        track.set_node(node);
+        for (time, z) in [(0.0, 0.0), (1.0, 2.0)].iter() {
+            track.add_key_frame(KeyFrame::new(
+                *time,
+                Vector3::new(0.0, 1.0, *z),
+                Vector3::new(1.0, 1.0, 1.0),
+                UnitQuaternion::identity(),
+            ));
+        }
+
+        let mut animation = Animation::default();
+        animation.add_track(track);
+        animation.set_root_motion_settings(Some(RootMotionSettings::new(node)));
+
+        animation.tick(0.25);
+        let motion = animation.root_motion().unwrap();
+        assert!((motion.delta_position - Vector3::new(0.0, 0.0, 0.5)).norm() < 1.0e-5);
+        assert_eq!(
+            animation.get_pose().local_poses[&node].position,
+            Vector3::new(0.0, 1.0, 0.0)
+        );
+
+        // Wrapping around the end of looped animation.
+        animation.set_time_position(0.9);
+        animation.tick(0.2);
+        let motion = animation.root_motion().unwrap();
+        assert!((motion.delta_position - Vector3::new(0.0, 0.0, 0.4)).norm() < 1.0e-5);
+    }
+}
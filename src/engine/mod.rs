@@ -184,6 +184,9 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
 
         self.resource_manager.state().update(dt);
 
+        let gpu_particle_simulation_supported =
+            self.renderer.is_gpu_particle_simulation_supported();
+
         for scene in self.scenes.iter_mut().filter(|s| s.enabled) {
             let frame_size = scene.render_target.as_ref().map_or(window_size, |rt| {
                 if let TextureKind::Rectangle { width, height } = rt.data_ref().kind {
@@ -193,7 +196,7 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
                 }
             });
 
-            scene.update(frame_size, dt);
+            scene.update(frame_size, dt, gpu_particle_simulation_supported);
         }
 
         for scene in self.scenes2d.iter_mut().filter(|s| s.enabled) {
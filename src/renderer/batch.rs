@@ -16,13 +16,17 @@ use crate::{
 };
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap},
     fmt::{Debug, Formatter},
+    hash::{Hash, Hasher},
     rc::Rc,
     sync::{Arc, RwLock},
 };
 
 pub const BONE_MATRICES_COUNT: usize = 64;
+// Weights are passed as uniforms, so amount of them is limited by amount of uniform components
+// available in vertex shader. Must be multiple of four, weights are packed in vectors.
+pub const BLEND_SHAPE_WEIGHTS_COUNT: usize = 16;
 
 #[repr(C)]
 #[doc(hidden)]
@@ -37,6 +41,7 @@ pub struct SurfaceInstance {
     pub owner: Handle<Node>,
     pub world_transform: Matrix4<f32>,
     pub bone_matrices: ArrayVec<Matrix4<f32>, BONE_MATRICES_COUNT>,
+    pub blend_shape_weights: ArrayVec<f32, BLEND_SHAPE_WEIGHTS_COUNT>,
     pub color: Color,
     pub depth_offset: f32,
 }
@@ -113,7 +118,17 @@ impl BatchStorage {
                 };
 
                 let data = surface.data();
-                let key = surface.batch_id();
+                let has_blend_shapes = !mesh.blend_shape_weights().is_empty()
+                    && !data.read().unwrap().blend_shapes().is_empty();
+                let key = if has_blend_shapes {
+                    // Weights of blend shapes are per-mesh, such surfaces can't be instanced.
+                    let mut hasher = DefaultHasher::new();
+                    surface.batch_id().hash(&mut hasher);
+                    handle.hash(&mut hasher);
+                    hasher.finish()
+                } else {
+                    surface.batch_id()
+                };
 
                 let diffuse_texture = surface
                     .diffuse_texture_ref()
@@ -185,6 +200,15 @@ impl BatchStorage {
                             bone_node.global_transform() * bone_node.inv_bind_pose_transform()
                         })
                         .collect(),
+                    blend_shape_weights: if has_blend_shapes {
+                        mesh.blend_shape_weights()
+                            .iter()
+                            .take(BLEND_SHAPE_WEIGHTS_COUNT)
+                            .copied()
+                            .collect()
+                    } else {
+                        Default::default()
+                    },
                     color: surface.color(),
                     owner: handle,
                     depth_offset: mesh.depth_offset_factor(),
@@ -0,0 +1,86 @@
+//! Blend shapes are applied in vertex shaders of every renderer that draws meshes, so geometry
+//! in GBuffer, shadow maps and forward pass always matches. This module contains shader code
+//! and uniforms shared between such renderers.
+
+use crate::{
+    core::algebra::Vector4,
+    renderer::{
+        batch::BLEND_SHAPE_WEIGHTS_COUNT,
+        cache::BlendShapeStorage,
+        framework::{
+            error::FrameworkError,
+            gpu_program::{GpuProgram, GpuProgramBinding, UniformLocation},
+            state::PipelineState,
+        },
+    },
+};
+
+/// Returns declarations of blend shape uniforms and `MorphVertex` and `MorphPosition`
+/// functions, that apply blend shapes to a vertex.
+pub(in crate) fn blend_shape_source() -> String {
+    // Four weights per vector, so 16 weights take 16 uniform components. Together with 60 bone
+    // matrices it fits into 1024 components guaranteed by GL 3.3.
+    let mut source = format!(
+        "uniform vec4 blendShapeWeights[{}];\n",
+        BLEND_SHAPE_WEIGHTS_COUNT / 4
+    );
+    source += include_str!("shaders/blend_shape.glsl");
+    source
+}
+
+/// Inserts blend shape code (see [`blend_shape_source`]) right after `#version` directive of
+/// given vertex shader source.
+pub(in crate) fn include_blend_shapes(source: &str) -> String {
+    let mut full = source.to_owned();
+    let end = full
+        .find('#')
+        .and_then(|p| full[p..].find('\n').map(|n| p + n + 1))
+        .unwrap_or(0);
+    full.insert_str(end, &blend_shape_source());
+    full
+}
+
+pub(in crate) struct BlendShapeUniforms {
+    storage: UniformLocation,
+    storage_width: UniformLocation,
+    vertex_count: UniformLocation,
+    count: UniformLocation,
+    weights: UniformLocation,
+}
+
+impl BlendShapeUniforms {
+    pub fn new(state: &mut PipelineState, program: &GpuProgram) -> Result<Self, FrameworkError> {
+        Ok(Self {
+            storage: program.uniform_location(state, "blendShapeStorage")?,
+            storage_width: program.uniform_location(state, "blendShapeStorageWidth")?,
+            vertex_count: program.uniform_location(state, "blendShapeVertexCount")?,
+            count: program.uniform_location(state, "blendShapeCount")?,
+            weights: program.uniform_location(state, "blendShapeWeights")?,
+        })
+    }
+
+    /// Binds blend shapes of a surface with weights of a mesh instance.
+    pub fn bind<'a>(
+        &self,
+        program_binding: GpuProgramBinding<'a>,
+        storage: &BlendShapeStorage,
+        weights: &[f32],
+    ) -> GpuProgramBinding<'a> {
+        let count = storage
+            .blend_shape_count
+            .min(weights.len())
+            .min(BLEND_SHAPE_WEIGHTS_COUNT);
+
+        let mut packed = [Vector4::default(); BLEND_SHAPE_WEIGHTS_COUNT / 4];
+        for (i, weight) in weights[..count].iter().enumerate() {
+            packed[i / 4][i % 4] = *weight;
+        }
+
+        program_binding
+            .set_texture(&self.storage, &storage.texture)
+            .set_integer(&self.storage_width, storage.width)
+            .set_integer(&self.vertex_count, storage.vertex_count)
+            .set_integer(&self.count, count as i32)
+            .set_vector4_slice(&self.weights, &packed)
+    }
+}
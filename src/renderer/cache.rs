@@ -1,14 +1,18 @@
 use crate::{
-    core::scope_profile,
+    core::{algebra::Vector4, scope_profile},
     engine::resource_manager::TimedEntry,
     renderer::{
         batch::InstanceData,
         framework::{
+            error::FrameworkError,
             geometry_buffer::{
                 AttributeDefinition, AttributeKind, BufferBuilder, ElementKind, GeometryBuffer,
                 GeometryBufferBuilder, GeometryBufferKind,
             },
-            gpu_texture::{Coordinate, GpuTexture, PixelKind},
+            gpu_texture::{
+                Coordinate, GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter,
+                PixelKind,
+            },
             state::PipelineState,
         },
     },
@@ -111,6 +115,167 @@ impl GeometryCache {
     }
 }
 
+/// Max width of a texture with offsets of blend shapes.
+pub(in crate) const BLEND_SHAPE_STORAGE_WIDTH: usize = 1024;
+
+/// Stores offsets of blend shapes of surfaces in textures. Every vertex of every blend shape
+/// takes three RGBA32F pixels - offset of position, normal and tangent. Offsets of first blend
+/// shape goes first, then offsets of second and so on.
+pub(in crate) struct BlendShapeCache {
+    map: HashMap<usize, TimedEntry<Rc<RefCell<GpuTexture>>>>,
+    // Bound instead of blend shape storage when a surface has no blend shapes.
+    dummy: Rc<RefCell<GpuTexture>>,
+}
+
+/// Blend shapes of a surface that are ready to be bound to a shader.
+pub(in crate) struct BlendShapeStorage {
+    pub texture: Rc<RefCell<GpuTexture>>,
+    pub width: i32,
+    pub vertex_count: i32,
+    pub blend_shape_count: usize,
+}
+
+impl BlendShapeCache {
+    pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        Ok(Self {
+            map: Default::default(),
+            dummy: Rc::new(RefCell::new(GpuTexture::new(
+                state,
+                GpuTextureKind::Rectangle {
+                    width: 1,
+                    height: 1,
+                },
+                PixelKind::RGBA32F,
+                MinificationFilter::Nearest,
+                MagnificationFilter::Nearest,
+                1,
+                Some(&[0u8; 16][..]),
+            )?)),
+        })
+    }
+
+    /// Returns blend shapes of given surface, surfaces without blend shapes get an empty
+    /// storage which does not change vertices.
+    pub fn storage(&mut self, state: &mut PipelineState, data: &SurfaceData) -> BlendShapeStorage {
+        match self.get(state, data) {
+            Some(texture) => {
+                let width = match texture.borrow().kind() {
+                    GpuTextureKind::Rectangle { width, .. } => width as i32,
+                    _ => unreachable!(),
+                };
+                BlendShapeStorage {
+                    texture,
+                    width,
+                    vertex_count: data.vertex_buffer().vertex_count() as i32,
+                    blend_shape_count: data.blend_shapes().len(),
+                }
+            }
+            None => BlendShapeStorage {
+                texture: self.dummy.clone(),
+                width: 1,
+                vertex_count: 0,
+                blend_shape_count: 0,
+            },
+        }
+    }
+
+    fn get(
+        &mut self,
+        state: &mut PipelineState,
+        data: &SurfaceData,
+    ) -> Option<Rc<RefCell<GpuTexture>>> {
+        scope_profile!();
+
+        if data.blend_shapes().is_empty() {
+            return None;
+        }
+
+        let key = (data as *const _) as usize;
+
+        let entry = match self.map.entry(key) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let vertex_count = data.vertex_buffer().vertex_count() as usize;
+
+                let mut pixels = Vec::with_capacity(data.blend_shapes().len() * vertex_count * 3);
+                for blend_shape in data.blend_shapes() {
+                    for i in 0..vertex_count {
+                        let delta = blend_shape.deltas().get(i).copied().unwrap_or_default();
+                        pixels.push(Vector4::new(
+                            delta.position.x,
+                            delta.position.y,
+                            delta.position.z,
+                            0.0,
+                        ));
+                        pixels.push(Vector4::new(
+                            delta.normal.x,
+                            delta.normal.y,
+                            delta.normal.z,
+                            0.0,
+                        ));
+                        pixels.push(Vector4::new(
+                            delta.tangent.x,
+                            delta.tangent.y,
+                            delta.tangent.z,
+                            0.0,
+                        ));
+                    }
+                }
+
+                let width = pixels.len().min(BLEND_SHAPE_STORAGE_WIDTH).max(1);
+                let height = (pixels.len() as f32 / width as f32).ceil().max(1.0) as usize;
+                // Pad data to actual size.
+                pixels.resize(width * height, Vector4::default());
+
+                let gpu_texture = match GpuTexture::new(
+                    state,
+                    GpuTextureKind::Rectangle { width, height },
+                    PixelKind::RGBA32F,
+                    MinificationFilter::Nearest,
+                    MagnificationFilter::Nearest,
+                    1,
+                    Some(unsafe {
+                        std::slice::from_raw_parts(
+                            pixels.as_ptr() as *const u8,
+                            pixels.len() * std::mem::size_of::<Vector4<f32>>(),
+                        )
+                    }),
+                ) {
+                    Ok(texture) => texture,
+                    Err(e) => {
+                        Log::writeln(
+                            MessageKind::Error,
+                            format!("Failed to create blend shape storage. Reason: {:?}", e),
+                        );
+                        return None;
+                    }
+                };
+
+                e.insert(TimedEntry {
+                    value: Rc::new(RefCell::new(gpu_texture)),
+                    time_to_live: 20.0,
+                })
+            }
+        };
+
+        entry.time_to_live = 20.0;
+        Some(entry.value.clone())
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        scope_profile!();
+
+        for entry in self.map.values_mut() {
+            entry.time_to_live -= dt;
+        }
+        self.map.retain(|_, v| v.time_to_live > 0.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
 #[derive(Default)]
 pub(in crate) struct TextureCache {
     pub(super) map: HashMap<usize, TimedEntry<Rc<RefCell<GpuTexture>>>>,
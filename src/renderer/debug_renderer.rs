@@ -2,9 +2,14 @@
 //! in its name its purpose - output debug information. It can be used to render collision
 //! shapes, contact information (normals, positions, etc.), paths build by navmesh and so
 //! on. It contains implementations to draw most common shapes (line, box, oob, frustum, etc).
+//! It is used for both 3D and 2D scenes, in latter case lines are drawn in XY plane.
 
 use crate::{
-    core::{algebra::Vector3, math::Rect, scope_profile},
+    core::{
+        algebra::{Matrix4, Vector3},
+        math::Rect,
+        scope_profile,
+    },
     renderer::framework::{
         error::FrameworkError,
         framebuffer::{CullFace, DrawParameters, FrameBuffer},
@@ -16,7 +21,7 @@ use crate::{
         state::PipelineState,
     },
     renderer::RenderPassStatistics,
-    scene::{camera::Camera, SceneDrawingContext},
+    scene::SceneDrawingContext,
 };
 
 #[repr(C)]
@@ -85,7 +90,8 @@ impl DebugRenderer {
         viewport: Rect<i32>,
         framebuffer: &mut FrameBuffer,
         drawing_context: &SceneDrawingContext,
-        camera: &Camera,
+        view_projection: &Matrix4<f32>,
+        depth_test: bool,
     ) -> RenderPassStatistics {
         scope_profile!();
 
@@ -122,12 +128,11 @@ impl DebugRenderer {
                 color_write: Default::default(),
                 depth_write: false,
                 stencil_test: false,
-                depth_test: true,
+                depth_test,
                 blend: false,
             },
             |program_binding| {
-                program_binding
-                    .set_matrix4(&self.shader.wvp_matrix, &camera.view_projection_matrix());
+                program_binding.set_matrix4(&self.shader.wvp_matrix, view_projection);
             },
         );
 
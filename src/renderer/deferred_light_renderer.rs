@@ -16,6 +16,7 @@ use crate::{
     },
     renderer::{
         batch::BatchStorage,
+        cache::BlendShapeCache,
         flat_shader::FlatShader,
         gbuffer::GBuffer,
         light_volume::LightVolumeRenderer,
@@ -256,6 +257,7 @@ pub(in crate) struct DeferredRendererContext<'a> {
     pub settings: &'a QualitySettings,
     pub textures: &'a mut TextureCache,
     pub geometry_cache: &'a mut GeometryCache,
+    pub blend_shape_cache: &'a mut BlendShapeCache,
     pub batch_storage: &'a BatchStorage,
 }
 
@@ -475,6 +477,7 @@ impl DeferredLightRenderer {
             settings,
             textures,
             geometry_cache,
+            blend_shape_cache,
             batch_storage,
         } = args;
 
@@ -698,6 +701,7 @@ impl DeferredLightRenderer {
                             &light_view_projection,
                             batch_storage,
                             geometry_cache,
+                            blend_shape_cache,
                             cascade_index,
                         );
 
@@ -717,6 +721,7 @@ impl DeferredLightRenderer {
                                     light_pos: light_position,
                                     light_radius,
                                     geom_cache: geometry_cache,
+                                    blend_shape_cache,
                                     cascade: cascade_index,
                                     batch_storage,
                                 });
@@ -14,7 +14,12 @@ use crate::{
         gpu_program::{GpuProgram, UniformLocation},
         state::PipelineState,
     },
-    renderer::{batch::BatchStorage, GeometryCache, RenderPassStatistics},
+    renderer::{
+        batch::BatchStorage,
+        blend_shape::{include_blend_shapes, BlendShapeUniforms},
+        cache::BlendShapeCache,
+        GeometryCache, RenderPassStatistics,
+    },
     scene::{camera::Camera, mesh::RenderPath},
 };
 
@@ -25,20 +30,22 @@ pub struct Shader {
     pub color: UniformLocation,
     pub use_skeletal_animation: UniformLocation,
     pub bone_matrices: UniformLocation,
+    blend_shapes: BlendShapeUniforms,
 }
 
 impl Shader {
     pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
         let fragment_source = include_str!("shaders/forward_fs.glsl");
-        let vertex_source = include_str!("shaders/forward_vs.glsl");
+        let vertex_source = include_blend_shapes(include_str!("shaders/forward_vs.glsl"));
         let program =
-            GpuProgram::from_source(state, "ForwardShader", vertex_source, fragment_source)?;
+            GpuProgram::from_source(state, "ForwardShader", &vertex_source, fragment_source)?;
         Ok(Self {
             wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
             diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
             color: program.uniform_location(state, "color")?,
             use_skeletal_animation: program.uniform_location(state, "useSkeletalAnimation")?,
             bone_matrices: program.uniform_location(state, "boneMatrices")?,
+            blend_shapes: BlendShapeUniforms::new(state, &program)?,
             program,
         })
     }
@@ -52,6 +59,7 @@ pub(in crate) struct ForwardRenderContext<'a, 'b> {
     pub state: &'a mut PipelineState,
     pub camera: &'b Camera,
     pub geom_cache: &'a mut GeometryCache,
+    pub blend_shape_cache: &'a mut BlendShapeCache,
     pub batch_storage: &'a BatchStorage,
     pub framebuffer: &'a mut FrameBuffer,
     pub viewport: Rect<i32>,
@@ -73,6 +81,7 @@ impl ForwardRenderer {
             state,
             camera,
             geom_cache,
+            blend_shape_cache,
             batch_storage,
             framebuffer,
             viewport,
@@ -99,6 +108,7 @@ impl ForwardRenderer {
         {
             let data = batch.data.read().unwrap();
             let geometry = geom_cache.get(state, &data);
+            let blend_shapes = blend_shape_cache.storage(state, &data);
 
             for instance in batch.instances.iter() {
                 if camera.visibility_cache.is_visible(instance.owner) {
@@ -117,7 +127,7 @@ impl ForwardRenderer {
                         &self.shader.program,
                         &params,
                         |program_binding| {
-                            program_binding
+                            let program_binding = program_binding
                                 .set_texture(&self.shader.diffuse_texture, &batch.diffuse_texture)
                                 .set_matrix4(
                                     &self.shader.wvp_matrix,
@@ -129,6 +139,11 @@ impl ForwardRenderer {
                                     &self.shader.bone_matrices,
                                     instance.bone_matrices.as_slice(),
                                 );
+                            self.shader.blend_shapes.bind(
+                                program_binding,
+                                &blend_shapes,
+                                &instance.blend_shape_weights,
+                            );
                         },
                     );
                 }
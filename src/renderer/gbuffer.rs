@@ -13,6 +13,8 @@ use crate::{
     },
     renderer::{
         batch::{BatchStorage, InstanceData, MatrixStorage, BONE_MATRICES_COUNT},
+        blend_shape::{blend_shape_source, BlendShapeUniforms},
+        cache::BlendShapeCache,
         GeometryCache, RenderPassStatistics, TextureCache,
     },
     scene::{camera::Camera, mesh::RenderPath},
@@ -108,6 +110,8 @@ fn make_vertex_shader_source(instancing: bool, lightmaps: bool) -> String {
             uniform mat4 worldViewProjection;
             uniform mat4 boneMatrices[60];
         "#;
+
+        source += &blend_shape_source();
     }
 
     source += r#"
@@ -138,10 +142,23 @@ fn make_vertex_shader_source(instancing: bool, lightmaps: bool) -> String {
             vec4 localPosition = vec4(0);
             vec3 localNormal = vec3(0);
             vec3 localTangent = vec3(0);
-    
+
+            vec3 morphedPosition = vertexPosition;
+            vec3 morphedNormal = vertexNormal;
+            vec3 morphedTangent = vertexTangent.xyz;
+            "#;
+
+    if !instancing {
+        // Blend shapes are applied before skinning.
+        source += r#"
+            MorphVertex(morphedPosition, morphedNormal, morphedTangent);
+            "#;
+    }
+
+    source += r#"
             if (useSkeletalAnimation)
             {
-                vec4 vertex = vec4(vertexPosition, 1.0);
+                vec4 vertex = vec4(morphedPosition, 1.0);
     
                 int i0 = int(boneIndices.x);
                 int i1 = int(boneIndices.y);
@@ -173,21 +190,21 @@ fn make_vertex_shader_source(instancing: bool, lightmaps: bool) -> String {
                 localPosition += m2 * vertex * boneWeights.z;
                 localPosition += m3 * vertex * boneWeights.w;
                 
-                localNormal += mat3(m0) * vertexNormal * boneWeights.x;
-                localNormal += mat3(m1) * vertexNormal * boneWeights.y;
-                localNormal += mat3(m2) * vertexNormal * boneWeights.z;
-                localNormal += mat3(m3) * vertexNormal * boneWeights.w;
+                localNormal += mat3(m0) * morphedNormal * boneWeights.x;
+                localNormal += mat3(m1) * morphedNormal * boneWeights.y;
+                localNormal += mat3(m2) * morphedNormal * boneWeights.z;
+                localNormal += mat3(m3) * morphedNormal * boneWeights.w;
                 
-                localTangent += mat3(m0) * vertexTangent.xyz * boneWeights.x;
-                localTangent += mat3(m1) * vertexTangent.xyz * boneWeights.y;
-                localTangent += mat3(m2) * vertexTangent.xyz * boneWeights.z;
-                localTangent += mat3(m3) * vertexTangent.xyz * boneWeights.w;             
+                localTangent += mat3(m0) * morphedTangent * boneWeights.x;
+                localTangent += mat3(m1) * morphedTangent * boneWeights.y;
+                localTangent += mat3(m2) * morphedTangent * boneWeights.z;
+                localTangent += mat3(m3) * morphedTangent * boneWeights.w;             
             }
             else
             {
-                localPosition = vec4(vertexPosition, 1.0);
-                localNormal = vertexNormal;
-                localTangent = vertexTangent.xyz;
+                localPosition = vec4(morphedPosition, 1.0);
+                localNormal = morphedNormal;
+                localTangent = morphedTangent;
             }
 
             mat3 nm = mat3(worldMatrix);
@@ -329,6 +346,7 @@ struct UberShader {
     wvp_matrix: Option<UniformLocation>,
     bone_matrices: Option<UniformLocation>,
     diffuse_color: Option<UniformLocation>,
+    blend_shapes: Option<BlendShapeUniforms>,
 }
 
 impl UberShader {
@@ -401,6 +419,11 @@ impl UberShader {
             } else {
                 None
             },
+            blend_shapes: if !instancing {
+                Some(BlendShapeUniforms::new(state, &program)?)
+            } else {
+                None
+            },
             program,
         })
     }
@@ -423,6 +446,7 @@ pub(in crate) struct GBufferRenderContext<'a, 'b> {
     pub state: &'a mut PipelineState,
     pub camera: &'b Camera,
     pub geom_cache: &'a mut GeometryCache,
+    pub blend_shape_cache: &'a mut BlendShapeCache,
     pub batch_storage: &'a BatchStorage,
     pub texture_cache: &'a mut TextureCache,
     pub environment_dummy: Rc<RefCell<GpuTexture>>,
@@ -599,6 +623,7 @@ impl GBuffer {
             state,
             camera,
             geom_cache,
+            blend_shape_cache,
             batch_storage,
             texture_cache,
             environment_dummy,
@@ -635,6 +660,13 @@ impl GBuffer {
             let geometry = geom_cache.get(state, &data);
             let use_instanced_rendering = batch.instances.len() > 1;
 
+            // Surfaces with blend shapes are never instanced, see batch generation.
+            let blend_shapes = if use_instanced_rendering {
+                None
+            } else {
+                Some(blend_shape_cache.storage(state, &data))
+            };
+
             let environment = match camera.environment_ref() {
                 Some(texture) => texture_cache.get(state, texture).unwrap(),
                 None => environment_dummy.clone(),
@@ -747,7 +779,7 @@ impl GBuffer {
                         } else {
                             initial_view_projection
                         };
-                        program_binding
+                        let program_binding = program_binding
                             .set_color(shader.diffuse_color.as_ref().unwrap(), &instance.color)
                             .set_matrix4(
                                 shader.wvp_matrix.as_ref().unwrap(),
@@ -761,6 +793,11 @@ impl GBuffer {
                                 shader.world_matrix.as_ref().unwrap(),
                                 &instance.world_transform,
                             );
+                        shader.blend_shapes.as_ref().unwrap().bind(
+                            program_binding,
+                            blend_shapes.as_ref().unwrap(),
+                            &instance.blend_shape_weights,
+                        );
                     }
                 };
 
@@ -0,0 +1,627 @@
+//! GPU simulation of particle systems.
+//!
+//! State of particles is stored in floating-point textures, every simulation step renders
+//! new state into another set of textures and then sets are swapped (ping-pong). Particles
+//! are spawned on CPU by emitters of a particle system, they're uploaded into spawn textures
+//! and written into a ring buffer of particles by simulation shader. Particles are drawn
+//! using instancing, vertex shader fetches state of a particle by instance index.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Vector2, Vector3, Vector4},
+        color::Color,
+        curve::Curve,
+        math::{Matrix4Ext, Rect, TriangleDefinition},
+        scope_profile,
+    },
+    engine::resource_manager::{TimedEntry, DEFAULT_RESOURCE_LIFETIME},
+    renderer::{
+        framework::{
+            error::FrameworkError,
+            framebuffer::{Attachment, AttachmentKind, CullFace, DrawParameters, FrameBuffer},
+            geometry_buffer::{
+                AttributeDefinition, AttributeKind, BufferBuilder, ElementKind, GeometryBuffer,
+                GeometryBufferBuilder, GeometryBufferKind,
+            },
+            gpu_program::{GpuProgram, UniformLocation},
+            gpu_texture::{
+                Coordinate, GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter,
+                PixelKind, WrapMode,
+            },
+            state::PipelineState,
+        },
+        RenderPassStatistics,
+    },
+    scene::{
+        camera::Camera,
+        graph::Graph,
+        node::Node,
+        particle_system::{ParticleFrameMode, ParticleSystem},
+    },
+    utils::array_as_u8_slice,
+};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Width of every state texture, height depends on capacity of a particle system.
+const STATE_TEXTURE_WIDTH: usize = 256;
+/// Max amount of particles per particle system.
+const MAX_CAPACITY: usize = STATE_TEXTURE_WIDTH * 4096;
+/// Amount of samples of curves and color gradient.
+const LOOKUP_TEXTURE_WIDTH: usize = 64;
+
+struct SimulationShader {
+    program: GpuProgram,
+    position_age_texture: UniformLocation,
+    velocity_lifetime_texture: UniformLocation,
+    size_rotation_texture: UniformLocation,
+    spawn_position_age_texture: UniformLocation,
+    spawn_velocity_lifetime_texture: UniformLocation,
+    spawn_size_rotation_texture: UniformLocation,
+    lookup_texture: UniformLocation,
+    texture_width: UniformLocation,
+    capacity: UniformLocation,
+    spawn_start: UniformLocation,
+    spawn_count: UniformLocation,
+    dt: UniformLocation,
+    acceleration: UniformLocation,
+}
+
+impl SimulationShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let vertex_source = include_str!("shaders/gpu_particle_simulation_vs.glsl");
+        let fragment_source = include_str!("shaders/gpu_particle_simulation_fs.glsl");
+        let program = GpuProgram::from_source(
+            state,
+            "GpuParticleSimulationShader",
+            vertex_source,
+            fragment_source,
+        )?;
+        Ok(Self {
+            position_age_texture: program.uniform_location(state, "positionAgeTexture")?,
+            velocity_lifetime_texture: program
+                .uniform_location(state, "velocityLifetimeTexture")?,
+            size_rotation_texture: program.uniform_location(state, "sizeRotationTexture")?,
+            spawn_position_age_texture: program
+                .uniform_location(state, "spawnPositionAgeTexture")?,
+            spawn_velocity_lifetime_texture: program
+                .uniform_location(state, "spawnVelocityLifetimeTexture")?,
+            spawn_size_rotation_texture: program
+                .uniform_location(state, "spawnSizeRotationTexture")?,
+            lookup_texture: program.uniform_location(state, "lookupTexture")?,
+            texture_width: program.uniform_location(state, "textureWidth")?,
+            capacity: program.uniform_location(state, "capacity")?,
+            spawn_start: program.uniform_location(state, "spawnStart")?,
+            spawn_count: program.uniform_location(state, "spawnCount")?,
+            dt: program.uniform_location(state, "dt")?,
+            acceleration: program.uniform_location(state, "acceleration")?,
+            program,
+        })
+    }
+}
+
+struct RenderShader {
+    program: GpuProgram,
+    position_age_texture: UniformLocation,
+    velocity_lifetime_texture: UniformLocation,
+    size_rotation_texture: UniformLocation,
+    lookup_texture: UniformLocation,
+    texture_width: UniformLocation,
+    view_projection_matrix: UniformLocation,
+    world_matrix: UniformLocation,
+    camera_side_vector: UniformLocation,
+    camera_up_vector: UniformLocation,
+    sheet_size: UniformLocation,
+    frame_mode: UniformLocation,
+    frame_rate: UniformLocation,
+    random_start_frame: UniformLocation,
+    diffuse_texture: UniformLocation,
+    depth_buffer_texture: UniformLocation,
+    inv_screen_size: UniformLocation,
+    proj_params: UniformLocation,
+}
+
+impl RenderShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let vertex_source = include_str!("shaders/gpu_particle_system_vs.glsl");
+        let fragment_source = include_str!("shaders/particle_system_fs.glsl");
+        let program = GpuProgram::from_source(
+            state,
+            "GpuParticleSystemShader",
+            vertex_source,
+            fragment_source,
+        )?;
+        Ok(Self {
+            position_age_texture: program.uniform_location(state, "positionAgeTexture")?,
+            velocity_lifetime_texture: program
+                .uniform_location(state, "velocityLifetimeTexture")?,
+            size_rotation_texture: program.uniform_location(state, "sizeRotationTexture")?,
+            lookup_texture: program.uniform_location(state, "lookupTexture")?,
+            texture_width: program.uniform_location(state, "textureWidth")?,
+            view_projection_matrix: program.uniform_location(state, "viewProjectionMatrix")?,
+            world_matrix: program.uniform_location(state, "worldMatrix")?,
+            camera_side_vector: program.uniform_location(state, "cameraSideVector")?,
+            camera_up_vector: program.uniform_location(state, "cameraUpVector")?,
+            sheet_size: program.uniform_location(state, "sheetSize")?,
+            frame_mode: program.uniform_location(state, "frameMode")?,
+            frame_rate: program.uniform_location(state, "frameRate")?,
+            random_start_frame: program.uniform_location(state, "randomStartFrame")?,
+            diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
+            depth_buffer_texture: program.uniform_location(state, "depthBufferTexture")?,
+            inv_screen_size: program.uniform_location(state, "invScreenSize")?,
+            proj_params: program.uniform_location(state, "projParams")?,
+            program,
+        })
+    }
+}
+
+fn make_float_texture(
+    state: &mut PipelineState,
+    width: usize,
+    height: usize,
+    filter: MagnificationFilter,
+    data: &[Vector4<f32>],
+) -> Result<GpuTexture, FrameworkError> {
+    let min_filter = match filter {
+        MagnificationFilter::Nearest => MinificationFilter::Nearest,
+        MagnificationFilter::Linear => MinificationFilter::Linear,
+    };
+    let mut texture = GpuTexture::new(
+        state,
+        GpuTextureKind::Rectangle { width, height },
+        PixelKind::RGBA32F,
+        min_filter,
+        filter,
+        1,
+        Some(unsafe { array_as_u8_slice(data) }),
+    )?;
+    texture
+        .bind_mut(state, 0)
+        .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+        .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
+    Ok(texture)
+}
+
+/// Creates a frame buffer with three attachments: position + age, velocity + lifetime,
+/// size + size modifier + rotation + rotation speed.
+fn make_state_buffer(
+    state: &mut PipelineState,
+    height: usize,
+) -> Result<FrameBuffer, FrameworkError> {
+    // Zeroed state means that all particles are dead (age >= lifetime).
+    let zeros = vec![Vector4::<f32>::default(); STATE_TEXTURE_WIDTH * height];
+
+    let mut color_attachments = Vec::new();
+    for _ in 0..3 {
+        color_attachments.push(Attachment {
+            kind: AttachmentKind::Color,
+            texture: Rc::new(RefCell::new(make_float_texture(
+                state,
+                STATE_TEXTURE_WIDTH,
+                height,
+                MagnificationFilter::Nearest,
+                &zeros,
+            )?)),
+        });
+    }
+
+    FrameBuffer::new(state, None, color_attachments)
+}
+
+struct GpuParticleState {
+    capacity: usize,
+    // Index of a slot for next spawned particle.
+    cursor: usize,
+    current: FrameBuffer,
+    next: FrameBuffer,
+    lookup: Rc<RefCell<GpuTexture>>,
+}
+
+impl GpuParticleState {
+    fn new(state: &mut PipelineState, capacity: usize) -> Result<Self, FrameworkError> {
+        let height = capacity / STATE_TEXTURE_WIDTH;
+        let lookup = vec![Vector4::<f32>::default(); LOOKUP_TEXTURE_WIDTH * 2];
+        Ok(Self {
+            capacity,
+            cursor: 0,
+            current: make_state_buffer(state, height)?,
+            next: make_state_buffer(state, height)?,
+            lookup: Rc::new(RefCell::new(make_float_texture(
+                state,
+                LOOKUP_TEXTURE_WIDTH,
+                2,
+                MagnificationFilter::Linear,
+                &lookup,
+            )?)),
+        })
+    }
+
+    fn texture(&self, index: usize) -> Rc<RefCell<GpuTexture>> {
+        self.current.color_attachments()[index].texture.clone()
+    }
+}
+
+/// Bakes color gradient and curves of a particle system into two rows of samples.
+fn bake_lookup(particle_system: &ParticleSystem) -> Vec<Vector4<f32>> {
+    let fetch = |curve: Option<&Curve>, k: f32| curve.map_or(1.0, |curve| curve.fetch(k));
+
+    let locations = (0..LOOKUP_TEXTURE_WIDTH)
+        .map(|i| i as f32 / (LOOKUP_TEXTURE_WIDTH - 1) as f32)
+        .collect::<Vec<_>>();
+
+    let colors = locations.iter().map(|&k| {
+        particle_system
+            .color_over_lifetime_gradient()
+            .map_or(Color::WHITE, |gradient| gradient.get_color(k))
+            .as_frgba()
+    });
+
+    let multipliers = locations.iter().map(|&k| {
+        Vector4::new(
+            fetch(particle_system.size_over_lifetime(), k),
+            fetch(particle_system.speed_over_lifetime(), k),
+            fetch(particle_system.rotation_speed_over_lifetime(), k),
+            fetch(particle_system.alpha_over_lifetime(), k),
+        )
+    });
+
+    colors.chain(multipliers).collect()
+}
+
+pub(in crate) struct GpuParticleSystemRenderContext<'a, 'b, 'c> {
+    pub state: &'a mut PipelineState,
+    pub framebuffer: &'b mut FrameBuffer,
+    pub particle_system: &'c ParticleSystem,
+    pub camera: &'c Camera,
+    pub diffuse_texture: Rc<RefCell<GpuTexture>>,
+    pub depth: Rc<RefCell<GpuTexture>>,
+    pub inv_screen_size: Vector2<f32>,
+    pub viewport: Rect<i32>,
+}
+
+pub struct GpuParticleSystemRenderer {
+    simulation_shader: SimulationShader,
+    render_shader: RenderShader,
+    quad: GeometryBuffer,
+    spawn_textures: [Rc<RefCell<GpuTexture>>; 3],
+    states: HashMap<u64, TimedEntry<GpuParticleState>>,
+}
+
+impl GpuParticleSystemRenderer {
+    pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let corners = [
+            Vector2::<f32>::new(0.0, 0.0),
+            Vector2::new(1.0, 0.0),
+            Vector2::new(1.0, 1.0),
+            Vector2::new(0.0, 1.0),
+        ];
+        let quad = GeometryBufferBuilder::new(ElementKind::Triangle)
+            .with_buffer_builder(
+                BufferBuilder::new(GeometryBufferKind::StaticDraw, Some(&corners[..]))
+                    .with_attribute(AttributeDefinition {
+                        location: 0,
+                        kind: AttributeKind::Float2,
+                        normalized: false,
+                        divisor: 0,
+                    }),
+            )
+            .build(state)?;
+        quad.bind(state)
+            .set_triangles(&[TriangleDefinition([0, 1, 2]), TriangleDefinition([0, 2, 3])]);
+
+        let mut make_spawn_texture = || -> Result<Rc<RefCell<GpuTexture>>, FrameworkError> {
+            Ok(Rc::new(RefCell::new(make_float_texture(
+                state,
+                STATE_TEXTURE_WIDTH,
+                1,
+                MagnificationFilter::Nearest,
+                &[Vector4::default(); STATE_TEXTURE_WIDTH],
+            )?)))
+        };
+
+        Ok(Self {
+            spawn_textures: [
+                make_spawn_texture()?,
+                make_spawn_texture()?,
+                make_spawn_texture()?,
+            ],
+            simulation_shader: SimulationShader::new(state)?,
+            render_shader: RenderShader::new(state)?,
+            quad,
+            states: Default::default(),
+        })
+    }
+
+    /// Removes states of particle systems that were not simulated for a while (deleted
+    /// or belong to disabled scenes).
+    pub fn update(&mut self, dt: f32) {
+        for entry in self.states.values_mut() {
+            entry.time_to_live -= dt;
+        }
+        self.states.retain(|_, entry| entry.time_to_live > 0.0);
+    }
+
+    /// Simulates every GPU particle system of the graph. Must be called once per frame
+    /// before rendering.
+    pub(in crate) fn simulate(
+        &mut self,
+        state: &mut PipelineState,
+        graph: &Graph,
+    ) -> Result<RenderPassStatistics, FrameworkError> {
+        scope_profile!();
+
+        let mut statistics = RenderPassStatistics::default();
+
+        for node in graph.linear_iter() {
+            let particle_system = match node {
+                Node::ParticleSystem(particle_system)
+                    if particle_system.is_gpu_simulation_enabled() =>
+                {
+                    particle_system
+                }
+                _ => continue,
+            };
+
+            let queue = particle_system.take_gpu_simulation_queue();
+
+            let capacity = {
+                let capacity = particle_system.gpu_capacity().min(MAX_CAPACITY);
+                // Round up to whole rows of state textures.
+                ((capacity + STATE_TEXTURE_WIDTH - 1) / STATE_TEXTURE_WIDTH) * STATE_TEXTURE_WIDTH
+            };
+
+            // State is temporarily taken out of the map to be able to borrow the rest
+            // of the renderer.
+            let id = particle_system.gpu_simulation_id();
+            let mut particle_state = match self.states.remove(&id) {
+                Some(entry) if entry.capacity == capacity => entry.value,
+                // Capacity has changed (or there is no state yet), old particles are lost.
+                _ => GpuParticleState::new(state, capacity)?,
+            };
+
+            particle_state
+                .lookup
+                .borrow_mut()
+                .bind_mut(state, 0)
+                .set_data(
+                    GpuTextureKind::Rectangle {
+                        width: LOOKUP_TEXTURE_WIDTH,
+                        height: 2,
+                    },
+                    PixelKind::RGBA32F,
+                    1,
+                    Some(unsafe { array_as_u8_slice(&bake_lookup(particle_system)) }),
+                )?;
+
+            // Ring buffer could hold only `capacity` particles, older ones are overwritten.
+            let spawned = &queue.spawned[queue.spawned.len().saturating_sub(capacity)..];
+            if !spawned.is_empty() {
+                let height = (spawned.len() + STATE_TEXTURE_WIDTH - 1) / STATE_TEXTURE_WIDTH;
+                let size = STATE_TEXTURE_WIDTH * height;
+
+                let mut position_age = Vec::with_capacity(size);
+                let mut velocity_lifetime = Vec::with_capacity(size);
+                let mut size_rotation = Vec::with_capacity(size);
+                for particle in spawned {
+                    let (position, velocity) = (particle.position, particle.velocity);
+                    position_age.push(Vector4::new(position.x, position.y, position.z, 0.0));
+                    velocity_lifetime.push(Vector4::new(
+                        velocity.x,
+                        velocity.y,
+                        velocity.z,
+                        particle.initial_lifetime,
+                    ));
+                    size_rotation.push(Vector4::new(
+                        particle.size,
+                        particle.size_modifier,
+                        particle.rotation,
+                        particle.rotation_speed,
+                    ));
+                }
+
+                for (texture, mut data) in self.spawn_textures.iter().zip(vec![
+                    position_age,
+                    velocity_lifetime,
+                    size_rotation,
+                ]) {
+                    data.resize(size, Vector4::default());
+                    texture.borrow_mut().bind_mut(state, 0).set_data(
+                        GpuTextureKind::Rectangle {
+                            width: STATE_TEXTURE_WIDTH,
+                            height,
+                        },
+                        PixelKind::RGBA32F,
+                        1,
+                        Some(unsafe { array_as_u8_slice(&data) }),
+                    )?;
+                }
+            }
+
+            let acceleration = particle_system.gpu_acceleration();
+
+            let mut steps = queue.steps;
+            if steps.is_empty() && !spawned.is_empty() {
+                // Spawned particles must be written anyway.
+                steps.push(0.0);
+            }
+
+            for (i, dt) in steps.into_iter().enumerate() {
+                let spawn_count = if i == 0 { spawned.len() } else { 0 };
+                statistics += self.simulation_step(
+                    state,
+                    &mut particle_state,
+                    dt,
+                    &acceleration,
+                    spawn_count,
+                );
+            }
+
+            particle_state.cursor = (particle_state.cursor + spawned.len()) % capacity;
+
+            self.states.insert(
+                id,
+                TimedEntry {
+                    value: particle_state,
+                    time_to_live: DEFAULT_RESOURCE_LIFETIME,
+                },
+            );
+        }
+
+        Ok(statistics)
+    }
+
+    fn simulation_step(
+        &self,
+        state: &mut PipelineState,
+        particle_state: &mut GpuParticleState,
+        dt: f32,
+        acceleration: &Vector3<f32>,
+        spawn_count: usize,
+    ) -> RenderPassStatistics {
+        let mut statistics = RenderPassStatistics::default();
+
+        let viewport = Rect::new(
+            0,
+            0,
+            STATE_TEXTURE_WIDTH as i32,
+            (particle_state.capacity / STATE_TEXTURE_WIDTH) as i32,
+        );
+
+        let position_age = particle_state.texture(0);
+        let velocity_lifetime = particle_state.texture(1);
+        let size_rotation = particle_state.texture(2);
+        let lookup = particle_state.lookup.clone();
+        let shader = &self.simulation_shader;
+        let spawn_textures = &self.spawn_textures;
+
+        statistics += particle_state.next.draw(
+            &self.quad,
+            state,
+            viewport,
+            &shader.program,
+            &DrawParameters {
+                cull_face: CullFace::Back,
+                culling: false,
+                color_write: Default::default(),
+                depth_write: false,
+                stencil_test: false,
+                depth_test: false,
+                blend: false,
+            },
+            |program_binding| {
+                program_binding
+                    .set_texture(&shader.position_age_texture, &position_age)
+                    .set_texture(&shader.velocity_lifetime_texture, &velocity_lifetime)
+                    .set_texture(&shader.size_rotation_texture, &size_rotation)
+                    .set_texture(&shader.spawn_position_age_texture, &spawn_textures[0])
+                    .set_texture(&shader.spawn_velocity_lifetime_texture, &spawn_textures[1])
+                    .set_texture(&shader.spawn_size_rotation_texture, &spawn_textures[2])
+                    .set_texture(&shader.lookup_texture, &lookup)
+                    .set_integer(&shader.texture_width, STATE_TEXTURE_WIDTH as i32)
+                    .set_integer(&shader.capacity, particle_state.capacity as i32)
+                    .set_integer(&shader.spawn_start, particle_state.cursor as i32)
+                    .set_integer(&shader.spawn_count, spawn_count as i32)
+                    .set_float(&shader.dt, dt)
+                    .set_vector3(&shader.acceleration, acceleration);
+            },
+        );
+
+        std::mem::swap(&mut particle_state.current, &mut particle_state.next);
+
+        statistics
+    }
+
+    /// Draws particles of a GPU-simulated particle system. Particle system must be
+    /// simulated first.
+    #[must_use]
+    pub(in crate) fn render(&mut self, args: GpuParticleSystemRenderContext) -> RenderPassStatistics {
+        scope_profile!();
+
+        let mut statistics = RenderPassStatistics::default();
+
+        let GpuParticleSystemRenderContext {
+            state,
+            framebuffer,
+            particle_system,
+            camera,
+            diffuse_texture,
+            depth,
+            inv_screen_size,
+            viewport,
+        } = args;
+
+        let particle_state = match self.states.get(&particle_system.gpu_simulation_id()) {
+            Some(particle_state) => particle_state,
+            None => return statistics,
+        };
+
+        let inv_view = camera.inv_view_matrix().unwrap();
+        let view_proj = camera.view_projection_matrix();
+        let camera_up = inv_view.up();
+        let camera_side = inv_view.side();
+        let proj_params = Vector2::new(camera.z_far(), camera.z_near());
+        let global_transform: Matrix4<f32> = particle_system.global_transform();
+
+        let (sheet_size, frame_mode, frame_rate, random_start_frame) =
+            if let Some(sprite_sheet) = particle_system.sprite_sheet() {
+                let sheet_size = Vector2::new(
+                    sprite_sheet.sheet.columns() as f32,
+                    sprite_sheet.sheet.rows() as f32,
+                );
+                match sprite_sheet.mode {
+                    ParticleFrameMode::FrameRate(frame_rate) => {
+                        (sheet_size, 0, frame_rate, sprite_sheet.random_start_frame)
+                    }
+                    ParticleFrameMode::OverLifetime => {
+                        (sheet_size, 1, 0.0, sprite_sheet.random_start_frame)
+                    }
+                }
+            } else {
+                (Vector2::new(1.0, 1.0), 1, 0.0, false)
+            };
+
+        let position_age = particle_state.texture(0);
+        let velocity_lifetime = particle_state.texture(1);
+        let size_rotation = particle_state.texture(2);
+        let lookup = particle_state.lookup.clone();
+        let shader = &self.render_shader;
+
+        statistics += framebuffer.draw_instances(
+            particle_state.capacity,
+            &self.quad,
+            state,
+            viewport,
+            &shader.program,
+            &DrawParameters {
+                cull_face: CullFace::Front,
+                culling: false,
+                color_write: Default::default(),
+                depth_write: false,
+                stencil_test: false,
+                depth_test: true,
+                blend: true,
+            },
+            |program_binding| {
+                program_binding
+                    .set_texture(&shader.position_age_texture, &position_age)
+                    .set_texture(&shader.velocity_lifetime_texture, &velocity_lifetime)
+                    .set_texture(&shader.size_rotation_texture, &size_rotation)
+                    .set_texture(&shader.lookup_texture, &lookup)
+                    .set_texture(&shader.depth_buffer_texture, &depth)
+                    .set_texture(&shader.diffuse_texture, &diffuse_texture)
+                    .set_integer(&shader.texture_width, STATE_TEXTURE_WIDTH as i32)
+                    .set_vector3(&shader.camera_side_vector, &camera_side)
+                    .set_vector3(&shader.camera_up_vector, &camera_up)
+                    .set_matrix4(&shader.view_projection_matrix, &view_proj)
+                    .set_matrix4(&shader.world_matrix, &global_transform)
+                    .set_vector2(&shader.sheet_size, &sheet_size)
+                    .set_integer(&shader.frame_mode, frame_mode)
+                    .set_float(&shader.frame_rate, frame_rate)
+                    .set_bool(&shader.random_start_frame, random_start_frame)
+                    .set_vector2(&shader.inv_screen_size, &inv_screen_size)
+                    .set_vector2(&shader.proj_params, &proj_params);
+            },
+        );
+
+        statistics
+    }
+}
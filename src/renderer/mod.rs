@@ -13,6 +13,7 @@ pub mod framework;
 pub mod renderer2d;
 
 mod batch;
+mod blend_shape;
 mod blur;
 mod cache;
 mod deferred_light_renderer;
@@ -20,6 +21,7 @@ mod flat_shader;
 mod forward_renderer;
 mod fxaa;
 mod gbuffer;
+mod gpu_particle_system;
 mod light_volume;
 mod particle_system_renderer;
 mod shadow_map_renderer;
@@ -40,7 +42,7 @@ use crate::{
     gui::{draw::DrawingContext, message::MessageData, Control, UserInterface},
     renderer::{
         batch::BatchStorage,
-        cache::{GeometryCache, TextureCache},
+        cache::{BlendShapeCache, GeometryCache, TextureCache},
         debug_renderer::DebugRenderer,
         deferred_light_renderer::{
             DeferredLightRenderer, DeferredRendererContext, LightingStatistics,
@@ -443,6 +445,7 @@ pub struct Renderer {
     backbuffer_clear_color: Color,
     texture_cache: TextureCache,
     geometry_cache: GeometryCache,
+    blend_shape_cache: BlendShapeCache,
     batch_storage: BatchStorage,
     forward_renderer: ForwardRenderer,
     fxaa_renderer: FxaaRenderer,
@@ -595,6 +598,7 @@ impl Renderer {
             backbuffer_clear_color: Color::BLACK,
             texture_cache: Default::default(),
             geometry_cache: Default::default(),
+            blend_shape_cache: BlendShapeCache::new(&mut state)?,
             batch_storage: Default::default(),
             forward_renderer: ForwardRenderer::new(&mut state)?,
             ui_frame_buffers: Default::default(),
@@ -647,6 +651,12 @@ impl Renderer {
         self.frame_size
     }
 
+    /// Returns true if particle systems can be simulated on GPU, see
+    /// [`crate::scene::particle_system::ParticleSystem::set_gpu_simulation`].
+    pub fn is_gpu_particle_simulation_supported(&self) -> bool {
+        self.particle_system_renderer.is_gpu_simulation_supported()
+    }
+
     /// Returns current bounds of back buffer.
     pub fn get_frame_bounds(&self) -> Vector2<f32> {
         Vector2::new(self.frame_size.0 as f32, self.frame_size.1 as f32)
@@ -675,6 +685,7 @@ impl Renderer {
     pub fn flush(&mut self) {
         self.texture_cache.clear();
         self.geometry_cache.clear();
+        self.blend_shape_cache.clear();
         self.renderer2d.flush();
     }
 
@@ -785,6 +796,8 @@ impl Renderer {
         // Update caches - this will remove timed out resources.
         self.update_texture_cache(dt);
         self.geometry_cache.update(dt);
+        self.blend_shape_cache.update(dt);
+        self.particle_system_renderer.update(dt);
 
         self.statistics.begin_frame();
 
@@ -818,6 +831,9 @@ impl Renderer {
 
             let state = &mut self.state;
 
+            // Particles must be simulated once per frame, not per camera.
+            self.statistics += self.particle_system_renderer.simulate(state, graph)?;
+
             self.batch_storage.generate_batches(
                 state,
                 graph,
@@ -876,6 +892,7 @@ impl Renderer {
                     state,
                     camera,
                     geom_cache: &mut self.geometry_cache,
+                    blend_shape_cache: &mut self.blend_shape_cache,
                     batch_storage: &self.batch_storage,
                     texture_cache: &mut self.texture_cache,
                     environment_dummy: self.environment_dummy.clone(),
@@ -894,6 +911,7 @@ impl Renderer {
                             settings: &self.quality_settings,
                             textures: &mut self.texture_cache,
                             geometry_cache: &mut self.geometry_cache,
+                            blend_shape_cache: &mut self.blend_shape_cache,
                             batch_storage: &self.batch_storage,
                         });
 
@@ -932,6 +950,7 @@ impl Renderer {
                     state,
                     camera,
                     geom_cache: &mut self.geometry_cache,
+                    blend_shape_cache: &mut self.blend_shape_cache,
                     batch_storage: &self.batch_storage,
                     framebuffer: &mut gbuffer.final_frame, // TODO: GBuffer **must not** contain final frame.
                     viewport,
@@ -942,7 +961,8 @@ impl Renderer {
                     viewport,
                     &mut gbuffer.final_frame,
                     &scene.drawing_context,
-                    camera,
+                    &camera.view_projection_matrix(),
+                    true,
                 );
 
                 // Finally render everything into back buffer.
@@ -11,8 +11,12 @@ use crate::{
         gpu_texture::GpuTexture,
         state::PipelineState,
     },
-    renderer::{RenderPassStatistics, TextureCache},
-    scene::{camera::Camera, graph::Graph, node::Node, particle_system},
+    renderer::{
+        gpu_particle_system::{GpuParticleSystemRenderContext, GpuParticleSystemRenderer},
+        RenderPassStatistics, TextureCache,
+    },
+    scene::{camera::Camera, graph::Graph, node::Node},
+    utils::log::{Log, MessageKind},
 };
 use std::{cell::RefCell, rc::Rc};
 
@@ -57,6 +61,8 @@ pub struct ParticleSystemRenderer {
     draw_data: particle_system::DrawData,
     geometry_buffer: GeometryBuffer,
     sorted_particles: Vec<u32>,
+    // `None` if GPU simulation is not supported, particle systems will be simulated on CPU.
+    gpu_renderer: Option<GpuParticleSystemRenderer>,
 }
 
 pub(in crate) struct ParticleSystemRenderContext<'a, 'b, 'c> {
@@ -109,18 +115,59 @@ impl ParticleSystemRenderer {
                     kind: AttributeKind::UnsignedByte4,
                     normalized: true,
                     divisor: 0,
+                })
+                .with_attribute(AttributeDefinition {
+                    location: 5,
+                    kind: AttributeKind::Float2,
+                    normalized: false,
+                    divisor: 0,
                 }),
             )
             .build(state)?;
 
+        let gpu_renderer = match GpuParticleSystemRenderer::new(state) {
+            Ok(gpu_renderer) => Some(gpu_renderer),
+            Err(e) => {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!("GPU particle simulation is not supported. Reason: {:?}", e),
+                );
+                None
+            }
+        };
         Ok(Self {
             shader: ParticleSystemShader::new(state)?,
             draw_data: Default::default(),
             geometry_buffer,
             sorted_particles: Vec::new(),
+            gpu_renderer,
         })
     }
 
+    pub fn is_gpu_simulation_supported(&self) -> bool {
+        self.gpu_renderer.is_some()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if let Some(gpu_renderer) = self.gpu_renderer.as_mut() {
+            gpu_renderer.update(dt);
+        }
+    }
+
+    /// Simulates GPU particle systems of the graph, must be called once per frame
+    /// before rendering.
+    pub(in crate) fn simulate(
+        &mut self,
+        state: &mut PipelineState,
+        graph: &Graph,
+    ) -> Result<RenderPassStatistics, FrameworkError> {
+        if let Some(gpu_renderer) = self.gpu_renderer.as_mut() {
+            gpu_renderer.simulate(state, graph)
+        } else {
+            Ok(Default::default())
+        }
+    }
+
     #[must_use]
     pub(in crate) fn render(&mut self, args: ParticleSystemRenderContext) -> RenderPassStatistics {
         scope_profile!();
@@ -158,6 +205,32 @@ impl ParticleSystemRenderer {
                 continue;
             };
 
+            let diffuse_texture = if let Some(texture) = particle_system.texture_ref() {
+                if let Some(texture) = texture_cache.get(state, texture) {
+                    texture
+                } else {
+                    white_dummy.clone()
+                }
+            } else {
+                white_dummy.clone()
+            };
+
+            if particle_system.is_gpu_simulation_enabled() {
+                if let Some(gpu_renderer) = self.gpu_renderer.as_mut() {
+                    statistics += gpu_renderer.render(GpuParticleSystemRenderContext {
+                        state,
+                        framebuffer,
+                        particle_system,
+                        camera,
+                        diffuse_texture,
+                        depth: depth.clone(),
+                        inv_screen_size,
+                        viewport,
+                    });
+                    continue;
+                }
+            }
+
             particle_system.generate_draw_data(
                 &mut self.sorted_particles,
                 &mut self.draw_data,
@@ -182,16 +255,6 @@ impl ParticleSystemRenderer {
                 blend: true,
             };
 
-            let diffuse_texture = if let Some(texture) = particle_system.texture_ref() {
-                if let Some(texture) = texture_cache.get(state, texture) {
-                    texture
-                } else {
-                    white_dummy.clone()
-                }
-            } else {
-                white_dummy.clone()
-            };
-
             statistics += framebuffer.draw(
                 &self.geometry_buffer,
                 state,
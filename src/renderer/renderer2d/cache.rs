@@ -1,6 +1,6 @@
 use crate::{
     core::{
-        algebra::{Matrix4, Vector2},
+        algebra::{Matrix4, Vector2, Vector4},
         color::Color,
         math::TriangleDefinition,
         scope_profile,
@@ -67,6 +67,8 @@ pub(in crate) struct GeometryCache {
 pub(in crate) struct InstanceData {
     pub color: Color,
     pub world_matrix: Matrix4<f32>,
+    /// xy - position of a frame in texture atlas, zw - size of the frame.
+    pub frame_rect: Vector4<f32>,
 }
 
 impl GeometryCache {
@@ -129,6 +131,13 @@ impl GeometryCache {
                             kind: AttributeKind::Float4,
                             normalized: false,
                             divisor: 1,
+                        })
+                        // Frame Rect
+                        .with_attribute(AttributeDefinition {
+                            location: 7,
+                            kind: AttributeKind::Float4,
+                            normalized: false,
+                            divisor: 1,
                         }),
                 )
                 .build(state)
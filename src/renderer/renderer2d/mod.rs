@@ -8,6 +8,7 @@ use crate::{
     },
     physics::parry::utils::hashmap::Entry,
     renderer::{
+        debug_renderer::DebugRenderer,
         framework::{
             error::FrameworkError,
             framebuffer::{Attachment, AttachmentKind, CullFace, DrawParameters, FrameBuffer},
@@ -104,6 +105,7 @@ pub(in crate) struct Renderer2d {
     framebuffers: HashMap<Handle<Scene2d>, RenderTarget>,
     batch_storage: BatchStorage,
     instance_data_set: Vec<InstanceData>,
+    debug_renderer: DebugRenderer,
 }
 
 #[derive(Default)]
@@ -160,11 +162,19 @@ impl BatchStorage {
                     self.batches.last_mut().unwrap()
                 };
 
+                let frame_rect = sprite.frame_rect();
+
                 batch.instances.push(Instance {
                     gpu_data: InstanceData {
                         color: sprite.color(),
                         world_matrix: sprite.global_transform()
                             * Matrix4::new_scaling(sprite.size()),
+                        frame_rect: Vector4::new(
+                            frame_rect.x(),
+                            frame_rect.y(),
+                            frame_rect.w(),
+                            frame_rect.h(),
+                        ),
                     },
                     bounds: sprite.global_bounds(),
                 });
@@ -192,6 +202,7 @@ impl Renderer2d {
             framebuffers: Default::default(),
             batch_storage: Default::default(),
             instance_data_set: Default::default(),
+            debug_renderer: DebugRenderer::new(state)?,
         })
     }
 
@@ -360,6 +371,16 @@ impl Renderer2d {
                         },
                     );
                 }
+
+                // Debug geometry is drawn on top of everything.
+                stats += self.debug_renderer.render(
+                    state,
+                    viewport,
+                    frame_buffer,
+                    &scene.drawing_context,
+                    &view_projection,
+                    false,
+                );
             }
         }
         Ok(stats)
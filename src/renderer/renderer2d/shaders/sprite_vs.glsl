@@ -4,6 +4,7 @@ layout(location = 0) in vec2 vertexPosition;
 layout(location = 1) in vec2 vertexTexCoord;
 layout(location = 2) in vec4 vertexColor;
 layout(location = 3) in mat4 worldMatrix;
+layout(location = 7) in vec4 frameRect;
 
 uniform mat4 viewProjection;
 
@@ -13,7 +14,7 @@ out vec2 fragmentPosition;
 
 void main()
 {
-    texCoord = vertexTexCoord;
+    texCoord = frameRect.xy + vertexTexCoord * frameRect.zw;
     vec4 worldPosition = worldMatrix * vec4(vertexPosition.x, vertexPosition.y, 0.0, 1.0);
     fragmentPosition = worldPosition.xy;
     gl_Position = viewProjection * worldPosition;
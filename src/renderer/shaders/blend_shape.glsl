@@ -0,0 +1,47 @@
+// Offsets of every vertex of every blend shape are stored in a texture, three texels per
+// vertex: offsets of position, normal and tangent.
+uniform sampler2D blendShapeStorage;
+uniform int blendShapeStorageWidth;
+uniform int blendShapeVertexCount;
+uniform int blendShapeCount;
+
+vec3 ReadBlendShapeDelta(int id)
+{
+    ivec2 coords = ivec2(id % blendShapeStorageWidth, id / blendShapeStorageWidth);
+    return texelFetch(blendShapeStorage, coords, 0).xyz;
+}
+
+// Weights are packed in vectors to save uniform components.
+float BlendShapeWeight(int index)
+{
+    return blendShapeWeights[index / 4][index % 4];
+}
+
+void MorphVertex(inout vec3 position, inout vec3 normal, inout vec3 tangent)
+{
+    for (int i = 0; i < blendShapeCount; ++i)
+    {
+        float weight = BlendShapeWeight(i);
+        if (weight != 0.0)
+        {
+            int id = 3 * (i * blendShapeVertexCount + gl_VertexID);
+            position += weight * ReadBlendShapeDelta(id);
+            normal += weight * ReadBlendShapeDelta(id + 1);
+            tangent += weight * ReadBlendShapeDelta(id + 2);
+        }
+    }
+}
+
+vec3 MorphPosition(vec3 position)
+{
+    for (int i = 0; i < blendShapeCount; ++i)
+    {
+        float weight = BlendShapeWeight(i);
+        if (weight != 0.0)
+        {
+            int id = 3 * (i * blendShapeVertexCount + gl_VertexID);
+            position += weight * ReadBlendShapeDelta(id);
+        }
+    }
+    return position;
+}
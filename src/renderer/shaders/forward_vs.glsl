@@ -16,9 +16,10 @@ out vec2 texCoord;
 void main()
 {
     vec4 localPosition = vec4(0);
+    vec3 morphedPosition = MorphPosition(vertexPosition);
     if (useSkeletalAnimation)
     {
-        vec4 vertex = vec4(vertexPosition, 1.0);
+        vec4 vertex = vec4(morphedPosition, 1.0);
 
         int i0 = int(boneIndices.x);
         int i1 = int(boneIndices.y);
@@ -32,7 +33,7 @@ void main()
     }
     else
     {
-        localPosition = vec4(vertexPosition, 1.0);
+        localPosition = vec4(morphedPosition, 1.0);
     }
     gl_Position = worldViewProjection * localPosition;
     texCoord = vertexTexCoord;
@@ -0,0 +1,71 @@
+#version 330 core
+
+// Current state of particles.
+uniform sampler2D positionAgeTexture;
+uniform sampler2D velocityLifetimeTexture;
+uniform sampler2D sizeRotationTexture;
+
+// Particles spawned since last step.
+uniform sampler2D spawnPositionAgeTexture;
+uniform sampler2D spawnVelocityLifetimeTexture;
+uniform sampler2D spawnSizeRotationTexture;
+
+// Row 0 - color over lifetime, row 1 - size, speed, rotation speed and alpha multipliers.
+uniform sampler2D lookupTexture;
+
+uniform int textureWidth;
+uniform int capacity;
+uniform int spawnStart;
+uniform int spawnCount;
+uniform float dt;
+uniform vec3 acceleration;
+
+layout(location = 0) out vec4 outPositionAge;
+layout(location = 1) out vec4 outVelocityLifetime;
+layout(location = 2) out vec4 outSizeRotation;
+
+void main()
+{
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    int index = texel.y * textureWidth + texel.x;
+
+    // Spawned particles are placed into a ring buffer starting from spawnStart.
+    int spawnIndex = (index - spawnStart + capacity) % capacity;
+    if (spawnIndex < spawnCount)
+    {
+        ivec2 spawnTexel = ivec2(spawnIndex % textureWidth, spawnIndex / textureWidth);
+        outPositionAge = texelFetch(spawnPositionAgeTexture, spawnTexel, 0);
+        outVelocityLifetime = texelFetch(spawnVelocityLifetimeTexture, spawnTexel, 0);
+        outSizeRotation = texelFetch(spawnSizeRotationTexture, spawnTexel, 0);
+        return;
+    }
+
+    vec4 positionAge = texelFetch(positionAgeTexture, texel, 0);
+    vec4 velocityLifetime = texelFetch(velocityLifetimeTexture, texel, 0);
+    vec4 sizeRotation = texelFetch(sizeRotationTexture, texel, 0);
+
+    float age = positionAge.w + dt;
+    float lifetime = velocityLifetime.w;
+
+    if (positionAge.w >= lifetime)
+    {
+        // Dead particle, keep it as is.
+        outPositionAge = positionAge;
+        outVelocityLifetime = velocityLifetime;
+        outSizeRotation = sizeRotation;
+        return;
+    }
+
+    float k = clamp(age / lifetime, 0.0, 1.0);
+    vec4 multipliers = texture(lookupTexture, vec2(k, 0.75));
+
+    // Velocity is a displacement per step, same as in CPU simulation.
+    vec3 velocity = velocityLifetime.xyz + acceleration * dt * dt;
+    vec3 position = positionAge.xyz + velocity * multipliers.y;
+    float size = max(sizeRotation.x + sizeRotation.y * dt, 0.0);
+    float rotation = sizeRotation.z + sizeRotation.w * multipliers.z * dt;
+
+    outPositionAge = vec4(position, age);
+    outVelocityLifetime = vec4(velocity, lifetime);
+    outSizeRotation = vec4(size, sizeRotation.y, rotation, sizeRotation.w);
+}
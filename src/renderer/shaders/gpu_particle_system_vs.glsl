@@ -0,0 +1,83 @@
+#version 330 core
+
+layout(location = 0) in vec2 vertexPosition;
+
+uniform sampler2D positionAgeTexture;
+uniform sampler2D velocityLifetimeTexture;
+uniform sampler2D sizeRotationTexture;
+uniform sampler2D lookupTexture;
+uniform int textureWidth;
+uniform mat4 viewProjectionMatrix;
+uniform mat4 worldMatrix;
+uniform vec3 cameraUpVector;
+uniform vec3 cameraSideVector;
+// Amount of columns and rows of sprite sheet.
+uniform vec2 sheetSize;
+// 0 - fixed frame rate, 1 - whole sequence over lifetime.
+uniform int frameMode;
+uniform float frameRate;
+uniform bool randomStartFrame;
+
+out vec2 texCoord;
+out vec4 color;
+
+vec2 rotateVec2(vec2 v, float angle)
+{
+    float c = cos(angle);
+    float s = sin(angle);
+    mat2 m = mat2(c, -s, s, c);
+    return m * v;
+}
+
+float hash(int n)
+{
+    return fract(sin(float(n) * 12.9898) * 43758.5453);
+}
+
+void main()
+{
+    ivec2 texel = ivec2(gl_InstanceID % textureWidth, gl_InstanceID / textureWidth);
+    vec4 positionAge = texelFetch(positionAgeTexture, texel, 0);
+    vec4 velocityLifetime = texelFetch(velocityLifetimeTexture, texel, 0);
+    vec4 sizeRotation = texelFetch(sizeRotationTexture, texel, 0);
+
+    float age = positionAge.w;
+    float lifetime = velocityLifetime.w;
+
+    if (age >= lifetime)
+    {
+        // Dead particle - move it out of clip volume.
+        texCoord = vec2(0.0);
+        color = vec4(0.0);
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+
+    float k = clamp(age / lifetime, 0.0, 1.0);
+    vec4 multipliers = texture(lookupTexture, vec2(k, 0.75));
+
+    color = texture(lookupTexture, vec2(k, 0.25));
+    color.a *= clamp(multipliers.w, 0.0, 1.0);
+
+    int columns = int(sheetSize.x);
+    int frameCount = columns * int(sheetSize.y);
+    int startFrame = randomStartFrame ? int(hash(gl_InstanceID) * float(frameCount)) : 0;
+    int frame;
+    if (frameMode == 0)
+    {
+        frame = startFrame + int(age * frameRate);
+    }
+    else
+    {
+        frame = startFrame + min(int(k * float(frameCount)), frameCount - 1);
+    }
+    frame = frame % frameCount;
+    vec2 frameSize = 1.0 / sheetSize;
+    texCoord = vec2(frame % columns, frame / columns) * frameSize + vertexPosition * frameSize;
+
+    float size = sizeRotation.x * multipliers.x;
+    vec2 vertexOffset = rotateVec2(vertexPosition * 2.0 - 1.0, sizeRotation.z);
+    vec4 worldPosition = worldMatrix * vec4(positionAge.xyz, 1.0);
+    vec3 offset = (vertexOffset.x * cameraSideVector + vertexOffset.y * cameraUpVector) * size;
+    gl_Position = viewProjectionMatrix * (worldPosition + vec4(offset.x, offset.y, offset.z, 0.0));
+}
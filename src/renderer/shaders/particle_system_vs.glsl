@@ -5,6 +5,7 @@ layout(location = 1) in vec2 vertexTexCoord;
 layout(location = 2) in float particleSize;
 layout(location = 3) in float particleRotation;
 layout(location = 4) in vec4 vertexColor;
+layout(location = 5) in vec2 vertexFrameTexCoord;
 
 uniform mat4 viewProjectionMatrix;
 uniform mat4 worldMatrix;
@@ -25,7 +26,7 @@ vec2 rotateVec2(vec2 v, float angle)
 void main()
 {
     color = vertexColor;
-    texCoord = vertexTexCoord;
+    texCoord = vertexFrameTexCoord;
     vec2 vertexOffset = rotateVec2(vertexTexCoord * 2.0 - 1.0, particleRotation);
     vec4 worldPosition = worldMatrix * vec4(vertexPosition, 1.0);
     vec3 offset = (vertexOffset.x * cameraSideVector + vertexOffset.y * cameraUpVector) * particleSize;
@@ -16,10 +16,11 @@ out vec3 worldPosition;
 void main()
 {
     vec4 localPosition = vec4(0);
+    vec3 morphedPosition = MorphPosition(vertexPosition);
 
     if (useSkeletalAnimation)
     {
-        vec4 vertex = vec4(vertexPosition, 1.0);
+        vec4 vertex = vec4(morphedPosition, 1.0);
 
         localPosition += boneMatrices[int(boneIndices.x)] * vertex * boneWeights.x;
         localPosition += boneMatrices[int(boneIndices.y)] * vertex * boneWeights.y;
@@ -28,7 +29,7 @@ void main()
     }
     else
     {
-        localPosition = vec4(vertexPosition, 1.0);
+        localPosition = vec4(morphedPosition, 1.0);
     }
 
     gl_Position = worldViewProjection * localPosition;
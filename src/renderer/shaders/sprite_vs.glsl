@@ -9,6 +9,8 @@ uniform vec3 cameraUpVector;
 uniform vec3 cameraSideVector;
 uniform float size;
 uniform float rotation;
+// xy - position of current frame in texture atlas, zw - size of the frame.
+uniform vec4 frameRect;
 
 out vec2 texCoord;
 
@@ -22,7 +24,7 @@ vec2 rotateVec2(vec2 v, float angle)
 
 void main()
 {
-    texCoord = vertexTexCoord;
+    texCoord = frameRect.xy + vertexTexCoord * frameRect.zw;
     vec2 vertexOffset = rotateVec2(vertexTexCoord * 2.0 - 1.0, rotation);
     vec4 worldPosition = worldMatrix * vec4(vertexPosition, 1.0);
     vec3 offset = (vertexOffset.x * cameraSideVector + vertexOffset.y * cameraUpVector) * size;
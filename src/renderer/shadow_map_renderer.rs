@@ -17,7 +17,12 @@ use crate::{
         },
         state::{ColorMask, PipelineState},
     },
-    renderer::{batch::BatchStorage, GeometryCache, RenderPassStatistics, ShadowMapPrecision},
+    renderer::{
+        batch::BatchStorage,
+        blend_shape::{include_blend_shapes, BlendShapeUniforms},
+        cache::BlendShapeCache,
+        GeometryCache, RenderPassStatistics, ShadowMapPrecision,
+    },
     scene::{graph::Graph, node::Node},
 };
 use std::{cell::RefCell, rc::Rc};
@@ -28,19 +33,25 @@ struct SpotShadowMapShader {
     world_view_projection_matrix: UniformLocation,
     use_skeletal_animation: UniformLocation,
     diffuse_texture: UniformLocation,
+    blend_shapes: BlendShapeUniforms,
 }
 
 impl SpotShadowMapShader {
     pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
         let fragment_source = include_str!("shaders/spot_shadow_map_fs.glsl");
-        let vertex_source = include_str!("shaders/spot_shadow_map_vs.glsl");
-        let program =
-            GpuProgram::from_source(state, "SpotShadowMapShader", vertex_source, fragment_source)?;
+        let vertex_source = include_blend_shapes(include_str!("shaders/spot_shadow_map_vs.glsl"));
+        let program = GpuProgram::from_source(
+            state,
+            "SpotShadowMapShader",
+            &vertex_source,
+            fragment_source,
+        )?;
         Ok(Self {
             bone_matrices: program.uniform_location(state, "boneMatrices")?,
             world_view_projection_matrix: program.uniform_location(state, "worldViewProjection")?,
             use_skeletal_animation: program.uniform_location(state, "useSkeletalAnimation")?,
             diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
+            blend_shapes: BlendShapeUniforms::new(state, &program)?,
 
             program,
         })
@@ -155,6 +166,7 @@ impl SpotShadowMapRenderer {
         light_view_projection: &Matrix4<f32>,
         batches: &BatchStorage,
         geom_cache: &mut GeometryCache,
+        blend_shape_cache: &mut BlendShapeCache,
         cascade: usize,
     ) -> RenderPassStatistics {
         scope_profile!();
@@ -170,7 +182,9 @@ impl SpotShadowMapRenderer {
         let frustum = Frustum::from(*light_view_projection).unwrap_or_default();
 
         for batch in batches.batches.iter() {
-            let geometry = geom_cache.get(state, &batch.data.read().unwrap());
+            let data = batch.data.read().unwrap();
+            let geometry = geom_cache.get(state, &data);
+            let blend_shapes = blend_shape_cache.storage(state, &data);
 
             for instance in batch.instances.iter() {
                 let node = &graph[instance.owner];
@@ -200,7 +214,7 @@ impl SpotShadowMapRenderer {
                             blend: false,
                         },
                         |program_binding| {
-                            program_binding
+                            let program_binding = program_binding
                                 .set_matrix4(
                                     &shader.world_view_projection_matrix,
                                     &(light_view_projection * instance.world_transform),
@@ -211,6 +225,11 @@ impl SpotShadowMapRenderer {
                                     instance.bone_matrices.as_slice(),
                                 )
                                 .set_texture(&shader.diffuse_texture, &batch.diffuse_texture);
+                            shader.blend_shapes.bind(
+                                program_binding,
+                                &blend_shapes,
+                                &instance.blend_shape_weights,
+                            );
                         },
                     );
                 }
@@ -229,16 +248,17 @@ struct PointShadowMapShader {
     use_skeletal_animation: UniformLocation,
     diffuse_texture: UniformLocation,
     light_position: UniformLocation,
+    blend_shapes: BlendShapeUniforms,
 }
 
 impl PointShadowMapShader {
     pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
         let fragment_source = include_str!("shaders/point_shadow_map_fs.glsl");
-        let vertex_source = include_str!("shaders/point_shadow_map_vs.glsl");
+        let vertex_source = include_blend_shapes(include_str!("shaders/point_shadow_map_vs.glsl"));
         let program = GpuProgram::from_source(
             state,
             "PointShadowMapShader",
-            vertex_source,
+            &vertex_source,
             fragment_source,
         )?;
         Ok(Self {
@@ -248,6 +268,7 @@ impl PointShadowMapShader {
             use_skeletal_animation: program.uniform_location(state, "useSkeletalAnimation")?,
             diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
             light_position: program.uniform_location(state, "lightPosition")?,
+            blend_shapes: BlendShapeUniforms::new(state, &program)?,
             program,
         })
     }
@@ -273,6 +294,7 @@ pub(in crate) struct PointShadowMapRenderContext<'a, 'c> {
     pub light_pos: Vector3<f32>,
     pub light_radius: f32,
     pub geom_cache: &'a mut GeometryCache,
+    pub blend_shape_cache: &'a mut BlendShapeCache,
     pub cascade: usize,
     pub batch_storage: &'a BatchStorage,
 }
@@ -420,6 +442,7 @@ impl PointShadowMapRenderer {
             light_pos,
             light_radius,
             geom_cache,
+            blend_shape_cache,
             cascade,
             batch_storage,
         } = args;
@@ -452,7 +475,9 @@ impl PointShadowMapRenderer {
             let frustum = Frustum::from(light_view_projection_matrix).unwrap();
 
             for batch in batch_storage.batches.iter() {
-                let geometry = geom_cache.get(state, &batch.data.read().unwrap());
+                let data = batch.data.read().unwrap();
+                let geometry = geom_cache.get(state, &data);
+                let blend_shapes = blend_shape_cache.storage(state, &data);
 
                 for instance in batch.instances.iter() {
                     let node = &graph[instance.owner];
@@ -482,7 +507,7 @@ impl PointShadowMapRenderer {
                                 blend: false,
                             },
                             |program_binding| {
-                                program_binding
+                                let program_binding = program_binding
                                     .set_vector3(&shader.light_position, &light_pos)
                                     .set_matrix4(&shader.world_matrix, &instance.world_transform)
                                     .set_matrix4(
@@ -495,6 +520,11 @@ impl PointShadowMapRenderer {
                                         instance.bone_matrices.as_slice(),
                                     )
                                     .set_texture(&shader.diffuse_texture, &batch.diffuse_texture);
+                                shader.blend_shapes.bind(
+                                    program_binding,
+                                    &blend_shapes,
+                                    &instance.blend_shape_weights,
+                                );
                             },
                         );
                     }
@@ -1,5 +1,5 @@
 use crate::{
-    core::{math::Matrix4Ext, math::Rect, scope_profile},
+    core::{algebra::Vector4, math::Matrix4Ext, math::Rect, scope_profile},
     renderer::framework::{
         error::FrameworkError,
         framebuffer::{CullFace, DrawParameters, FrameBuffer},
@@ -23,6 +23,7 @@ struct SpriteShader {
     diffuse_texture: UniformLocation,
     size: UniformLocation,
     rotation: UniformLocation,
+    frame_rect: UniformLocation,
 }
 
 impl SpriteShader {
@@ -39,6 +40,7 @@ impl SpriteShader {
             diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
             color: program.uniform_location(state, "color")?,
             rotation: program.uniform_location(state, "rotation")?,
+            frame_rect: program.uniform_location(state, "frameRect")?,
             program,
         })
     }
@@ -125,6 +127,14 @@ impl SpriteRenderer {
                 white_dummy.clone()
             };
 
+            let frame_rect = sprite.frame_rect();
+            let frame_rect = Vector4::new(
+                frame_rect.x(),
+                frame_rect.y(),
+                frame_rect.w(),
+                frame_rect.h(),
+            );
+
             statistics += framebuffer.draw(
                 geom_map.get(state, &self.surface),
                 state,
@@ -148,7 +158,8 @@ impl SpriteRenderer {
                         .set_vector3(&self.shader.camera_side_vector, &camera_side)
                         .set_float(&self.shader.size, sprite.size())
                         .set_color(&self.shader.color, &sprite.color())
-                        .set_float(&self.shader.rotation, sprite.rotation());
+                        .set_float(&self.shader.rotation, sprite.rotation())
+                        .set_vector4(&self.shader.frame_rect, &frame_rect);
                 },
             );
         }
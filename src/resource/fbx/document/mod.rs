@@ -122,7 +122,11 @@ fn is_binary(data: &[u8]) -> bool {
 impl FbxDocument {
     pub async fn new<P: AsRef<Path>>(path: P) -> Result<FbxDocument, FbxError> {
         let data = io::load_file(path).await?;
+        Self::from_data(data)
+    }
 
+    /// Parses document from contents of a binary or ASCII FBX file.
+    pub fn from_data(data: Vec<u8>) -> Result<FbxDocument, FbxError> {
         let is_bin = is_binary(&data);
 
         let mut reader = Cursor::new(data);
@@ -13,10 +13,15 @@ mod scene;
 use crate::scene::mesh::buffer::{VertexAttributeKind, VertexWriteTrait};
 use crate::scene::mesh::vertex::{AnimatedVertex, StaticVertex};
 use crate::{
-    animation::{Animation, AnimationContainer, KeyFrame, Track},
+    animation::{
+        compression::KeyReductionOptions,
+        property::{NodeProperty, PropertyTrack, PropertyValue},
+        Animation, AnimationContainer, KeyFrame, Track,
+    },
     core::instant::Instant,
     core::{
         algebra::{Matrix4, Point3, UnitQuaternion, Vector2, Vector3, Vector4},
+        curve::CurveKeyKind,
         math::{self, triangulator::triangulate, RotationOrder},
         pool::Handle,
     },
@@ -25,14 +30,15 @@ use crate::{
         document::FbxDocument,
         error::FbxError,
         scene::{
-            animation::FbxAnimationCurveNodeType, geometry::FbxGeometry, model::FbxModel,
-            FbxComponent, FbxMapping, FbxScene,
+            animation::FbxAnimationCurveNodeType, blend_shape::FbxBlendShapeChannel,
+            geometry::FbxGeometry, model::FbxModel, FbxComponent, FbxMapping, FbxScene,
         },
     },
     scene::{
         base::BaseBuilder,
         graph::Graph,
         mesh::{
+            blend_shape::{BlendShape, BlendShapeDelta},
             surface::{Surface, SurfaceData, VertexWeightSet},
             MeshBuilder,
         },
@@ -239,6 +245,29 @@ impl FbxMeshBuilder {
 struct FbxSurfaceData {
     builder: FbxMeshBuilder,
     skin_data: Vec<VertexWeightSet>,
+    // Index of control point of geometry for every vertex, it is used to map offsets of
+    // blend shapes to vertices.
+    control_points: Vec<usize>,
+}
+
+/// Returns every blend shape channel of every geometry of a model together with handle of
+/// the geometry. Index of a channel in the list is index of blend shape in the mesh.
+fn blend_shape_channels<'a>(
+    fbx_scene: &'a FbxScene,
+    model: &FbxModel,
+) -> Result<Vec<(Handle<FbxComponent>, &'a FbxBlendShapeChannel)>, FbxError> {
+    let mut channels = Vec::new();
+    for &geom_handle in model.geoms.iter() {
+        let geom = fbx_scene.get(geom_handle).as_geometry()?;
+        for &blend_shape_handle in geom.blend_shapes.iter() {
+            let blend_shape = fbx_scene.get(blend_shape_handle).as_blend_shape()?;
+            for &channel_handle in blend_shape.channels.iter() {
+                let channel = fbx_scene.get(channel_handle).as_blend_shape_channel()?;
+                channels.push((geom_handle, channel));
+            }
+        }
+    }
+    Ok(channels)
 }
 
 fn create_surfaces(
@@ -292,6 +321,7 @@ fn convert_mesh(
     fbx_scene: &FbxScene,
     resource_manager: ResourceManager,
     model: &FbxModel,
+    blend_shape_channels: &[(Handle<FbxComponent>, &FbxBlendShapeChannel)],
     graph: &mut Graph,
 ) -> Result<Handle<Node>, FbxError> {
     let geometric_transform = Matrix4::new_translation(&model.geometric_translation)
@@ -318,6 +348,7 @@ fn convert_mesh(
                     FbxMeshBuilder::Animated(RawMeshBuilder::new(1024, 1024))
                 },
                 skin_data: Default::default(),
+                control_points: Default::default(),
             };
             model.materials.len().max(1)
         ];
@@ -351,6 +382,7 @@ fn convert_mesh(
                         FbxMeshBuilder::Animated(ref mut builder) => builder.insert(vertex.into()),
                     };
                     if is_unique_vertex {
+                        data.control_points.push(index);
                         if let Some(skin_data) = weights {
                             data.skin_data.push(skin_data);
                         }
@@ -364,6 +396,11 @@ fn convert_mesh(
             }
         }
 
+        let control_points = data_set
+            .iter_mut()
+            .map(|data| std::mem::take(&mut data.control_points))
+            .collect::<Vec<_>>();
+
         let mut surfaces = create_surfaces(fbx_scene, data_set, resource_manager.clone(), model)?;
 
         if geom.tangents.is_none() {
@@ -377,6 +414,46 @@ fn convert_mesh(
             }
         }
 
+        if !blend_shape_channels.is_empty() {
+            // Offsets of control points of every channel, channels of other geometries have
+            // no offsets.
+            let mut channel_deltas = Vec::with_capacity(blend_shape_channels.len());
+            for &(channel_geom_handle, channel) in blend_shape_channels.iter() {
+                channel_deltas.push(match channel.shapes.first() {
+                    Some(&shape_handle) if channel_geom_handle == geom_handle => Some(
+                        fbx_scene
+                            .get(shape_handle)
+                            .as_shape()?
+                            .control_point_deltas(geom.vertices.len()),
+                    ),
+                    _ => None,
+                });
+            }
+
+            for (surface, control_points) in surfaces.iter().zip(control_points.iter()) {
+                let data = surface.data();
+                let mut data = data.write().unwrap();
+                for ((_, channel), deltas) in blend_shape_channels.iter().zip(channel_deltas.iter())
+                {
+                    let deltas = match deltas {
+                        Some(deltas) => control_points
+                            .iter()
+                            .map(|&control_point| {
+                                let (position, normal) = deltas[control_point];
+                                BlendShapeDelta {
+                                    position: geometric_transform.transform_vector(&position),
+                                    normal: geometric_transform.transform_vector(&normal),
+                                    tangent: Default::default(),
+                                }
+                            })
+                            .collect(),
+                        None => Default::default(),
+                    };
+                    data.add_blend_shape(BlendShape::new(&channel.name, deltas));
+                }
+            }
+        }
+
         for surface in surfaces {
             mesh_surfaces.push(surface);
         }
@@ -384,6 +461,12 @@ fn convert_mesh(
 
     Ok(MeshBuilder::new(base)
         .with_surfaces(mesh_surfaces)
+        .with_blend_shape_weights(
+            blend_shape_channels
+                .iter()
+                .map(|(_, channel)| channel.deform_percent / 100.0)
+                .collect(),
+        )
         .build(graph))
 }
 
@@ -406,19 +489,34 @@ fn convert_model_to_base(model: &FbxModel) -> BaseBuilder {
         )
 }
 
+/// Every animation stack of FBX is converted to separate animation clip.
+struct FbxAnimationClip {
+    animation: Handle<Animation>,
+    // `None` means that clip uses all animation curve nodes of the file.
+    curve_nodes: Option<HashSet<Handle<FbxComponent>>>,
+}
+
 fn convert_model(
     fbx_scene: &FbxScene,
     model: &FbxModel,
     resource_manager: ResourceManager,
     graph: &mut Graph,
     animations: &mut AnimationContainer,
-    animation_handle: Handle<Animation>,
+    clips: &[FbxAnimationClip],
 ) -> Result<Handle<Node>, FbxError> {
     let base = convert_model_to_base(model);
+    let blend_shape_channels = blend_shape_channels(fbx_scene, model)?;
 
     // Create node with correct kind.
     let node_handle = if !model.geoms.is_empty() {
-        convert_mesh(base, fbx_scene, resource_manager, model, graph)?
+        convert_mesh(
+            base,
+            fbx_scene,
+            resource_manager,
+            model,
+            &blend_shape_channels,
+            graph,
+        )?
     } else if model.light.is_some() {
         fbx_scene.get(model.light).as_light()?.convert(base, graph)
     } else {
@@ -426,12 +524,50 @@ fn convert_model(
     };
 
     // Convert animations
-    if !model.animation_curve_nodes.is_empty() {
+    for clip in clips {
+        // Weights of blend shapes are animated by "DeformPercent" curves of channels.
+        for (index, (_, channel)) in blend_shape_channels.iter().enumerate() {
+            for &curve_node_handle in channel.animation_curve_nodes.iter() {
+                if let Some(curve_nodes) = clip.curve_nodes.as_ref() {
+                    if !curve_nodes.contains(&curve_node_handle) {
+                        continue;
+                    }
+                }
+                if let FbxComponent::AnimationCurveNode(curve_node) =
+                    fbx_scene.get(curve_node_handle)
+                {
+                    let mut track = PropertyTrack::new(
+                        node_handle,
+                        NodeProperty::BlendShapeWeight(index as u32),
+                    );
+                    if let Some(&curve_handle) = curve_node.curves.first() {
+                        if let FbxComponent::AnimationCurve(curve) = fbx_scene.get(curve_handle) {
+                            for key in curve.keys.iter() {
+                                track.add_key(
+                                    key.time,
+                                    PropertyValue::Float(key.value / 100.0),
+                                    CurveKeyKind::Linear,
+                                );
+                            }
+                        }
+                    }
+                    animations.get_mut(clip.animation).add_property_track(track);
+                }
+            }
+        }
+
         // Find supported curve nodes (translation, rotation, scale)
+        let mut animated = false;
         let mut lcl_translation = None;
         let mut lcl_rotation = None;
         let mut lcl_scale = None;
         for &anim_curve_node_handle in model.animation_curve_nodes.iter() {
+            if let Some(curve_nodes) = clip.curve_nodes.as_ref() {
+                if !curve_nodes.contains(&anim_curve_node_handle) {
+                    continue;
+                }
+            }
+            animated = true;
             let component = fbx_scene.get(anim_curve_node_handle);
             if let FbxComponent::AnimationCurveNode(curve_node) = component {
                 if curve_node.actual_type == FbxAnimationCurveNodeType::Rotation {
@@ -444,6 +580,10 @@ fn convert_model(
             }
         }
 
+        if !animated {
+            continue;
+        }
+
         // Convert to engine format
         let mut track = Track::new();
         track.set_node(node_handle);
@@ -492,7 +632,10 @@ fn convert_model(
             time = next_time;
         }
 
-        animations.get_mut(animation_handle).add_track(track);
+        // FBX usually has a key per frame for every curve, most of them are redundant.
+        track.reduce_keys(&KeyReductionOptions::default());
+
+        animations.get_mut(clip.animation).add_track(track);
     }
 
     Ok(node_handle)
@@ -507,7 +650,32 @@ fn convert(
     scene: &mut Scene,
 ) -> Result<(), FbxError> {
     let root = scene.graph.get_root();
-    let animation_handle = scene.animations.add(Animation::default());
+
+    let mut clips = Vec::new();
+    for (_, component) in fbx_scene.pair_iter() {
+        if let FbxComponent::AnimationStack(stack) = component {
+            let mut animation = Animation::default();
+            animation.set_name(&stack.name);
+            let mut curve_nodes = HashSet::new();
+            for &layer_handle in stack.layers.iter() {
+                if let FbxComponent::AnimationLayer(layer) = fbx_scene.get(layer_handle) {
+                    curve_nodes.extend(layer.curve_nodes.iter().copied());
+                }
+            }
+            clips.push(FbxAnimationClip {
+                animation: scene.animations.add(animation),
+                curve_nodes: Some(curve_nodes),
+            });
+        }
+    }
+    if clips.is_empty() {
+        // Old files may have no animation stacks, put every animation curve into single clip.
+        clips.push(FbxAnimationClip {
+            animation: scene.animations.add(Animation::default()),
+            curve_nodes: None,
+        });
+    }
+
     let mut fbx_model_to_node_map = HashMap::new();
     for (component_handle, component) in fbx_scene.pair_iter() {
         if let FbxComponent::Model(model) = component {
@@ -517,7 +685,7 @@ fn convert(
                 resource_manager.clone(),
                 &mut scene.graph,
                 &mut scene.animations,
-                animation_handle,
+                &clips,
             )?;
             scene.graph.link_nodes(node, root);
             fbx_model_to_node_map.insert(component_handle, node);
@@ -144,3 +144,45 @@ impl FbxAnimationCurveNode {
         quat_from_euler(self.eval_vec3(scene, time))
     }
 }
+
+pub struct FbxAnimationLayer {
+    pub curve_nodes: Vec<Handle<FbxComponent>>,
+}
+
+impl FbxAnimationLayer {
+    pub fn read(_node_handle: Handle<FbxNode>, _nodes: &FbxNodeContainer) -> Self {
+        FbxAnimationLayer {
+            curve_nodes: Vec::new(),
+        }
+    }
+}
+
+/// Animation stack is a "take" - named set of animation layers. Single FBX file can
+/// contain multiple takes.
+pub struct FbxAnimationStack {
+    pub name: String,
+    pub layers: Vec<Handle<FbxComponent>>,
+}
+
+impl FbxAnimationStack {
+    pub fn read(node_handle: Handle<FbxNode>, nodes: &FbxNodeContainer) -> Self {
+        let mut name = nodes
+            .get(node_handle)
+            .get_attrib(1)
+            .map(|attrib| attrib.as_string())
+            .unwrap_or_default();
+
+        // Binary FBX stores names as "Name\0\x01Class", ASCII FBX - as "Class::Name".
+        if let Some(position) = name.find('\0') {
+            name.truncate(position);
+        }
+        if name.starts_with("AnimStack::") {
+            name = name.chars().skip(11).collect();
+        }
+
+        FbxAnimationStack {
+            name,
+            layers: Vec::new(),
+        }
+    }
+}
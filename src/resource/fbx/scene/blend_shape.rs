@@ -0,0 +1,222 @@
+use crate::{
+    core::{algebra::Vector3, pool::Handle},
+    resource::fbx::{
+        document::{FbxNode, FbxNodeContainer},
+        error::FbxError,
+        scene::FbxComponent,
+    },
+};
+
+/// Target geometry of a blend shape channel, it stores offsets only for affected control
+/// points.
+pub struct FbxShape {
+    pub indices: Vec<i32>,
+    pub vertices: Vec<Vector3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+}
+
+fn read_vec3_array(
+    shape_node_handle: Handle<FbxNode>,
+    nodes: &FbxNodeContainer,
+    name: &str,
+) -> Result<Vec<Vector3<f32>>, FbxError> {
+    let array_node_handle = nodes.find(shape_node_handle, name)?;
+    let array_node = nodes.get_by_name(array_node_handle, "a")?;
+    let mut vectors = Vec::with_capacity(array_node.attrib_count() / 3);
+    for vector in array_node.attributes().chunks_exact(3) {
+        vectors.push(Vector3::new(
+            vector[0].as_f32()?,
+            vector[1].as_f32()?,
+            vector[2].as_f32()?,
+        ));
+    }
+    Ok(vectors)
+}
+
+impl FbxShape {
+    pub(in crate::resource::fbx) fn read(
+        shape_node_handle: Handle<FbxNode>,
+        nodes: &FbxNodeContainer,
+    ) -> Result<Self, FbxError> {
+        let indices_node_handle = nodes.find(shape_node_handle, "Indexes")?;
+        let indices_array_node = nodes.get_by_name(indices_node_handle, "a")?;
+        let mut indices = Vec::with_capacity(indices_array_node.attrib_count());
+        for index in indices_array_node.attributes() {
+            indices.push(index.as_i32()?);
+        }
+
+        Ok(Self {
+            indices,
+            vertices: read_vec3_array(shape_node_handle, nodes, "Vertices")?,
+            // Normals are optional.
+            normals: read_vec3_array(shape_node_handle, nodes, "Normals").unwrap_or_default(),
+        })
+    }
+
+    /// Returns offsets of position and normal of every control point of a geometry with
+    /// given amount of control points.
+    pub fn control_point_deltas(
+        &self,
+        control_point_count: usize,
+    ) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+        let mut deltas = vec![(Vector3::default(), Vector3::default()); control_point_count];
+        for (i, &index) in self.indices.iter().enumerate() {
+            if let Some(delta) = deltas.get_mut(index as usize) {
+                if let Some(position) = self.vertices.get(i) {
+                    delta.0 = *position;
+                }
+                if let Some(normal) = self.normals.get(i) {
+                    delta.1 = *normal;
+                }
+            }
+        }
+        deltas
+    }
+}
+
+/// Blend shape deformer, it is attached to a geometry and has a set of channels.
+pub struct FbxBlendShape {
+    pub channels: Vec<Handle<FbxComponent>>,
+}
+
+impl FbxBlendShape {
+    pub(in crate::resource::fbx) fn read() -> Self {
+        Self {
+            channels: Default::default(),
+        }
+    }
+}
+
+/// Single blend shape of a geometry. FBX allows to have multiple target shapes per channel
+/// (in-between shapes), only the first one is used.
+pub struct FbxBlendShapeChannel {
+    pub name: String,
+    /// Initial weight in [0; 100] range.
+    pub deform_percent: f32,
+    pub shapes: Vec<Handle<FbxComponent>>,
+    /// Animation curve nodes of deform percent.
+    pub animation_curve_nodes: Vec<Handle<FbxComponent>>,
+}
+
+impl FbxBlendShapeChannel {
+    pub(in crate::resource::fbx) fn read(
+        channel_node_handle: Handle<FbxNode>,
+        nodes: &FbxNodeContainer,
+    ) -> Result<Self, FbxError> {
+        let mut name = nodes.get(channel_node_handle).get_attrib(1)?.as_string();
+
+        // Binary FBX stores names as "Name\0\x01Class", ASCII FBX - as "Class::Name".
+        if let Some(position) = name.find('\0') {
+            name.truncate(position);
+        }
+        if let Some(position) = name.find("::") {
+            name = name[(position + 2)..].to_owned();
+        }
+
+        let deform_percent = match nodes.get_by_name(channel_node_handle, "DeformPercent") {
+            Ok(deform_percent) => deform_percent.get_attrib(0)?.as_f32()?,
+            Err(_) => 0.0,
+        };
+
+        Ok(Self {
+            name,
+            deform_percent,
+            shapes: Default::default(),
+            animation_curve_nodes: Default::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        resource::fbx::{
+            document::FbxDocument,
+            scene::{FbxComponent, FbxScene},
+        },
+    };
+
+    const DOCUMENT: &str = r#"; FBX 7.4.0 project file
+FBXHeaderExtension:  {
+    FBXVersion: 7400
+}
+Objects:  {
+    Geometry: 1, "Geometry::Plane", "Mesh" {
+        Vertices: *9 {
+            a: 0,0,0,1,0,0,0,1,0
+        }
+        PolygonVertexIndex: *3 {
+            a: 0,1,-3
+        }
+    }
+    Geometry: 2, "Geometry::Smile", "Shape" {
+        Indexes: *1 {
+            a: 2
+        }
+        Vertices: *3 {
+            a: 0,0.5,0
+        }
+        Normals: *3 {
+            a: 0,0,1
+        }
+    }
+    Deformer: 3, "Deformer::Plane", "BlendShape" {
+        Version: 100
+    }
+    Deformer: 4, "SubDeformer::Smile", "BlendShapeChannel" {
+        DeformPercent: 25
+    }
+}
+Connections:  {
+    C: "OO",3,1
+    C: "OO",4,3
+    C: "OO",2,4
+}
+"#;
+
+    #[test]
+    fn test_blend_shape_import() {
+        let document = FbxDocument::from_data(DOCUMENT.as_bytes().to_vec()).unwrap();
+        let scene = FbxScene::new(&document).unwrap();
+
+        let geometry = scene
+            .pair_iter()
+            .find_map(|(_, component)| match component {
+                FbxComponent::Geometry(geometry) => Some(geometry),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(geometry.blend_shapes.len(), 1);
+
+        let blend_shape = scene
+            .get(geometry.blend_shapes[0])
+            .as_blend_shape()
+            .unwrap();
+        assert_eq!(blend_shape.channels.len(), 1);
+
+        let channel = scene
+            .get(blend_shape.channels[0])
+            .as_blend_shape_channel()
+            .unwrap();
+        assert_eq!(channel.name, "Smile");
+        assert_eq!(channel.deform_percent, 25.0);
+        assert_eq!(channel.shapes.len(), 1);
+
+        let shape = scene.get(channel.shapes[0]).as_shape().unwrap();
+        assert_eq!(shape.indices, vec![2]);
+        assert_eq!(shape.vertices, vec![Vector3::new(0.0, 0.5, 0.0)]);
+        assert_eq!(shape.normals, vec![Vector3::new(0.0, 0.0, 1.0)]);
+
+        // Only affected control points have offsets.
+        let deltas = shape.control_point_deltas(geometry.vertices.len());
+        assert_eq!(
+            deltas,
+            vec![
+                (Vector3::default(), Vector3::default()),
+                (Vector3::default(), Vector3::default()),
+                (Vector3::new(0.0, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+            ]
+        );
+    }
+}
@@ -5,7 +5,10 @@ use crate::{
         document::{attribute::FbxAttribute, FbxDocument, FbxNode, FbxNodeContainer},
         error::FbxError,
         scene::{
-            animation::{FbxAnimationCurve, FbxAnimationCurveNode},
+            animation::{
+                FbxAnimationCurve, FbxAnimationCurveNode, FbxAnimationLayer, FbxAnimationStack,
+            },
+            blend_shape::{FbxBlendShape, FbxBlendShapeChannel, FbxShape},
             geometry::FbxGeometry,
             light::FbxLight,
             model::FbxModel,
@@ -16,6 +19,7 @@ use crate::{
 use std::collections::HashMap;
 
 pub mod animation;
+pub mod blend_shape;
 pub mod geometry;
 pub mod light;
 pub mod model;
@@ -50,9 +54,14 @@ impl FbxScene {
             let mut component_handle: Handle<FbxComponent> = Handle::NONE;
             match object.name() {
                 "Geometry" => {
-                    component_handle = components.spawn(FbxComponent::Geometry(Box::new(
-                        FbxGeometry::read(*object_handle, nodes)?,
-                    )));
+                    if object.attrib_count() > 2 && object.get_attrib(2)?.as_string() == "Shape" {
+                        component_handle = components
+                            .spawn(FbxComponent::Shape(FbxShape::read(*object_handle, nodes)?));
+                    } else {
+                        component_handle = components.spawn(FbxComponent::Geometry(Box::new(
+                            FbxGeometry::read(*object_handle, nodes)?,
+                        )));
+                    }
                 }
                 "Model" => {
                     component_handle = components.spawn(FbxComponent::Model(Box::new(
@@ -85,6 +94,16 @@ impl FbxScene {
                         FbxAnimationCurveNode::read(*object_handle, nodes)?,
                     ));
                 }
+                "AnimationLayer" => {
+                    component_handle = components.spawn(FbxComponent::AnimationLayer(
+                        FbxAnimationLayer::read(*object_handle, nodes),
+                    ));
+                }
+                "AnimationStack" => {
+                    component_handle = components.spawn(FbxComponent::AnimationStack(
+                        FbxAnimationStack::read(*object_handle, nodes),
+                    ));
+                }
                 "Deformer" => match object.get_attrib(2)?.as_string().as_str() {
                     "Cluster" => {
                         component_handle = components.spawn(FbxComponent::SubDeformer(
@@ -96,6 +115,15 @@ impl FbxScene {
                             FbxDeformer::read(*object_handle, nodes),
                         ));
                     }
+                    "BlendShape" => {
+                        component_handle =
+                            components.spawn(FbxComponent::BlendShape(FbxBlendShape::read()));
+                    }
+                    "BlendShapeChannel" => {
+                        component_handle = components.spawn(FbxComponent::BlendShapeChannel(
+                            FbxBlendShapeChannel::read(*object_handle, nodes)?,
+                        ));
+                    }
                     _ => (),
                 },
                 _ => (),
@@ -164,6 +192,18 @@ fn link_child_with_parent_component(
                 anim_curve_node.curves.push(child_handle);
             }
         }
+        // Link animation layer with animation curve nodes
+        FbxComponent::AnimationLayer(layer) => {
+            if let FbxComponent::AnimationCurveNode(_) = child {
+                layer.curve_nodes.push(child_handle);
+            }
+        }
+        // Link animation stack with animation layers
+        FbxComponent::AnimationStack(stack) => {
+            if let FbxComponent::AnimationLayer(_) = child {
+                stack.layers.push(child_handle);
+            }
+        }
         // Link deformer with sub-deformers
         FbxComponent::Deformer(deformer) => {
             if let FbxComponent::SubDeformer(_) = child {
@@ -171,11 +211,23 @@ fn link_child_with_parent_component(
             }
         }
         // Link geometry with deformers
-        FbxComponent::Geometry(geometry) => {
-            if let FbxComponent::Deformer(_) = child {
-                geometry.deformers.push(child_handle);
+        FbxComponent::Geometry(geometry) => match child {
+            FbxComponent::Deformer(_) => geometry.deformers.push(child_handle),
+            FbxComponent::BlendShape(_) => geometry.blend_shapes.push(child_handle),
+            _ => (),
+        },
+        // Link blend shape with channels
+        FbxComponent::BlendShape(blend_shape) => {
+            if let FbxComponent::BlendShapeChannel(_) = child {
+                blend_shape.channels.push(child_handle);
             }
         }
+        // Link blend shape channel with target shapes and animation curve nodes
+        FbxComponent::BlendShapeChannel(channel) => match child {
+            FbxComponent::Shape(_) => channel.shapes.push(child_handle),
+            FbxComponent::AnimationCurveNode(_) => channel.animation_curve_nodes.push(child_handle),
+            _ => (),
+        },
         // Link sub-deformer with model
         FbxComponent::SubDeformer(sub_deformer) => {
             if let FbxComponent::Model(model) = child {
@@ -197,7 +249,12 @@ pub enum FbxComponent {
     Material(FbxMaterial),
     AnimationCurveNode(FbxAnimationCurveNode),
     AnimationCurve(FbxAnimationCurve),
+    AnimationLayer(FbxAnimationLayer),
+    AnimationStack(FbxAnimationStack),
     Geometry(Box<FbxGeometry>),
+    Shape(FbxShape),
+    BlendShape(FbxBlendShape),
+    BlendShapeChannel(FbxBlendShapeChannel),
 }
 
 macro_rules! define_as {
@@ -219,6 +276,14 @@ impl FbxComponent {
     define_as!(self, as_light, FbxLight, Light);
     define_as!(self, as_material, FbxMaterial, Material);
     define_as!(self, as_geometry, FbxGeometry, Geometry);
+    define_as!(self, as_shape, FbxShape, Shape);
+    define_as!(self, as_blend_shape, FbxBlendShape, BlendShape);
+    define_as!(
+        self,
+        as_blend_shape_channel,
+        FbxBlendShapeChannel,
+        BlendShapeChannel
+    );
 }
 
 // https://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_anim_curve_html
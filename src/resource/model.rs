@@ -131,48 +131,101 @@ impl Model {
     ///
     /// # Notes
     ///
-    /// Most of the 3d model formats can contain only one animation, so in most cases
-    /// this function will return vector with only one animation.
+    /// Model can contain multiple animation clips (for example FBX can have multiple takes),
+    /// this function retargets all of them. Use [`Model::retarget_animation`] to retarget
+    /// only specific clip.
     pub fn retarget_animations(
         &self,
         root: Handle<Node>,
         dest_scene: &mut Scene,
     ) -> Vec<Handle<Animation>> {
         let data = self.data_ref();
-        let mut animation_handles = Vec::new();
-
-        for ref_anim in data.scene.animations.iter() {
-            let mut anim_copy = ref_anim.clone();
-
-            // Keep reference to resource from which this animation was taken from. This will help
-            // us to correctly reload keyframes for each track when we'll be loading a save file.
-            anim_copy.resource = Some(self.clone());
-
-            // Remap animation track nodes from resource to instance. This is required
-            // because we've made a plain copy and it has tracks with node handles mapped
-            // to nodes of internal scene.
-            for (i, ref_track) in ref_anim.get_tracks().iter().enumerate() {
-                let ref_node = &data.scene.graph[ref_track.get_node()];
-                // Find instantiated node that corresponds to node in resource
-                let instance_node = dest_scene.graph.find_by_name(root, ref_node.name());
-                if instance_node.is_none() {
-                    Log::writeln(
-                        MessageKind::Error,
-                        format!(
-                            "Failed to retarget animation {:?} for node {}",
-                            data.path(),
-                            ref_node.name()
-                        ),
-                    );
-                }
-                // One-to-one track mapping so there is [i] indexing.
-                anim_copy.get_tracks_mut()[i].set_node(instance_node);
+
+        data.scene
+            .animations
+            .iter()
+            .map(|ref_anim| self.retarget_animation_internal(&data, ref_anim, root, dest_scene))
+            .collect()
+    }
+
+    /// Tries to retarget animation clip with given name from the model resource to a node
+    /// hierarchy starting from `root` on a given scene. Returns `None` if there is no clip
+    /// with such name. See [`Model::retarget_animations`] for more info.
+    pub fn retarget_animation<N: AsRef<str>>(
+        &self,
+        name: N,
+        root: Handle<Node>,
+        dest_scene: &mut Scene,
+    ) -> Option<Handle<Animation>> {
+        let data = self.data_ref();
+
+        let ref_anim = data.find_animation(name)?;
+        Some(self.retarget_animation_internal(&data, ref_anim, root, dest_scene))
+    }
+
+    /// Tries to instantiate model from given resource with only specified animation clips.
+    /// Clips that are missing in the resource will be ignored.
+    pub fn instantiate_with_clips<N: AsRef<str>>(
+        &self,
+        dest_scene: &mut Scene,
+        clips: &[N],
+    ) -> ModelInstance {
+        let root = self.instantiate_geometry(dest_scene);
+        ModelInstance {
+            root,
+            animations: clips
+                .iter()
+                .filter_map(|name| self.retarget_animation(name, root, dest_scene))
+                .collect(),
+        }
+    }
+
+    fn retarget_animation_internal(
+        &self,
+        data: &ModelData,
+        ref_anim: &Animation,
+        root: Handle<Node>,
+        dest_scene: &mut Scene,
+    ) -> Handle<Animation> {
+        let mut anim_copy = ref_anim.clone();
+
+        // Keep reference to resource from which this animation was taken from. This will help
+        // us to correctly reload keyframes for each track when we'll be loading a save file.
+        anim_copy.resource = Some(self.clone());
+
+        // Remap animation track nodes from resource to instance. This is required
+        // because we've made a plain copy and it has tracks with node handles mapped
+        // to nodes of internal scene.
+        for (i, ref_track) in ref_anim.get_tracks().iter().enumerate() {
+            let ref_node = &data.scene.graph[ref_track.get_node()];
+            // Find instantiated node that corresponds to node in resource
+            let instance_node = dest_scene.graph.find_by_name(root, ref_node.name());
+            if instance_node.is_none() {
+                Log::writeln(
+                    MessageKind::Error,
+                    format!(
+                        "Failed to retarget animation {:?} for node {}",
+                        data.path(),
+                        ref_node.name()
+                    ),
+                );
             }
+            // One-to-one track mapping so there is [i] indexing.
+            anim_copy.get_tracks_mut()[i].set_node(instance_node);
+        }
 
-            animation_handles.push(dest_scene.animations.add(anim_copy));
+        // Property tracks are remapped the same way.
+        for (i, ref_track) in ref_anim.property_tracks().iter().enumerate() {
+            // Node-less tracks (custom properties) do not need retargeting.
+            if ref_track.node().is_none() {
+                continue;
+            }
+            let ref_node = &data.scene.graph[ref_track.node()];
+            let instance_node = dest_scene.graph.find_by_name(root, ref_node.name());
+            anim_copy.property_tracks_mut()[i].set_node(instance_node);
         }
 
-        animation_handles
+        dest_scene.animations.add(anim_copy)
     }
 }
 
@@ -295,4 +348,93 @@ impl ModelData {
     pub fn find_node_by_name(&self, name: &str) -> Handle<Node> {
         self.scene.graph.find_by_name_from_root(name)
     }
+
+    /// Returns iterator over names of animation clips of the model.
+    pub fn animation_names(&self) -> impl Iterator<Item = &str> {
+        self.scene
+            .animations
+            .iter()
+            .map(|animation| animation.name())
+    }
+
+    /// Tries to find animation clip by its name.
+    pub fn find_animation<N: AsRef<str>>(&self, name: N) -> Option<&Animation> {
+        self.scene
+            .animations
+            .iter()
+            .find(|animation| animation.name() == name.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{Animation, Track},
+        core::pool::Handle,
+        resource::{
+            model::{Model, ModelData, NodeMapping},
+            ResourceState,
+        },
+        scene::{base::BaseBuilder, Scene},
+    };
+    use std::path::PathBuf;
+
+    /// Creates model with a single bone and two animation clips that animate the bone.
+    fn make_model() -> Model {
+        let mut scene = Scene::new();
+        let bone = BaseBuilder::new().with_name("Bone").build(&mut scene.graph);
+
+        for name in ["Idle", "Run"].iter() {
+            let mut track = Track::new();
+            track.set_node(bone);
+
+            let mut animation = Animation::default();
+            animation.set_name(name);
+            animation.add_track(track);
+            scene.animations.add(animation);
+        }
+
+        Model::new(ResourceState::Ok(ModelData {
+            path: PathBuf::from("test.fbx"),
+            mapping: NodeMapping::UseNames,
+            scene,
+        }))
+    }
+
+    #[test]
+    fn test_find_by_name() {
+        let model = make_model();
+        let mut scene = Scene::new();
+        let instance = model.instantiate(&mut scene);
+
+        assert_eq!(instance.animations.len(), 2);
+        for (handle, name) in instance.animations.iter().zip(["Idle", "Run"].iter()) {
+            assert_eq!(scene.animations.find_by_name(name), *handle);
+        }
+        assert_eq!(scene.animations.find_by_name("Walk"), Handle::NONE);
+
+        let data = model.data_ref();
+        assert_eq!(data.find_animation("Run").unwrap().name(), "Run");
+        assert!(data.find_animation("Walk").is_none());
+    }
+
+    #[test]
+    fn test_instantiate_with_clips() {
+        let model = make_model();
+        let mut scene = Scene::new();
+        let instance = model.instantiate_with_clips(&mut scene, &["Run", "Walk"]);
+
+        // Missing clip is ignored, other clips are not instantiated.
+        assert_eq!(instance.animations.len(), 1);
+        assert_eq!(scene.animations.iter().count(), 1);
+
+        let animation = &scene.animations[instance.animations[0]];
+        assert_eq!(animation.name(), "Run");
+        assert!(animation.resource.is_some());
+
+        // Track is retargeted to the bone of the instance.
+        let bone = scene.graph.find_by_name(instance.root, "Bone");
+        assert_ne!(bone, Handle::NONE);
+        assert_eq!(animation.get_tracks()[0].get_node(), bone);
+    }
 }
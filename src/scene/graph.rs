@@ -34,7 +34,7 @@ use crate::{
         VecExtensions,
     },
     resource::{model::NodeMapping, ResourceState},
-    scene::{node::Node, transform::TransformBuilder, VisibilityCache},
+    scene::{node::Node, particle_system::Emitter, transform::TransformBuilder, VisibilityCache},
     utils::log::{Log, MessageKind},
 };
 use std::{
@@ -90,6 +90,17 @@ fn remap_handles(old_new_mapping: &HashMap<Handle<Node>, Handle<Node>>, dest_gra
             }
         }
 
+        // Mesh emitters of particle systems are referencing meshes by handles.
+        if let Node::ParticleSystem(particle_system) = new_node {
+            for emitter in particle_system.emitters.iter_mut() {
+                if let Emitter::Mesh(mesh_emitter) = emitter {
+                    if let Some(entry) = old_new_mapping.get(&mesh_emitter.mesh()) {
+                        mesh_emitter.set_mesh(*entry);
+                    }
+                }
+            }
+        }
+
         // LODs also have handles that must be remapped too.
         if let Some(lod_group) = new_node.lod_group_mut() {
             for level in lod_group.levels.iter_mut() {
@@ -765,8 +776,15 @@ impl Graph {
         self.pool.is_valid_handle(node_handle)
     }
 
-    /// Updates nodes in graph using given delta time. There is no need to call it manually.
-    pub fn update_nodes(&mut self, frame_size: Vector2<f32>, dt: f32) {
+    /// Updates nodes in graph using given delta time. `gpu_particle_simulation_supported` tells
+    /// whether particle systems can be simulated on GPU by the renderer. There is no need to
+    /// call it manually.
+    pub fn update_nodes(
+        &mut self,
+        frame_size: Vector2<f32>,
+        dt: f32,
+        gpu_particle_simulation_supported: bool,
+    ) {
         self.update_hierarchical_data();
 
         for i in 0..self.pool.get_capacity() {
@@ -809,7 +827,21 @@ impl Graph {
                                 .as_camera_mut()
                                 .visibility_cache = new_cache;
                         }
-                        Node::ParticleSystem(particle_system) => particle_system.update(dt),
+                        Node::ParticleSystem(particle_system) => {
+                            if particle_system.has_mesh_emitters() {
+                                // Mesh emitters have to read surfaces of other nodes, so particle
+                                // system must be temporarily taken out of the graph.
+                                let handle = self.pool.handle_from_index(i);
+                                let (ticket, mut node) = self.pool.take_reserve(handle);
+                                let particle_system = node.as_particle_system_mut();
+                                particle_system.sync_mesh_emitters(self);
+                                particle_system.update(dt, gpu_particle_simulation_supported);
+                                self.pool.put_back(ticket, node);
+                            } else {
+                                particle_system.update(dt, gpu_particle_simulation_supported)
+                            }
+                        }
+                        Node::Sprite(sprite) => sprite.update(dt),
                         _ => (),
                     }
                 }
@@ -0,0 +1,112 @@
+//! Blend shapes (also known as morph targets) allow to deform a surface by a weighted sum of
+//! per-vertex offsets. They're mostly used for facial animation, where bones are not precise
+//! enough.
+//!
+//! Every blend shape stores an offset of position, normal and tangent for every vertex of a
+//! surface (see [`SurfaceData::add_blend_shape`](super::surface::SurfaceData::add_blend_shape)),
+//! while weights of blend shapes are stored in a mesh (see
+//! [`Mesh::set_blend_shape_weight`](super::Mesh::set_blend_shape_weight)), so the same data
+//! can be shared across multiple meshes with different weights. Surfaces of a mesh should have
+//! the same set of blend shapes, index of a weight in the mesh is index of a blend shape in
+//! every surface of the mesh.
+//!
+//! Weights can be animated using property tracks with
+//! [`NodeProperty::BlendShapeWeight`](crate::animation::property::NodeProperty::BlendShapeWeight).
+//!
+//! Blend shapes are applied on GPU before skinning, currently only by deferred renderer.
+
+use crate::core::{algebra::Vector3, visitor::prelude::*};
+
+/// Offset of a single vertex.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Visit)]
+pub struct BlendShapeDelta {
+    /// Offset of position.
+    pub position: Vector3<f32>,
+    /// Offset of normal.
+    pub normal: Vector3<f32>,
+    /// Offset of tangent.
+    pub tangent: Vector3<f32>,
+}
+
+/// Named set of vertex offsets. See module docs.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct BlendShape {
+    name: String,
+    deltas: Vec<BlendShapeDelta>,
+}
+
+impl BlendShape {
+    /// Creates new blend shape with given offsets, there must be one offset per vertex of
+    /// a surface.
+    pub fn new<N: AsRef<str>>(name: N, deltas: Vec<BlendShapeDelta>) -> Self {
+        Self {
+            name: name.as_ref().to_owned(),
+            deltas,
+        }
+    }
+
+    /// Returns name of the blend shape.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns offsets of vertices.
+    pub fn deltas(&self) -> &[BlendShapeDelta] {
+        &self.deltas
+    }
+
+    pub(in crate) fn deltas_mut(&mut self) -> &mut Vec<BlendShapeDelta> {
+        &mut self.deltas
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::property::{NodeProperty, PropertyValue},
+        core::algebra::{Matrix4, Vector3},
+        scene::{
+            base::BaseBuilder,
+            mesh::{
+                blend_shape::{BlendShape, BlendShapeDelta},
+                surface::{Surface, SurfaceData},
+                MeshBuilder,
+            },
+            node::Node,
+        },
+    };
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn test_blend_shapes() {
+        let mut data = SurfaceData::make_cube(Matrix4::identity());
+        let vertex_count = data.vertex_buffer().vertex_count() as usize;
+        data.add_blend_shape(BlendShape::new(
+            "Smile",
+            vec![BlendShapeDelta {
+                position: Vector3::new(0.0, 1.0, 0.0),
+                ..Default::default()
+            }],
+        ));
+        // Missing offsets are filled with zeros.
+        assert_eq!(data.blend_shapes()[0].deltas().len(), vertex_count);
+
+        data.transform_geometry(&Matrix4::new_scaling(2.0)).unwrap();
+        assert_eq!(
+            data.blend_shapes()[0].deltas()[0].position,
+            Vector3::new(0.0, 2.0, 0.0)
+        );
+
+        let mut node = MeshBuilder::new(BaseBuilder::new())
+            .with_surfaces(vec![Surface::new(Arc::new(RwLock::new(data)))])
+            .build_node();
+        NodeProperty::BlendShapeWeight(0).apply(&mut node, PropertyValue::Float(0.5));
+        if let Node::Mesh(mesh) = node {
+            assert_eq!(mesh.find_blend_shape("Smile"), Some(0));
+            assert_eq!(mesh.blend_shape_weight(0), 0.5);
+            assert_eq!(mesh.blend_shape_weight(1), 0.0);
+        } else {
+            unreachable!()
+        }
+    }
+}
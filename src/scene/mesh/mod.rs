@@ -29,6 +29,7 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
+pub mod blend_shape;
 pub mod buffer;
 pub mod surface;
 pub mod vertex;
@@ -72,6 +73,7 @@ pub struct Mesh {
     bounding_box_dirty: Cell<bool>,
     cast_shadows: bool,
     render_path: RenderPath,
+    blend_shape_weights: Vec<f32>,
 }
 
 impl Default for Mesh {
@@ -83,6 +85,7 @@ impl Default for Mesh {
             bounding_box_dirty: Cell::new(true),
             cast_shadows: true,
             render_path: RenderPath::Deferred,
+            blend_shape_weights: Default::default(),
         }
     }
 }
@@ -118,6 +121,15 @@ impl Visit for Mesh {
         // recreated on resolve stage! Serialization of surfaces needed for procedural surfaces.
         self.surfaces.visit("Surfaces", visitor)?;
 
+        // Backward compatibility - old versions have no blend shapes.
+        if let Err(e) = self.blend_shape_weights.visit("BlendShapeWeights", visitor) {
+            if visitor.is_reading() {
+                self.blend_shape_weights = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
         visitor.leave_region()
     }
 }
@@ -197,6 +209,39 @@ impl Mesh {
         self.render_path
     }
 
+    /// Sets new weight of a blend shape with given index. See [`blend_shape`] module docs
+    /// for more info.
+    pub fn set_blend_shape_weight(&mut self, index: usize, weight: f32) {
+        if index >= self.blend_shape_weights.len() {
+            self.blend_shape_weights.resize(index + 1, 0.0);
+        }
+        self.blend_shape_weights[index] = weight;
+    }
+
+    /// Returns weight of a blend shape with given index.
+    pub fn blend_shape_weight(&self, index: usize) -> f32 {
+        self.blend_shape_weights.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Returns weights of blend shapes of the mesh.
+    pub fn blend_shape_weights(&self) -> &[f32] {
+        &self.blend_shape_weights
+    }
+
+    /// Returns index of a blend shape with given name, if any. Index can be used to set
+    /// weight of the blend shape.
+    pub fn find_blend_shape<N: AsRef<str>>(&self, name: N) -> Option<usize> {
+        self.surfaces.iter().find_map(|surface| {
+            surface
+                .data()
+                .read()
+                .unwrap()
+                .blend_shapes()
+                .iter()
+                .position(|blend_shape| blend_shape.name() == name.as_ref())
+        })
+    }
+
     /// Calculate bounding box in *world coordinates*. This method is very heavy and not
     /// intended to use every frame! WARNING: This method does *not* includes bounds of bones!
     pub fn world_bounding_box(&self) -> AxisAlignedBoundingBox {
@@ -304,6 +349,7 @@ impl Mesh {
             bounding_box_dirty: self.bounding_box_dirty.clone(),
             cast_shadows: self.cast_shadows,
             render_path: self.render_path,
+            blend_shape_weights: self.blend_shape_weights.clone(),
         }
     }
 }
@@ -314,6 +360,7 @@ pub struct MeshBuilder {
     surfaces: Vec<Surface>,
     cast_shadows: bool,
     render_path: RenderPath,
+    blend_shape_weights: Vec<f32>,
 }
 
 impl MeshBuilder {
@@ -324,6 +371,7 @@ impl MeshBuilder {
             surfaces: Default::default(),
             cast_shadows: true,
             render_path: RenderPath::Deferred,
+            blend_shape_weights: Default::default(),
         }
     }
 
@@ -346,6 +394,12 @@ impl MeshBuilder {
         self
     }
 
+    /// Sets desired weights of blend shapes.
+    pub fn with_blend_shape_weights(mut self, weights: Vec<f32>) -> Self {
+        self.blend_shape_weights = weights;
+        self
+    }
+
     /// Creates new mesh.
     pub fn build_node(self) -> Node {
         Node::Mesh(Mesh {
@@ -355,6 +409,7 @@ impl MeshBuilder {
             bounding_box: Default::default(),
             bounding_box_dirty: Cell::new(true),
             render_path: self.render_path,
+            blend_shape_weights: self.blend_shape_weights,
         })
     }
 
@@ -17,6 +17,7 @@ use crate::{
     resource::texture::Texture,
     scene::{
         mesh::{
+            blend_shape::BlendShape,
             buffer::{
                 VertexAttributeDescriptor, VertexAttributeKind, VertexBuffer, VertexReadTrait,
                 VertexWriteTrait,
@@ -40,6 +41,7 @@ use std::{
 pub struct SurfaceData {
     pub(in crate) vertex_buffer: VertexBuffer,
     pub(in crate) triangles: Vec<TriangleDefinition>,
+    blend_shapes: Vec<BlendShape>,
     // If true - indicates that surface was generated and does not have reference
     // resource. Procedural data will be serialized.
     is_procedural: bool,
@@ -50,6 +52,7 @@ impl Default for SurfaceData {
         Self {
             vertex_buffer: Default::default(),
             triangles: Default::default(),
+            blend_shapes: Default::default(),
             is_procedural: false,
         }
     }
@@ -65,6 +68,7 @@ impl SurfaceData {
         Self {
             vertex_buffer,
             triangles,
+            blend_shapes: Default::default(),
             is_procedural,
         }
     }
@@ -94,6 +98,14 @@ impl SurfaceData {
             )?;
         }
 
+        for blend_shape in self.blend_shapes.iter_mut() {
+            for delta in blend_shape.deltas_mut().iter_mut() {
+                delta.position = transform.transform_vector(&delta.position);
+                delta.normal = normal_matrix.transform_vector(&delta.normal);
+                delta.tangent = normal_matrix.transform_vector(&delta.tangent);
+            }
+        }
+
         Ok(())
     }
 
@@ -107,6 +119,7 @@ impl SurfaceData {
         Self {
             vertex_buffer: VertexBuffer::new(raw.vertices.len(), layout, raw.vertices).unwrap(),
             triangles: raw.triangles,
+            blend_shapes: Default::default(),
             is_procedural,
         }
     }
@@ -128,6 +141,27 @@ impl SurfaceData {
         self.triangles.as_slice()
     }
 
+    /// Adds new blend shape to the data. Offsets of the blend shape will be truncated or
+    /// padded with zeros to match vertex count.
+    pub fn add_blend_shape(&mut self, mut blend_shape: BlendShape) {
+        blend_shape.deltas_mut().resize(
+            self.vertex_buffer.vertex_count() as usize,
+            Default::default(),
+        );
+        self.blend_shapes.push(blend_shape);
+    }
+
+    /// Returns shared reference to blend shapes array.
+    #[inline]
+    pub fn blend_shapes(&self) -> &[BlendShape] {
+        &self.blend_shapes
+    }
+
+    /// Removes all blend shapes from the data.
+    pub fn clear_blend_shapes(&mut self) {
+        self.blend_shapes.clear();
+    }
+
     /// Calculates tangents of surface. Tangents are needed for correct lighting, you will
     /// get incorrect lighting if tangents of your surface are invalid! When engine loads
     /// a mesh from "untrusted" source, it automatically calculates tangents for you, so
@@ -831,6 +865,14 @@ impl Visit for SurfaceData {
                         .unwrap();
             };
             self.triangles.visit("Triangles", visitor)?;
+            // Backward compatibility - old versions have no blend shapes.
+            if let Err(e) = self.blend_shapes.visit("BlendShapes", visitor) {
+                if visitor.is_reading() {
+                    self.blend_shapes = Default::default();
+                } else {
+                    return Err(e);
+                }
+            }
         }
 
         visitor.leave_region()
@@ -13,6 +13,7 @@ pub mod node;
 pub mod particle_system;
 pub mod physics;
 pub mod sprite;
+pub mod sprite_sheet;
 pub mod terrain;
 pub mod transform;
 
@@ -21,7 +22,7 @@ use crate::scene::mesh::buffer::{
     VertexAttributeDataKind, VertexAttributeDescriptor, VertexAttributeKind, VertexWriteTrait,
 };
 use crate::{
-    animation::AnimationContainer,
+    animation::{event::AnimationEventQueue, AnimationContainer},
     core::{
         algebra::{Isometry3, Matrix4, Point3, Translation, Vector2, Vector3},
         color::Color,
@@ -34,7 +35,7 @@ use crate::{
     resource::texture::Texture,
     scene::{
         base::PhysicsBinding, graph::Graph, node::Node, physics::Physics,
-        physics::PhysicsPerformanceStatistics,
+        physics::PhysicsPerformanceStatistics, physics::PhysicsSnapshot,
     },
     sound::{context::SoundContext, engine::SoundEngine},
     utils::{lightmap::Lightmap, log::Log, log::MessageKind, navmesh::Navmesh},
@@ -781,6 +782,57 @@ impl SceneDrawingContext {
         }
     }
 
+    /// Draws a wire circle in XY plane of given transform. Mostly useful for 2D scenes.
+    pub fn draw_circle(
+        &mut self,
+        position: Vector3<f32>,
+        radius: f32,
+        segments: usize,
+        transform: Matrix4<f32>,
+        color: Color,
+    ) {
+        let d_phi = 2.0 * std::f32::consts::PI / segments as f32;
+        for i in 0..segments {
+            let (s0, c0) = (d_phi * i as f32).sin_cos();
+            let (s1, c1) = (d_phi * (i + 1) as f32).sin_cos();
+            self.add_line(Line {
+                begin: transform
+                    .transform_point(&Point3::from(position + Vector3::new(c0, s0, 0.0) * radius))
+                    .coords,
+                end: transform
+                    .transform_point(&Point3::from(position + Vector3::new(c1, s1, 0.0) * radius))
+                    .coords,
+                color,
+            });
+        }
+    }
+
+    /// Draws a rectangle with given half extents in XY plane of given transform. Mostly
+    /// useful for 2D scenes.
+    pub fn draw_rectangle(
+        &mut self,
+        half_extents: Vector2<f32>,
+        transform: Matrix4<f32>,
+        color: Color,
+    ) {
+        let corners = [
+            Vector3::new(-half_extents.x, -half_extents.y, 0.0),
+            Vector3::new(half_extents.x, -half_extents.y, 0.0),
+            Vector3::new(half_extents.x, half_extents.y, 0.0),
+            Vector3::new(-half_extents.x, half_extents.y, 0.0),
+        ];
+        for i in 0..corners.len() {
+            let next = (i + 1) % corners.len();
+            self.add_line(Line {
+                begin: transform.transform_point(&Point3::from(corners[i])).coords,
+                end: transform
+                    .transform_point(&Point3::from(corners[next]))
+                    .coords,
+                color,
+            });
+        }
+    }
+
     /// Adds single line into internal buffer.
     pub fn add_line(&mut self, line: Line) {
         self.lines.push(line);
@@ -872,6 +924,11 @@ pub struct Scene {
     /// has handles to graph nodes. See `animation` module docs for more info.
     pub animations: AnimationContainer,
 
+    /// Events of every animation signal passed during updates of the scene. Events of animation
+    /// machines can be added here using `Machine::forward_events`. The queue is limited, the
+    /// oldest events are discarded if nobody reads them. See `animation::event` module docs.
+    pub animation_events: AnimationEventQueue,
+
     /// Physics world. Allows you create various physics objects such as static geometries and
     /// rigid bodies. Rigid bodies then should be linked with graph nodes using binder.
     pub physics: Physics,
@@ -922,6 +979,7 @@ impl Default for Scene {
         Self {
             graph: Default::default(),
             animations: Default::default(),
+            animation_events: Default::default(),
             physics: Default::default(),
             physics_binder: Default::default(),
             render_target: None,
@@ -988,6 +1046,7 @@ impl Scene {
             graph: Graph::new(),
             physics: Default::default(),
             animations: Default::default(),
+            animation_events: Default::default(),
             physics_binder: Default::default(),
             render_target: None,
             lightmap: None,
@@ -1100,8 +1159,44 @@ impl Scene {
         Ok(scene)
     }
 
-    fn update_physics(&mut self) {
-        self.physics.step();
+    fn update_physics(&mut self, dt: f32) {
+        self.physics.update(dt);
+        self.sync_with_physics(false);
+    }
+
+    /// Makes a full copy of physics world together with node-body bindings, see
+    /// [`PhysicsSnapshot`] docs.
+    pub fn snapshot_physics(&self) -> PhysicsSnapshot {
+        let mut snapshot = self.physics.snapshot();
+        snapshot.bindings = Some(self.physics_binder.forward_map().clone());
+        snapshot
+    }
+
+    /// Restores physics world and node-body bindings from given snapshot. Nodes bound to
+    /// bodies are moved to the restored positions of the bodies immediately, regardless of
+    /// binding kind, so the restored state is never overwritten by transforms of nodes.
+    pub fn restore_physics(&mut self, snapshot: &PhysicsSnapshot) {
+        self.physics.restore(snapshot);
+        if let Some(bindings) = snapshot.bindings.as_ref() {
+            self.physics_binder.clear();
+            for (&node, &body) in bindings.iter() {
+                self.physics_binder.bind(node, body);
+            }
+        }
+        self.sync_with_physics(true);
+        self.graph.update_hierarchical_data();
+    }
+
+    /// Performs given amount of physics steps and syncs bound nodes with the bodies, usually
+    /// used after [`Self::restore_physics`] to catch up with current time.
+    pub fn resimulate_physics(&mut self, steps: usize) {
+        self.physics.resimulate(steps);
+        self.sync_with_physics(false);
+    }
+
+    // When `restored` is set, transforms are only copied from bodies to nodes.
+    fn sync_with_physics(&mut self, restored: bool) {
+        self.physics.bind_event_nodes(&self.physics_binder);
 
         self.performance_statistics.physics = self.physics.performance_statistics.clone();
         self.physics.performance_statistics.reset();
@@ -1114,16 +1209,11 @@ impl Scene {
 
         // Sync node positions with assigned physics bodies
         if self.physics_binder.enabled {
-            for (&node_handle, body) in self.physics_binder.forward_map().iter() {
-                let body = physics.body_mut(body).unwrap();
+            for (&node_handle, body_handle) in self.physics_binder.forward_map().iter() {
                 let node = &mut self.graph[node_handle];
                 match node.physics_binding {
-                    PhysicsBinding::NodeWithBody => {
-                        node.local_transform_mut()
-                            .set_position(body.position().translation.vector)
-                            .set_rotation(body.position().rotation);
-                    }
-                    PhysicsBinding::BodyWithNode => {
+                    PhysicsBinding::BodyWithNode if !restored => {
+                        let body = physics.body_mut(body_handle).unwrap();
                         let (r, p) = self.graph.isometric_global_rotation_position(node_handle);
                         body.set_position(
                             Isometry3 {
@@ -1133,9 +1223,19 @@ impl Scene {
                             true,
                         );
                     }
+                    PhysicsBinding::NodeWithBody | PhysicsBinding::BodyWithNode => {
+                        // Position is interpolated between two last physics states if fixed
+                        // timestep interpolation is enabled.
+                        let position = physics.interpolated_body_position(body_handle).unwrap();
+                        node.local_transform_mut()
+                            .set_position(position.translation.vector)
+                            .set_rotation(position.rotation);
+                    }
                 }
             }
         }
+
+        self.physics.sync_vehicle_wheels(&mut self.graph);
     }
 
     /// Removes node from scene with all associated entities, like animations etc. This method
@@ -1276,18 +1376,27 @@ impl Scene {
     }
 
     /// Performs single update tick with given delta time from last frame. Internally
-    /// it updates physics, animations, and each graph node. In most cases there is
-    /// no need to call it directly, engine automatically updates all available scenes.
-    pub fn update(&mut self, frame_size: Vector2<f32>, dt: f32) {
-        self.update_physics();
+    /// it updates physics, animations, and each graph node. `gpu_particle_simulation_supported`
+    /// should be taken from [`crate::renderer::Renderer::is_gpu_particle_simulation_supported`].
+    /// In most cases there is no need to call it directly, engine automatically updates all
+    /// available scenes.
+    pub fn update(
+        &mut self,
+        frame_size: Vector2<f32>,
+        dt: f32,
+        gpu_particle_simulation_supported: bool,
+    ) {
+        self.update_physics(dt);
 
         let last = instant::Instant::now();
-        self.animations.update_animations(dt);
+        self.animations
+            .update_animations_with_events(dt, &mut self.animation_events);
         self.performance_statistics.animations_update_time =
             (instant::Instant::now() - last).as_secs_f32();
 
         let last = instant::Instant::now();
-        self.graph.update_nodes(frame_size, dt);
+        self.graph
+            .update_nodes(frame_size, dt, gpu_particle_simulation_supported);
         self.performance_statistics.graph_update_time =
             (instant::Instant::now() - last).as_secs_f32();
 
@@ -1313,9 +1422,23 @@ impl Scene {
             for track in animation.get_tracks_mut() {
                 track.set_node(old_new_map[&track.get_node()]);
             }
+            // Node-less tracks (custom properties) are kept as is.
+            animation.retain_property_tracks(|track| {
+                track.node().is_none() || old_new_map.contains_key(&track.node())
+            });
+            for track in animation.property_tracks_mut() {
+                if track.node().is_some() {
+                    track.set_node(old_new_map[&track.node()]);
+                }
+            }
+            if let Some(mut settings) = animation.root_motion_settings().cloned() {
+                settings.node = old_new_map.get(&settings.node).cloned().unwrap_or_default();
+                animation.set_root_motion_settings(Some(settings));
+            }
         }
         // It is ok to use old binder here, because handles maps one-to-one.
-        let physics = self.physics.deep_copy(&self.physics_binder, &graph);
+        let mut physics = self.physics.deep_copy(&self.physics_binder, &graph);
+        physics.remap_vehicle_nodes(&old_new_map);
         let mut physics_binder = PhysicsBinder::default();
         for (node, &body) in self.physics_binder.forward_map().iter() {
             // Make sure we bind existing node with new physical body.
@@ -1329,6 +1452,7 @@ impl Scene {
             Self {
                 graph,
                 animations,
+                animation_events: Default::default(),
                 physics,
                 physics_binder,
                 // Render target is intentionally not copied, because it does not makes sense - a copy
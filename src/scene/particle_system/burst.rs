@@ -0,0 +1,93 @@
+//! Bursts allow emitters to spawn a bunch of particles at once at specific moments of
+//! time, in addition to continuous emission defined by spawn rate.
+
+use crate::core::visitor::prelude::*;
+
+/// Burst emits `count` particles at `time` seconds from the moment when emitter started
+/// to work, and then repeats `cycles` times with `interval` seconds between repetitions.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub struct Burst {
+    /// Time of first burst in seconds.
+    pub time: f32,
+    /// Amount of particles emitted per cycle.
+    pub count: u32,
+    /// Amount of cycles, zero means infinite repetition.
+    pub cycles: u32,
+    /// Time between cycles in seconds.
+    pub interval: f32,
+}
+
+impl Default for Burst {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            count: 10,
+            cycles: 1,
+            interval: 1.0,
+        }
+    }
+}
+
+impl Burst {
+    /// Creates new single burst of `count` particles at given time.
+    pub fn new(time: f32, count: u32) -> Self {
+        Self {
+            time,
+            count,
+            cycles: 1,
+            interval: 1.0,
+        }
+    }
+
+    /// Sets amount of cycles (zero means infinite) and interval between them.
+    pub fn with_cycles(mut self, cycles: u32, interval: f32) -> Self {
+        self.cycles = cycles;
+        self.interval = interval;
+        self
+    }
+
+    /// Returns amount of particles that should be emitted in [begin; end) time span.
+    pub fn count_in(&self, begin: f32, end: f32) -> u32 {
+        if end <= self.time || end <= begin {
+            return 0;
+        }
+
+        if self.interval <= 0.0 || self.cycles == 1 {
+            return if begin <= self.time { self.count } else { 0 };
+        }
+
+        let first = ((begin - self.time) / self.interval).ceil().max(0.0) as u64;
+        let mut end_cycle = ((end - self.time) / self.interval).ceil() as u64;
+        if self.cycles != 0 {
+            end_cycle = end_cycle.min(u64::from(self.cycles));
+        }
+
+        if end_cycle > first {
+            ((end_cycle - first) * u64::from(self.count)).min(u64::from(u32::MAX)) as u32
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scene::particle_system::burst::Burst;
+
+    #[test]
+    fn test_burst_count() {
+        let single = Burst::new(1.0, 5);
+        assert_eq!(single.count_in(0.0, 0.5), 0);
+        assert_eq!(single.count_in(0.5, 1.0), 0);
+        assert_eq!(single.count_in(1.0, 2.0), 5);
+        assert_eq!(single.count_in(1.5, 2.0), 0);
+
+        let repeated = Burst::new(0.0, 2).with_cycles(3, 1.0);
+        assert_eq!(repeated.count_in(0.0, 0.1), 2);
+        assert_eq!(repeated.count_in(0.1, 2.5), 4);
+        assert_eq!(repeated.count_in(2.5, 10.0), 0);
+
+        let infinite = Burst::new(0.0, 1).with_cycles(0, 0.5);
+        assert_eq!(infinite.count_in(10.0, 12.0), 4);
+    }
+}
@@ -0,0 +1,186 @@
+//! Force fields are affectors that change velocity of particles of a particle system
+//! depending on position of a particle. Every field works in local coordinates of
+//! particle system.
+
+use crate::core::{algebra::Vector3, math::lerpf, visitor::prelude::*};
+
+/// Point attractor pulls particles to its center. Force linearly fades to zero at the
+/// `radius` distance from the center. Negative strength makes it a repeller.
+#[derive(Copy, Clone, Debug, Visit)]
+pub struct PointAttractor {
+    /// Position of the attractor in local coordinates of particle system.
+    pub position: Vector3<f32>,
+    /// Acceleration at the center of the attractor.
+    pub strength: f32,
+    /// Radius of influence.
+    pub radius: f32,
+}
+
+impl Default for PointAttractor {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            strength: 1.0,
+            radius: 1.0,
+        }
+    }
+}
+
+impl PointAttractor {
+    fn acceleration(&self, position: &Vector3<f32>) -> Vector3<f32> {
+        let offset = self.position - position;
+        let distance = offset.norm();
+        if distance > f32::EPSILON && distance < self.radius {
+            offset.scale(self.strength * (1.0 - distance / self.radius) / distance)
+        } else {
+            Vector3::default()
+        }
+    }
+}
+
+/// Vortex spins particles around its axis. Force linearly fades to zero at the `radius`
+/// distance from the axis.
+#[derive(Copy, Clone, Debug, Visit)]
+pub struct Vortex {
+    /// Position of a point on the axis of the vortex.
+    pub position: Vector3<f32>,
+    /// Direction of the axis of the vortex, does not have to be normalized.
+    pub axis: Vector3<f32>,
+    /// Tangential acceleration near the axis. Sign defines direction of rotation.
+    pub strength: f32,
+    /// Radius of influence.
+    pub radius: f32,
+}
+
+impl Default for Vortex {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            axis: Vector3::y(),
+            strength: 1.0,
+            radius: 1.0,
+        }
+    }
+}
+
+impl Vortex {
+    fn acceleration(&self, position: &Vector3<f32>) -> Vector3<f32> {
+        if let Some(axis) = self.axis.try_normalize(f32::EPSILON) {
+            let offset = position - self.position;
+            // Perpendicular from the axis to the particle.
+            let radial = offset - axis.scale(axis.dot(&offset));
+            let distance = radial.norm();
+            if distance > f32::EPSILON && distance < self.radius {
+                return axis
+                    .cross(&radial)
+                    .scale(self.strength * (1.0 - distance / self.radius) / distance);
+            }
+        }
+        Vector3::default()
+    }
+}
+
+/// Turbulence applies smooth pseudo-random acceleration that depends on position of a
+/// particle and its age, it makes particles to move chaotically.
+#[derive(Copy, Clone, Debug, Visit)]
+pub struct Turbulence {
+    /// Max acceleration.
+    pub strength: f32,
+    /// Spatial frequency of noise, the higher the value, the smaller "vortices" will be.
+    pub frequency: f32,
+    /// Speed of change of noise over time.
+    pub speed: f32,
+}
+
+impl Default for Turbulence {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            frequency: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+impl Turbulence {
+    fn acceleration(&self, position: &Vector3<f32>, time: f32) -> Vector3<f32> {
+        let p = position.scale(self.frequency) + Vector3::repeat(time * self.speed);
+        // Offsets are arbitrary, they just decorrelate components.
+        Vector3::new(
+            value_noise(p),
+            value_noise(p + Vector3::new(31.416, 47.853, 12.793)),
+            value_noise(p + Vector3::new(-17.317, 83.155, -59.561)),
+        )
+        .scale(self.strength)
+    }
+}
+
+/// Wind applies constant acceleration to every particle.
+#[derive(Copy, Clone, Debug, Default, Visit)]
+pub struct Wind {
+    /// Direction and magnitude of the wind.
+    pub acceleration: Vector3<f32>,
+}
+
+/// Force field is an enum over all possible affectors of particles.
+#[derive(Copy, Clone, Debug, Visit)]
+pub enum ForceField {
+    /// See [`PointAttractor`] docs.
+    Attractor(PointAttractor),
+    /// See [`Vortex`] docs.
+    Vortex(Vortex),
+    /// See [`Turbulence`] docs.
+    Turbulence(Turbulence),
+    /// See [`Wind`] docs.
+    Wind(Wind),
+}
+
+impl Default for ForceField {
+    fn default() -> Self {
+        Self::Wind(Default::default())
+    }
+}
+
+impl ForceField {
+    /// Calculates acceleration of a particle at given position in local coordinates of
+    /// a particle system. `time` is an age of the particle, it is used to animate noise.
+    pub fn acceleration(&self, position: &Vector3<f32>, time: f32) -> Vector3<f32> {
+        match self {
+            ForceField::Attractor(attractor) => attractor.acceleration(position),
+            ForceField::Vortex(vortex) => vortex.acceleration(position),
+            ForceField::Turbulence(turbulence) => turbulence.acceleration(position, time),
+            ForceField::Wind(wind) => wind.acceleration,
+        }
+    }
+}
+
+fn hash(x: i32, y: i32, z: i32) -> f32 {
+    let mut h = (x.wrapping_mul(73_856_093)
+        ^ y.wrapping_mul(19_349_663)
+        ^ z.wrapping_mul(83_492_791)) as u32;
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    h ^= h >> 16;
+    // Map to [-1; 1] range.
+    (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+/// Smooth value noise in [-1; 1] range.
+fn value_noise(p: Vector3<f32>) -> f32 {
+    let fx = p.x.floor();
+    let fy = p.y.floor();
+    let fz = p.z.floor();
+
+    let (x, y, z) = (fx as i32, fy as i32, fz as i32);
+
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let tx = smooth(p.x - fx);
+    let ty = smooth(p.y - fy);
+    let tz = smooth(p.z - fz);
+
+    let x00 = lerpf(hash(x, y, z), hash(x + 1, y, z), tx);
+    let x10 = lerpf(hash(x, y + 1, z), hash(x + 1, y + 1, z), tx);
+    let x01 = lerpf(hash(x, y, z + 1), hash(x + 1, y, z + 1), tx);
+    let x11 = lerpf(hash(x, y + 1, z + 1), hash(x + 1, y + 1, z + 1), tx);
+
+    lerpf(lerpf(x00, x10, ty), lerpf(x01, x11, ty), tz)
+}
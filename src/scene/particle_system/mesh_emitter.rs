@@ -0,0 +1,552 @@
+//! Mesh emitter spawns particles on the surface of a mesh.
+//!
+//! Particles are distributed uniformly over the surface of the mesh, this means that
+//! larger triangles will emit more particles. Emitter can optionally follow skinning
+//! of a mesh, so particles will be emitted from actual (animated) surface. This is
+//! useful for effects like a burning character.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Point3, Vector3},
+        numeric_range::NumericRange,
+        pool::Handle,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        graph::Graph,
+        mesh::{
+            buffer::{VertexAttributeKind, VertexReadTrait},
+            surface::Surface,
+            Mesh,
+        },
+        node::Node,
+        particle_system::{
+            BaseEmitter, BaseEmitterBuilder, Emit, Emitter, Particle, ParticleSystem,
+        },
+    },
+};
+use std::{
+    cmp::Ordering,
+    ops::{Deref, DerefMut},
+};
+
+#[derive(Clone, Debug)]
+struct EmitterTriangle {
+    vertices: [Vector3<f32>; 3],
+    normals: [Vector3<f32>; 3],
+}
+
+/// Area-weighted set of triangles.
+#[derive(Clone, Debug, Default)]
+struct SurfaceSampler {
+    triangles: Vec<EmitterTriangle>,
+    cumulative_area: Vec<f32>,
+}
+
+impl SurfaceSampler {
+    fn clear(&mut self) {
+        self.triangles.clear();
+        self.cumulative_area.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    fn push(&mut self, triangle: EmitterTriangle) {
+        let [a, b, c] = triangle.vertices;
+        let area = (b - a).cross(&(c - a)).norm() * 0.5;
+        let total = self.cumulative_area.last().cloned().unwrap_or(0.0);
+        self.cumulative_area.push(total + area);
+        self.triangles.push(triangle);
+    }
+
+    /// Returns random point on the surface and interpolated normal at the point.
+    fn sample(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let total = *self.cumulative_area.last()?;
+
+        let area = NumericRange::new(0.0, total).random();
+        let index = match self
+            .cumulative_area
+            .binary_search_by(|a| a.partial_cmp(&area).unwrap_or(Ordering::Equal))
+        {
+            Ok(index) | Err(index) => index.min(self.triangles.len() - 1),
+        };
+        let triangle = &self.triangles[index];
+
+        // Uniform point picking in triangle.
+        let mut u = NumericRange::new(0.0, 1.0).random();
+        let mut v = NumericRange::new(0.0, 1.0).random();
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        let w = 1.0 - u - v;
+
+        let [a, b, c] = triangle.vertices;
+        let [na, nb, nc] = triangle.normals;
+
+        let position = a.scale(w) + b.scale(u) + c.scale(v);
+        let normal = (na.scale(w) + nb.scale(u) + nc.scale(v))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+
+        Some((position, normal))
+    }
+
+    /// Adds triangles of a surface to the sampler. If `bone_matrices` is empty, vertices are
+    /// transformed by `transform`, otherwise skinning is applied. Influence of bones without
+    /// matrix is skipped.
+    fn add_surface(
+        &mut self,
+        surface: &Surface,
+        transform: &Matrix4<f32>,
+        bone_matrices: &[Option<Matrix4<f32>>],
+    ) {
+        let data = surface.data();
+        let data = data.read().unwrap();
+        let vertex_buffer = data.vertex_buffer();
+
+        let vertices = vertex_buffer
+            .iter()
+            .map(|view| {
+                let position = view
+                    .read_3_f32(VertexAttributeKind::Position)
+                    .unwrap_or_default();
+                let normal = view
+                    .read_3_f32(VertexAttributeKind::Normal)
+                    .unwrap_or_default();
+
+                if bone_matrices.is_empty() {
+                    (
+                        transform.transform_point(&Point3::from(position)).coords,
+                        transform.transform_vector(&normal),
+                    )
+                } else {
+                    let mut skinned_position = Vector3::default();
+                    let mut skinned_normal = Vector3::default();
+                    if let (Ok(indices), Ok(weights)) = (
+                        view.read_4_u8(VertexAttributeKind::BoneIndices),
+                        view.read_4_f32(VertexAttributeKind::BoneWeight),
+                    ) {
+                        for (&bone_index, &weight) in indices.iter().zip(weights.iter()) {
+                            if let Some(Some(matrix)) = bone_matrices.get(bone_index as usize) {
+                                skinned_position += matrix
+                                    .transform_point(&Point3::from(position))
+                                    .coords
+                                    .scale(weight);
+                                skinned_normal += matrix.transform_vector(&normal).scale(weight);
+                            }
+                        }
+                    }
+                    (skinned_position, skinned_normal)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for triangle in data.triangles() {
+            if let (Some(a), Some(b), Some(c)) = (
+                vertices.get(triangle[0] as usize),
+                vertices.get(triangle[1] as usize),
+                vertices.get(triangle[2] as usize),
+            ) {
+                self.push(EmitterTriangle {
+                    vertices: [a.0, b.0, c.0],
+                    normals: [a.1, b.1, c.1],
+                });
+            }
+        }
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug)]
+pub struct MeshEmitter {
+    emitter: BaseEmitter,
+    mesh: Handle<Node>,
+    follow_skinning: bool,
+    inherit_normals: bool,
+    sampler: SurfaceSampler,
+    /// Transforms points from space of sampler to local space of particle system.
+    sampler_transform: Matrix4<f32>,
+    /// A mesh from which static sampler was built.
+    sampled_mesh: Handle<Node>,
+}
+
+impl Default for MeshEmitter {
+    fn default() -> Self {
+        Self {
+            emitter: Default::default(),
+            mesh: Default::default(),
+            follow_skinning: true,
+            inherit_normals: true,
+            sampler: Default::default(),
+            sampler_transform: Matrix4::identity(),
+            sampled_mesh: Default::default(),
+        }
+    }
+}
+
+impl Deref for MeshEmitter {
+    type Target = BaseEmitter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.emitter
+    }
+}
+
+impl DerefMut for MeshEmitter {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.emitter
+    }
+}
+
+impl MeshEmitter {
+    /// Returns handle of a mesh from which particles are emitted.
+    pub fn mesh(&self) -> Handle<Node> {
+        self.mesh
+    }
+
+    /// Sets new mesh from which particles will be emitted.
+    pub fn set_mesh(&mut self, mesh: Handle<Node>) {
+        self.mesh = mesh;
+        self.sampler.clear();
+    }
+
+    /// Returns true if emitter follows skinning of the mesh.
+    pub fn is_following_skinning(&self) -> bool {
+        self.follow_skinning
+    }
+
+    /// Defines whether emitter should use skinned (animated) surface of the mesh or its
+    /// bind pose. Following skinning is much more expensive, because sampler has to be
+    /// rebuilt every frame.
+    pub fn set_follow_skinning(&mut self, state: bool) {
+        self.follow_skinning = state;
+        self.sampler.clear();
+    }
+
+    /// Returns true if initial velocity of particles is directed along surface normals.
+    pub fn is_inheriting_normals(&self) -> bool {
+        self.inherit_normals
+    }
+
+    /// Defines whether initial velocity of particles should be directed along normal of
+    /// the surface at the emission point. Magnitude of the velocity is still defined
+    /// by velocity ranges of the emitter.
+    pub fn set_inherit_normals(&mut self, state: bool) {
+        self.inherit_normals = state;
+    }
+
+    /// Prepares emitter for the next emission, it gathers surfaces of the mesh (with
+    /// skinning if needed) in local coordinates of particle system. There is no need to
+    /// call it manually, it will be automatically called by scene update call.
+    pub fn sync(&mut self, graph: &Graph, inv_particle_system_transform: &Matrix4<f32>) {
+        let mesh = if graph.is_valid_handle(self.mesh) {
+            match &graph[self.mesh] {
+                Node::Mesh(mesh) => Some(mesh),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        let mesh = if let Some(mesh) = mesh {
+            mesh
+        } else {
+            self.sampler.clear();
+            return;
+        };
+
+        if self.follow_skinning && is_skinned(mesh) {
+            // Skinned surfaces gathered in world space.
+            self.sampler.clear();
+            for surface in mesh.surfaces() {
+                let bone_matrices = surface
+                    .bones()
+                    .iter()
+                    .map(|&b| {
+                        // Bone could be deleted, keep its slot to preserve indices of others.
+                        if graph.is_valid_handle(b) {
+                            let bone_node = &graph[b];
+                            Some(bone_node.global_transform() * bone_node.inv_bind_pose_transform())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                // Surfaces without bones are still moved with the mesh.
+                self.sampler
+                    .add_surface(surface, &mesh.global_transform(), &bone_matrices);
+            }
+            self.sampled_mesh = Handle::NONE;
+            self.sampler_transform = *inv_particle_system_transform;
+        } else {
+            // Static surfaces gathered once in local space of the mesh.
+            if self.sampler.is_empty() || self.sampled_mesh != self.mesh {
+                self.sampler.clear();
+                for surface in mesh.surfaces() {
+                    self.sampler.add_surface(surface, &Matrix4::identity(), &[]);
+                }
+                self.sampled_mesh = self.mesh;
+            }
+            self.sampler_transform = inv_particle_system_transform * mesh.global_transform();
+        }
+    }
+}
+
+fn is_skinned(mesh: &Mesh) -> bool {
+    mesh.surfaces().iter().any(|s| !s.bones().is_empty())
+}
+
+impl Emit for MeshEmitter {
+    fn emit(&self, _particle_system: &ParticleSystem, particle: &mut Particle) {
+        self.emitter.emit(particle);
+        if let Some((position, normal)) = self.sampler.sample() {
+            particle.position = self.position
+                + self
+                    .sampler_transform
+                    .transform_point(&Point3::from(position))
+                    .coords;
+            if self.inherit_normals {
+                if let Some(normal) = self
+                    .sampler_transform
+                    .transform_vector(&normal)
+                    .try_normalize(f32::EPSILON)
+                {
+                    particle.velocity = normal.scale(particle.velocity.norm());
+                }
+            }
+        } else {
+            particle.position = self.position;
+        }
+    }
+}
+
+impl Visit for MeshEmitter {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.emitter.visit("Emitter", visitor)?;
+        self.mesh.visit("Mesh", visitor)?;
+        self.follow_skinning.visit("FollowSkinning", visitor)?;
+        self.inherit_normals.visit("InheritNormals", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Mesh emitter builder allows you to construct mesh emitter in declarative manner.
+/// This is typical implementation of Builder pattern.
+pub struct MeshEmitterBuilder {
+    base: BaseEmitterBuilder,
+    mesh: Handle<Node>,
+    follow_skinning: bool,
+    inherit_normals: bool,
+}
+
+impl MeshEmitterBuilder {
+    /// Creates new mesh emitter builder.
+    pub fn new(base: BaseEmitterBuilder) -> Self {
+        Self {
+            base,
+            mesh: Handle::NONE,
+            follow_skinning: true,
+            inherit_normals: true,
+        }
+    }
+
+    /// Sets desired mesh from which particles will be emitted.
+    pub fn with_mesh(mut self, mesh: Handle<Node>) -> Self {
+        self.mesh = mesh;
+        self
+    }
+
+    /// Sets whether emitter should follow skinning of the mesh or not.
+    pub fn with_follow_skinning(mut self, state: bool) -> Self {
+        self.follow_skinning = state;
+        self
+    }
+
+    /// Sets whether initial velocity of particles should be directed along normals.
+    pub fn with_inherit_normals(mut self, state: bool) -> Self {
+        self.inherit_normals = state;
+        self
+    }
+
+    /// Creates new mesh emitter.
+    pub fn build(self) -> Emitter {
+        Emitter::Mesh(MeshEmitter {
+            emitter: self.base.build(),
+            mesh: self.mesh,
+            follow_skinning: self.follow_skinning,
+            inherit_normals: self.inherit_normals,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Matrix4, Vector2, Vector3, Vector4},
+            math::TriangleDefinition,
+        },
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::{
+                buffer::VertexBuffer,
+                surface::{SurfaceBuilder, SurfaceData},
+                vertex::AnimatedVertex,
+                MeshBuilder,
+            },
+            particle_system::mesh_emitter::{EmitterTriangle, MeshEmitter, SurfaceSampler},
+            transform::TransformBuilder,
+        },
+    };
+    use std::sync::{Arc, RwLock};
+
+    fn triangle(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> EmitterTriangle {
+        EmitterTriangle {
+            vertices: [a, b, c],
+            normals: [Vector3::z(); 3],
+        }
+    }
+
+    /// Right triangle with legs of length 2 on oXY plane, every vertex is influenced by
+    /// bones 0 and 1 with equal weights.
+    fn skinned_triangle_data() -> SurfaceData {
+        let vertices = [
+            Vector3::default(),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+        ]
+        .iter()
+        .map(|&position| AnimatedVertex {
+            position,
+            tex_coord: Vector2::default(),
+            normal: Vector3::z(),
+            tangent: Vector4::default(),
+            bone_weights: [0.5, 0.5, 0.0, 0.0],
+            bone_indices: [0, 1, 0, 0],
+        })
+        .collect::<Vec<_>>();
+
+        SurfaceData::new(
+            VertexBuffer::new(vertices.len(), AnimatedVertex::layout(), vertices).unwrap(),
+            vec![TriangleDefinition([0, 1, 2])],
+            true,
+        )
+    }
+
+    #[test]
+    fn test_sampler_cumulative_area() {
+        let mut sampler = SurfaceSampler::default();
+        assert!(sampler.sample().is_none());
+
+        sampler.push(triangle(Vector3::default(), Vector3::x(), Vector3::y()));
+        sampler.push(triangle(
+            Vector3::default(),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+        ));
+
+        assert_eq!(sampler.cumulative_area, vec![0.5, 3.5]);
+
+        sampler.clear();
+        assert!(sampler.is_empty());
+        assert!(sampler.sample().is_none());
+    }
+
+    #[test]
+    fn test_sampler_skips_degenerate_triangles() {
+        let mut sampler = SurfaceSampler::default();
+        // Triangle on z = 1 plane with area of 2.
+        sampler.push(triangle(
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(0.0, 2.0, 1.0),
+        ));
+        // Degenerate triangle on z = 0 plane, it has no area and must never be sampled.
+        sampler.push(triangle(
+            Vector3::default(),
+            Vector3::x(),
+            Vector3::new(2.0, 0.0, 0.0),
+        ));
+
+        for _ in 0..1000 {
+            let (position, normal) = sampler.sample().unwrap();
+            assert!((position.z - 1.0).abs() <= f32::EPSILON * 4.0);
+            assert!(position.x >= 0.0 && position.y >= 0.0);
+            assert!(position.x + position.y <= 2.0 + f32::EPSILON * 4.0);
+            assert_eq!(normal, Vector3::z());
+        }
+    }
+
+    #[test]
+    fn test_add_surface_skips_missing_bones() {
+        let surface = SurfaceBuilder::new(Arc::new(RwLock::new(skinned_triangle_data()))).build();
+
+        // First bone is missing, so only half of influence of the second bone is left.
+        let mut sampler = SurfaceSampler::default();
+        sampler.add_surface(
+            &surface,
+            &Matrix4::identity(),
+            &[
+                None,
+                Some(Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0))),
+            ],
+        );
+
+        assert_eq!(sampler.triangles.len(), 1);
+        assert_eq!(
+            sampler.triangles[0].vertices,
+            [
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 2.0, 0.0),
+            ]
+        );
+        assert_eq!(sampler.cumulative_area, vec![0.5]);
+    }
+
+    #[test]
+    fn test_sync_with_deleted_bone() {
+        let mut graph = Graph::new();
+
+        let deleted_bone = BaseBuilder::new().build(&mut graph);
+        let bone = BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(0.0, 2.0, 0.0))
+                    .build(),
+            )
+            .build(&mut graph);
+        let mesh = MeshBuilder::new(BaseBuilder::new())
+            .with_surfaces(vec![SurfaceBuilder::new(Arc::new(RwLock::new(
+                skinned_triangle_data(),
+            )))
+            .with_bones(vec![deleted_bone, bone])
+            .build()])
+            .build(&mut graph);
+
+        graph.remove_node(deleted_bone);
+        graph.update_hierarchical_data();
+
+        let mut emitter = MeshEmitter::default();
+        emitter.set_mesh(mesh);
+        emitter.sync(&graph, &Matrix4::identity());
+
+        // Indices of bones are preserved, so the second bone still affects the vertices.
+        assert_eq!(emitter.sampler.triangles.len(), 1);
+        assert_eq!(
+            emitter.sampler.triangles[0].vertices,
+            [
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 2.0, 0.0),
+            ]
+        );
+    }
+}
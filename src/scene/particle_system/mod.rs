@@ -12,6 +12,40 @@
 //! Particle system can contain multiple particle emitters, each emitter has its own
 //! set of properties and it defines law of change of particle parameters over time.
 //!
+//! Available emitters are: box, sphere, cylinder and mesh. Mesh emitter spawns particles on
+//! the surface of a mesh node (optionally following its skinning), see [`MeshEmitter`] docs.
+//!
+//! # Curves and force fields
+//!
+//! Size, speed, rotation speed and alpha of particles can be modulated over normalized
+//! lifetime of a particle by curves. Each curve defines a multiplier which is applied
+//! to a respective parameter of a particle. Particles could also be affected by
+//! force fields - attractors, vortices, turbulence and wind, see [`ForceField`] docs.
+//!
+//! # Sprite sheet animation
+//!
+//! Texture of a particle system can be an atlas of frames, in this case each particle
+//! will play flipbook animation either with fixed frame rate or over its lifetime. See
+//! [`ParticleSpriteSheet`] docs.
+//!
+//! # Bursts and sub-emitters
+//!
+//! Besides continuous emission, emitters can emit groups of particles at specific moments
+//! of time, see [`Burst`] docs. Emitters can also be triggered by events of particles of
+//! other emitters of the same particle system (birth, death or collision with one of
+//! collision planes of the particle system), see [`SubEmitter`] docs.
+//!
+//! # GPU simulation
+//!
+//! Particle system can be flagged to be simulated on GPU, see
+//! [`ParticleSystem::set_gpu_simulation`]. In this case emitters still spawn particles on
+//! CPU (so emitters are configured exactly the same way), but movement of particles is
+//! done by the renderer, this allows to have hundreds of thousands particles. There are
+//! some limitations: GPU particles are not sorted, force fields other than wind and collision
+//! planes are ignored, only sub-emitters triggered by birth of particles work. If renderer
+//! does not support GPU simulation (see `Renderer::is_gpu_particle_simulation_supported`),
+//! such particle systems fall back to CPU simulation.
+//!
 //! # Performance
 //!
 //! In general particle system can be considered as heavy visual effect, but total impact
@@ -70,10 +104,11 @@
 
 use crate::{
     core::{
-        algebra::{Vector2, Vector3},
+        algebra::{Matrix4, Vector2, Vector3},
         color::Color,
         color_gradient::ColorGradient,
-        math::TriangleDefinition,
+        curve::Curve,
+        math::{plane::Plane, Rect, TriangleDefinition},
         numeric_range::NumericRange,
         pool::Handle,
         visitor::{Visit, VisitResult, Visitor},
@@ -83,15 +118,54 @@ use crate::{
         base::{Base, BaseBuilder},
         graph::Graph,
         node::Node,
+        particle_system::{
+            burst::Burst,
+            force_field::ForceField,
+            mesh_emitter::MeshEmitter,
+            sub_emitter::{SubEmitter, SubEmitterTrigger},
+        },
+        sprite_sheet::SpriteSheet,
     },
 };
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     cmp::Ordering,
     fmt::Debug,
     ops::{Deref, DerefMut},
+    sync::atomic::{self, AtomicU64},
 };
 
+pub mod burst;
+pub mod force_field;
+pub mod mesh_emitter;
+pub mod sub_emitter;
+
+static GPU_SIMULATION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Max amount of simulation steps that can wait for the renderer. Extra steps will be
+/// merged into one.
+const MAX_PENDING_GPU_STEPS: usize = 8;
+
+fn next_gpu_simulation_id() -> u64 {
+    GPU_SIMULATION_ID.fetch_add(1, atomic::Ordering::SeqCst)
+}
+
+/// Simulation steps and spawned particles of a GPU-simulated particle system which were
+/// not yet consumed by the renderer.
+#[derive(Default, Debug)]
+pub(in crate) struct GpuSimulationQueue {
+    /// Time steps of every update call since last frame.
+    pub steps: Vec<f32>,
+    /// Particles that were spawned since last frame.
+    pub spawned: Vec<Particle>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct GpuParticleRecord {
+    emitter_index: u32,
+    death_time: f64,
+}
+
 /// OpenGL expects this structure packed as in C.
 #[repr(C)]
 #[derive(Debug)]
@@ -101,6 +175,7 @@ pub struct Vertex {
     size: f32,
     rotation: f32,
     color: Color,
+    frame_tex_coord: Vector2<f32>,
 }
 
 /// Particle system is "rendered" into special buffer, which contains vertices and faces.
@@ -159,6 +234,7 @@ pub struct Particle {
     /// Color of particle.
     pub color: Color,
     emitter_index: u32,
+    start_frame: u32,
     sqr_distance_to_camera: Cell<f32>,
 }
 
@@ -175,6 +251,7 @@ impl Default for Particle {
             rotation_speed: 0.0,
             rotation: 0.0,
             emitter_index: 0,
+            start_frame: 0,
             color: Color::WHITE,
             sqr_distance_to_camera: Cell::new(0.0),
         }
@@ -196,11 +273,86 @@ impl Visit for Particle {
         self.rotation.visit("Rotation", visitor)?;
         self.color.visit("Color", visitor)?;
         self.emitter_index.visit("EmitterIndex", visitor)?;
+        // Backward compatibility - start frame may be missing in old files.
+        if let Err(e) = self.start_frame.visit("StartFrame", visitor) {
+            if visitor.is_reading() {
+                self.start_frame = 0;
+            } else {
+                return Err(e);
+            }
+        }
 
         visitor.leave_region()
     }
 }
 
+impl Particle {
+    /// Returns ratio of current lifetime to initial lifetime of particle, it is in
+    /// [0; 1] range.
+    pub fn normalized_lifetime(&self) -> f32 {
+        if self.initial_lifetime > 0.0 {
+            (self.lifetime / self.initial_lifetime).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Defines how particles switch frames of a sprite sheet.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub enum ParticleFrameMode {
+    /// Frames are switched with fixed rate (frames per second) and looped.
+    FrameRate(f32),
+    /// Whole sequence of frames is played exactly once over lifetime of a particle.
+    OverLifetime,
+}
+
+impl Default for ParticleFrameMode {
+    fn default() -> Self {
+        Self::OverLifetime
+    }
+}
+
+/// Sprite sheet (flipbook) animation settings of particle system.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Visit)]
+pub struct ParticleSpriteSheet {
+    /// Layout of frames in the texture of particle system.
+    pub sheet: SpriteSheet,
+    /// Defines how frames are switched.
+    pub mode: ParticleFrameMode,
+    /// Each particle will start from random frame if set, this helps to hide repetition.
+    pub random_start_frame: bool,
+}
+
+impl ParticleSpriteSheet {
+    /// Creates new sprite sheet settings.
+    pub fn new(sheet: SpriteSheet, mode: ParticleFrameMode) -> Self {
+        Self {
+            sheet,
+            mode,
+            random_start_frame: false,
+        }
+    }
+
+    /// Sets whether each particle should start from random frame.
+    pub fn with_random_start_frame(mut self, state: bool) -> Self {
+        self.random_start_frame = state;
+        self
+    }
+
+    fn frame_of(&self, particle: &Particle) -> u32 {
+        let frame_count = self.sheet.frame_count();
+        let offset = match self.mode {
+            ParticleFrameMode::FrameRate(frame_rate) => (particle.lifetime * frame_rate) as u32,
+            // Last frame must be held instead of wrapping to the first one.
+            ParticleFrameMode::OverLifetime => {
+                ((particle.normalized_lifetime() * frame_count as f32) as u32).min(frame_count - 1)
+            }
+        };
+        particle.start_frame.wrapping_add(offset) % frame_count
+    }
+}
+
 /// Emit trait must be implemented for any particle system emitter.
 pub trait Emit {
     /// Initializes state of particle using given emitter and particle system.
@@ -595,6 +747,8 @@ pub enum Emitter {
     Sphere(SphereEmitter),
     /// Cylinder emitter.
     Cylinder(CylinderEmitter),
+    /// See MeshEmitter docs.
+    Mesh(MeshEmitter),
 }
 
 impl Emitter {
@@ -605,6 +759,7 @@ impl Emitter {
             1 => Ok(Self::Box(Default::default())),
             2 => Ok(Self::Sphere(Default::default())),
             3 => Ok(Self::Cylinder(Default::default())),
+            4 => Ok(Self::Mesh(Default::default())),
             _ => Err(format!("Invalid emitter id {}!", id)),
         }
     }
@@ -616,6 +771,7 @@ impl Emitter {
             Self::Box(_) => 1,
             Self::Sphere(_) => 2,
             Self::Cylinder(_) => 3,
+            Self::Mesh(_) => 4,
         }
     }
 }
@@ -627,6 +783,7 @@ macro_rules! static_dispatch {
             Emitter::Box(v) => v.$func($($args),*),
             Emitter::Sphere(v) => v.$func($($args),*),
             Emitter::Cylinder(v) => v.$func($($args),*),
+            Emitter::Mesh(v) => v.$func($($args),*),
         }
     };
 }
@@ -644,6 +801,7 @@ impl Clone for Emitter {
             Self::Box(box_emitter) => Self::Box(box_emitter.clone()),
             Self::Sphere(sphere_emitter) => Self::Sphere(sphere_emitter.clone()),
             Self::Cylinder(cylinder) => Self::Cylinder(cylinder.clone()),
+            Self::Mesh(mesh) => Self::Mesh(mesh.clone()),
         }
     }
 }
@@ -743,6 +901,10 @@ pub struct BaseEmitter {
     particles_to_spawn: usize,
     resurrect_particles: bool,
     spawned_particles: u64,
+    bursts: Vec<Burst>,
+    sub_emitters: Vec<SubEmitter>,
+    /// Time since emitter started to work, used to trigger bursts.
+    age: f32,
 }
 
 /// Emitter builder allows you to construct emitter in declarative manner.
@@ -760,6 +922,8 @@ pub struct BaseEmitterBuilder {
     rotation_speed: Option<NumericRange>,
     rotation: Option<NumericRange>,
     resurrect_particles: bool,
+    bursts: Vec<Burst>,
+    sub_emitters: Vec<SubEmitter>,
 }
 
 impl Default for BaseEmitterBuilder {
@@ -784,6 +948,8 @@ impl BaseEmitterBuilder {
             rotation_speed: None,
             rotation: None,
             resurrect_particles: true,
+            bursts: Default::default(),
+            sub_emitters: Default::default(),
         }
     }
 
@@ -859,6 +1025,18 @@ impl BaseEmitterBuilder {
         self
     }
 
+    /// Sets desired bursts of emitter.
+    pub fn with_bursts(mut self, bursts: Vec<Burst>) -> Self {
+        self.bursts = bursts;
+        self
+    }
+
+    /// Sets desired sub-emitters of emitter.
+    pub fn with_sub_emitters(mut self, sub_emitters: Vec<SubEmitter>) -> Self {
+        self.sub_emitters = sub_emitters;
+        self
+    }
+
     /// Creates new instance of emitter.
     pub fn build(self) -> BaseEmitter {
         BaseEmitter {
@@ -894,23 +1072,36 @@ impl BaseEmitterBuilder {
             particles_to_spawn: 0,
             resurrect_particles: self.resurrect_particles,
             spawned_particles: 0,
+            bursts: self.bursts,
+            sub_emitters: self.sub_emitters,
+            age: 0.0,
         }
     }
 }
 
 impl BaseEmitter {
-    /// Updates emitter and emits required amount of particles each call. There is no
-    /// need to call it manually, it will be automatically called by scene update call.
+    /// Updates emitter and emits required amount of particles each call. Amount of particles
+    /// (including bursts) is clamped so emitter never has more alive particles than its
+    /// limit. There is no need to call it manually, it will be automatically called by scene
+    /// update call.
     pub fn tick(&mut self, dt: f32) {
-        self.time += dt;
-        let time_amount_per_particle = 1.0 / self.particle_spawn_rate as f32;
-        let mut particle_count = (self.time / time_amount_per_particle) as u32;
-        self.time -= time_amount_per_particle * particle_count as f32;
+        let mut particle_count = if self.particle_spawn_rate > 0 {
+            self.time += dt;
+            let time_amount_per_particle = 1.0 / self.particle_spawn_rate as f32;
+            let particle_count = (self.time / time_amount_per_particle) as u32;
+            self.time -= time_amount_per_particle * particle_count as f32;
+            particle_count
+        } else {
+            0
+        };
+        let burst_begin = self.age;
+        self.age += dt;
+        for burst in self.bursts.iter() {
+            particle_count = particle_count.saturating_add(burst.count_in(burst_begin, self.age));
+        }
         if let ParticleLimit::Strict(max_particles) = self.max_particles {
             let alive_particles = self.alive_particles.get();
-            if alive_particles < max_particles && alive_particles + particle_count > max_particles {
-                particle_count = max_particles - particle_count;
-            }
+            particle_count = particle_count.min(max_particles.saturating_sub(alive_particles));
             if !self.resurrect_particles && self.spawned_particles > u64::from(max_particles) {
                 self.particles_to_spawn = 0;
                 return;
@@ -1087,6 +1278,33 @@ impl BaseEmitter {
     pub fn spawned_particles(&self) -> u64 {
         self.spawned_particles
     }
+
+    /// Sets new bursts of emitter.
+    pub fn set_bursts(&mut self, bursts: Vec<Burst>) -> &mut Self {
+        self.bursts = bursts;
+        self
+    }
+
+    /// Returns bursts of emitter.
+    pub fn bursts(&self) -> &[Burst] {
+        &self.bursts
+    }
+
+    /// Sets new sub-emitters of emitter.
+    pub fn set_sub_emitters(&mut self, sub_emitters: Vec<SubEmitter>) -> &mut Self {
+        self.sub_emitters = sub_emitters;
+        self
+    }
+
+    /// Returns sub-emitters of emitter.
+    pub fn sub_emitters(&self) -> &[SubEmitter] {
+        &self.sub_emitters
+    }
+
+    /// Returns time since emitter started to work.
+    pub fn age(&self) -> f32 {
+        self.age
+    }
 }
 
 impl Visit for BaseEmitter {
@@ -1110,6 +1328,29 @@ impl Visit for BaseEmitter {
             .visit("ResurrectParticles", visitor)?;
         self.spawned_particles.visit("SpawnedParticles", visitor)?;
 
+        // Backward compatibility - these fields may be missing in old files.
+        if let Err(e) = self.bursts.visit("Bursts", visitor) {
+            if visitor.is_reading() {
+                self.bursts = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.sub_emitters.visit("SubEmitters", visitor) {
+            if visitor.is_reading() {
+                self.sub_emitters = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.age.visit("Age", visitor) {
+            if visitor.is_reading() {
+                self.age = 0.0;
+            } else {
+                return Err(e);
+            }
+        }
+
         visitor.leave_region()
     }
 }
@@ -1133,6 +1374,9 @@ impl Clone for BaseEmitter {
             particles_to_spawn: 0,
             resurrect_particles: self.resurrect_particles,
             spawned_particles: self.spawned_particles,
+            bursts: self.bursts.clone(),
+            sub_emitters: self.sub_emitters.clone(),
+            age: self.age,
         }
     }
 }
@@ -1156,6 +1400,9 @@ impl Default for BaseEmitter {
             particles_to_spawn: 0,
             resurrect_particles: true,
             spawned_particles: 0,
+            bursts: Default::default(),
+            sub_emitters: Default::default(),
+            age: 0.0,
         }
     }
 }
@@ -1171,6 +1418,24 @@ pub struct ParticleSystem {
     texture: Option<Texture>,
     acceleration: Vector3<f32>,
     color_over_lifetime: Option<ColorGradient>,
+    size_over_lifetime: Option<Curve>,
+    speed_over_lifetime: Option<Curve>,
+    rotation_speed_over_lifetime: Option<Curve>,
+    alpha_over_lifetime: Option<Curve>,
+    /// List of force fields that affect particles of the particle system.
+    pub force_fields: Vec<ForceField>,
+    /// List of planes (in local coordinates) particles collide with. Normals of planes point
+    /// to the half-space where particles can move freely.
+    pub collision_planes: Vec<Plane>,
+    collision_bounciness: f32,
+    sprite_sheet: Option<ParticleSpriteSheet>,
+    gpu_simulation: bool,
+    gpu_simulation_id: u64,
+    gpu_queue: RefCell<GpuSimulationQueue>,
+    // GPU particles are not accessible on CPU, so only their death times are tracked to
+    // correctly limit amount of particles of emitters.
+    gpu_particles: Vec<GpuParticleRecord>,
+    gpu_time: f64,
 }
 
 impl Deref for ParticleSystem {
@@ -1198,6 +1463,20 @@ impl ParticleSystem {
             texture: self.texture.clone(),
             acceleration: self.acceleration,
             color_over_lifetime: self.color_over_lifetime.clone(),
+            size_over_lifetime: self.size_over_lifetime.clone(),
+            speed_over_lifetime: self.speed_over_lifetime.clone(),
+            rotation_speed_over_lifetime: self.rotation_speed_over_lifetime.clone(),
+            alpha_over_lifetime: self.alpha_over_lifetime.clone(),
+            force_fields: self.force_fields.clone(),
+            collision_planes: self.collision_planes.clone(),
+            collision_bounciness: self.collision_bounciness,
+            sprite_sheet: self.sprite_sheet,
+            gpu_simulation: self.gpu_simulation,
+            // Copy must have its own state on GPU.
+            gpu_simulation_id: next_gpu_simulation_id(),
+            gpu_queue: Default::default(),
+            gpu_particles: Default::default(),
+            gpu_time: 0.0,
         }
     }
 
@@ -1217,23 +1496,177 @@ impl ParticleSystem {
         self.color_over_lifetime = Some(gradient)
     }
 
+    /// Sets new curve that defines multiplier of size of particles over their normalized
+    /// lifetime. `None` means that size will not be modulated.
+    pub fn set_size_over_lifetime(&mut self, curve: Option<Curve>) {
+        self.size_over_lifetime = curve;
+    }
+
+    /// Returns current curve of size multiplier over lifetime.
+    pub fn size_over_lifetime(&self) -> Option<&Curve> {
+        self.size_over_lifetime.as_ref()
+    }
+
+    /// Sets new curve that defines multiplier of speed of particles over their normalized
+    /// lifetime. `None` means that speed will not be modulated.
+    pub fn set_speed_over_lifetime(&mut self, curve: Option<Curve>) {
+        self.speed_over_lifetime = curve;
+    }
+
+    /// Returns current curve of speed multiplier over lifetime.
+    pub fn speed_over_lifetime(&self) -> Option<&Curve> {
+        self.speed_over_lifetime.as_ref()
+    }
+
+    /// Sets new curve that defines multiplier of rotation speed of particles over their
+    /// normalized lifetime. `None` means that rotation speed will not be modulated.
+    pub fn set_rotation_speed_over_lifetime(&mut self, curve: Option<Curve>) {
+        self.rotation_speed_over_lifetime = curve;
+    }
+
+    /// Returns current curve of rotation speed multiplier over lifetime.
+    pub fn rotation_speed_over_lifetime(&self) -> Option<&Curve> {
+        self.rotation_speed_over_lifetime.as_ref()
+    }
+
+    /// Sets new curve that defines multiplier of alpha of particles over their normalized
+    /// lifetime. It is applied on top of color gradient. `None` means that alpha will not
+    /// be modulated.
+    pub fn set_alpha_over_lifetime(&mut self, curve: Option<Curve>) {
+        self.alpha_over_lifetime = curve;
+    }
+
+    /// Returns current curve of alpha multiplier over lifetime.
+    pub fn alpha_over_lifetime(&self) -> Option<&Curve> {
+        self.alpha_over_lifetime.as_ref()
+    }
+
+    /// Sets new bounciness of particles colliding with collision planes. Zero means that
+    /// particles will lose their velocity on collision, one - that particles will bounce
+    /// off without loss of speed.
+    pub fn set_collision_bounciness(&mut self, bounciness: f32) {
+        self.collision_bounciness = bounciness.max(0.0);
+    }
+
+    /// Returns current bounciness of particles colliding with collision planes.
+    pub fn collision_bounciness(&self) -> f32 {
+        self.collision_bounciness
+    }
+
+    /// Sets new sprite sheet animation settings. `None` means that whole texture will be
+    /// used for every particle.
+    pub fn set_sprite_sheet(&mut self, sprite_sheet: Option<ParticleSpriteSheet>) {
+        self.sprite_sheet = sprite_sheet;
+    }
+
+    /// Returns current sprite sheet animation settings.
+    pub fn sprite_sheet(&self) -> Option<&ParticleSpriteSheet> {
+        self.sprite_sheet.as_ref()
+    }
+
+    /// Defines whether particle system should be simulated on GPU or not. Switching the mode
+    /// removes all existing particles. See module docs for more info.
+    pub fn set_gpu_simulation(&mut self, state: bool) {
+        if self.gpu_simulation != state {
+            self.gpu_simulation = state;
+            self.clear_particles();
+        }
+    }
+
+    /// Returns true if particle system is flagged to be simulated on GPU. Actual simulation
+    /// can still be done on CPU if GPU simulation is not supported by the renderer, see
+    /// [`ParticleSystem::update`].
+    pub fn is_gpu_simulation_enabled(&self) -> bool {
+        self.gpu_simulation
+    }
+
+    /// Returns max amount of particles that could be alive at the same time, it defines
+    /// size of GPU buffers. Emitters with unlimited amount of particles are estimated by
+    /// their spawn rate and max lifetime.
+    pub fn gpu_capacity(&self) -> usize {
+        self.emitters
+            .iter()
+            .map(|emitter| match emitter.max_particles {
+                ParticleLimit::Strict(max_particles) => max_particles as usize,
+                ParticleLimit::Unlimited => {
+                    (emitter.particle_spawn_rate as f32 * emitter.lifetime.max()).ceil() as usize
+                }
+            })
+            .sum::<usize>()
+            .max(1)
+    }
+
+    pub(in crate) fn gpu_simulation_id(&self) -> u64 {
+        self.gpu_simulation_id
+    }
+
+    pub(in crate) fn take_gpu_simulation_queue(&self) -> GpuSimulationQueue {
+        std::mem::take(&mut *self.gpu_queue.borrow_mut())
+    }
+
+    /// Returns total constant acceleration for GPU simulation - acceleration of particle
+    /// system plus all wind force fields.
+    pub(in crate) fn gpu_acceleration(&self) -> Vector3<f32> {
+        self.force_fields
+            .iter()
+            .fold(self.acceleration, |acceleration, field| {
+                if let ForceField::Wind(wind) = field {
+                    acceleration + wind.acceleration
+                } else {
+                    acceleration
+                }
+            })
+    }
+
+    /// Returns color gradient over lifetime.
+    pub fn color_over_lifetime_gradient(&self) -> Option<&ColorGradient> {
+        self.color_over_lifetime.as_ref()
+    }
+
+    /// Returns true if particle system has at least one mesh emitter.
+    pub fn has_mesh_emitters(&self) -> bool {
+        self.emitters.iter().any(|e| matches!(e, Emitter::Mesh(_)))
+    }
+
+    /// Prepares mesh emitters for emission. This method should not be used directly,
+    /// it will be automatically called by scene update.
+    pub fn sync_mesh_emitters(&mut self, graph: &Graph) {
+        let inv_transform = self
+            .global_transform()
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        for emitter in self.emitters.iter_mut() {
+            if let Emitter::Mesh(mesh_emitter) = emitter {
+                mesh_emitter.sync(graph, &inv_transform);
+            }
+        }
+    }
+
     /// Removes all generated particles.
     pub fn clear_particles(&mut self) {
         self.particles.clear();
         self.free_particles.clear();
+        self.gpu_particles.clear();
+        *self.gpu_queue.borrow_mut() = Default::default();
         for emitter in self.emitters.iter_mut() {
             emitter.alive_particles.set(0);
         }
     }
 
     /// Updates state of particle system, this means that it moves particles,
-    /// changes their color, size, rotation, etc. This method should not be
+    /// changes their color, size, rotation, etc. `gpu_simulation_supported` tells whether
+    /// renderer is able to simulate particles on GPU, if it is not, particle system will be
+    /// simulated on CPU even if GPU simulation is enabled. This method should not be
     /// used directly, it will be automatically called by scene update.
-    pub fn update(&mut self, dt: f32) {
+    pub fn update(&mut self, dt: f32, gpu_simulation_supported: bool) {
         for emitter in self.emitters.iter_mut() {
             emitter.tick(dt);
         }
 
+        let gpu_simulated = self.gpu_simulation && gpu_simulation_supported;
+
+        let mut spawned = Vec::new();
+        let mut sub_emissions = Vec::new();
         for (i, emitter) in self.emitters.iter().enumerate() {
             for _ in 0..emitter.particles_to_spawn {
                 let mut particle = Particle {
@@ -1244,44 +1677,194 @@ impl ParticleSystem {
                     .alive_particles
                     .set(emitter.alive_particles.get() + 1);
                 emitter.emit(self, &mut particle);
-                if let Some(free_index) = self.free_particles.pop() {
-                    self.particles[free_index as usize] = particle;
-                } else {
-                    self.particles.push(particle);
+                self.init_start_frame(&mut particle);
+                for sub_emitter in emitter.sub_emitters.iter() {
+                    if sub_emitter.trigger == SubEmitterTrigger::Birth {
+                        sub_emissions.push((*sub_emitter, particle.position));
+                    }
                 }
+                spawned.push(particle);
             }
         }
+        for particle in spawned {
+            self.add_particle(particle, gpu_simulated);
+        }
+        self.spawn_sub_emissions(&sub_emissions, gpu_simulated);
+
+        if gpu_simulated {
+            self.update_gpu_bookkeeping(dt);
+            return;
+        }
+
+        sub_emissions.clear();
 
         let acceleration_offset = self.acceleration.scale(dt * dt);
 
+        let fetch = |curve: &Option<Curve>, k: f32| curve.as_ref().map_or(1.0, |c| c.fetch(k));
+
         for (i, particle) in self.particles.iter_mut().enumerate() {
             if particle.alive {
+                let emitter = self.emitters.get(particle.emitter_index as usize);
                 particle.lifetime += dt;
                 if particle.lifetime >= particle.initial_lifetime {
                     self.free_particles.push(i as u32);
-                    if let Some(emitter) = self.emitters.get(particle.emitter_index as usize) {
+                    if let Some(emitter) = emitter {
                         emitter
                             .alive_particles
                             .set(emitter.alive_particles.get() - 1);
+                        for sub_emitter in emitter.sub_emitters.iter() {
+                            if sub_emitter.trigger == SubEmitterTrigger::Death {
+                                sub_emissions.push((*sub_emitter, particle.position));
+                            }
+                        }
                     }
                     particle.alive = false;
                     particle.lifetime = particle.initial_lifetime;
                 } else {
+                    let k = particle.normalized_lifetime();
+
                     particle.velocity += acceleration_offset;
-                    particle.position += particle.velocity;
+                    for force_field in self.force_fields.iter() {
+                        particle.velocity += force_field
+                            .acceleration(&particle.position, particle.lifetime)
+                            .scale(dt * dt);
+                    }
+                    let old_position = particle.position;
+                    particle.position +=
+                        particle.velocity.scale(fetch(&self.speed_over_lifetime, k));
+                    let mut collided = false;
+                    for plane in self.collision_planes.iter() {
+                        let distance = plane.dot(&particle.position);
+                        if distance < 0.0 && plane.dot(&old_position) >= 0.0 {
+                            // Push particle back to the surface and reflect its velocity.
+                            particle.position -= plane.normal.scale(distance);
+                            let normal_velocity =
+                                plane.normal.scale(particle.velocity.dot(&plane.normal));
+                            particle.velocity = (particle.velocity - normal_velocity)
+                                - normal_velocity.scale(self.collision_bounciness);
+                            collided = true;
+                        }
+                    }
+                    if collided {
+                        if let Some(emitter) = emitter {
+                            for sub_emitter in emitter.sub_emitters.iter() {
+                                if sub_emitter.trigger == SubEmitterTrigger::Collision {
+                                    sub_emissions.push((*sub_emitter, particle.position));
+                                }
+                            }
+                        }
+                    }
                     particle.size += particle.size_modifier * dt;
                     if particle.size < 0.0 {
                         particle.size = 0.0;
                     }
-                    particle.rotation += particle.rotation_speed * dt;
+                    particle.rotation +=
+                        particle.rotation_speed * fetch(&self.rotation_speed_over_lifetime, k) * dt;
                     if let Some(color_over_lifetime) = &self.color_over_lifetime {
-                        let k = particle.lifetime / particle.initial_lifetime;
                         particle.color = color_over_lifetime.get_color(k);
                     } else {
                         particle.color = Color::WHITE;
                     }
+                    if let Some(alpha_over_lifetime) = &self.alpha_over_lifetime {
+                        particle.color.a = (particle.color.a as f32
+                            * alpha_over_lifetime.fetch(k).max(0.0).min(1.0))
+                            as u8;
+                    }
+                }
+            }
+        }
+
+        self.spawn_sub_emissions(&sub_emissions, false);
+    }
+
+    fn init_start_frame(&self, particle: &mut Particle) {
+        if let Some(sprite_sheet) = self.sprite_sheet.as_ref() {
+            if sprite_sheet.random_start_frame {
+                particle.start_frame = sprite_sheet.sheet.random_frame();
+            }
+        }
+    }
+
+    fn add_particle(&mut self, particle: Particle, gpu_simulated: bool) {
+        if gpu_simulated {
+            self.gpu_particles.push(GpuParticleRecord {
+                emitter_index: particle.emitter_index,
+                death_time: self.gpu_time + particle.initial_lifetime as f64,
+            });
+            self.gpu_queue.borrow_mut().spawned.push(particle);
+        } else if let Some(free_index) = self.free_particles.pop() {
+            self.particles[free_index as usize] = particle;
+        } else {
+            self.particles.push(particle);
+        }
+    }
+
+    fn spawn_sub_emissions(
+        &mut self,
+        sub_emissions: &[(SubEmitter, Vector3<f32>)],
+        gpu_simulated: bool,
+    ) {
+        let mut spawned = Vec::new();
+        for (sub_emitter, position) in sub_emissions {
+            let emitter = match self.emitters.get(sub_emitter.emitter as usize) {
+                Some(emitter) => emitter,
+                None => continue,
+            };
+            for _ in 0..sub_emitter.count {
+                if let ParticleLimit::Strict(max_particles) = emitter.max_particles {
+                    if emitter.alive_particles.get() >= max_particles {
+                        break;
+                    }
+                }
+                let mut particle = Particle {
+                    emitter_index: sub_emitter.emitter,
+                    ..Particle::default()
+                };
+                emitter
+                    .alive_particles
+                    .set(emitter.alive_particles.get() + 1);
+                emitter.emit(self, &mut particle);
+                // Emitter emits particles relative to its own position, move them to the
+                // position of the particle that triggered sub-emitter.
+                particle.position += position - emitter.position;
+                self.init_start_frame(&mut particle);
+                spawned.push(particle);
+            }
+        }
+        for particle in spawned {
+            self.add_particle(particle, gpu_simulated);
+        }
+    }
+
+    fn update_gpu_bookkeeping(&mut self, dt: f32) {
+        self.gpu_time += dt as f64;
+
+        let time = self.gpu_time;
+        let emitters = &self.emitters;
+        self.gpu_particles.retain(|record| {
+            if record.death_time > time {
+                true
+            } else {
+                if let Some(emitter) = emitters.get(record.emitter_index as usize) {
+                    emitter
+                        .alive_particles
+                        .set(emitter.alive_particles.get().saturating_sub(1));
                 }
+                false
             }
+        });
+
+        let capacity = self.gpu_capacity();
+        let mut queue = self.gpu_queue.borrow_mut();
+        queue.steps.push(dt);
+        if queue.steps.len() > MAX_PENDING_GPU_STEPS {
+            let first = queue.steps.remove(0);
+            queue.steps[0] += first;
+        }
+        // Older particles will be overwritten anyway.
+        if queue.spawned.len() > capacity {
+            let excess = queue.spawned.len() - capacity;
+            queue.spawned.drain(..excess);
         }
     }
 
@@ -1325,36 +1908,57 @@ impl ParticleSystem {
         for (i, particle_index) in sorted_particles.iter().enumerate() {
             let particle = self.particles.get(*particle_index as usize).unwrap();
 
+            let size = particle.size
+                * self
+                    .size_over_lifetime
+                    .as_ref()
+                    .map_or(1.0, |c| c.fetch(particle.normalized_lifetime()));
+
+            let frame =
+                self.sprite_sheet
+                    .as_ref()
+                    .map_or(Rect::new(0.0, 0.0, 1.0, 1.0), |sprite_sheet| {
+                        sprite_sheet
+                            .sheet
+                            .frame_rect(sprite_sheet.frame_of(particle))
+                    });
+            let frame_tex_coord =
+                |tex_coord: Vector2<f32>| frame.position + tex_coord.component_mul(&frame.size);
+
             draw_data.vertices.push(Vertex {
                 position: particle.position,
                 tex_coord: Vector2::default(),
-                size: particle.size,
+                size,
                 rotation: particle.rotation,
                 color: particle.color,
+                frame_tex_coord: frame_tex_coord(Vector2::default()),
             });
 
             draw_data.vertices.push(Vertex {
                 position: particle.position,
                 tex_coord: Vector2::new(1.0, 0.0),
-                size: particle.size,
+                size,
                 rotation: particle.rotation,
                 color: particle.color,
+                frame_tex_coord: frame_tex_coord(Vector2::new(1.0, 0.0)),
             });
 
             draw_data.vertices.push(Vertex {
                 position: particle.position,
                 tex_coord: Vector2::new(1.0, 1.0),
-                size: particle.size,
+                size,
                 rotation: particle.rotation,
                 color: particle.color,
+                frame_tex_coord: frame_tex_coord(Vector2::new(1.0, 1.0)),
             });
 
             draw_data.vertices.push(Vertex {
                 position: particle.position,
                 tex_coord: Vector2::new(0.0, 1.0),
-                size: particle.size,
+                size,
                 rotation: particle.rotation,
                 color: particle.color,
+                frame_tex_coord: frame_tex_coord(Vector2::new(0.0, 1.0)),
             });
 
             let base_index = (i * 4) as u32;
@@ -1400,6 +2004,77 @@ impl Visit for ParticleSystem {
         self.color_over_lifetime.visit("ColorGradient", visitor)?;
         self.base.visit("Base", visitor)?;
 
+        // Backward compatibility - these fields may be missing in old files.
+        if let Err(e) = self.size_over_lifetime.visit("SizeOverLifetime", visitor) {
+            if visitor.is_reading() {
+                self.size_over_lifetime = None;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.speed_over_lifetime.visit("SpeedOverLifetime", visitor) {
+            if visitor.is_reading() {
+                self.speed_over_lifetime = None;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self
+            .rotation_speed_over_lifetime
+            .visit("RotationSpeedOverLifetime", visitor)
+        {
+            if visitor.is_reading() {
+                self.rotation_speed_over_lifetime = None;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.alpha_over_lifetime.visit("AlphaOverLifetime", visitor) {
+            if visitor.is_reading() {
+                self.alpha_over_lifetime = None;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.force_fields.visit("ForceFields", visitor) {
+            if visitor.is_reading() {
+                self.force_fields = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.sprite_sheet.visit("SpriteSheet", visitor) {
+            if visitor.is_reading() {
+                self.sprite_sheet = None;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.gpu_simulation.visit("GpuSimulation", visitor) {
+            if visitor.is_reading() {
+                self.gpu_simulation = false;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.collision_planes.visit("CollisionPlanes", visitor) {
+            if visitor.is_reading() {
+                self.collision_planes = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self
+            .collision_bounciness
+            .visit("CollisionBounciness", visitor)
+        {
+            if visitor.is_reading() {
+                self.collision_bounciness = 0.5;
+            } else {
+                return Err(e);
+            }
+        }
+
         visitor.leave_region()
     }
 }
@@ -1418,6 +2093,15 @@ pub struct ParticleSystemBuilder {
     texture: Option<Texture>,
     acceleration: Vector3<f32>,
     color_over_lifetime: Option<ColorGradient>,
+    size_over_lifetime: Option<Curve>,
+    speed_over_lifetime: Option<Curve>,
+    rotation_speed_over_lifetime: Option<Curve>,
+    alpha_over_lifetime: Option<Curve>,
+    force_fields: Vec<ForceField>,
+    collision_planes: Vec<Plane>,
+    collision_bounciness: f32,
+    sprite_sheet: Option<ParticleSpriteSheet>,
+    gpu_simulation: bool,
 }
 
 impl ParticleSystemBuilder {
@@ -1429,6 +2113,15 @@ impl ParticleSystemBuilder {
             texture: None,
             acceleration: Vector3::new(0.0, -9.81, 0.0),
             color_over_lifetime: None,
+            size_over_lifetime: None,
+            speed_over_lifetime: None,
+            rotation_speed_over_lifetime: None,
+            alpha_over_lifetime: None,
+            force_fields: Default::default(),
+            collision_planes: Default::default(),
+            collision_bounciness: 0.5,
+            sprite_sheet: None,
+            gpu_simulation: false,
         }
     }
 
@@ -1462,6 +2155,60 @@ impl ParticleSystemBuilder {
         self
     }
 
+    /// Sets curve of size multiplier over lifetime for particle system.
+    pub fn with_size_over_lifetime(mut self, curve: Curve) -> Self {
+        self.size_over_lifetime = Some(curve);
+        self
+    }
+
+    /// Sets curve of speed multiplier over lifetime for particle system.
+    pub fn with_speed_over_lifetime(mut self, curve: Curve) -> Self {
+        self.speed_over_lifetime = Some(curve);
+        self
+    }
+
+    /// Sets curve of rotation speed multiplier over lifetime for particle system.
+    pub fn with_rotation_speed_over_lifetime(mut self, curve: Curve) -> Self {
+        self.rotation_speed_over_lifetime = Some(curve);
+        self
+    }
+
+    /// Sets curve of alpha multiplier over lifetime for particle system.
+    pub fn with_alpha_over_lifetime(mut self, curve: Curve) -> Self {
+        self.alpha_over_lifetime = Some(curve);
+        self
+    }
+
+    /// Sets desired force fields for particle system.
+    pub fn with_force_fields(mut self, force_fields: Vec<ForceField>) -> Self {
+        self.force_fields = force_fields;
+        self
+    }
+
+    /// Sets desired collision planes for particle system.
+    pub fn with_collision_planes(mut self, collision_planes: Vec<Plane>) -> Self {
+        self.collision_planes = collision_planes;
+        self
+    }
+
+    /// Sets desired bounciness of particles colliding with collision planes.
+    pub fn with_collision_bounciness(mut self, bounciness: f32) -> Self {
+        self.collision_bounciness = bounciness.max(0.0);
+        self
+    }
+
+    /// Sets desired sprite sheet animation settings for particle system.
+    pub fn with_sprite_sheet(mut self, sprite_sheet: ParticleSpriteSheet) -> Self {
+        self.sprite_sheet = Some(sprite_sheet);
+        self
+    }
+
+    /// Sets whether particle system should be simulated on GPU or not.
+    pub fn with_gpu_simulation(mut self, state: bool) -> Self {
+        self.gpu_simulation = state;
+        self
+    }
+
     fn build_particle_system(self) -> ParticleSystem {
         ParticleSystem {
             base: self.base_builder.build_base(),
@@ -1471,6 +2218,19 @@ impl ParticleSystemBuilder {
             texture: self.texture.clone(),
             acceleration: self.acceleration,
             color_over_lifetime: self.color_over_lifetime,
+            size_over_lifetime: self.size_over_lifetime,
+            speed_over_lifetime: self.speed_over_lifetime,
+            rotation_speed_over_lifetime: self.rotation_speed_over_lifetime,
+            alpha_over_lifetime: self.alpha_over_lifetime,
+            force_fields: self.force_fields,
+            collision_planes: self.collision_planes,
+            collision_bounciness: self.collision_bounciness,
+            sprite_sheet: self.sprite_sheet,
+            gpu_simulation: self.gpu_simulation,
+            gpu_simulation_id: next_gpu_simulation_id(),
+            gpu_queue: Default::default(),
+            gpu_particles: Default::default(),
+            gpu_time: 0.0,
         }
     }
 
@@ -1484,3 +2244,236 @@ impl ParticleSystemBuilder {
         graph.add_node(self.build_node())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, math::plane::Plane, numeric_range::NumericRange},
+        scene::{
+            base::BaseBuilder,
+            particle_system::{
+                sub_emitter::{SubEmitter, SubEmitterTrigger},
+                BaseEmitterBuilder, ParticleSystem, ParticleSystemBuilder, SphereEmitterBuilder,
+                MAX_PENDING_GPU_STEPS,
+            },
+        },
+    };
+
+    // Emitter that spawns particles exactly at its position without initial velocity.
+    fn still_emitter(base: BaseEmitterBuilder) -> BaseEmitterBuilder {
+        base.with_x_velocity_range(NumericRange::new(0.0, 0.0))
+            .with_y_velocity_range(NumericRange::new(0.0, 0.0))
+            .with_z_velocity_range(NumericRange::new(0.0, 0.0))
+    }
+
+    fn alive_particles_of(particle_system: &ParticleSystem, emitter_index: u32) -> usize {
+        particle_system
+            .particles
+            .iter()
+            .filter(|p| p.alive && p.emitter_index == emitter_index)
+            .count()
+    }
+
+    fn gpu_particle_system(max_particles: u32) -> ParticleSystem {
+        ParticleSystemBuilder::new(BaseBuilder::new())
+            .with_emitters(vec![SphereEmitterBuilder::new(
+                BaseEmitterBuilder::new()
+                    .with_spawn_rate(16)
+                    .with_max_particles(max_particles)
+                    .with_lifetime_range(NumericRange::new(1.0, 1.0)),
+            )
+            .build()])
+            .with_gpu_simulation(true)
+            .build_particle_system()
+    }
+
+    #[test]
+    fn test_spawn_clamped_by_max_particles() {
+        let mut emitter = BaseEmitterBuilder::new()
+            .with_spawn_rate(16)
+            .with_max_particles(5)
+            .build();
+
+        // Sixteen particles should be spawned, but only five are allowed.
+        emitter.tick(1.0);
+        assert_eq!(emitter.particles_to_spawn, 5);
+
+        // Only free slots are filled.
+        emitter.alive_particles.set(3);
+        emitter.tick(0.5);
+        assert_eq!(emitter.particles_to_spawn, 2);
+
+        emitter.alive_particles.set(5);
+        emitter.tick(0.5);
+        assert_eq!(emitter.particles_to_spawn, 0);
+    }
+
+    #[test]
+    fn test_sub_emitters() {
+        let mut particle_system = ParticleSystemBuilder::new(BaseBuilder::new())
+            .with_emitters(vec![
+                SphereEmitterBuilder::new(
+                    BaseEmitterBuilder::new()
+                        .with_spawn_rate(16)
+                        .with_max_particles(2)
+                        .with_lifetime_range(NumericRange::new(1.0, 1.0))
+                        .with_sub_emitters(vec![
+                            SubEmitter::new(SubEmitterTrigger::Birth, 1, 2),
+                            SubEmitter::new(SubEmitterTrigger::Death, 1, 3),
+                        ]),
+                )
+                .build(),
+                SphereEmitterBuilder::new(
+                    BaseEmitterBuilder::new()
+                        .with_spawn_rate(0)
+                        .with_lifetime_range(NumericRange::new(10.0, 10.0)),
+                )
+                .build(),
+            ])
+            .build_particle_system();
+
+        // Two particles are born, every birth spawns two particles of sub-emitter.
+        particle_system.update(0.125, false);
+        assert_eq!(alive_particles_of(&particle_system, 0), 2);
+        assert_eq!(alive_particles_of(&particle_system, 1), 4);
+
+        // Both particles die, every death spawns three particles of sub-emitter.
+        particle_system.update(1.0, false);
+        assert_eq!(alive_particles_of(&particle_system, 0), 0);
+        assert_eq!(alive_particles_of(&particle_system, 1), 10);
+        assert_eq!(particle_system.emitters[1].alive_particles.get(), 10);
+    }
+
+    #[test]
+    fn test_collision_planes() {
+        let mut particle_system = ParticleSystemBuilder::new(BaseBuilder::new())
+            .with_emitters(vec![
+                SphereEmitterBuilder::new(
+                    still_emitter(BaseEmitterBuilder::new())
+                        .with_position(Vector3::new(0.0, 1.0, 0.0))
+                        .with_y_velocity_range(NumericRange::new(-0.5, -0.5))
+                        .with_spawn_rate(16)
+                        .with_max_particles(1)
+                        .with_lifetime_range(NumericRange::new(10.0, 10.0))
+                        .with_sub_emitters(vec![SubEmitter::new(
+                            SubEmitterTrigger::Collision,
+                            1,
+                            1,
+                        )]),
+                )
+                .with_radius(0.0)
+                .build(),
+                SphereEmitterBuilder::new(
+                    still_emitter(BaseEmitterBuilder::new())
+                        .with_spawn_rate(0)
+                        .with_lifetime_range(NumericRange::new(10.0, 10.0)),
+                )
+                .with_radius(0.0)
+                .build(),
+            ])
+            .with_acceleration(Vector3::default())
+            .with_collision_planes(vec![Plane::from_normal_and_point(
+                &Vector3::new(0.0, 1.0, 0.0),
+                &Vector3::default(),
+            )
+            .unwrap()])
+            .with_collision_bounciness(0.5)
+            .build_particle_system();
+
+        // Particle moves down by 0.5 every update and reaches the plane.
+        particle_system.update(0.0625, false);
+        particle_system.update(0.0625, false);
+        assert_eq!(particle_system.particles[0].position.y, 0.0);
+        assert_eq!(alive_particles_of(&particle_system, 1), 0);
+
+        // Particle crosses the plane, so it is pushed back to the surface and bounces off.
+        particle_system.update(0.0625, false);
+        let particle = &particle_system.particles[0];
+        assert_eq!(particle.position.y, 0.0);
+        assert_eq!(particle.velocity, Vector3::new(0.0, 0.25, 0.0));
+
+        // Collision triggered sub-emitter at the point of collision.
+        assert_eq!(alive_particles_of(&particle_system, 1), 1);
+        let sub_particle = particle_system
+            .particles
+            .iter()
+            .find(|p| p.emitter_index == 1)
+            .unwrap();
+        assert_eq!(sub_particle.position.y, 0.0);
+    }
+
+    #[test]
+    fn test_gpu_capacity() {
+        let particle_system = ParticleSystemBuilder::new(BaseBuilder::new())
+            .with_emitters(vec![
+                SphereEmitterBuilder::new(BaseEmitterBuilder::new().with_max_particles(100))
+                    .build(),
+                // Unlimited emitter is estimated by spawn rate and max lifetime.
+                SphereEmitterBuilder::new(
+                    BaseEmitterBuilder::new()
+                        .with_spawn_rate(10)
+                        .with_lifetime_range(NumericRange::new(2.0, 3.0)),
+                )
+                .build(),
+            ])
+            .build_particle_system();
+        assert_eq!(particle_system.gpu_capacity(), 130);
+
+        // Capacity is never zero, because it defines size of GPU buffers.
+        let empty = ParticleSystemBuilder::new(BaseBuilder::new())
+            .with_emitters(vec![])
+            .build_particle_system();
+        assert_eq!(empty.gpu_capacity(), 1);
+    }
+
+    #[test]
+    fn test_update_gpu_bookkeeping() {
+        let mut particle_system = gpu_particle_system(5);
+
+        // Eight particles should be spawned, but max particles limits them to five.
+        particle_system.update(0.5, true);
+        assert!(particle_system.particles.is_empty());
+        assert_eq!(particle_system.gpu_particles.len(), 5);
+        assert_eq!(particle_system.emitters[0].alive_particles.get(), 5);
+        let queue = particle_system.take_gpu_simulation_queue();
+        assert_eq!(queue.spawned.len(), 5);
+        assert_eq!(queue.steps, vec![0.5]);
+
+        // Nothing is spawned because of the limit, but every particle dies.
+        particle_system.update(0.75, true);
+        assert_eq!(particle_system.emitters[0].alive_particles.get(), 0);
+        assert!(particle_system.gpu_particles.is_empty());
+
+        // Now emitter is able to spawn new particles.
+        particle_system.update(0.125, true);
+        assert_eq!(particle_system.emitters[0].alive_particles.get(), 2);
+        assert_eq!(particle_system.gpu_particles.len(), 2);
+
+        // Steps are merged when renderer does not consume them, total time is preserved.
+        particle_system.take_gpu_simulation_queue();
+        for _ in 0..MAX_PENDING_GPU_STEPS * 2 {
+            particle_system.update(0.25, true);
+        }
+        let queue = particle_system.take_gpu_simulation_queue();
+        assert_eq!(queue.steps.len(), MAX_PENDING_GPU_STEPS);
+        assert_eq!(
+            queue.steps.iter().sum::<f32>(),
+            0.25 * (MAX_PENDING_GPU_STEPS * 2) as f32
+        );
+        // Spawned particles that do not fit into GPU buffers are dropped.
+        assert!(queue.spawned.len() <= particle_system.gpu_capacity());
+    }
+
+    #[test]
+    fn test_gpu_simulation_fallback() {
+        let mut particle_system = gpu_particle_system(5);
+
+        // Renderer does not support GPU simulation, so particles are simulated on CPU.
+        particle_system.update(0.5, false);
+        assert!(particle_system.gpu_particles.is_empty());
+        assert_eq!(particle_system.particles.len(), 5);
+        let queue = particle_system.take_gpu_simulation_queue();
+        assert!(queue.spawned.is_empty());
+        assert!(queue.steps.is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+//! Sub-emitters allow to spawn particles of one emitter at positions of particles of other
+//! emitter when some event happens with a particle - it was born, died or collided with
+//! something. This is useful for effects like fireworks, sparks from hits, etc.
+
+use crate::core::visitor::prelude::*;
+
+/// Defines an event that triggers a sub-emitter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Visit)]
+pub enum SubEmitterTrigger {
+    /// Sub-emitter triggers when a particle is spawned. Particles spawned by sub-emitters
+    /// do not trigger this event to prevent infinite recursion.
+    Birth,
+    /// Sub-emitter triggers when a particle dies.
+    Death,
+    /// Sub-emitter triggers when a particle collides with one of collision planes of particle
+    /// system.
+    Collision,
+}
+
+impl Default for SubEmitterTrigger {
+    fn default() -> Self {
+        Self::Death
+    }
+}
+
+/// Sub-emitter spawns `count` particles of an emitter with `emitter` index of the same particle
+/// system at the position of a particle, when `trigger` event happened to the particle.
+/// Emitter that is used as sub-emitter usually should have zero spawn rate so it emits
+/// particles only by triggers.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub struct SubEmitter {
+    /// Event that triggers sub-emitter.
+    pub trigger: SubEmitterTrigger,
+    /// Index of emitter in the particle system that will emit particles.
+    pub emitter: u32,
+    /// Amount of particles to emit per event.
+    pub count: u32,
+}
+
+impl Default for SubEmitter {
+    fn default() -> Self {
+        Self {
+            trigger: Default::default(),
+            emitter: 0,
+            count: 1,
+        }
+    }
+}
+
+impl SubEmitter {
+    /// Creates new sub-emitter.
+    pub fn new(trigger: SubEmitterTrigger, emitter: u32, count: u32) -> Self {
+        Self {
+            trigger,
+            emitter,
+            count,
+        }
+    }
+}
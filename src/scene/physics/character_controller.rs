@@ -0,0 +1,734 @@
+//! Kinematic character controller.
+//!
+//! Character controller moves a capsule through physics world using shape casts instead of
+//! forces, which gives precise and predictable movement that is hard to achieve with dynamic
+//! rigid bodies. It handles:
+//!
+//! - collide-and-slide - movement along obstacles instead of stopping at them,
+//! - step climbing - small obstacles (stairs) are climbed automatically,
+//! - slope limits - the character can't walk on surfaces steeper than given angle,
+//! - ground snapping - the character sticks to the ground when walking down slopes and stairs,
+//! - moving platforms - the character is carried by the body it stands on.
+//!
+//! The controller owns a kinematic rigid body with a capsule collider, the body can be bound to
+//! a scene node using [`PhysicsBinder`] as any other body. Gravity, jumping and so on are up to
+//! the user: the controller only tries to move the character by given translation.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, pool::Handle},
+//!     scene::{node::Node, physics::character_controller::CharacterControllerBuilder, Scene},
+//! };
+//!
+//! fn create_player(scene: &mut Scene, pivot: Handle<Node>) {
+//!     let mut controller = CharacterControllerBuilder::new()
+//!         .with_radius(0.3)
+//!         .with_height(1.8)
+//!         .with_position(Vector3::new(0.0, 2.0, 0.0))
+//!         .build_and_bind(&mut scene.physics, &mut scene.physics_binder, pivot);
+//!
+//!     // Every frame.
+//!     let dt = 1.0 / 60.0;
+//!     let mut vertical_velocity = 0.0;
+//!     if !controller.is_grounded() {
+//!         vertical_velocity -= 9.81 * dt;
+//!     }
+//!     let walk = Vector3::new(0.0, 0.0, 2.0 * dt);
+//!     controller.move_and_slide(
+//!         &mut scene.physics,
+//!         walk + Vector3::new(0.0, vertical_velocity * dt, 0.0),
+//!     );
+//! }
+//! ```
+
+use crate::{
+    core::{
+        pool::Handle,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    engine::{ColliderHandle, PhysicsBinder, RigidBodyHandle},
+    scene::{node::Node, physics::Physics},
+};
+use rapier3d::{
+    dynamics::RigidBodyBuilder,
+    geometry::{Collider, ColliderBuilder, InteractionGroups, Shape},
+    na::{Isometry3, Point3, Vector3},
+    parry::query,
+    pipeline::QueryPipeline,
+};
+use std::cell::Ref;
+
+const EPSILON: f32 = 1.0e-5;
+
+/// Movement state of a character.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CharacterState {
+    /// The character stands on a walkable surface.
+    Grounded,
+    /// The character is falling or jumping.
+    Air,
+}
+
+impl Default for CharacterState {
+    fn default() -> Self {
+        Self::Air
+    }
+}
+
+/// Information about a surface the character stands on.
+#[derive(Copy, Clone, Debug)]
+pub struct GroundContact {
+    /// A collider the character stands on.
+    pub collider: ColliderHandle,
+    /// World-space normal of the surface.
+    pub normal: Vector3<f32>,
+    // Position of the ground collider at the moment of contact, it is used to calculate
+    // displacement of moving platforms.
+    platform_position: Isometry3<f32>,
+}
+
+struct Hit {
+    collider: rapier3d::geometry::ColliderHandle,
+    // Fraction of translation at which the hit occurred.
+    toi: f32,
+    normal: Vector3<f32>,
+}
+
+struct SweepContext<'a> {
+    physics: &'a Physics,
+    query: Ref<'a, QueryPipeline>,
+    shape: &'a dyn Shape,
+    own_collider: rapier3d::geometry::ColliderHandle,
+    groups: InteractionGroups,
+    skin_width: f32,
+}
+
+impl<'a> SweepContext<'a> {
+    fn cast(&self, position: Vector3<f32>, translation: Vector3<f32>) -> Option<Hit> {
+        let shape_position = Isometry3::translation(position.x, position.y, position.z);
+        let own_collider = self.own_collider;
+        let filter = |handle: rapier3d::geometry::ColliderHandle, collider: &Collider| {
+            handle != own_collider && !collider.is_sensor()
+        };
+
+        let (handle, toi) = self.query.cast_shape(
+            &self.physics.colliders,
+            &shape_position,
+            &translation,
+            self.shape,
+            1.0,
+            self.groups,
+            Some(&filter),
+        )?;
+
+        // Calculate normal explicitly using contact at the time of impact, this way
+        // normal always points from the obstacle towards the character.
+        let impact = position + translation.scale(toi.toi);
+        let impact_position = Isometry3::translation(impact.x, impact.y, impact.z);
+        let collider = &self.physics.colliders[handle];
+        let normal = match query::contact(
+            collider.position(),
+            collider.shape(),
+            &impact_position,
+            self.shape,
+            self.skin_width * 2.0 + EPSILON,
+        ) {
+            Ok(Some(contact)) => contact.normal1.into_inner(),
+            _ => -translation
+                .try_normalize(EPSILON)
+                .unwrap_or_else(Vector3::y),
+        };
+
+        Some(Hit {
+            collider: handle,
+            toi: toi.toi,
+            normal,
+        })
+    }
+
+    // Returns distance that can be travelled along given translation, taking skin width into
+    // account.
+    fn travel(&self, translation: Vector3<f32>, hit: &Option<Hit>) -> f32 {
+        let length = translation.norm();
+        match hit {
+            Some(hit) => (length * hit.toi - self.skin_width).max(0.0),
+            None => length,
+        }
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug)]
+pub struct CharacterController {
+    body: RigidBodyHandle,
+    collider: ColliderHandle,
+    position: Vector3<f32>,
+    max_slope_angle: f32,
+    step_height: f32,
+    snap_distance: f32,
+    skin_width: f32,
+    max_iterations: u32,
+    collision_groups: InteractionGroups,
+    carried_by_platforms: bool,
+    state: CharacterState,
+    ground: Option<GroundContact>,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            body: Default::default(),
+            collider: Default::default(),
+            position: Default::default(),
+            max_slope_angle: 45.0f32.to_radians(),
+            step_height: 0.3,
+            snap_distance: 0.2,
+            skin_width: 0.02,
+            max_iterations: 4,
+            collision_groups: InteractionGroups::all(),
+            carried_by_platforms: true,
+            state: Default::default(),
+            ground: None,
+        }
+    }
+}
+
+impl CharacterController {
+    /// Returns handle of kinematic rigid body of the character.
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
+    }
+
+    /// Returns handle of capsule collider of the character.
+    pub fn collider(&self) -> ColliderHandle {
+        self.collider
+    }
+
+    /// Returns world-space position of the character. The position will be applied to the body
+    /// at next physics step.
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    /// Returns current movement state of the character.
+    pub fn state(&self) -> CharacterState {
+        self.state
+    }
+
+    /// Returns true if the character stands on a walkable surface.
+    pub fn is_grounded(&self) -> bool {
+        self.state == CharacterState::Grounded
+    }
+
+    /// Returns information about a surface the character stands on (if any).
+    pub fn ground(&self) -> Option<&GroundContact> {
+        self.ground.as_ref()
+    }
+
+    /// Sets max angle (in radians) between up vector and normal of a surface at which the
+    /// surface is still walkable.
+    pub fn set_max_slope_angle(&mut self, angle: f32) {
+        self.max_slope_angle = angle;
+    }
+
+    /// Returns max walkable slope angle in radians.
+    pub fn max_slope_angle(&self) -> f32 {
+        self.max_slope_angle
+    }
+
+    /// Sets max height of obstacles that will be climbed automatically.
+    pub fn set_step_height(&mut self, height: f32) {
+        self.step_height = height.max(0.0);
+    }
+
+    /// Returns max height of obstacles that will be climbed automatically.
+    pub fn step_height(&self) -> f32 {
+        self.step_height
+    }
+
+    /// Sets max distance at which grounded character will be pulled down to the ground.
+    /// It keeps the character on the ground when walking down on slopes and stairs.
+    pub fn set_snap_distance(&mut self, distance: f32) {
+        self.snap_distance = distance.max(0.0);
+    }
+
+    /// Returns max ground snapping distance.
+    pub fn snap_distance(&self) -> f32 {
+        self.snap_distance
+    }
+
+    /// Sets a gap that is kept between the character and obstacles. Small gap prevents the
+    /// character from getting stuck in obstacles because of numerical errors.
+    pub fn set_skin_width(&mut self, width: f32) {
+        self.skin_width = width.max(0.0);
+    }
+
+    /// Returns skin width.
+    pub fn skin_width(&self) -> f32 {
+        self.skin_width
+    }
+
+    /// Sets max amount of slide iterations per move.
+    pub fn set_max_iterations(&mut self, max_iterations: u32) {
+        self.max_iterations = max_iterations;
+    }
+
+    /// Returns max amount of slide iterations per move.
+    pub fn max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+
+    /// Sets groups of colliders with which the character can collide.
+    pub fn set_collision_groups(&mut self, groups: InteractionGroups) {
+        self.collision_groups = groups;
+    }
+
+    /// Returns groups of colliders with which the character can collide.
+    pub fn collision_groups(&self) -> InteractionGroups {
+        self.collision_groups
+    }
+
+    /// Defines whether the character should be moved together with a body it stands on.
+    pub fn set_carried_by_platforms(&mut self, state: bool) {
+        self.carried_by_platforms = state;
+    }
+
+    /// Returns true if the character will be moved together with a body it stands on.
+    pub fn is_carried_by_platforms(&self) -> bool {
+        self.carried_by_platforms
+    }
+
+    fn is_walkable(&self, normal: &Vector3<f32>) -> bool {
+        normal.dot(&Vector3::y()) >= self.max_slope_angle.cos() - EPSILON
+    }
+
+    fn platform_displacement(&self, physics: &Physics) -> Vector3<f32> {
+        if let Some(ground) = self.ground.as_ref() {
+            if let Some(collider) = physics.collider(&ground.collider) {
+                let delta = collider.position() * ground.platform_position.inverse();
+                return delta.transform_point(&Point3::from(self.position)).coords - self.position;
+            }
+        }
+        Vector3::default()
+    }
+
+    /// Moves the character by given translation, the character will slide along obstacles,
+    /// climb steps and stick to the ground. Returns translation that was actually made.
+    ///
+    /// New position is applied to the rigid body of the character at next physics step.
+    pub fn move_and_slide(
+        &mut self,
+        physics: &mut Physics,
+        translation: Vector3<f32>,
+    ) -> Vector3<f32> {
+        let start = self.position;
+
+        let own_collider = match physics.collider_handle_map.value_of(&self.collider) {
+            Some(&collider) => collider,
+            None => return Vector3::default(),
+        };
+
+        let mut position = self.position;
+        {
+            let mut query = physics.query.borrow_mut();
+            query.update(&physics.bodies, &physics.colliders);
+        }
+
+        let context = SweepContext {
+            physics,
+            query: physics.query.borrow(),
+            shape: physics.colliders[own_collider].shape(),
+            own_collider,
+            groups: self.collision_groups,
+            skin_width: self.skin_width,
+        };
+
+        if self.carried_by_platforms {
+            let displacement = self.platform_displacement(physics);
+            position = self.slide(&context, position, displacement, false);
+        }
+
+        position = self.slide(&context, position, translation, self.is_grounded());
+
+        if self.is_grounded() && translation.y <= 0.0 && self.snap_distance > 0.0 {
+            let snap = Vector3::new(0.0, -(self.snap_distance + self.skin_width), 0.0);
+            let hit = context.cast(position, snap);
+            if let Some(normal) = hit.as_ref().map(|h| h.normal) {
+                if self.is_walkable(&normal) {
+                    position.y -= context.travel(snap, &hit);
+                }
+            }
+        }
+
+        self.update_ground(&context, position);
+
+        drop(context);
+
+        self.position = position;
+        if let Some(body) = physics.body_mut(&self.body) {
+            let rotation = body.position().rotation;
+            body.set_next_kinematic_position(Isometry3::from_parts(position.into(), rotation));
+        }
+
+        self.position - start
+    }
+
+    fn slide(
+        &self,
+        context: &SweepContext,
+        mut position: Vector3<f32>,
+        mut remaining: Vector3<f32>,
+        can_step: bool,
+    ) -> Vector3<f32> {
+        for _ in 0..self.max_iterations {
+            let length = remaining.norm();
+            if length < EPSILON {
+                break;
+            }
+            let direction = remaining.scale(1.0 / length);
+
+            let hit = context.cast(position, remaining);
+            let travel = context.travel(remaining, &hit);
+            position += direction.scale(travel);
+            remaining = direction.scale(length - travel);
+
+            let hit = match hit {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            let mut normal = hit.normal;
+            if !self.is_walkable(&normal) {
+                if can_step {
+                    if let Some((stepped_position, stepped_remaining)) =
+                        self.try_step(context, position, remaining)
+                    {
+                        position = stepped_position;
+                        remaining = stepped_remaining;
+                        continue;
+                    }
+                }
+
+                // Do not allow to climb steep slopes: treat them as vertical walls.
+                if normal.y > 0.0 {
+                    if let Some(horizontal) =
+                        Vector3::new(normal.x, 0.0, normal.z).try_normalize(EPSILON)
+                    {
+                        normal = horizontal;
+                    }
+                }
+            }
+
+            let projection = remaining.dot(&normal);
+            if projection < 0.0 {
+                remaining -= normal.scale(projection);
+            }
+        }
+
+        position
+    }
+
+    // Tries to climb an obstacle: moves the character up by step height, then forward,
+    // then down and checks if the character is on walkable surface.
+    fn try_step(
+        &self,
+        context: &SweepContext,
+        position: Vector3<f32>,
+        remaining: Vector3<f32>,
+    ) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let horizontal = Vector3::new(remaining.x, 0.0, remaining.z);
+        let horizontal_length = horizontal.norm();
+        if horizontal_length < EPSILON || self.step_height < EPSILON {
+            return None;
+        }
+        let direction = horizontal.scale(1.0 / horizontal_length);
+
+        let up = Vector3::new(0.0, self.step_height, 0.0);
+        let height = context.travel(up, &context.cast(position, up));
+        if height < EPSILON {
+            return None;
+        }
+        let raised = position + Vector3::new(0.0, height, 0.0);
+
+        let travel = context.travel(horizontal, &context.cast(raised, horizontal));
+        if travel < EPSILON {
+            return None;
+        }
+        let moved = raised + direction.scale(travel);
+
+        let down = Vector3::new(0.0, -height, 0.0);
+        let hit = context.cast(moved, down)?;
+        if !self.is_walkable(&hit.normal) {
+            return None;
+        }
+        let landed = moved + down.scale(context.travel(down, &Some(hit)) / height);
+
+        Some((
+            landed,
+            Vector3::new(0.0, remaining.y, 0.0) + direction.scale(horizontal_length - travel),
+        ))
+    }
+
+    fn update_ground(&mut self, context: &SweepContext, position: Vector3<f32>) {
+        self.ground = None;
+        self.state = CharacterState::Air;
+
+        let probe = Vector3::new(0.0, -(self.skin_width * 2.0 + EPSILON), 0.0);
+        if let Some(hit) = context.cast(position, probe) {
+            if self.is_walkable(&hit.normal) {
+                let physics = context.physics;
+                if let Some(&collider) = physics.collider_handle_map.key_of(&hit.collider) {
+                    self.ground = Some(GroundContact {
+                        collider,
+                        normal: hit.normal,
+                        platform_position: *physics.colliders[hit.collider].position(),
+                    });
+                    self.state = CharacterState::Grounded;
+                }
+            }
+        }
+    }
+
+    /// Instantly moves the character to given position without collision checks.
+    pub fn teleport(&mut self, physics: &mut Physics, position: Vector3<f32>) {
+        self.position = position;
+        self.ground = None;
+        self.state = CharacterState::Air;
+        if let Some(body) = physics.body_mut(&self.body) {
+            let rotation = body.position().rotation;
+            body.set_position(Isometry3::from_parts(position.into(), rotation), true);
+        }
+    }
+}
+
+impl Visit for CharacterController {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.body.visit("Body", visitor)?;
+        self.collider.visit("Collider", visitor)?;
+        self.position.visit("Position", visitor)?;
+        self.max_slope_angle.visit("MaxSlopeAngle", visitor)?;
+        self.step_height.visit("StepHeight", visitor)?;
+        self.snap_distance.visit("SnapDistance", visitor)?;
+        self.skin_width.visit("SkinWidth", visitor)?;
+        self.max_iterations.visit("MaxIterations", visitor)?;
+        self.collision_groups.0.visit("CollisionGroups", visitor)?;
+        self.carried_by_platforms
+            .visit("CarriedByPlatforms", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Character controller builder allows you to construct a character controller in declarative
+/// manner.
+pub struct CharacterControllerBuilder {
+    radius: f32,
+    height: f32,
+    position: Vector3<f32>,
+    max_slope_angle: f32,
+    step_height: f32,
+    snap_distance: f32,
+    skin_width: f32,
+    max_iterations: u32,
+    collision_groups: InteractionGroups,
+    carried_by_platforms: bool,
+}
+
+impl Default for CharacterControllerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CharacterControllerBuilder {
+    /// Creates new builder instance with default parameters.
+    pub fn new() -> Self {
+        let defaults = CharacterController::default();
+        Self {
+            radius: 0.3,
+            height: 1.8,
+            position: Default::default(),
+            max_slope_angle: defaults.max_slope_angle,
+            step_height: defaults.step_height,
+            snap_distance: defaults.snap_distance,
+            skin_width: defaults.skin_width,
+            max_iterations: defaults.max_iterations,
+            collision_groups: defaults.collision_groups,
+            carried_by_platforms: defaults.carried_by_platforms,
+        }
+    }
+
+    /// Sets desired radius of the capsule.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets desired total height of the capsule.
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets desired initial position (center of the capsule).
+    pub fn with_position(mut self, position: Vector3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets desired max walkable slope angle in radians.
+    pub fn with_max_slope_angle(mut self, angle: f32) -> Self {
+        self.max_slope_angle = angle;
+        self
+    }
+
+    /// Sets desired max height of automatically climbed obstacles.
+    pub fn with_step_height(mut self, height: f32) -> Self {
+        self.step_height = height;
+        self
+    }
+
+    /// Sets desired ground snapping distance.
+    pub fn with_snap_distance(mut self, distance: f32) -> Self {
+        self.snap_distance = distance;
+        self
+    }
+
+    /// Sets desired skin width.
+    pub fn with_skin_width(mut self, width: f32) -> Self {
+        self.skin_width = width;
+        self
+    }
+
+    /// Sets desired max amount of slide iterations per move.
+    pub fn with_max_iterations(mut self, max_iterations: u32) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Sets desired collision groups.
+    pub fn with_collision_groups(mut self, groups: InteractionGroups) -> Self {
+        self.collision_groups = groups;
+        self
+    }
+
+    /// Defines whether the character should be moved together with a body it stands on.
+    pub fn with_carried_by_platforms(mut self, state: bool) -> Self {
+        self.carried_by_platforms = state;
+        self
+    }
+
+    /// Creates kinematic rigid body with capsule collider and returns controller for them.
+    pub fn build(self, physics: &mut Physics) -> CharacterController {
+        let body = physics.add_body(
+            RigidBodyBuilder::new_kinematic()
+                .translation(self.position.x, self.position.y, self.position.z)
+                .build(),
+        );
+        let half_height = (self.height * 0.5 - self.radius).max(0.0);
+        let collider = physics.add_collider(
+            ColliderBuilder::capsule_y(half_height, self.radius)
+                .collision_groups(self.collision_groups)
+                .build(),
+            &body,
+        );
+
+        CharacterController {
+            body,
+            collider,
+            position: self.position,
+            max_slope_angle: self.max_slope_angle,
+            step_height: self.step_height,
+            snap_distance: self.snap_distance,
+            skin_width: self.skin_width,
+            max_iterations: self.max_iterations,
+            collision_groups: self.collision_groups,
+            carried_by_platforms: self.carried_by_platforms,
+            state: CharacterState::Air,
+            ground: None,
+        }
+    }
+
+    /// Creates controller and binds its body to given node, so the node will follow the
+    /// character.
+    pub fn build_and_bind(
+        self,
+        physics: &mut Physics,
+        binder: &mut PhysicsBinder<Node>,
+        node: Handle<Node>,
+    ) -> CharacterController {
+        let controller = self.build(physics);
+        binder.bind(node, controller.body);
+        controller
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scene::physics::{character_controller::CharacterControllerBuilder, Physics};
+    use rapier3d::{dynamics::RigidBodyBuilder, geometry::ColliderBuilder, na::Vector3};
+
+    fn add_box(physics: &mut Physics, position: Vector3<f32>, half_extents: Vector3<f32>) {
+        let body = physics.add_body(
+            RigidBodyBuilder::new_static()
+                .translation(position.x, position.y, position.z)
+                .build(),
+        );
+        physics.add_collider(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z).build(),
+            &body,
+        );
+    }
+
+    #[test]
+    fn test_character_controller() {
+        let mut physics = Physics::new();
+        // Floor with top at y = 0.0
+        add_box(
+            &mut physics,
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(20.0, 0.5, 20.0),
+        );
+        // Step 0.2 high.
+        add_box(
+            &mut physics,
+            Vector3::new(0.0, 0.1, 5.0),
+            Vector3::new(2.0, 0.1, 2.0),
+        );
+        // Wall.
+        add_box(
+            &mut physics,
+            Vector3::new(5.0, 2.0, 0.0),
+            Vector3::new(0.5, 2.0, 5.0),
+        );
+
+        let mut controller = CharacterControllerBuilder::new()
+            .with_radius(0.3)
+            .with_height(1.8)
+            .with_position(Vector3::new(0.0, 1.5, 0.0))
+            .build(&mut physics);
+
+        // Falling down to the floor.
+        assert!(!controller.is_grounded());
+        controller.move_and_slide(&mut physics, Vector3::new(0.0, -2.0, 0.0));
+        assert!(controller.is_grounded());
+        assert!((controller.position().y - 0.9).abs() < 0.05);
+
+        // Walking onto the step.
+        for _ in 0..40 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.0, -0.01, 0.1));
+        }
+        assert!(controller.is_grounded());
+        assert!((controller.position().y - 1.1).abs() < 0.05);
+        assert!(controller.position().z > 3.5);
+
+        // Sliding along the wall.
+        for _ in 0..40 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.1, -0.01, -0.05));
+        }
+        assert!(controller.position().x < 4.5 - 0.3 + 0.05);
+        assert!(controller.position().z < 3.5);
+    }
+}
@@ -0,0 +1,160 @@
+//! Built-in collector of physics events.
+//!
+//! Every physics step, contact and sensor events produced by rapier are collected and converted
+//! into [`PhysicsEvent`]s, that are expressed in engine [`ColliderHandle`]s and graph nodes
+//! bound to parent bodies of colliders (see [`PhysicsBinder`]). Events are stored in a per-frame
+//! queue, that is cleared at the beginning of every scene update, use
+//! [`Physics::events`](super::Physics::events) to read them.
+//!
+//! ```no_run
+//! use rg3d::scene::{physics::event::PhysicsEvent, Scene};
+//!
+//! fn handle_physics_events(scene: &Scene) {
+//!     for event in scene.physics.events() {
+//!         match event {
+//!             PhysicsEvent::ContactStarted(contact) => {
+//!                 println!(
+//!                     "{:?} hit {:?} with impulse {}",
+//!                     contact.node1, contact.node2, contact.total_impulse
+//!                 );
+//!             }
+//!             PhysicsEvent::SensorEntered(sensor) => {
+//!                 println!("{:?} entered sensor {:?}", sensor.other_node, sensor.sensor_node);
+//!             }
+//!             _ => (),
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Custom [`EventHandler`] still can be set to `Physics::event_handler`, it will receive the
+//! same events as the built-in collector.
+
+use crate::{
+    core::pool::Handle,
+    engine::{ColliderHandle, PhysicsBinder, RigidBodyHandle},
+    scene::node::Node,
+};
+use rapier3d::{
+    geometry::{ContactEvent, IntersectionEvent},
+    na::{Point3, Vector3},
+    pipeline::EventHandler,
+};
+use std::sync::Mutex;
+
+/// A single contact point between two colliders.
+#[derive(Copy, Clone, Debug)]
+pub struct ContactPoint {
+    /// World-space position of the contact point on the first collider.
+    pub position: Point3<f32>,
+
+    /// World-space contact normal, it points from the first collider to the second.
+    pub normal: Vector3<f32>,
+
+    /// Magnitude of impulse along the normal that was applied at this contact point during
+    /// the last step.
+    pub impulse: f32,
+}
+
+/// Information about contact between two colliders.
+#[derive(Clone, Debug)]
+pub struct ContactEventInfo {
+    /// First collider of the contact pair.
+    pub collider1: ColliderHandle,
+
+    /// Second collider of the contact pair.
+    pub collider2: ColliderHandle,
+
+    /// A node that is bound to the parent body of the first collider (if any).
+    pub node1: Handle<Node>,
+
+    /// A node that is bound to the parent body of the second collider (if any).
+    pub node2: Handle<Node>,
+
+    /// Contact points of the pair, it is empty for stopped contacts.
+    pub points: Vec<ContactPoint>,
+
+    /// Sum of impulses of all contact points.
+    pub total_impulse: f32,
+}
+
+/// Information about intersection of a sensor with other collider.
+#[derive(Clone, Debug)]
+pub struct SensorEventInfo {
+    /// A sensor collider.
+    pub sensor: ColliderHandle,
+
+    /// A collider that has entered or exited the sensor.
+    pub other: ColliderHandle,
+
+    /// A node that is bound to the parent body of the sensor (if any).
+    pub sensor_node: Handle<Node>,
+
+    /// A node that is bound to the parent body of the other collider (if any).
+    pub other_node: Handle<Node>,
+}
+
+/// An event produced by physics world during a step.
+#[derive(Clone, Debug)]
+pub enum PhysicsEvent {
+    /// Two colliders have started touching each other.
+    ContactStarted(ContactEventInfo),
+
+    /// Two colliders have stopped touching each other.
+    ContactStopped(ContactEventInfo),
+
+    /// A collider has entered a sensor.
+    SensorEntered(SensorEventInfo),
+
+    /// A collider has exited a sensor.
+    SensorExited(SensorEventInfo),
+}
+
+impl PhysicsEvent {
+    pub(in crate) fn bind_nodes<F>(&mut self, binder: &PhysicsBinder<Node>, mut body_of: F)
+    where
+        F: FnMut(&ColliderHandle) -> Option<RigidBodyHandle>,
+    {
+        let mut node_of = |collider: &ColliderHandle| {
+            body_of(collider)
+                .and_then(|body| binder.node_of(body))
+                .unwrap_or_default()
+        };
+
+        match self {
+            PhysicsEvent::ContactStarted(info) | PhysicsEvent::ContactStopped(info) => {
+                info.node1 = node_of(&info.collider1);
+                info.node2 = node_of(&info.collider2);
+            }
+            PhysicsEvent::SensorEntered(info) | PhysicsEvent::SensorExited(info) => {
+                info.sensor_node = node_of(&info.sensor);
+                info.other_node = node_of(&info.other);
+            }
+        }
+    }
+}
+
+/// Stores raw rapier events during a step, they're converted to [`PhysicsEvent`]s right after
+/// the step. Events are forwarded to user-defined handler as well.
+#[derive(Default)]
+pub(in crate) struct RawEventCollector {
+    pub(in crate) contacts: Mutex<Vec<ContactEvent>>,
+    pub(in crate) intersections: Mutex<Vec<IntersectionEvent>>,
+}
+
+pub(in crate) struct ForwardingEventHandler<'a> {
+    pub(in crate) collector: &'a RawEventCollector,
+    pub(in crate) user: &'a dyn EventHandler,
+}
+
+impl<'a> EventHandler for ForwardingEventHandler<'a> {
+    fn handle_intersection_event(&self, event: IntersectionEvent) {
+        self.collector.intersections.lock().unwrap().push(event);
+        self.user.handle_intersection_event(event);
+    }
+
+    fn handle_contact_event(&self, event: ContactEvent) {
+        self.collector.contacts.lock().unwrap().push(event);
+        self.user.handle_contact_event(event);
+    }
+}
@@ -0,0 +1,4129 @@
+//! Contains all structures and methods to operate with physics world.
+
+use crate::core::instant;
+use crate::scene::mesh::buffer::{VertexAttributeKind, VertexReadTrait};
+use crate::{
+    core::{
+        arrayvec::ArrayVec,
+        color::Color,
+        math::{aabb::AxisAlignedBoundingBox, ray::Ray, TriangleDefinition},
+        pool::{ErasedHandle, Handle, Pool},
+        uuid::Uuid,
+        visitor::{Visit, VisitResult, Visitor},
+        BiDirHashMap,
+    },
+    engine::{ColliderHandle, JointHandle, PhysicsBinder, RigidBodyHandle},
+    physics::math::AngVector,
+    resource::model::Model,
+    scene::{
+        graph::Graph,
+        node::Node,
+        physics::{
+            event::{
+                ContactEventInfo, ContactPoint, ForwardingEventHandler, PhysicsEvent,
+                RawEventCollector, SensorEventInfo,
+            },
+            vehicle::{RaycastVehicle, VehicleDesc},
+        },
+        Line, SceneDrawingContext,
+    },
+    utils::{
+        log::{Log, MessageKind},
+        raw_mesh::{RawMeshBuilder, RawVertex},
+    },
+};
+use rapier3d::{
+    dynamics::{
+        BallJoint, BodyStatus, CCDSolver, FixedJoint, IntegrationParameters, Joint, JointParams,
+        JointSet, PrismaticJoint, RevoluteJoint, RigidBody, RigidBodyBuilder, RigidBodySet,
+    },
+    geometry::{
+        BroadPhase, Collider, ColliderBuilder, ColliderSet, ContactEvent, InteractionGroups,
+        MassProperties, NarrowPhase, Segment, Shape,
+    },
+    na::{
+        DMatrix, Dynamic, Isometry3, Matrix4, Point3, Translation, Translation3, Unit,
+        UnitQuaternion, VecStorage, Vector3,
+    },
+    parry::{
+        shape::{Compound, ConvexPolyhedron, FeatureId, SharedShape, TriMesh},
+        transformation::vhacd::VHACDParameters,
+    },
+    pipeline::{EventHandler, PhysicsPipeline, QueryPipeline},
+};
+use std::{
+    cell::{Cell, RefCell, RefMut},
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    hash::Hash,
+    time::Duration,
+};
+
+pub mod character_controller;
+pub mod event;
+pub mod ragdoll;
+pub mod vehicle;
+
+/// A ray intersection result.
+#[derive(Debug, Clone)]
+pub struct Intersection {
+    /// A handle of the collider with which intersection was detected.
+    pub collider: ColliderHandle,
+
+    /// A normal at the intersection position.
+    pub normal: Vector3<f32>,
+
+    /// A position of the intersection in world coordinates.
+    pub position: Point3<f32>,
+
+    /// Additional data that contains a kind of the feature with which
+    /// intersection was detected as well as its index.    
+    pub feature: FeatureId,
+
+    /// Distance from the ray origin.
+    pub toi: f32,
+}
+
+/// A set of options for the ray cast.
+pub struct RayCastOptions {
+    /// A ray for cast.
+    pub ray: Ray,
+
+    /// Maximum distance of cast.
+    pub max_len: f32,
+
+    /// Groups to check.
+    pub groups: InteractionGroups,
+
+    /// Whether to sort intersections from closest to farthest.
+    pub sort_results: bool,
+}
+
+/// A set of options for the shape cast.
+pub struct ShapeCastOptions {
+    /// A shape to cast.
+    pub shape: ColliderShapeDesc,
+
+    /// Initial position and orientation of the shape.
+    pub position: Isometry3<f32>,
+
+    /// Direction of the cast, it does not need to be normalized.
+    pub direction: Vector3<f32>,
+
+    /// Maximum distance of cast.
+    pub max_len: f32,
+
+    /// Groups to check.
+    pub groups: InteractionGroups,
+}
+
+/// A shape cast result.
+#[derive(Debug, Clone)]
+pub struct ShapeCastResult {
+    /// A handle of the first collider hit by the shape.
+    pub collider: ColliderHandle,
+
+    /// Distance that the shape has travelled before the hit.
+    pub toi: f32,
+
+    /// A contact point on the collider in world coordinates.
+    pub position: Point3<f32>,
+
+    /// A normal of the collider at the contact point in world coordinates.
+    pub normal: Vector3<f32>,
+}
+
+/// A point projection result.
+#[derive(Debug, Clone)]
+pub struct PointProjectionResult {
+    /// A handle of the collider closest to the point.
+    pub collider: ColliderHandle,
+
+    /// A closest point on the collider in world coordinates.
+    pub position: Point3<f32>,
+
+    /// Whether the point is inside the collider or not.
+    pub is_inside: bool,
+}
+
+/// A set of data that has all associations with physics from resource.
+/// It is used to embedding physics from resource to a scene during
+/// the instantiation process.
+#[derive(Default, Clone)]
+pub struct ResourceLink {
+    model: Model,
+    // HandleInResource->HandleInInstance mappings
+    bodies: HashMap<RigidBodyHandle, RigidBodyHandle>,
+    colliders: HashMap<ColliderHandle, ColliderHandle>,
+    joints: HashMap<JointHandle, JointHandle>,
+}
+
+impl Visit for ResourceLink {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.model.visit("Model", visitor)?;
+        self.bodies.visit("Bodies", visitor)?;
+        self.colliders.visit("Colliders", visitor)?;
+        self.joints.visit("Visit", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Fixed timestep settings of physics. When enabled, time of every scene update is accumulated
+/// and physics is advanced by a number of steps of `integration_parameters.dt` each, so speed
+/// of simulation does not depend on how often scene is updated.
+#[derive(Copy, Clone, Debug, Visit)]
+pub struct FixedTimestep {
+    /// Whether fixed timestep is enabled or not. If disabled, exactly one step is performed per
+    /// scene update. Default is false.
+    pub enabled: bool,
+
+    /// Maximum amount of steps per scene update. Remaining time is discarded, this prevents
+    /// simulation from falling further behind when a step takes longer than its duration.
+    /// Zero is treated as one, otherwise physics would never advance.
+    pub max_substeps: u32,
+
+    /// Whether transforms of nodes bound to bodies should be interpolated between two last
+    /// physics states. This makes movement smooth when scene is updated more often than
+    /// physics, at the cost of one step of latency. Default is false.
+    pub interpolate: bool,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_substeps: 8,
+            interpolate: false,
+        }
+    }
+}
+
+/// An in-memory copy of the entire physics world: bodies, colliders, joints, state of broad and
+/// narrow phases, vehicles and engine handle mappings. It is intended for rollback networking:
+/// take a snapshot every step, restore it when corrected input arrives and re-simulate the
+/// steps that were made since then. See [`Physics::snapshot`] and [`Physics::restore`].
+///
+/// Restoring a snapshot and re-simulating the same steps with the same input produces the
+/// same state on one machine. Cross-platform determinism additionally requires the
+/// `enhanced_determinism` feature.
+#[derive(Clone)]
+pub struct PhysicsSnapshot {
+    gravity: Vector3<f32>,
+    integration_parameters: IntegrationParameters,
+    accumulator: f32,
+    previous_positions: HashMap<rapier3d::dynamics::RigidBodyHandle, Isometry3<f32>>,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    ccd_solver: CCDSolver,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    joints: JointSet,
+    vehicles: Pool<RaycastVehicle>,
+    body_handle_map: BiDirHashMap<RigidBodyHandle, rapier3d::dynamics::RigidBodyHandle>,
+    collider_handle_map: BiDirHashMap<ColliderHandle, rapier3d::geometry::ColliderHandle>,
+    joint_handle_map: BiDirHashMap<JointHandle, rapier3d::dynamics::JointHandle>,
+    // Node-body bindings of a scene, they're set only by scene level snapshots.
+    pub(in crate) bindings: Option<HashMap<Handle<Node>, RigidBodyHandle>>,
+}
+
+impl Debug for PhysicsSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "PhysicsSnapshot")
+    }
+}
+
+impl PhysicsSnapshot {
+    /// Returns amount of rigid bodies in the snapshot.
+    pub fn body_count(&self) -> usize {
+        self.bodies.len()
+    }
+}
+
+/// Defines which information will be drawn by `draw_with_options` of 3D and 2D physics and
+/// which colors will be used for that.
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicsDrawOptions {
+    /// Draw shapes of colliders, color of a shape depends on status of parent body.
+    /// Default is true.
+    pub shapes: bool,
+
+    /// Draw transforms of bodies as basis vectors. Default is true.
+    pub transforms: bool,
+
+    /// Draw axis-aligned bounding boxes of colliders. Default is false.
+    pub aabbs: bool,
+
+    /// Draw contact points with their normals. Default is true.
+    pub contacts: bool,
+
+    /// Draw anchors of joints and their limits (if any). Default is true.
+    pub joints: bool,
+
+    /// Length of a line that is used to draw contact normals and joint axes.
+    pub normal_length: f32,
+
+    /// Color of colliders of static bodies.
+    pub static_color: Color,
+
+    /// Color of colliders of dynamic bodies.
+    pub dynamic_color: Color,
+
+    /// Color of colliders of kinematic bodies.
+    pub kinematic_color: Color,
+
+    /// Color of colliders of sleeping bodies, it has priority over colors for body statuses.
+    pub sleeping_color: Color,
+
+    /// Color of sensor colliders, it has priority over other colors of shapes.
+    pub sensor_color: Color,
+
+    /// Color of contact points and normals.
+    pub contact_color: Color,
+
+    /// Color of joint anchors and limits.
+    pub joint_color: Color,
+
+    /// Color of bounding boxes.
+    pub aabb_color: Color,
+}
+
+impl Default for PhysicsDrawOptions {
+    fn default() -> Self {
+        Self {
+            shapes: true,
+            transforms: true,
+            aabbs: false,
+            contacts: true,
+            joints: true,
+            normal_length: 0.25,
+            static_color: Color::opaque(200, 200, 200),
+            dynamic_color: Color::opaque(80, 200, 80),
+            kinematic_color: Color::opaque(80, 140, 220),
+            sleeping_color: Color::opaque(100, 100, 100),
+            sensor_color: Color::opaque(220, 200, 60),
+            contact_color: Color::opaque(230, 50, 50),
+            joint_color: Color::opaque(220, 100, 220),
+            aabb_color: Color::opaque(60, 180, 180),
+        }
+    }
+}
+
+/// Physics world.
+pub struct Physics {
+    /// Current physics pipeline.
+    pipeline: PhysicsPipeline,
+    /// Current gravity vector. Default is (0.0, -9.81, 0.0)
+    pub gravity: Vector3<f32>,
+    /// A set of parameters that define behavior of every rigid body.
+    pub integration_parameters: IntegrationParameters,
+    /// Fixed timestep settings, see [`FixedTimestep`] docs.
+    pub fixed_timestep: FixedTimestep,
+    // Time that was not simulated yet.
+    accumulator: f32,
+    // Positions of non-static bodies before the last step, used for interpolation.
+    previous_positions: HashMap<rapier3d::dynamics::RigidBodyHandle, Isometry3<f32>>,
+    /// Broad phase performs rough intersection checks.
+    pub broad_phase: BroadPhase,
+    /// Narrow phase is responsible for precise contact generation.
+    pub narrow_phase: NarrowPhase,
+    /// A continuous collision detection solver.
+    pub ccd_solver: CCDSolver,
+
+    /// A set of rigid bodies.
+    bodies: RigidBodySet,
+
+    /// A set of colliders.
+    colliders: ColliderSet,
+
+    /// A set of joints.
+    joints: JointSet,
+
+    /// Event handler collects info about contacts and proximity events. Built-in event
+    /// collector receives the same events, see [`Self::events`].
+    pub event_handler: Box<dyn EventHandler>,
+
+    raw_events: RawEventCollector,
+
+    events: Vec<PhysicsEvent>,
+
+    vehicles: Pool<RaycastVehicle>,
+
+    /// Descriptors have two purposes:
+    /// 1) Defer deserialization to resolve stage - the stage where all meshes
+    ///    were loaded, so trimeshes from old files (which were saved without
+    ///    geometry) can be restored from associated meshes. Resolve stage will
+    ///    drain these vectors. This is normal use case.
+    /// 2) Save data that does not exist in the physics world yet: when
+    ///    descriptors are set, they will be written to output instead of
+    ///    descriptors generated from the actual state.
+    pub desc: Option<PhysicsDesc>,
+
+    /// A list of external resources that were embedded in the physics during
+    /// instantiation process.
+    pub embedded_resources: Vec<ResourceLink>,
+
+    query: RefCell<QueryPipeline>,
+
+    pub(in crate) performance_statistics: PhysicsPerformanceStatistics,
+
+    body_handle_map: BiDirHashMap<RigidBodyHandle, rapier3d::dynamics::RigidBodyHandle>,
+
+    collider_handle_map: BiDirHashMap<ColliderHandle, rapier3d::geometry::ColliderHandle>,
+
+    joint_handle_map: BiDirHashMap<JointHandle, rapier3d::dynamics::JointHandle>,
+}
+
+impl Debug for Physics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Physics")
+    }
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A trait for query results storage. It has two implementations: Vec and ArrayVec.
+/// Latter is needed for the cases where you need to avoid runtime memory allocations
+/// and do everything on stack. Ray casts store [`Intersection`]s, overlap tests store
+/// handles of colliders.
+pub trait QueryResultsStorage<T = Intersection> {
+    /// Pushes new intersection in the storage. Returns true if intersection was
+    /// successfully inserted, false otherwise.
+    fn push(&mut self, intersection: T) -> bool;
+
+    /// Clears the storage.
+    fn clear(&mut self);
+
+    /// Sorts intersections by given compare function.
+    fn sort_intersections_by<C: FnMut(&T, &T) -> Ordering>(&mut self, cmp: C);
+}
+
+impl<T> QueryResultsStorage<T> for Vec<T> {
+    fn push(&mut self, intersection: T) -> bool {
+        self.push(intersection);
+        true
+    }
+
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    fn sort_intersections_by<C>(&mut self, cmp: C)
+    where
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        self.sort_by(cmp);
+    }
+}
+
+impl<T, const CAP: usize> QueryResultsStorage<T> for ArrayVec<T, CAP> {
+    fn push(&mut self, intersection: T) -> bool {
+        self.try_push(intersection).is_ok()
+    }
+
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    fn sort_intersections_by<C>(&mut self, cmp: C)
+    where
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        self.sort_by(cmp);
+    }
+}
+
+/// Performance statistics for the physics part of the engine.
+#[derive(Debug, Default, Clone)]
+pub struct PhysicsPerformanceStatistics {
+    /// A time that was needed to perform a single simulation step.
+    pub step_time: Duration,
+
+    /// A time that was needed to perform all ray casts.
+    pub total_ray_cast_time: Cell<Duration>,
+}
+
+impl Display for PhysicsPerformanceStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Physics Step Time: {:?}\nPhysics Ray Cast Time: {:?}",
+            self.step_time,
+            self.total_ray_cast_time.get(),
+        )
+    }
+}
+
+impl PhysicsPerformanceStatistics {
+    pub(in crate) fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+impl Physics {
+    pub(in crate) fn new() -> Self {
+        Self {
+            pipeline: PhysicsPipeline::new(),
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            integration_parameters: IntegrationParameters::default(),
+            fixed_timestep: Default::default(),
+            accumulator: 0.0,
+            previous_positions: Default::default(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            ccd_solver: CCDSolver::new(),
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            joints: JointSet::new(),
+            event_handler: Box::new(()),
+            raw_events: Default::default(),
+            events: Default::default(),
+            vehicles: Default::default(),
+            query: Default::default(),
+            desc: Default::default(),
+            embedded_resources: Default::default(),
+            performance_statistics: Default::default(),
+            body_handle_map: Default::default(),
+            collider_handle_map: Default::default(),
+            joint_handle_map: Default::default(),
+        }
+    }
+
+    // Deep copy is performed using descriptors.
+    pub(in crate) fn deep_copy(&self, binder: &PhysicsBinder<Node>, graph: &Graph) -> Self {
+        let mut phys = Self::new();
+        phys.fixed_timestep = self.fixed_timestep;
+        // Vehicles can be copied as is, because body handles are preserved by the copy.
+        phys.vehicles = self.vehicles.clone();
+        phys.embedded_resources = self.embedded_resources.clone();
+        phys.desc = Some(self.generate_desc());
+        phys.resolve(binder, graph);
+        phys
+    }
+
+    /// Draws physics world. Very useful for debugging, it allows you to see where are
+    /// rigid bodies, which colliders they have and so on.
+    pub fn draw(&self, context: &mut SceneDrawingContext) {
+        self.draw_with_options(context, &Default::default())
+    }
+
+    /// Draws physics world using given options. Drawing is not immediate, it only pushes lines
+    /// into the context, they will be rendered later on by debug renderer.
+    pub fn draw_with_options(
+        &self,
+        context: &mut SceneDrawingContext,
+        options: &PhysicsDrawOptions,
+    ) {
+        if options.transforms {
+            for (_, body) in self.bodies.iter() {
+                context.draw_transform(body.position().to_homogeneous());
+            }
+        }
+
+        for (_, collider) in self.colliders.iter() {
+            let body = self.bodies.get(collider.parent()).unwrap();
+
+            if options.shapes {
+                let color = if collider.is_sensor() {
+                    options.sensor_color
+                } else if body.is_sleeping() {
+                    options.sleeping_color
+                } else {
+                    match body.body_status() {
+                        BodyStatus::Static => options.static_color,
+                        BodyStatus::Dynamic => options.dynamic_color,
+                        BodyStatus::Kinematic => options.kinematic_color,
+                    }
+                };
+                Self::draw_shape(
+                    context,
+                    collider.shape(),
+                    collider.position().to_homogeneous(),
+                    color,
+                );
+            }
+
+            if options.aabbs {
+                let aabb = collider.compute_aabb();
+                context.draw_aabb(
+                    &AxisAlignedBoundingBox::from_min_max(aabb.mins.coords, aabb.maxs.coords),
+                    options.aabb_color,
+                );
+            }
+        }
+
+        if options.contacts {
+            for pair in self.narrow_phase.contact_pairs() {
+                if let Some(collider1) = self.colliders.get(pair.pair.collider1) {
+                    for manifold in pair.manifolds.iter() {
+                        for point in manifold.points.iter() {
+                            let position = (collider1.position() * point.local_p1).coords;
+                            context.draw_sphere(position, 4, 4, 0.025, options.contact_color);
+                            context.add_line(Line {
+                                begin: position,
+                                end: position + manifold.data.normal * options.normal_length,
+                                color: options.contact_color,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if options.joints {
+            for (_, joint) in self.joints.iter() {
+                if let (Some(body1), Some(body2)) =
+                    (self.bodies.get(joint.body1), self.bodies.get(joint.body2))
+                {
+                    Self::draw_joint(context, joint, body1, body2, options);
+                }
+            }
+        }
+    }
+
+    fn draw_shape(
+        context: &mut SceneDrawingContext,
+        shape: &dyn Shape,
+        transform: Matrix4<f32>,
+        color: Color,
+    ) {
+        if let Some(trimesh) = shape.as_trimesh() {
+            let trimesh: &TriMesh = trimesh;
+            for triangle in trimesh.triangles() {
+                let a = transform.transform_point(&triangle.a);
+                let b = transform.transform_point(&triangle.b);
+                let c = transform.transform_point(&triangle.c);
+                context.draw_triangle(a.coords, b.coords, c.coords, color);
+            }
+        } else if let Some(heightfield) = shape.as_heightfield() {
+            for triangle in heightfield.triangles() {
+                let a = transform.transform_point(&triangle.a);
+                let b = transform.transform_point(&triangle.b);
+                let c = transform.transform_point(&triangle.c);
+                context.draw_triangle(a.coords, b.coords, c.coords, color);
+            }
+        } else if let Some(cuboid) = shape.as_cuboid() {
+            let min = -cuboid.half_extents;
+            let max = cuboid.half_extents;
+            context.draw_oob(
+                &AxisAlignedBoundingBox::from_min_max(min, max),
+                transform,
+                color,
+            );
+        } else if let Some(ball) = shape.as_ball() {
+            context.draw_sphere(
+                transform.transform_point(&Point3::origin()).coords,
+                10,
+                10,
+                ball.radius,
+                color,
+            );
+        } else if let Some(cone) = shape.as_cone() {
+            context.draw_cone(10, cone.radius, cone.half_height * 2.0, transform, color);
+        } else if let Some(cylinder) = shape.as_cylinder() {
+            context.draw_cylinder(
+                10,
+                cylinder.radius,
+                cylinder.half_height * 2.0,
+                true,
+                transform,
+                color,
+            );
+        } else if let Some(round_cylinder) = shape.as_round_cylinder() {
+            context.draw_cylinder(
+                10,
+                round_cylinder.base_shape.radius,
+                round_cylinder.base_shape.half_height * 2.0,
+                false,
+                transform,
+                color,
+            );
+        } else if let Some(triangle) = shape.as_triangle() {
+            context.draw_triangle(
+                transform.transform_point(&triangle.a).coords,
+                transform.transform_point(&triangle.b).coords,
+                transform.transform_point(&triangle.c).coords,
+                color,
+            );
+        } else if let Some(capsule) = shape.as_capsule() {
+            context.draw_segment_capsule(
+                capsule.segment.a.coords,
+                capsule.segment.b.coords,
+                capsule.radius,
+                10,
+                10,
+                transform,
+                color,
+            );
+        } else if let Some(segment) = shape.downcast_ref::<Segment>() {
+            context.add_line(Line {
+                begin: transform.transform_point(&segment.a).coords,
+                end: transform.transform_point(&segment.b).coords,
+                color,
+            });
+        } else if let Some(convex) = shape.downcast_ref::<ConvexPolyhedron>() {
+            let (vertices, indices) = convex.to_trimesh();
+            for triangle in indices {
+                context.draw_triangle(
+                    transform
+                        .transform_point(&vertices[triangle[0] as usize])
+                        .coords,
+                    transform
+                        .transform_point(&vertices[triangle[1] as usize])
+                        .coords,
+                    transform
+                        .transform_point(&vertices[triangle[2] as usize])
+                        .coords,
+                    color,
+                );
+            }
+        } else if let Some(compound) = shape.downcast_ref::<Compound>() {
+            for (position, part) in compound.shapes() {
+                Self::draw_shape(
+                    context,
+                    &**part,
+                    transform * position.to_homogeneous(),
+                    color,
+                );
+            }
+        }
+    }
+
+    fn draw_joint(
+        context: &mut SceneDrawingContext,
+        joint: &Joint,
+        body1: &RigidBody,
+        body2: &RigidBody,
+        options: &PhysicsDrawOptions,
+    ) {
+        let color = options.joint_color;
+        let (anchor1, anchor2) = match &joint.params {
+            JointParams::BallJoint(v) => (
+                body1.position() * v.local_anchor1,
+                body2.position() * v.local_anchor2,
+            ),
+            JointParams::FixedJoint(v) => (
+                body1.position() * Point3::from(v.local_anchor1.translation.vector),
+                body2.position() * Point3::from(v.local_anchor2.translation.vector),
+            ),
+            JointParams::PrismaticJoint(v) => {
+                let anchor1 = body1.position() * v.local_anchor1;
+                let axis = body1.position() * v.local_axis1().into_inner();
+                let (min, max) = if v.limits_enabled {
+                    (v.limits[0], v.limits[1])
+                } else {
+                    (-options.normal_length, options.normal_length)
+                };
+                context.add_line(Line {
+                    begin: (anchor1 + axis * min).coords,
+                    end: (anchor1 + axis * max).coords,
+                    color,
+                });
+                if v.limits_enabled {
+                    // Mark limits with small spheres.
+                    context.draw_sphere((anchor1 + axis * min).coords, 4, 4, 0.025, color);
+                    context.draw_sphere((anchor1 + axis * max).coords, 4, 4, 0.025, color);
+                }
+                (anchor1, body2.position() * v.local_anchor2)
+            }
+            JointParams::RevoluteJoint(v) => {
+                let anchor1 = body1.position() * v.local_anchor1;
+                let axis = body1.position() * v.local_axis1.into_inner();
+                context.add_line(Line {
+                    begin: (anchor1 - axis * options.normal_length).coords,
+                    end: (anchor1 + axis * options.normal_length).coords,
+                    color,
+                });
+                (anchor1, body2.position() * v.local_anchor2)
+            }
+        };
+
+        // Connect bodies with their anchors, anchors itself are drawn as small spheres.
+        for (body, anchor) in [(body1, anchor1), (body2, anchor2)].iter() {
+            context.add_line(Line {
+                begin: body.position().translation.vector,
+                end: anchor.coords,
+                color,
+            });
+            context.draw_sphere(anchor.coords, 6, 6, 0.05, color);
+        }
+    }
+
+    /// TODO
+    pub fn bodies(&self) -> &RigidBodySet {
+        &self.bodies
+    }
+
+    /// TODO
+    pub fn colliders(&self) -> &ColliderSet {
+        &self.colliders
+    }
+
+    /// TODO
+    pub fn joints(&self) -> &JointSet {
+        &self.joints
+    }
+
+    /// TODO
+    pub fn body_mut(&mut self, handle: &RigidBodyHandle) -> Option<&mut RigidBody> {
+        let bodies = &mut self.bodies;
+        self.body_handle_map
+            .value_of(handle)
+            .and_then(move |&h| bodies.get_mut(h))
+    }
+
+    /// TODO
+    pub fn body_mut_rapier(
+        &mut self,
+        handle: rapier3d::dynamics::RigidBodyHandle,
+    ) -> Option<&mut RigidBody> {
+        self.bodies.get_mut(handle)
+    }
+
+    /// TODO
+    pub fn body(&self, handle: &RigidBodyHandle) -> Option<&RigidBody> {
+        let bodies = &self.bodies;
+        self.body_handle_map
+            .value_of(handle)
+            .and_then(move |&h| bodies.get(h))
+    }
+
+    /// TODO
+    pub fn body_rapier(&self, handle: rapier3d::dynamics::RigidBodyHandle) -> Option<&RigidBody> {
+        self.bodies.get(handle)
+    }
+
+    /// TODO
+    pub fn contains_body(&self, handle: &RigidBodyHandle) -> bool {
+        self.body(handle).is_some()
+    }
+
+    /// TODO
+    pub fn collider_mut(&mut self, handle: &ColliderHandle) -> Option<&mut Collider> {
+        let colliders = &mut self.colliders;
+        self.collider_handle_map
+            .value_of(handle)
+            .and_then(move |&h| colliders.get_mut(h))
+    }
+
+    /// TODO
+    pub fn collider(&self, handle: &ColliderHandle) -> Option<&Collider> {
+        let colliders = &self.colliders;
+        self.collider_handle_map
+            .value_of(handle)
+            .and_then(|&h| colliders.get(h))
+    }
+
+    /// TODO
+    pub fn collider_rapier(&self, handle: rapier3d::geometry::ColliderHandle) -> Option<&Collider> {
+        self.colliders.get(handle)
+    }
+
+    /// TODO
+    pub fn contains_collider(&self, handle: &ColliderHandle) -> bool {
+        self.collider(handle).is_some()
+    }
+
+    /// TODO
+    pub fn body_handle_map(
+        &self,
+    ) -> &BiDirHashMap<RigidBodyHandle, rapier3d::dynamics::RigidBodyHandle> {
+        &self.body_handle_map
+    }
+
+    /// TODO
+    pub fn collider_handle_map(
+        &self,
+    ) -> &BiDirHashMap<ColliderHandle, rapier3d::geometry::ColliderHandle> {
+        &self.collider_handle_map
+    }
+
+    /// TODO
+    pub fn joint_handle_map(&self) -> &BiDirHashMap<JointHandle, rapier3d::dynamics::JointHandle> {
+        &self.joint_handle_map
+    }
+
+    /// TODO
+    pub fn collider_parent(&self, collider: &ColliderHandle) -> Option<&RigidBodyHandle> {
+        self.collider(collider)
+            .and_then(|c| self.body_handle_map.key_of(&c.parent()))
+    }
+
+    /// Advances physics by given amount of time. Depending on fixed timestep settings it
+    /// performs zero or more steps. Events of all performed steps are collected.
+    pub(in crate) fn update(&mut self, dt: f32) {
+        self.events.clear();
+
+        if !self.fixed_timestep.enabled {
+            self.step();
+            return;
+        }
+
+        let step_dt = self.integration_parameters.dt;
+        if step_dt <= 0.0 {
+            return;
+        }
+
+        let max_substeps = self.fixed_timestep.max_substeps.max(1);
+        self.accumulator += dt;
+        let mut steps = 0;
+        while self.accumulator >= step_dt {
+            if steps >= max_substeps {
+                // Discard time that can't be simulated, but keep fraction of a step to keep
+                // interpolation smooth.
+                self.accumulator %= step_dt;
+                break;
+            }
+            if self.fixed_timestep.interpolate {
+                self.remember_positions();
+            }
+            self.step();
+            self.accumulator -= step_dt;
+            steps += 1;
+        }
+    }
+
+    fn remember_positions(&mut self) {
+        self.previous_positions.clear();
+        for (handle, body) in self.bodies.iter() {
+            if !body.is_static() {
+                self.previous_positions.insert(handle, *body.position());
+            }
+        }
+    }
+
+    /// Returns interpolation factor between previous and current physics states, it is always
+    /// 1.0 if fixed timestep or interpolation is disabled.
+    pub fn interpolation_factor(&self) -> f32 {
+        if self.fixed_timestep.enabled
+            && self.fixed_timestep.interpolate
+            && self.integration_parameters.dt > 0.0
+        {
+            (self.accumulator / self.integration_parameters.dt).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns position of a body interpolated between two last physics states. See
+    /// [`FixedTimestep::interpolate`].
+    pub fn interpolated_body_position(&self, handle: &RigidBodyHandle) -> Option<Isometry3<f32>> {
+        let rapier_handle = *self.body_handle_map.value_of(handle)?;
+        let current = *self.bodies.get(rapier_handle)?.position();
+        let t = self.interpolation_factor();
+        if t >= 1.0 {
+            return Some(current);
+        }
+        Some(match self.previous_positions.get(&rapier_handle) {
+            Some(previous) => previous.lerp_slerp(&current, t),
+            None => current,
+        })
+    }
+
+    /// Makes a full copy of current state of physics world. Event handler, descriptors and
+    /// embedded resources are not included in the snapshot. Use
+    /// [`Scene::snapshot_physics`](crate::scene::Scene::snapshot_physics) to also save
+    /// node-body bindings of a scene.
+    pub fn snapshot(&self) -> PhysicsSnapshot {
+        PhysicsSnapshot {
+            gravity: self.gravity,
+            integration_parameters: self.integration_parameters.clone(),
+            accumulator: self.accumulator,
+            previous_positions: self.previous_positions.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            ccd_solver: self.ccd_solver.clone(),
+            bodies: self.bodies.clone(),
+            colliders: self.colliders.clone(),
+            joints: self.joints.clone(),
+            vehicles: self.vehicles.clone(),
+            body_handle_map: self.body_handle_map.clone(),
+            collider_handle_map: self.collider_handle_map.clone(),
+            joint_handle_map: self.joint_handle_map.clone(),
+            bindings: None,
+        }
+    }
+
+    /// Restores state of physics world from given snapshot. Every body, collider and joint that
+    /// were added after the snapshot was made will be removed, handles of entities from the
+    /// snapshot become valid again. Events of the last update are discarded.
+    pub fn restore(&mut self, snapshot: &PhysicsSnapshot) {
+        self.gravity = snapshot.gravity;
+        self.integration_parameters = snapshot.integration_parameters.clone();
+        self.accumulator = snapshot.accumulator;
+        self.previous_positions = snapshot.previous_positions.clone();
+        self.broad_phase = snapshot.broad_phase.clone();
+        self.narrow_phase = snapshot.narrow_phase.clone();
+        self.ccd_solver = snapshot.ccd_solver.clone();
+        self.bodies = snapshot.bodies.clone();
+        self.colliders = snapshot.colliders.clone();
+        self.joints = snapshot.joints.clone();
+        self.vehicles = snapshot.vehicles.clone();
+        self.body_handle_map = snapshot.body_handle_map.clone();
+        self.collider_handle_map = snapshot.collider_handle_map.clone();
+        self.joint_handle_map = snapshot.joint_handle_map.clone();
+        self.events.clear();
+        // Query pipeline could contain colliders that does not exist anymore.
+        *self.query.borrow_mut() = QueryPipeline::new();
+    }
+
+    /// Performs exactly given amount of steps of `integration_parameters.dt` each, regardless
+    /// of fixed timestep settings. Time accumulated for fixed timestep is left untouched.
+    /// Events of all performed steps are collected. Usually used after [`Self::restore`]
+    /// to catch up with current time.
+    pub fn resimulate(&mut self, steps: usize) {
+        self.events.clear();
+        for _ in 0..steps {
+            if self.fixed_timestep.enabled && self.fixed_timestep.interpolate {
+                self.remember_positions();
+            }
+            self.step();
+        }
+    }
+
+    fn step(&mut self) {
+        let time = instant::Instant::now();
+
+        self.update_vehicles();
+
+        self.pipeline.step(
+            &self.gravity,
+            &self.integration_parameters,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.bodies,
+            &mut self.colliders,
+            &mut self.joints,
+            &mut self.ccd_solver,
+            &(),
+            &ForwardingEventHandler {
+                collector: &self.raw_events,
+                user: &*self.event_handler,
+            },
+        );
+
+        self.collect_events();
+
+        self.performance_statistics.step_time += instant::Instant::now() - time;
+    }
+
+    fn update_vehicles(&mut self) {
+        if self.vehicles.alive_count() == 0 {
+            return;
+        }
+        let dt = self.integration_parameters.dt;
+        let mut vehicles = std::mem::take(&mut self.vehicles);
+        for vehicle in vehicles.iter_mut() {
+            vehicle.update(self, dt);
+        }
+        self.vehicles = vehicles;
+    }
+
+    /// Adds new raycast vehicle, see [`vehicle`] module docs.
+    pub fn add_vehicle(&mut self, desc: VehicleDesc) -> Handle<RaycastVehicle> {
+        self.vehicles.spawn(RaycastVehicle::from_desc(desc))
+    }
+
+    /// Removes a vehicle. Chassis body is not removed.
+    pub fn remove_vehicle(&mut self, handle: Handle<RaycastVehicle>) -> RaycastVehicle {
+        self.vehicles.free(handle)
+    }
+
+    /// Borrows a vehicle by its handle.
+    pub fn vehicle(&self, handle: Handle<RaycastVehicle>) -> &RaycastVehicle {
+        self.vehicles.borrow(handle)
+    }
+
+    /// Borrows a vehicle by its handle.
+    pub fn vehicle_mut(&mut self, handle: Handle<RaycastVehicle>) -> &mut RaycastVehicle {
+        self.vehicles.borrow_mut(handle)
+    }
+
+    /// Returns a reference to the pool of vehicles.
+    pub fn vehicles(&self) -> &Pool<RaycastVehicle> {
+        &self.vehicles
+    }
+
+    pub(in crate) fn sync_vehicle_wheels(&self, graph: &mut Graph) {
+        for vehicle in self.vehicles.iter() {
+            vehicle.sync_wheel_nodes(graph);
+        }
+    }
+
+    pub(in crate) fn remap_vehicle_nodes(
+        &mut self,
+        old_new_map: &HashMap<Handle<Node>, Handle<Node>>,
+    ) {
+        for vehicle in self.vehicles.iter_mut() {
+            vehicle.remap_nodes(old_new_map);
+        }
+    }
+
+    fn engine_collider_handle(&self, handle: rapier3d::geometry::ColliderHandle) -> ColliderHandle {
+        // Collider could be removed already, default handle is used in this case.
+        self.collider_handle_map
+            .key_of(&handle)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn contact_info(
+        &self,
+        collider1: rapier3d::geometry::ColliderHandle,
+        collider2: rapier3d::geometry::ColliderHandle,
+        with_points: bool,
+    ) -> ContactEventInfo {
+        let mut points = Vec::new();
+        if with_points {
+            if let Some(pair) = self.narrow_phase.contact_pair(collider1, collider2) {
+                // Order of colliders in the pair may differ from the order in the event.
+                let flip = pair.pair.collider1 != collider1;
+                if let (Some(pair_collider1), Some(pair_collider2)) = (
+                    self.colliders.get(pair.pair.collider1),
+                    self.colliders.get(pair.pair.collider2),
+                ) {
+                    for manifold in pair.manifolds.iter() {
+                        for point in manifold.points.iter() {
+                            points.push(if flip {
+                                ContactPoint {
+                                    position: pair_collider2.position() * point.local_p2,
+                                    normal: -manifold.data.normal,
+                                    impulse: point.data.impulse,
+                                }
+                            } else {
+                                ContactPoint {
+                                    position: pair_collider1.position() * point.local_p1,
+                                    normal: manifold.data.normal,
+                                    impulse: point.data.impulse,
+                                }
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        ContactEventInfo {
+            collider1: self.engine_collider_handle(collider1),
+            collider2: self.engine_collider_handle(collider2),
+            node1: Default::default(),
+            node2: Default::default(),
+            total_impulse: points.iter().map(|p| p.impulse).sum(),
+            points,
+        }
+    }
+
+    fn collect_events(&mut self) {
+        let contacts = std::mem::take(&mut *self.raw_events.contacts.lock().unwrap());
+        for event in contacts {
+            let event = match event {
+                ContactEvent::Started(collider1, collider2) => {
+                    PhysicsEvent::ContactStarted(self.contact_info(collider1, collider2, true))
+                }
+                ContactEvent::Stopped(collider1, collider2) => {
+                    PhysicsEvent::ContactStopped(self.contact_info(collider1, collider2, false))
+                }
+            };
+            self.events.push(event);
+        }
+
+        let intersections = std::mem::take(&mut *self.raw_events.intersections.lock().unwrap());
+        for event in intersections {
+            let (sensor, other) = if self
+                .colliders
+                .get(event.collider1)
+                .map_or(false, |c| c.is_sensor())
+            {
+                (event.collider1, event.collider2)
+            } else {
+                (event.collider2, event.collider1)
+            };
+            let info = SensorEventInfo {
+                sensor: self.engine_collider_handle(sensor),
+                other: self.engine_collider_handle(other),
+                sensor_node: Default::default(),
+                other_node: Default::default(),
+            };
+            self.events.push(if event.intersecting {
+                PhysicsEvent::SensorEntered(info)
+            } else {
+                PhysicsEvent::SensorExited(info)
+            });
+        }
+    }
+
+    /// Fills nodes of events collected during last step using given binder.
+    pub(in crate) fn bind_event_nodes(&mut self, binder: &PhysicsBinder<Node>) {
+        let colliders = &self.colliders;
+        let collider_handle_map = &self.collider_handle_map;
+        let body_handle_map = &self.body_handle_map;
+        for event in self.events.iter_mut() {
+            event.bind_nodes(binder, |collider| {
+                collider_handle_map
+                    .value_of(collider)
+                    .and_then(|&h| colliders.get(h))
+                    .and_then(|c| body_handle_map.key_of(&c.parent()).cloned())
+            });
+        }
+    }
+
+    /// Returns events (contacts, sensor intersections) that were produced during last update.
+    /// The queue is cleared at the beginning of every update. See [`event`] module docs.
+    pub fn events(&self) -> &[PhysicsEvent] {
+        &self.events
+    }
+
+    #[doc(hidden)]
+    pub fn generate_desc(&self) -> PhysicsDesc {
+        let body_dense_map = self
+            .bodies
+            .iter()
+            .enumerate()
+            .map(|(i, (h, _))| (h, rapier3d::dynamics::RigidBodyHandle::from_raw_parts(i, 0)))
+            .collect::<HashMap<_, _>>();
+
+        let mut body_handle_map = BiDirHashMap::default();
+        for (engine_handle, rapier_handle) in self.body_handle_map.forward_map() {
+            body_handle_map.insert(*engine_handle, body_dense_map[rapier_handle]);
+        }
+
+        let collider_dense_map = self
+            .colliders
+            .iter()
+            .enumerate()
+            .map(|(i, (h, _))| (h, rapier3d::geometry::ColliderHandle::from_raw_parts(i, 0)))
+            .collect::<HashMap<_, _>>();
+
+        let mut collider_handle_map = BiDirHashMap::default();
+        for (engine_handle, rapier_handle) in self.collider_handle_map.forward_map() {
+            collider_handle_map.insert(*engine_handle, collider_dense_map[rapier_handle]);
+        }
+
+        let joint_dense_map = self
+            .joints
+            .iter()
+            .enumerate()
+            .map(|(i, (h, _))| (h, rapier3d::dynamics::JointHandle::from_raw_parts(i, 0)))
+            .collect::<HashMap<_, _>>();
+
+        let mut joint_handle_map = BiDirHashMap::default();
+        for (engine_handle, rapier_handle) in self.joint_handle_map.forward_map() {
+            joint_handle_map.insert(*engine_handle, joint_dense_map[rapier_handle]);
+        }
+
+        PhysicsDesc {
+            integration_parameters: self.integration_parameters.clone().into(),
+
+            bodies: self
+                .bodies
+                .iter()
+                .map(|(_, b)| RigidBodyDesc::from_body(b, &self.collider_handle_map))
+                .collect::<Vec<_>>(),
+
+            colliders: self
+                .colliders
+                .iter()
+                .map(|(_, c)| ColliderDesc::from_collider(c, &self.body_handle_map))
+                .collect::<Vec<_>>(),
+
+            gravity: self.gravity,
+
+            joints: self
+                .joints
+                .iter()
+                .map(|(_, j)| JointDesc::from_joint(j, &self.body_handle_map))
+                .collect::<Vec<_>>(),
+
+            body_handle_map,
+            collider_handle_map,
+            joint_handle_map,
+        }
+    }
+
+    // Collects geometry of every mesh in given subtree into one triangle soup, vertices are
+    // relative to the root with scale baked in.
+    fn collect_mesh_geometry(
+        root: Handle<Node>,
+        graph: &Graph,
+    ) -> (Vec<Point3<f32>>, Vec<[u32; 3]>) {
+        let mut mesh_builder = RawMeshBuilder::new(0, 0);
+
+        // Create inverse transform that will discard rotation and translation, but leave scaling and
+        // other parameters of global transform.
+        // When global transform of node is combined with this transform, we'll get relative transform
+        // with scale baked in. We need to do this because root's transform will be synced with body's
+        // but we don't want to bake entire transform including root's transform.
+        let root_inv_transform = graph
+            .isometric_global_transform(root)
+            .try_inverse()
+            .unwrap();
+
+        // Iterate over hierarchy of nodes and build one single trimesh.
+        let mut stack = vec![root];
+        while let Some(handle) = stack.pop() {
+            let node = &graph[handle];
+            if let Node::Mesh(mesh) = node {
+                let global_transform = root_inv_transform * mesh.global_transform();
+
+                for surface in mesh.surfaces() {
+                    let shared_data = surface.data();
+                    let shared_data = shared_data.read().unwrap();
+
+                    let vertices = shared_data.vertex_buffer();
+                    for triangle in shared_data.triangles() {
+                        let a = RawVertex::from(
+                            global_transform
+                                .transform_point(&Point3::from(
+                                    vertices
+                                        .get(triangle[0] as usize)
+                                        .unwrap()
+                                        .read_3_f32(VertexAttributeKind::Position)
+                                        .unwrap(),
+                                ))
+                                .coords,
+                        );
+                        let b = RawVertex::from(
+                            global_transform
+                                .transform_point(&Point3::from(
+                                    vertices
+                                        .get(triangle[1] as usize)
+                                        .unwrap()
+                                        .read_3_f32(VertexAttributeKind::Position)
+                                        .unwrap(),
+                                ))
+                                .coords,
+                        );
+                        let c = RawVertex::from(
+                            global_transform
+                                .transform_point(&Point3::from(
+                                    vertices
+                                        .get(triangle[2] as usize)
+                                        .unwrap()
+                                        .read_3_f32(VertexAttributeKind::Position)
+                                        .unwrap(),
+                                ))
+                                .coords,
+                        );
+
+                        mesh_builder.insert(a);
+                        mesh_builder.insert(b);
+                        mesh_builder.insert(c);
+                    }
+                }
+            }
+            stack.extend_from_slice(node.children.as_slice());
+        }
+
+        let raw_mesh = mesh_builder.build();
+
+        let vertices: Vec<Point3<f32>> = raw_mesh
+            .vertices
+            .into_iter()
+            .map(|v| Point3::new(v.x, v.y, v.z))
+            .collect();
+
+        let indices = raw_mesh
+            .triangles
+            .into_iter()
+            .map(|t| [t.0[0], t.0[1], t.0[2]])
+            .collect::<Vec<_>>();
+
+        (vertices, indices)
+    }
+
+    /// Creates new trimesh collider shape from given mesh node. It also bakes scale into
+    /// vertices of trimesh because rapier does not support collider scaling yet.
+    pub fn make_trimesh(root: Handle<Node>, graph: &Graph) -> SharedShape {
+        let (vertices, indices) = Self::collect_mesh_geometry(root, graph);
+
+        if indices.is_empty() {
+            Log::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Failed to create triangle mesh collider for {}, it has no vertices!",
+                    graph[root].name()
+                ),
+            );
+
+            SharedShape::trimesh(vec![Point3::new(0.0, 0.0, 0.0)], vec![[0, 0, 0]])
+        } else {
+            SharedShape::trimesh(vertices, indices)
+        }
+    }
+
+    /// Small helper that creates static physics geometry from given mesh.
+    ///
+    /// # Notes
+    ///
+    /// This method *bakes* global transform of given mesh into static geometry
+    /// data. So if given mesh was at some position with any rotation and scale
+    /// resulting static geometry will have vertices that exactly matches given
+    /// mesh.
+    pub fn mesh_to_trimesh(&mut self, root: Handle<Node>, graph: &Graph) -> RigidBodyHandle {
+        let shape = Self::make_trimesh(root, graph);
+        let tri_mesh = ColliderBuilder::new(shape).friction(0.0).build();
+        let (global_rotation, global_position) = graph.isometric_global_rotation_position(root);
+        let body = RigidBodyBuilder::new(BodyStatus::Static)
+            .position(Isometry3 {
+                rotation: global_rotation,
+                translation: Translation {
+                    vector: global_position,
+                },
+            })
+            .build();
+        let handle = self.add_body(body);
+        self.add_collider(tri_mesh, &handle);
+        handle
+    }
+
+    /// Creates new convex hull collider shape that encloses every mesh in given subtree. Scale
+    /// is baked into vertices as in [`Self::make_trimesh`]. Unlike trimeshes, convex shapes can
+    /// be used on dynamic bodies. Returns `None` if subtree has no meshes or their geometry is
+    /// degenerated (all vertices lie on a plane for example).
+    pub fn make_convex_hull(root: Handle<Node>, graph: &Graph) -> Option<SharedShape> {
+        let (vertices, _) = Self::collect_mesh_geometry(root, graph);
+
+        SharedShape::convex_hull(&vertices)
+    }
+
+    /// Creates compound collider shape that consists of convex parts which approximate every
+    /// mesh in given subtree. Decomposition is done using V-HACD algorithm, it could be slow for
+    /// big meshes, so it is better to bake results into a model resource instead of doing it
+    /// at run time. Returns `None` if subtree has no meshes.
+    pub fn make_convex_decomposition(
+        root: Handle<Node>,
+        graph: &Graph,
+        params: &VHACDParameters,
+    ) -> Option<SharedShape> {
+        let (vertices, indices) = Self::collect_mesh_geometry(root, graph);
+
+        if indices.is_empty() {
+            None
+        } else {
+            Some(SharedShape::convex_decomposition_with_params(
+                &vertices, &indices, params,
+            ))
+        }
+    }
+
+    /// Small helper that creates dynamic rigid body with convex hull collider for given mesh.
+    /// Initial position and rotation of the body matches global transform of the root. Returns
+    /// `None` and does not create a body if convex hull cannot be built, see
+    /// [`Self::make_convex_hull`].
+    pub fn mesh_to_convex_hull(
+        &mut self,
+        root: Handle<Node>,
+        graph: &Graph,
+    ) -> Option<RigidBodyHandle> {
+        let shape = Self::make_convex_hull(root, graph)?;
+        Some(self.add_convex_body(shape, root, graph))
+    }
+
+    /// Small helper that creates dynamic rigid body with convex decomposition of given mesh as
+    /// collider. See [`Self::make_convex_decomposition`]. Returns `None` and does not create a
+    /// body if subtree has no meshes.
+    pub fn mesh_to_convex_decomposition(
+        &mut self,
+        root: Handle<Node>,
+        graph: &Graph,
+        params: &VHACDParameters,
+    ) -> Option<RigidBodyHandle> {
+        let shape = Self::make_convex_decomposition(root, graph, params)?;
+        Some(self.add_convex_body(shape, root, graph))
+    }
+
+    fn add_convex_body(
+        &mut self,
+        shape: SharedShape,
+        root: Handle<Node>,
+        graph: &Graph,
+    ) -> RigidBodyHandle {
+        let (global_rotation, global_position) = graph.isometric_global_rotation_position(root);
+        let body = RigidBodyBuilder::new(BodyStatus::Dynamic)
+            .position(Isometry3 {
+                rotation: global_rotation,
+                translation: Translation {
+                    vector: global_position,
+                },
+            })
+            .build();
+        let handle = self.add_body(body);
+        self.add_collider(ColliderBuilder::new(shape).build(), &handle);
+        handle
+    }
+
+    /// Casts a ray with given options.
+    pub fn cast_ray<S: QueryResultsStorage>(&self, opts: RayCastOptions, query_buffer: &mut S) {
+        let time = instant::Instant::now();
+
+        let mut query = self.query.borrow_mut();
+
+        // TODO: Ideally this must be called once per frame, but it seems to be impossible because
+        // a body can be deleted during the consecutive calls of this method which will most
+        // likely end up in panic because of invalid handle stored in internal acceleration
+        // structure. This could be fixed by delaying deleting of bodies/collider to the end
+        // of the frame.
+        query.update(&self.bodies, &self.colliders);
+
+        query_buffer.clear();
+        let ray = rapier3d::geometry::Ray::new(
+            Point3::from(opts.ray.origin),
+            opts.ray
+                .dir
+                .try_normalize(std::f32::EPSILON)
+                .unwrap_or_default(),
+        );
+        query.intersections_with_ray(
+            &self.colliders,
+            &ray,
+            opts.max_len,
+            true,
+            opts.groups,
+            None, // TODO
+            |handle, _, intersection| {
+                query_buffer.push(Intersection {
+                    collider: self.collider_handle_map.key_of(&handle).cloned().unwrap(),
+                    normal: intersection.normal,
+                    position: ray.point_at(intersection.toi),
+                    feature: intersection.feature,
+                    toi: intersection.toi,
+                })
+            },
+        );
+        if opts.sort_results {
+            query_buffer.sort_intersections_by(|a, b| {
+                if a.toi > b.toi {
+                    Ordering::Greater
+                } else if a.toi < b.toi {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            })
+        }
+
+        self.performance_statistics.total_ray_cast_time.set(
+            self.performance_statistics.total_ray_cast_time.get()
+                + (instant::Instant::now() - time),
+        );
+    }
+
+    fn updated_query(&self) -> RefMut<QueryPipeline> {
+        let mut query = self.query.borrow_mut();
+        // See comment in `cast_ray` about why the pipeline is updated on every query.
+        query.update(&self.bodies, &self.colliders);
+        query
+    }
+
+    /// Sweeps a shape along given direction and returns the first collider hit by the shape.
+    pub fn cast_shape(&self, opts: ShapeCastOptions) -> Option<ShapeCastResult> {
+        let query = self.updated_query();
+
+        let shape = opts.shape.into_collider_shape();
+        let direction = opts
+            .direction
+            .try_normalize(std::f32::EPSILON)
+            .unwrap_or_default();
+        query
+            .cast_shape(
+                &self.colliders,
+                &opts.position,
+                &direction,
+                &*shape,
+                opts.max_len,
+                opts.groups,
+                None,
+            )
+            .map(|(handle, toi)| ShapeCastResult {
+                collider: self.collider_handle_map.key_of(&handle).cloned().unwrap(),
+                toi: toi.toi,
+                // Witness and normal on the first shape are in world coordinates, because the
+                // first shape is the query pipeline itself.
+                position: toi.witness1,
+                normal: toi.normal1.into_inner(),
+            })
+    }
+
+    /// Finds a collider that is closest to given point. If `solid` is true, points inside
+    /// colliders will be projected on themselves, otherwise on boundaries of colliders.
+    pub fn project_point(
+        &self,
+        point: Point3<f32>,
+        solid: bool,
+        groups: InteractionGroups,
+    ) -> Option<PointProjectionResult> {
+        let query = self.updated_query();
+
+        query
+            .project_point(&self.colliders, &point, solid, groups, None)
+            .map(|(handle, projection)| PointProjectionResult {
+                collider: self.collider_handle_map.key_of(&handle).cloned().unwrap(),
+                position: projection.point,
+                is_inside: projection.is_inside,
+            })
+    }
+
+    /// Collects every collider that intersects with given shape.
+    pub fn intersections_with_shape<S: QueryResultsStorage<ColliderHandle>>(
+        &self,
+        shape: ColliderShapeDesc,
+        position: Isometry3<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        let shape = shape.into_collider_shape();
+        query.intersections_with_shape(
+            &self.colliders,
+            &position,
+            &*shape,
+            groups,
+            None,
+            |handle| query_buffer.push(self.collider_handle_map.key_of(&handle).cloned().unwrap()),
+        );
+    }
+
+    /// Collects every collider that contains given point.
+    pub fn intersections_with_point<S: QueryResultsStorage<ColliderHandle>>(
+        &self,
+        point: Point3<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        query.intersections_with_point(&self.colliders, &point, groups, None, |handle| {
+            query_buffer.push(self.collider_handle_map.key_of(&handle).cloned().unwrap())
+        });
+    }
+
+    pub(in crate) fn resolve(&mut self, binder: &PhysicsBinder<Node>, graph: &Graph) {
+        assert_eq!(self.bodies.len(), 0);
+        assert_eq!(self.colliders.len(), 0);
+
+        let mut phys_desc = self.desc.take().unwrap();
+
+        self.body_handle_map = phys_desc.body_handle_map;
+        self.collider_handle_map = phys_desc.collider_handle_map;
+        self.joint_handle_map = phys_desc.joint_handle_map;
+
+        self.integration_parameters = phys_desc.integration_parameters.into();
+
+        let mut mass_properties = Vec::new();
+        for desc in phys_desc.bodies.drain(..) {
+            let properties = desc.mass_properties.clone();
+            let handle = self.bodies.insert(desc.convert_to_body());
+            mass_properties.push((handle, properties));
+        }
+
+        for mut desc in phys_desc.colliders.drain(..) {
+            if matches!(&desc.shape, ColliderShapeDesc::Trimesh(trimesh) if trimesh.is_empty()) {
+                // Old files do not contain geometry of trimeshes, restore it from associated
+                // mesh in the scene.
+                match binder.node_of(desc.parent) {
+                    Some(associated_node) if graph.is_valid_handle(associated_node) => {
+                        desc.shape = ColliderShapeDesc::from_collider_shape(&*Self::make_trimesh(
+                            associated_node,
+                            graph,
+                        ));
+
+                        Log::writeln(
+                            MessageKind::Information,
+                            format!(
+                                "Geometry for trimesh {:?} was restored from node at handle {:?}!",
+                                desc.parent, associated_node
+                            ),
+                        )
+                    }
+                    associated_node => {
+                        Log::writeln(
+                            MessageKind::Error,
+                            format!(
+                                "Unable to get geometry for trimesh, node {:?} does not exists!",
+                                associated_node
+                            ),
+                        );
+                        continue;
+                    }
+                }
+            }
+
+            let (collider, parent) = desc.convert_to_collider();
+            self.colliders.insert(
+                collider,
+                self.body_handle_map.value_of(&parent).cloned().unwrap(),
+                &mut self.bodies,
+            );
+        }
+
+        // Mass properties must be restored after colliders, because every collider adds its
+        // mass to parent body.
+        for (handle, properties) in mass_properties {
+            if let (Some(body), Some(properties)) = (self.bodies.get_mut(handle), properties) {
+                body.set_mass_properties(properties.into(), false);
+            }
+        }
+
+        for desc in phys_desc.joints.drain(..) {
+            let b1 = self
+                .body_handle_map()
+                .value_of(&desc.body1)
+                .cloned()
+                .unwrap();
+            let b2 = self
+                .body_handle_map()
+                .value_of(&desc.body2)
+                .cloned()
+                .unwrap();
+            self.joints
+                .insert(&mut self.bodies, b1, b2, desc.convert_to_params());
+        }
+    }
+
+    pub(in crate) fn embed_resource(
+        &mut self,
+        target_binder: &mut PhysicsBinder<Node>,
+        target_graph: &Graph,
+        old_to_new: HashMap<Handle<Node>, Handle<Node>>,
+        resource: Model,
+    ) {
+        let data = resource.data_ref();
+        let resource_scene = data.get_scene();
+        let resource_binder = &resource_scene.physics_binder;
+        let resource_physics = &resource_scene.physics;
+        let mut link = ResourceLink::default();
+        let mut embedded_mass_properties = Vec::new();
+
+        // Instantiate rigid bodies.
+        for (resource_handle, body) in resource_physics.bodies.iter() {
+            let desc = RigidBodyDesc::<ColliderHandle>::from_body(
+                body,
+                &resource_physics.collider_handle_map,
+            );
+            let mass_properties = desc.mass_properties.clone();
+            let new_handle = self.add_body(desc.convert_to_body());
+            embedded_mass_properties.push((new_handle, mass_properties));
+
+            link.bodies.insert(
+                resource_physics
+                    .body_handle_map
+                    .key_of(&resource_handle)
+                    .cloned()
+                    .unwrap(),
+                new_handle,
+            );
+        }
+
+        // Bind instantiated nodes with their respective rigid bodies from resource.
+        for (handle, body) in resource_binder.forward_map().iter() {
+            let new_handle = *old_to_new.get(handle).unwrap();
+            let new_body = *link.bodies.get(body).unwrap();
+            target_binder.bind(new_handle, new_body);
+        }
+
+        // Instantiate colliders.
+        for (resource_handle, collider) in resource_physics.colliders.iter() {
+            let desc = ColliderDesc::from_collider(collider, &resource_physics.body_handle_map);
+            // Remap handle from resource to one that was created above.
+            let remapped_parent = *link.bodies.get(&desc.parent).unwrap();
+            // Geometry of trimeshes is already in the resource, it was either loaded or restored
+            // from resource meshes on resolve stage.
+            let (new_collider, _) = desc.convert_to_collider();
+            let new_handle = self.add_collider(new_collider, &remapped_parent);
+            link.colliders.insert(
+                resource_physics
+                    .collider_handle_map
+                    .key_of(&resource_handle)
+                    .cloned()
+                    .unwrap(),
+                new_handle,
+            );
+        }
+
+        for (handle, properties) in embedded_mass_properties {
+            if let (Some(body), Some(properties)) = (self.body_mut(&handle), properties) {
+                body.set_mass_properties(properties.into(), false);
+            }
+        }
+
+        // Instantiate joints.
+        for (resource_handle, joint) in resource_physics.joints.iter() {
+            let desc =
+                JointDesc::<RigidBodyHandle>::from_joint(joint, &resource_physics.body_handle_map);
+            let new_body1_handle = *link.bodies.get(&desc.body1).unwrap();
+            let new_body2_handle = *link.bodies.get(&desc.body2).unwrap();
+            let new_handle = self.add_joint(
+                &new_body1_handle,
+                &new_body2_handle,
+                desc.convert_to_params(),
+            );
+            link.joints.insert(
+                *resource_physics
+                    .joint_handle_map
+                    .key_of(&resource_handle)
+                    .unwrap(),
+                new_handle,
+            );
+        }
+
+        self.embedded_resources.push(link);
+
+        Log::writeln(
+            MessageKind::Information,
+            format!(
+                "Resource {} was successfully embedded into physics world!",
+                data.path.display()
+            ),
+        );
+    }
+
+    /// Adds new rigid body.
+    pub fn add_body(&mut self, rigid_body: RigidBody) -> RigidBodyHandle {
+        let handle = self.bodies.insert(rigid_body);
+        let id = RigidBodyHandle::from(Uuid::new_v4());
+        self.body_handle_map.insert(id, handle);
+        id
+    }
+
+    /// Removes a rigid body.
+    pub fn remove_body(&mut self, rigid_body: &RigidBodyHandle) -> Option<RigidBody> {
+        let bodies = &mut self.bodies;
+        let colliders = &mut self.colliders;
+        let joints = &mut self.joints;
+        let result = self
+            .body_handle_map
+            .value_of(rigid_body)
+            .and_then(|&h| bodies.remove(h, colliders, joints));
+        if let Some(body) = result.as_ref() {
+            for collider in body.colliders() {
+                self.collider_handle_map.remove_by_value(collider);
+            }
+            self.body_handle_map.remove_by_key(rigid_body);
+        }
+        result
+    }
+
+    /// Adds new collider.
+    pub fn add_collider(
+        &mut self,
+        collider: Collider,
+        rigid_body: &RigidBodyHandle,
+    ) -> ColliderHandle {
+        let handle = self.colliders.insert(
+            collider,
+            *self.body_handle_map.value_of(rigid_body).unwrap(),
+            &mut self.bodies,
+        );
+        let id = ColliderHandle::from(Uuid::new_v4());
+        self.collider_handle_map.insert(id, handle);
+        id
+    }
+
+    /// Removes a collider.
+    pub fn remove_collider(&mut self, collider_handle: &ColliderHandle) -> Option<Collider> {
+        let bodies = &mut self.bodies;
+        let colliders = &mut self.colliders;
+        let result = self
+            .collider_handle_map
+            .value_of(collider_handle)
+            .and_then(|&h| colliders.remove(h, bodies, true));
+        self.collider_handle_map.remove_by_key(collider_handle);
+        result
+    }
+
+    /// Adds new joint.
+    pub fn add_joint<J>(
+        &mut self,
+        body1: &RigidBodyHandle,
+        body2: &RigidBodyHandle,
+        joint_params: J,
+    ) -> JointHandle
+    where
+        J: Into<JointParams>,
+    {
+        let handle = self.joints.insert(
+            &mut self.bodies,
+            *self.body_handle_map.value_of(body1).unwrap(),
+            *self.body_handle_map.value_of(body2).unwrap(),
+            joint_params,
+        );
+        let id = JointHandle::from(Uuid::new_v4());
+        self.joint_handle_map.insert(id, handle);
+        id
+    }
+
+    /// Removes a joint.
+    pub fn remove_joint(&mut self, joint_handle: &JointHandle, wake_up: bool) -> Option<Joint> {
+        let bodies = &mut self.bodies;
+        let joints = &mut self.joints;
+        let result = self
+            .joint_handle_map
+            .value_of(joint_handle)
+            .and_then(|&h| joints.remove(h, bodies, wake_up));
+        self.joint_handle_map.remove_by_key(joint_handle);
+        result
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(u32)]
+#[doc(hidden)]
+pub enum BodyStatusDesc {
+    Dynamic = 0,
+    Static = 1,
+    Kinematic = 2,
+}
+
+impl Default for BodyStatusDesc {
+    fn default() -> Self {
+        Self::Dynamic
+    }
+}
+
+impl BodyStatusDesc {
+    fn id(self) -> u32 {
+        self as u32
+    }
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Dynamic),
+            1 => Ok(Self::Static),
+            2 => Ok(Self::Kinematic),
+            _ => Err(format!("Invalid body status id {}!", id)),
+        }
+    }
+}
+
+impl Visit for BodyStatusDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut id = self.id();
+        id.visit(name, visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<BodyStatus> for BodyStatusDesc {
+    fn from(s: BodyStatus) -> Self {
+        match s {
+            BodyStatus::Dynamic => Self::Dynamic,
+            BodyStatus::Static => Self::Static,
+            BodyStatus::Kinematic => Self::Kinematic,
+        }
+    }
+}
+
+impl Into<BodyStatus> for BodyStatusDesc {
+    fn into(self) -> BodyStatus {
+        match self {
+            BodyStatusDesc::Dynamic => BodyStatus::Dynamic,
+            BodyStatusDesc::Static => BodyStatus::Static,
+            BodyStatusDesc::Kinematic => BodyStatus::Kinematic,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub struct RigidBodyDesc<C> {
+    pub position: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub linvel: Vector3<f32>,
+    pub angvel: Vector3<f32>,
+    pub sleeping: bool,
+    pub status: BodyStatusDesc,
+    pub colliders: Vec<C>,
+    pub mass: f32,
+    pub x_rotation_locked: bool,
+    pub y_rotation_locked: bool,
+    pub z_rotation_locked: bool,
+    pub translation_locked: bool,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    pub ccd_enabled: bool,
+    /// Actual mass properties of body, including contribution of colliders. `None` means
+    /// that the desc came from an old file and `mass` should be used instead.
+    pub mass_properties: Option<MassPropertiesDesc>,
+}
+
+impl<C> Default for RigidBodyDesc<C> {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            rotation: Default::default(),
+            linvel: Default::default(),
+            angvel: Default::default(),
+            sleeping: false,
+            status: Default::default(),
+            colliders: vec![],
+            mass: 1.0,
+            x_rotation_locked: false,
+            y_rotation_locked: false,
+            z_rotation_locked: false,
+            translation_locked: false,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            gravity_scale: 1.0,
+            ccd_enabled: false,
+            mass_properties: None,
+        }
+    }
+}
+
+impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
+    #[doc(hidden)]
+    pub fn from_body(
+        body: &RigidBody,
+        handle_map: &BiDirHashMap<C, rapier3d::geometry::ColliderHandle>,
+    ) -> Self {
+        let rotation_locked = body.is_rotation_locked();
+        Self {
+            position: body.position().translation.vector,
+            rotation: body.position().rotation,
+            linvel: *body.linvel(),
+            angvel: *body.angvel(),
+            status: body.body_status().into(),
+            sleeping: body.is_sleeping(),
+            colliders: body
+                .colliders()
+                .iter()
+                .map(|c| handle_map.key_of(c).cloned().unwrap())
+                .collect(),
+            mass: body.mass(),
+            x_rotation_locked: rotation_locked[0],
+            y_rotation_locked: rotation_locked[1],
+            z_rotation_locked: rotation_locked[2],
+            translation_locked: body.is_translation_locked(),
+            linear_damping: body.linear_damping,
+            angular_damping: body.angular_damping,
+            gravity_scale: body.gravity_scale(),
+            ccd_enabled: body.is_ccd_enabled(),
+            mass_properties: Some(body.mass_properties().into()),
+        }
+    }
+
+    /// Creates body from the descriptor. If the descriptor has mass properties, they must be
+    /// applied after colliders were attached to the body, because each attached collider
+    /// adds its mass to the body.
+    fn convert_to_body(self) -> RigidBody {
+        let mut builder = RigidBodyBuilder::new(self.status.into())
+            .position(Isometry3 {
+                translation: Translation {
+                    vector: self.position,
+                },
+                rotation: self.rotation,
+            })
+            .linear_damping(self.linear_damping)
+            .angular_damping(self.angular_damping)
+            .gravity_scale(self.gravity_scale)
+            .ccd_enabled(self.ccd_enabled)
+            .linvel(self.linvel.x, self.linvel.y, self.linvel.z)
+            .angvel(AngVector::new(self.angvel.x, self.angvel.y, self.angvel.z))
+            .restrict_rotations(
+                self.x_rotation_locked,
+                self.y_rotation_locked,
+                self.z_rotation_locked,
+            );
+
+        if self.translation_locked {
+            builder = builder.lock_translations();
+        }
+
+        if self.mass_properties.is_none() {
+            builder = builder.additional_mass(self.mass);
+        }
+
+        let mut body = builder.build();
+        if self.sleeping {
+            body.sleep();
+        }
+        body
+    }
+}
+
+impl<C> RigidBodyDesc<C> {
+    #[doc(hidden)]
+    pub fn local_transform(&self) -> Isometry3<f32> {
+        Isometry3 {
+            rotation: self.rotation,
+            translation: Translation {
+                vector: self.position,
+            },
+        }
+    }
+}
+
+impl<C: Visit + Default + 'static> Visit for RigidBodyDesc<C> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.position.visit("Position", visitor)?;
+        self.rotation.visit("Rotation", visitor)?;
+        self.linvel.visit("LinVel", visitor)?;
+        self.angvel.visit("AngVel", visitor)?;
+        self.sleeping.visit("Sleeping", visitor)?;
+        self.status.visit("Status", visitor)?;
+        self.colliders.visit("Colliders", visitor)?;
+        self.mass.visit("Mass", visitor)?;
+        self.x_rotation_locked.visit("XRotationLocked", visitor)?;
+        self.y_rotation_locked.visit("YRotationLocked", visitor)?;
+        self.z_rotation_locked.visit("ZRotationLocked", visitor)?;
+        self.translation_locked
+            .visit("TranslationLocked", visitor)?;
+
+        // Backward compatibility - following fields may be missing in old files.
+        if let Err(e) = self.linear_damping.visit("LinearDamping", visitor) {
+            if visitor.is_reading() {
+                self.linear_damping = 0.0;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.angular_damping.visit("AngularDamping", visitor) {
+            if visitor.is_reading() {
+                self.angular_damping = 0.0;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.gravity_scale.visit("GravityScale", visitor) {
+            if visitor.is_reading() {
+                self.gravity_scale = 1.0;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.ccd_enabled.visit("CcdEnabled", visitor) {
+            if visitor.is_reading() {
+                self.ccd_enabled = false;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.mass_properties.visit("MassProperties", visitor) {
+            if visitor.is_reading() {
+                self.mass_properties = None;
+            } else {
+                return Err(e);
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+#[doc(hidden)]
+pub struct MassPropertiesDesc {
+    pub local_com: Vector3<f32>,
+    pub inv_mass: f32,
+    pub inv_principal_inertia_sqrt: Vector3<f32>,
+    pub principal_inertia_local_frame: UnitQuaternion<f32>,
+}
+
+impl From<&MassProperties> for MassPropertiesDesc {
+    fn from(properties: &MassProperties) -> Self {
+        Self {
+            local_com: properties.local_com.coords,
+            inv_mass: properties.inv_mass,
+            inv_principal_inertia_sqrt: properties.inv_principal_inertia_sqrt,
+            principal_inertia_local_frame: properties.principal_inertia_local_frame,
+        }
+    }
+}
+
+impl Into<MassProperties> for MassPropertiesDesc {
+    fn into(self) -> MassProperties {
+        MassProperties {
+            local_com: Point3::from(self.local_com),
+            inv_mass: self.inv_mass,
+            inv_principal_inertia_sqrt: self.inv_principal_inertia_sqrt,
+            principal_inertia_local_frame: self.principal_inertia_local_frame,
+        }
+    }
+}
+
+impl Visit for MassPropertiesDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_com.visit("LocalCom", visitor)?;
+        self.inv_mass.visit("InvMass", visitor)?;
+        self.inv_principal_inertia_sqrt
+            .visit("InvPrincipalInertiaSqrt", visitor)?;
+        self.principal_inertia_local_frame
+            .visit("PrincipalInertiaLocalFrame", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct BallDesc {
+    pub radius: f32,
+}
+
+impl Visit for BallDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.radius.visit("Radius", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct CylinderDesc {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+impl Visit for CylinderDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.radius.visit("Radius", visitor)?;
+        self.half_height.visit("HalfHeight", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct RoundCylinderDesc {
+    pub half_height: f32,
+    pub radius: f32,
+    pub border_radius: f32,
+}
+
+impl Visit for RoundCylinderDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.radius.visit("Radius", visitor)?;
+        self.half_height.visit("HalfHeight", visitor)?;
+        self.border_radius.visit("BorderRadius", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct ConeDesc {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+impl Visit for ConeDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.radius.visit("Radius", visitor)?;
+        self.half_height.visit("HalfHeight", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct CuboidDesc {
+    pub half_extents: Vector3<f32>,
+}
+
+impl Visit for CuboidDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.half_extents.visit("HalfExtents", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct CapsuleDesc {
+    pub begin: Vector3<f32>,
+    pub end: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl Visit for CapsuleDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.begin.visit("Begin", visitor)?;
+        self.end.visit("End", visitor)?;
+        self.radius.visit("Radius", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct SegmentDesc {
+    pub begin: Vector3<f32>,
+    pub end: Vector3<f32>,
+}
+
+impl Visit for SegmentDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.begin.visit("Begin", visitor)?;
+        self.end.visit("End", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[doc(hidden)]
+pub struct TriangleDesc {
+    pub a: Vector3<f32>,
+    pub b: Vector3<f32>,
+    pub c: Vector3<f32>,
+}
+
+impl Visit for TriangleDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.a.visit("A", visitor)?;
+        self.b.visit("B", visitor)?;
+        self.c.visit("C", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Trimesh is stored with its geometry. Old files does not have geometry for trimeshes, in
+/// this case it is restored from a mesh associated with parent body on resolve stage.
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct TrimeshDesc {
+    pub vertices: Vec<Vector3<f32>>,
+    pub indices: Vec<TriangleDefinition>,
+}
+
+impl TrimeshDesc {
+    #[doc(hidden)]
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty() || self.indices.is_empty()
+    }
+}
+
+impl Visit for TrimeshDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        // Backward compatibility - geometry is missing in old files.
+        if let Err(e) = self.vertices.visit("Vertices", visitor) {
+            if visitor.is_reading() {
+                self.vertices = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.indices.visit("Indices", visitor) {
+            if visitor.is_reading() {
+                self.indices = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Heights are stored in column-major order, the same as in the height matrix.
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub struct HeightfieldDesc {
+    pub heights: Vec<f32>,
+    pub rows: u32,
+    pub columns: u32,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for HeightfieldDesc {
+    fn default() -> Self {
+        Self {
+            heights: Default::default(),
+            rows: 0,
+            columns: 0,
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Visit for HeightfieldDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        // Backward compatibility - data is missing in old files.
+        if let Err(e) = self.heights.visit("Heights", visitor) {
+            if visitor.is_reading() {
+                self.heights = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.rows.visit("Rows", visitor) {
+            if visitor.is_reading() {
+                self.rows = 0;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.columns.visit("Columns", visitor) {
+            if visitor.is_reading() {
+                self.columns = 0;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.scale.visit("Scale", visitor) {
+            if visitor.is_reading() {
+                self.scale = Vector3::new(1.0, 1.0, 1.0);
+            } else {
+                return Err(e);
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Convex hull is stored as a set of points, the hull is rebuilt from them on load.
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct ConvexHullDesc {
+    pub vertices: Vec<Vector3<f32>>,
+}
+
+impl Visit for ConvexHullDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.vertices.visit("Vertices", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl ConvexHullDesc {
+    fn into_convex_hull(self) -> Option<SharedShape> {
+        SharedShape::convex_hull(
+            &self
+                .vertices
+                .into_iter()
+                .map(Point3::from)
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// Convex decomposition is stored as a set of convex hulls, vertices of every hull are in
+/// local coordinates of the collider.
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct ConvexDecompositionDesc {
+    pub hulls: Vec<ConvexHullDesc>,
+}
+
+impl Visit for ConvexDecompositionDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.hulls.visit("Hulls", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Part of a compound shape, its position is relative to the collider.
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct CompoundPartDesc {
+    pub shape: ColliderShapeDesc,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+}
+
+impl Visit for CompoundPartDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.shape.visit("Shape", visitor)?;
+        self.translation.visit("Translation", visitor)?;
+        self.rotation.visit("Rotation", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Compound shape made of arbitrary (non-compound) shapes.
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct CompoundDesc {
+    pub parts: Vec<CompoundPartDesc>,
+}
+
+impl Visit for CompoundDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.parts.visit("Parts", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub enum ColliderShapeDesc {
+    Ball(BallDesc),
+    Cylinder(CylinderDesc),
+    RoundCylinder(RoundCylinderDesc),
+    Cone(ConeDesc),
+    Cuboid(CuboidDesc),
+    Capsule(CapsuleDesc),
+    Segment(SegmentDesc),
+    Triangle(TriangleDesc),
+    Trimesh(TrimeshDesc),
+    Heightfield(HeightfieldDesc),
+    ConvexHull(ConvexHullDesc),
+    ConvexDecomposition(ConvexDecompositionDesc),
+    Compound(CompoundDesc),
+}
+
+impl Default for ColliderShapeDesc {
+    fn default() -> Self {
+        Self::Ball(Default::default())
+    }
+}
+
+impl ColliderShapeDesc {
+    #[doc(hidden)]
+    pub fn id(&self) -> u32 {
+        match self {
+            ColliderShapeDesc::Ball(_) => 0,
+            ColliderShapeDesc::Cylinder(_) => 1,
+            ColliderShapeDesc::RoundCylinder(_) => 2,
+            ColliderShapeDesc::Cone(_) => 3,
+            ColliderShapeDesc::Cuboid(_) => 4,
+            ColliderShapeDesc::Capsule(_) => 5,
+            ColliderShapeDesc::Segment(_) => 6,
+            ColliderShapeDesc::Triangle(_) => 7,
+            ColliderShapeDesc::Trimesh(_) => 8,
+            ColliderShapeDesc::Heightfield(_) => 9,
+            ColliderShapeDesc::ConvexHull(_) => 10,
+            ColliderShapeDesc::ConvexDecomposition(_) => 11,
+            ColliderShapeDesc::Compound(_) => 12,
+        }
+    }
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(ColliderShapeDesc::Ball(Default::default())),
+            1 => Ok(ColliderShapeDesc::Cylinder(Default::default())),
+            2 => Ok(ColliderShapeDesc::RoundCylinder(Default::default())),
+            3 => Ok(ColliderShapeDesc::Cone(Default::default())),
+            4 => Ok(ColliderShapeDesc::Cuboid(Default::default())),
+            5 => Ok(ColliderShapeDesc::Capsule(Default::default())),
+            6 => Ok(ColliderShapeDesc::Segment(Default::default())),
+            7 => Ok(ColliderShapeDesc::Triangle(Default::default())),
+            8 => Ok(ColliderShapeDesc::Trimesh(Default::default())),
+            9 => Ok(ColliderShapeDesc::Heightfield(Default::default())),
+            10 => Ok(ColliderShapeDesc::ConvexHull(Default::default())),
+            11 => Ok(ColliderShapeDesc::ConvexDecomposition(Default::default())),
+            12 => Ok(ColliderShapeDesc::Compound(Default::default())),
+            _ => Err(format!("Invalid collider shape desc id {}!", id)),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn from_collider_shape(shape: &dyn Shape) -> Self {
+        if let Some(ball) = shape.as_ball() {
+            ColliderShapeDesc::Ball(BallDesc {
+                radius: ball.radius,
+            })
+        } else if let Some(cylinder) = shape.as_cylinder() {
+            ColliderShapeDesc::Cylinder(CylinderDesc {
+                half_height: cylinder.half_height,
+                radius: cylinder.radius,
+            })
+        } else if let Some(round_cylinder) = shape.as_round_cylinder() {
+            ColliderShapeDesc::RoundCylinder(RoundCylinderDesc {
+                half_height: round_cylinder.base_shape.half_height,
+                radius: round_cylinder.base_shape.radius,
+                border_radius: round_cylinder.border_radius,
+            })
+        } else if let Some(cone) = shape.as_cone() {
+            ColliderShapeDesc::Cone(ConeDesc {
+                half_height: cone.half_height,
+                radius: cone.radius,
+            })
+        } else if let Some(cuboid) = shape.as_cuboid() {
+            ColliderShapeDesc::Cuboid(CuboidDesc {
+                half_extents: cuboid.half_extents,
+            })
+        } else if let Some(capsule) = shape.as_capsule() {
+            ColliderShapeDesc::Capsule(CapsuleDesc {
+                begin: capsule.segment.a.coords,
+                end: capsule.segment.b.coords,
+                radius: capsule.radius,
+            })
+        } else if let Some(segment) = shape.downcast_ref::<Segment>() {
+            ColliderShapeDesc::Segment(SegmentDesc {
+                begin: segment.a.coords,
+                end: segment.b.coords,
+            })
+        } else if let Some(triangle) = shape.as_triangle() {
+            ColliderShapeDesc::Triangle(TriangleDesc {
+                a: triangle.a.coords,
+                b: triangle.b.coords,
+                c: triangle.c.coords,
+            })
+        } else if let Some(trimesh) = shape.as_trimesh() {
+            ColliderShapeDesc::Trimesh(TrimeshDesc {
+                vertices: trimesh.vertices().iter().map(|p| p.coords).collect(),
+                indices: trimesh
+                    .indices()
+                    .iter()
+                    .map(|&triangle| TriangleDefinition(triangle))
+                    .collect(),
+            })
+        } else if let Some(heightfield) = shape.as_heightfield() {
+            let heights = heightfield.heights();
+            ColliderShapeDesc::Heightfield(HeightfieldDesc {
+                heights: heights.iter().cloned().collect(),
+                rows: heights.nrows() as u32,
+                columns: heights.ncols() as u32,
+                scale: *heightfield.scale(),
+            })
+        } else if let Some(convex) = shape.downcast_ref::<ConvexPolyhedron>() {
+            ColliderShapeDesc::ConvexHull(ConvexHullDesc {
+                vertices: convex.points().iter().map(|p| p.coords).collect(),
+            })
+        } else if let Some(compound) = shape.downcast_ref::<Compound>() {
+            if compound
+                .shapes()
+                .iter()
+                .all(|(_, part)| part.downcast_ref::<ConvexPolyhedron>().is_some())
+            {
+                // Result of convex decomposition, vertices of parts are transformed to
+                // collider space.
+                ColliderShapeDesc::ConvexDecomposition(ConvexDecompositionDesc {
+                    hulls: compound
+                        .shapes()
+                        .iter()
+                        .filter_map(|(position, part)| {
+                            part.downcast_ref::<ConvexPolyhedron>()
+                                .map(|convex| ConvexHullDesc {
+                                    vertices: convex
+                                        .points()
+                                        .iter()
+                                        .map(|p| (position * p).coords)
+                                        .collect(),
+                                })
+                        })
+                        .collect(),
+                })
+            } else {
+                ColliderShapeDesc::Compound(CompoundDesc {
+                    parts: compound
+                        .shapes()
+                        .iter()
+                        .map(|(position, part)| CompoundPartDesc {
+                            shape: Self::from_collider_shape(&**part),
+                            translation: position.translation.vector,
+                            rotation: position.rotation,
+                        })
+                        .collect(),
+                })
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn into_collider_shape(self) -> SharedShape {
+        match self {
+            ColliderShapeDesc::Ball(ball) => SharedShape::ball(ball.radius),
+            ColliderShapeDesc::Cylinder(cylinder) => {
+                SharedShape::cylinder(cylinder.half_height, cylinder.radius)
+            }
+            ColliderShapeDesc::RoundCylinder(rcylinder) => SharedShape::round_cylinder(
+                rcylinder.half_height,
+                rcylinder.radius,
+                rcylinder.border_radius,
+            ),
+            ColliderShapeDesc::Cone(cone) => SharedShape::cone(cone.half_height, cone.radius),
+            ColliderShapeDesc::Cuboid(cuboid) => SharedShape::cuboid(
+                cuboid.half_extents.x,
+                cuboid.half_extents.y,
+                cuboid.half_extents.z,
+            ),
+            ColliderShapeDesc::Capsule(capsule) => SharedShape::capsule(
+                Point3::from(capsule.begin),
+                Point3::from(capsule.end),
+                capsule.radius,
+            ),
+            ColliderShapeDesc::Segment(segment) => {
+                SharedShape::segment(Point3::from(segment.begin), Point3::from(segment.end))
+            }
+            ColliderShapeDesc::Triangle(triangle) => SharedShape::triangle(
+                Point3::from(triangle.a),
+                Point3::from(triangle.b),
+                Point3::from(triangle.c),
+            ),
+            ColliderShapeDesc::Trimesh(trimesh) => {
+                if trimesh.is_empty() {
+                    // Create fake trimesh, old files does not have geometry for trimeshes.
+                    let a = Point3::new(0.0, 0.0, 1.0);
+                    let b = Point3::new(1.0, 0.0, 1.0);
+                    let c = Point3::new(1.0, 0.0, 0.0);
+                    SharedShape::trimesh(vec![a, b, c], vec![[0, 1, 2]])
+                } else {
+                    SharedShape::trimesh(
+                        trimesh.vertices.into_iter().map(Point3::from).collect(),
+                        trimesh.indices.into_iter().map(|t| t.0).collect(),
+                    )
+                }
+            }
+            ColliderShapeDesc::Heightfield(heightfield) => {
+                let rows = heightfield.rows as usize;
+                let columns = heightfield.columns as usize;
+                if rows >= 2 && columns >= 2 && heightfield.heights.len() == rows * columns {
+                    SharedShape::heightfield(
+                        DMatrix::from_data(VecStorage::new(
+                            Dynamic::new(rows),
+                            Dynamic::new(columns),
+                            heightfield.heights,
+                        )),
+                        heightfield.scale,
+                    )
+                } else {
+                    // Create fake heightfield, old files does not have data for heightfields.
+                    SharedShape::heightfield(
+                        DMatrix::from_data(VecStorage::new(
+                            Dynamic::new(2),
+                            Dynamic::new(2),
+                            vec![0.0, 1.0, 0.0, 0.0],
+                        )),
+                        Default::default(),
+                    )
+                }
+            }
+            ColliderShapeDesc::ConvexHull(convex_hull) => {
+                convex_hull.into_convex_hull().unwrap_or_else(|| {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        "Unable to restore convex hull, it has degenerated geometry!".to_owned(),
+                    );
+                    SharedShape::ball(0.0)
+                })
+            }
+            ColliderShapeDesc::ConvexDecomposition(decomposition) => {
+                let parts = decomposition
+                    .hulls
+                    .into_iter()
+                    .filter_map(|hull| hull.into_convex_hull())
+                    .map(|shape| (Isometry3::identity(), shape))
+                    .collect::<Vec<_>>();
+                if parts.is_empty() {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        "Unable to restore convex decomposition, it has no valid parts!".to_owned(),
+                    );
+                    SharedShape::ball(0.0)
+                } else {
+                    SharedShape::compound(parts)
+                }
+            }
+            ColliderShapeDesc::Compound(compound) => {
+                let parts = compound
+                    .parts
+                    .into_iter()
+                    .map(|part| {
+                        (
+                            Isometry3::from_parts(
+                                Translation3::from(part.translation),
+                                part.rotation,
+                            ),
+                            part.shape.into_collider_shape(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if parts.is_empty() {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        "Unable to restore compound shape, it has no parts!".to_owned(),
+                    );
+                    SharedShape::ball(0.0)
+                } else {
+                    SharedShape::compound(parts)
+                }
+            }
+        }
+    }
+}
+
+impl Visit for ColliderShapeDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id: u32 = if visitor.is_reading() { 0 } else { self.id() };
+        id.visit("Id", visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+        match self {
+            ColliderShapeDesc::Ball(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Cylinder(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::RoundCylinder(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Cone(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Cuboid(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Capsule(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Segment(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Triangle(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Trimesh(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Heightfield(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::ConvexHull(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::ConvexDecomposition(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Compound(v) => v.visit(name, visitor)?,
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub struct ColliderDesc<R> {
+    pub shape: ColliderShapeDesc,
+    pub parent: R,
+    pub friction: f32,
+    pub density: Option<f32>,
+    pub restitution: f32,
+    pub is_sensor: bool,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub collision_groups: u32,
+    pub solver_groups: u32,
+}
+
+impl<R: Default> Default for ColliderDesc<R> {
+    fn default() -> Self {
+        Self {
+            shape: Default::default(),
+            parent: Default::default(),
+            friction: 0.5,
+            density: None,
+            restitution: 0.0,
+            is_sensor: false,
+            translation: Default::default(),
+            rotation: Default::default(),
+            collision_groups: u32::MAX,
+            solver_groups: u32::MAX,
+        }
+    }
+}
+
+impl<R: Hash + Clone + Eq> ColliderDesc<R> {
+    fn from_collider(
+        collider: &Collider,
+        handle_map: &BiDirHashMap<R, rapier3d::dynamics::RigidBodyHandle>,
+    ) -> Self {
+        Self {
+            shape: ColliderShapeDesc::from_collider_shape(collider.shape()),
+            parent: handle_map.key_of(&collider.parent()).cloned().unwrap(),
+            friction: collider.friction,
+            density: collider.density(),
+            restitution: collider.restitution,
+            is_sensor: collider.is_sensor(),
+            translation: collider.position_wrt_parent().translation.vector,
+            rotation: collider.position_wrt_parent().rotation,
+            collision_groups: collider.collision_groups().0,
+            solver_groups: collider.solver_groups().0,
+        }
+    }
+
+    fn convert_to_collider(self) -> (Collider, R) {
+        let mut builder = ColliderBuilder::new(self.shape.into_collider_shape())
+            .friction(self.friction)
+            .restitution(self.restitution)
+            .position_wrt_parent(Isometry3 {
+                translation: Translation3 {
+                    vector: self.translation,
+                },
+                rotation: self.rotation,
+            })
+            .solver_groups(InteractionGroups(self.solver_groups))
+            .collision_groups(InteractionGroups(self.collision_groups))
+            .sensor(self.is_sensor);
+        if let Some(density) = self.density {
+            builder = builder.density(density);
+        }
+        (builder.build(), self.parent)
+    }
+}
+
+impl<R: 'static + Visit + Default> Visit for ColliderDesc<R> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.shape.visit("Shape", visitor)?;
+        self.parent.visit("Parent", visitor)?;
+        self.friction.visit("Friction", visitor)?;
+        self.restitution.visit("Restitution", visitor)?;
+        self.is_sensor.visit("IsSensor", visitor)?;
+        self.translation.visit("Translation", visitor)?;
+        self.rotation.visit("Rotation", visitor)?;
+        self.collision_groups.visit("CollisionGroups", visitor)?;
+        self.solver_groups.visit("SolverGroups", visitor)?;
+        self.density.visit("Density", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl Visit for Physics {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut desc = if visitor.is_reading() {
+            Default::default()
+        } else if let Some(desc) = self.desc.as_ref() {
+            desc.clone()
+        } else {
+            self.generate_desc()
+        };
+        desc.visit("Desc", visitor)?;
+
+        self.embedded_resources
+            .visit("EmbeddedResources", visitor)?;
+
+        // Backward compatibility - fixed timestep settings may be missing in old files.
+        if let Err(e) = self.fixed_timestep.visit("FixedTimestep", visitor) {
+            if visitor.is_reading() {
+                self.fixed_timestep = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+        // Backward compatibility - vehicles may be missing in old files.
+        if let Err(e) = self.vehicles.visit("Vehicles", visitor) {
+            if visitor.is_reading() {
+                self.vehicles = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
+        // Save descriptors for resolve stage.
+        if visitor.is_reading() {
+            self.desc = Some(desc);
+        }
+
+        visitor.leave_region()
+    }
+}
+
+// Almost full copy of rapier's IntegrationParameters
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub struct IntegrationParametersDesc {
+    pub dt: f32,
+    pub erp: f32,
+    pub min_ccd_dt: f32,
+    pub joint_erp: f32,
+    pub warmstart_coeff: f32,
+    pub warmstart_correction_slope: f32,
+    pub velocity_solve_fraction: f32,
+    pub velocity_based_erp: f32,
+    pub allowed_linear_error: f32,
+    pub prediction_distance: f32,
+    pub allowed_angular_error: f32,
+    pub max_linear_correction: f32,
+    pub max_angular_correction: f32,
+    pub max_velocity_iterations: u32,
+    pub max_position_iterations: u32,
+    pub min_island_size: u32,
+    pub max_ccd_substeps: u32,
+}
+
+impl Default for IntegrationParametersDesc {
+    fn default() -> Self {
+        Self::from(IntegrationParameters::default())
+    }
+}
+
+impl From<IntegrationParameters> for IntegrationParametersDesc {
+    fn from(params: IntegrationParameters) -> Self {
+        Self {
+            dt: params.dt,
+            erp: params.erp,
+            min_ccd_dt: params.min_ccd_dt,
+            joint_erp: params.joint_erp,
+            warmstart_coeff: params.warmstart_coeff,
+            warmstart_correction_slope: params.warmstart_correction_slope,
+            velocity_solve_fraction: params.velocity_solve_fraction,
+            velocity_based_erp: params.velocity_based_erp,
+            allowed_linear_error: params.allowed_linear_error,
+            prediction_distance: params.prediction_distance,
+            allowed_angular_error: params.allowed_angular_error,
+            max_linear_correction: params.max_linear_correction,
+            max_angular_correction: params.max_angular_correction,
+            max_velocity_iterations: params.max_velocity_iterations as u32,
+            max_position_iterations: params.max_position_iterations as u32,
+            min_island_size: params.min_island_size as u32,
+            max_ccd_substeps: params.max_ccd_substeps as u32,
+        }
+    }
+}
+
+impl Into<IntegrationParameters> for IntegrationParametersDesc {
+    fn into(self) -> IntegrationParameters {
+        IntegrationParameters {
+            dt: self.dt,
+            min_ccd_dt: self.min_ccd_dt,
+            erp: self.erp,
+            joint_erp: self.joint_erp,
+            warmstart_coeff: self.warmstart_coeff,
+            warmstart_correction_slope: self.warmstart_correction_slope,
+            velocity_solve_fraction: self.velocity_solve_fraction,
+            velocity_based_erp: self.velocity_based_erp,
+            allowed_linear_error: self.allowed_linear_error,
+            allowed_angular_error: self.allowed_angular_error,
+            max_linear_correction: self.max_linear_correction,
+            max_angular_correction: self.max_angular_correction,
+            prediction_distance: self.prediction_distance,
+            max_velocity_iterations: self.max_velocity_iterations as usize,
+            max_position_iterations: self.max_position_iterations as usize,
+            min_island_size: self.min_island_size as usize,
+            max_ccd_substeps: self.max_ccd_substeps as usize,
+        }
+    }
+}
+
+impl Visit for IntegrationParametersDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.dt.visit("DeltaTime", visitor)?;
+        self.min_ccd_dt.visit("MinCcdDt", visitor)?;
+        self.erp.visit("Erp", visitor)?;
+        self.joint_erp.visit("JointErp", visitor)?;
+        self.warmstart_coeff.visit("WarmstartCoeff", visitor)?;
+        self.warmstart_correction_slope
+            .visit("WarmstartCorrectionSlope", visitor)?;
+        self.velocity_solve_fraction
+            .visit("VelocitySolveFraction", visitor)?;
+        self.velocity_based_erp.visit("VelocityBasedErp", visitor)?;
+        self.allowed_linear_error
+            .visit("AllowedLinearError", visitor)?;
+        self.max_linear_correction
+            .visit("MaxLinearCorrection", visitor)?;
+        self.max_angular_correction
+            .visit("MaxAngularCorrection", visitor)?;
+        self.max_velocity_iterations
+            .visit("MaxVelocityIterations", visitor)?;
+        self.max_position_iterations
+            .visit("MaxPositionIterations", visitor)?;
+        self.min_island_size.visit("MinIslandSize", visitor)?;
+        self.max_ccd_substeps.visit("MaxCcdSubsteps", visitor)?;
+
+        // TODO: Remove
+        if self.min_island_size == 0 {
+            self.min_island_size = 128;
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct BallJointDesc {
+    pub local_anchor1: Vector3<f32>,
+    pub local_anchor2: Vector3<f32>,
+}
+
+impl Visit for BallJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct FixedJointDesc {
+    pub local_anchor1_translation: Vector3<f32>,
+    pub local_anchor1_rotation: UnitQuaternion<f32>,
+    pub local_anchor2_translation: Vector3<f32>,
+    pub local_anchor2_rotation: UnitQuaternion<f32>,
+}
+
+impl Visit for FixedJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1_translation
+            .visit("LocalAnchor1Translation", visitor)?;
+        self.local_anchor1_rotation
+            .visit("LocalAnchor1Rotation", visitor)?;
+        self.local_anchor2_translation
+            .visit("LocalAnchor2Translation", visitor)?;
+        self.local_anchor2_rotation
+            .visit("LocalAnchor2Rotation", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct PrismaticJointDesc {
+    pub local_anchor1: Vector3<f32>,
+    pub local_axis1: Vector3<f32>,
+    pub local_anchor2: Vector3<f32>,
+    pub local_axis2: Vector3<f32>,
+    pub limits_enabled: bool,
+    pub limits: [f32; 2],
+    // TODO: Rapier does not provide a way to extract tangents, so we can't
+    // serialize them yet.
+    // pub local_tangent1: Vector3<f32>,
+    // pub local_tangent2: Vector3<f32>,
+}
+
+impl Visit for PrismaticJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_axis1.visit("LocalAxis1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        self.local_axis2.visit("LocalAxis2", visitor)?;
+        // Backward compatibility - limits may be missing in old files.
+        if let Err(e) = self.limits_enabled.visit("LimitsEnabled", visitor) {
+            if visitor.is_reading() {
+                self.limits_enabled = false;
+            } else {
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.limits.visit("Limits", visitor) {
+            if visitor.is_reading() {
+                self.limits = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
+        // TODO: Rapier does not provide a way to extract tangents, so we can't
+        // serialize them yet.
+        // self.local_tangent1.visit("LocalTangent1", visitor)?;
+        // self.local_tangent2.visit("LocalTangent2", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct RevoluteJointDesc {
+    pub local_anchor1: Vector3<f32>,
+    pub local_axis1: Vector3<f32>,
+    pub local_anchor2: Vector3<f32>,
+    pub local_axis2: Vector3<f32>,
+}
+
+impl Visit for RevoluteJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_axis1.visit("LocalAxis1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        self.local_axis2.visit("LocalAxis2", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub enum JointParamsDesc {
+    BallJoint(BallJointDesc),
+    FixedJoint(FixedJointDesc),
+    PrismaticJoint(PrismaticJointDesc),
+    RevoluteJoint(RevoluteJointDesc),
+}
+
+impl Default for JointParamsDesc {
+    fn default() -> Self {
+        Self::BallJoint(Default::default())
+    }
+}
+
+impl Into<JointParams> for JointParamsDesc {
+    fn into(self) -> JointParams {
+        match self {
+            JointParamsDesc::BallJoint(v) => JointParams::from(BallJoint::new(
+                Point3::from(v.local_anchor1),
+                Point3::from(v.local_anchor2),
+            )),
+            JointParamsDesc::FixedJoint(v) => JointParams::from(FixedJoint::new(
+                Isometry3 {
+                    translation: Translation3 {
+                        vector: v.local_anchor1_translation,
+                    },
+                    rotation: v.local_anchor1_rotation,
+                },
+                Isometry3 {
+                    translation: Translation3 {
+                        vector: v.local_anchor2_translation,
+                    },
+                    rotation: v.local_anchor2_rotation,
+                },
+            )),
+            JointParamsDesc::PrismaticJoint(v) => {
+                let mut joint = PrismaticJoint::new(
+                    Point3::from(v.local_anchor1),
+                    Unit::<Vector3<f32>>::new_normalize(v.local_axis1),
+                    Default::default(), // TODO
+                    Point3::from(v.local_anchor2),
+                    Unit::<Vector3<f32>>::new_normalize(v.local_axis2),
+                    Default::default(), // TODO
+                );
+                joint.limits_enabled = v.limits_enabled;
+                joint.limits = v.limits;
+                JointParams::from(joint)
+            }
+            JointParamsDesc::RevoluteJoint(v) => JointParams::from(RevoluteJoint::new(
+                Point3::from(v.local_anchor1),
+                Unit::<Vector3<f32>>::new_normalize(v.local_axis1),
+                Point3::from(v.local_anchor2),
+                Unit::<Vector3<f32>>::new_normalize(v.local_axis2),
+            )),
+        }
+    }
+}
+
+impl JointParamsDesc {
+    #[doc(hidden)]
+    pub fn id(&self) -> u32 {
+        match self {
+            JointParamsDesc::BallJoint(_) => 0,
+            JointParamsDesc::FixedJoint(_) => 1,
+            JointParamsDesc::PrismaticJoint(_) => 2,
+            JointParamsDesc::RevoluteJoint(_) => 3,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::BallJoint(Default::default())),
+            1 => Ok(Self::FixedJoint(Default::default())),
+            2 => Ok(Self::PrismaticJoint(Default::default())),
+            3 => Ok(Self::RevoluteJoint(Default::default())),
+            _ => Err(format!("Invalid joint param desc id {}!", id)),
+        }
+    }
+}
+
+impl Visit for JointParamsDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id = self.id();
+        id.visit("Id", visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+        match self {
+            JointParamsDesc::BallJoint(v) => v.visit("Data", visitor)?,
+            JointParamsDesc::FixedJoint(v) => v.visit("Data", visitor)?,
+            JointParamsDesc::PrismaticJoint(v) => v.visit("Data", visitor)?,
+            JointParamsDesc::RevoluteJoint(v) => v.visit("Data", visitor)?,
+        }
+
+        visitor.leave_region()
+    }
+}
+
+impl JointParamsDesc {
+    #[doc(hidden)]
+    pub fn from_params(params: &JointParams) -> Self {
+        match params {
+            JointParams::BallJoint(v) => Self::BallJoint(BallJointDesc {
+                local_anchor1: v.local_anchor1.coords,
+                local_anchor2: v.local_anchor2.coords,
+            }),
+            JointParams::FixedJoint(v) => Self::FixedJoint(FixedJointDesc {
+                local_anchor1_translation: v.local_anchor1.translation.vector,
+                local_anchor1_rotation: v.local_anchor1.rotation,
+                local_anchor2_translation: v.local_anchor2.translation.vector,
+                local_anchor2_rotation: v.local_anchor2.rotation,
+            }),
+            JointParams::PrismaticJoint(v) => Self::PrismaticJoint(PrismaticJointDesc {
+                local_anchor1: v.local_anchor1.coords,
+                local_axis1: v.local_axis1().into_inner(),
+                local_anchor2: v.local_anchor2.coords,
+                local_axis2: v.local_axis2().into_inner(),
+                limits_enabled: v.limits_enabled,
+                limits: v.limits,
+            }),
+            JointParams::RevoluteJoint(v) => Self::RevoluteJoint(RevoluteJointDesc {
+                local_anchor1: v.local_anchor1.coords,
+                local_axis1: v.local_axis1.into_inner(),
+                local_anchor2: v.local_anchor2.coords,
+                local_axis2: v.local_axis2.into_inner(),
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+#[doc(hidden)]
+pub struct JointDesc<R> {
+    pub body1: R,
+    pub body2: R,
+    pub params: JointParamsDesc,
+    /// Accumulated impulse of joint, it is used to warmstart the solver so restored joints
+    /// behave exactly like before saving.
+    pub impulse: Vec<f32>,
+}
+
+impl<R: Hash + Clone + Eq> JointDesc<R> {
+    #[doc(hidden)]
+    pub fn from_joint(
+        joint: &Joint,
+        handle_map: &BiDirHashMap<R, rapier3d::dynamics::RigidBodyHandle>,
+    ) -> Self {
+        Self {
+            body1: handle_map.key_of(&joint.body1).cloned().unwrap(),
+            body2: handle_map.key_of(&joint.body2).cloned().unwrap(),
+            params: JointParamsDesc::from_params(&joint.params),
+            impulse: match &joint.params {
+                JointParams::BallJoint(v) => v.impulse.iter().cloned().collect(),
+                JointParams::FixedJoint(v) => v.impulse.iter().cloned().collect(),
+                JointParams::PrismaticJoint(v) => v.impulse.iter().cloned().collect(),
+                JointParams::RevoluteJoint(v) => v.impulse.iter().cloned().collect(),
+            },
+        }
+    }
+}
+
+impl<R> JointDesc<R> {
+    fn convert_to_params(self) -> JointParams {
+        let mut params: JointParams = self.params.into();
+        let impulse = match &mut params {
+            JointParams::BallJoint(v) => v.impulse.as_mut_slice(),
+            JointParams::FixedJoint(v) => v.impulse.as_mut_slice(),
+            JointParams::PrismaticJoint(v) => v.impulse.as_mut_slice(),
+            JointParams::RevoluteJoint(v) => v.impulse.as_mut_slice(),
+        };
+        // Impulse could be missing in old files.
+        if impulse.len() == self.impulse.len() {
+            impulse.copy_from_slice(&self.impulse);
+        }
+        params
+    }
+}
+
+impl<R: 'static + Visit + Default> Visit for JointDesc<R> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.body1.visit("Body1", visitor)?;
+        self.body2.visit("Body2", visitor)?;
+        self.params.visit("Params", visitor)?;
+        // Backward compatibility - impulse may be missing in old files.
+        if let Err(e) = self.impulse.visit("Impulse", visitor) {
+            if visitor.is_reading() {
+                self.impulse = Default::default();
+            } else {
+                return Err(e);
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[doc(hidden)]
+pub struct PhysicsDesc {
+    pub integration_parameters: IntegrationParametersDesc,
+    pub colliders: Vec<ColliderDesc<RigidBodyHandle>>,
+    pub bodies: Vec<RigidBodyDesc<ColliderHandle>>,
+    pub gravity: Vector3<f32>,
+    pub joints: Vec<JointDesc<RigidBodyHandle>>,
+    pub body_handle_map: BiDirHashMap<RigidBodyHandle, rapier3d::dynamics::RigidBodyHandle>,
+    pub collider_handle_map: BiDirHashMap<ColliderHandle, rapier3d::geometry::ColliderHandle>,
+    pub joint_handle_map: BiDirHashMap<JointHandle, rapier3d::dynamics::JointHandle>,
+}
+
+impl Visit for PhysicsDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.integration_parameters
+            .visit("IntegrationParameters", visitor)?;
+        self.gravity.visit("Gravity", visitor)?;
+        self.colliders.visit("Colliders", visitor)?;
+        self.bodies.visit("Bodies", visitor)?;
+        self.joints.visit("Joints", visitor)?;
+
+        // TODO: Refactor duplicates here.
+        {
+            let mut body_handle_map = if visitor.is_reading() {
+                Default::default()
+            } else {
+                let mut hash_map: HashMap<RigidBodyHandle, ErasedHandle> = Default::default();
+                for (k, v) in self.body_handle_map.forward_map().iter() {
+                    let (index, gen) = v.into_raw_parts();
+                    hash_map.insert(*k, ErasedHandle::new(index as u32, gen as u32));
+                }
+                hash_map
+            };
+            if body_handle_map.visit("BodyHandleMap", visitor).is_err() {
+                body_handle_map = (0..self.bodies.len())
+                    .map(|i| {
+                        let bytes = unsafe { std::mem::transmute::<_, [u8; 16]>([i as u64, 0u64]) };
+                        (
+                            RigidBodyHandle::from(Uuid::from_bytes(bytes)),
+                            ErasedHandle::new(i as u32, 0),
+                        )
+                    })
+                    .collect();
+            }
+            if visitor.is_reading() {
+                self.body_handle_map = BiDirHashMap::from(
+                    body_handle_map
+                        .iter()
+                        .map(|(k, v)| {
+                            (
+                                *k,
+                                rapier3d::dynamics::RigidBodyHandle::from_raw_parts(
+                                    v.index() as usize,
+                                    v.generation() as u64,
+                                ),
+                            )
+                        })
+                        .collect::<HashMap<_, _>>(),
+                );
+            }
+        }
+
+        {
+            let mut collider_handle_map = if visitor.is_reading() {
+                Default::default()
+            } else {
+                let mut hash_map: HashMap<ColliderHandle, ErasedHandle> = Default::default();
+                for (k, v) in self.collider_handle_map.forward_map().iter() {
+                    let (index, gen) = v.into_raw_parts();
+                    hash_map.insert(*k, ErasedHandle::new(index as u32, gen as u32));
+                }
+                hash_map
+            };
+            if collider_handle_map
+                .visit("ColliderHandleMap", visitor)
+                .is_err()
+            {
+                collider_handle_map = (0..self.colliders.len())
+                    .map(|i| {
+                        let bytes = unsafe { std::mem::transmute::<_, [u8; 16]>([i as u64, 0u64]) };
+                        (
+                            ColliderHandle::from(Uuid::from_bytes(bytes)),
+                            ErasedHandle::new(i as u32, 0),
+                        )
+                    })
+                    .collect();
+            }
+            if visitor.is_reading() {
+                self.collider_handle_map = BiDirHashMap::from(
+                    collider_handle_map
+                        .iter()
+                        .map(|(k, v)| {
+                            (
+                                *k,
+                                rapier3d::geometry::ColliderHandle::from_raw_parts(
+                                    v.index() as usize,
+                                    v.generation() as u64,
+                                ),
+                            )
+                        })
+                        .collect::<HashMap<_, _>>(),
+                );
+            }
+        }
+
+        {
+            let mut joint_handle_map = if visitor.is_reading() {
+                Default::default()
+            } else {
+                let mut hash_map: HashMap<JointHandle, ErasedHandle> = Default::default();
+                for (k, v) in self.joint_handle_map.forward_map().iter() {
+                    let (index, gen) = v.into_raw_parts();
+                    hash_map.insert(*k, ErasedHandle::new(index as u32, gen as u32));
+                }
+                hash_map
+            };
+            if joint_handle_map.visit("JointHandleMap", visitor).is_err() {
+                joint_handle_map = (0..self.joints.len())
+                    .map(|i| {
+                        let bytes = unsafe { std::mem::transmute::<_, [u8; 16]>([i as u64, 0u64]) };
+                        (
+                            JointHandle::from(Uuid::from_bytes(bytes)),
+                            ErasedHandle::new(i as u32, 0),
+                        )
+                    })
+                    .collect();
+            }
+            if visitor.is_reading() {
+                self.joint_handle_map = BiDirHashMap::from(
+                    joint_handle_map
+                        .iter()
+                        .map(|(k, v)| {
+                            (
+                                *k,
+                                rapier3d::dynamics::JointHandle::from_raw_parts(
+                                    v.index() as usize,
+                                    v.generation() as u64,
+                                ),
+                            )
+                        })
+                        .collect::<HashMap<_, _>>(),
+                );
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            futures::executor::block_on,
+            visitor::{Visit, Visitor},
+        },
+        engine::{ColliderHandle, PhysicsBinder},
+        scene::{
+            base::{BaseBuilder, PhysicsBinding},
+            graph::Graph,
+            physics::{
+                event::{ContactEventInfo, PhysicsEvent, SensorEventInfo},
+                BallDesc, ColliderShapeDesc, FixedTimestep, Physics, PhysicsDrawOptions,
+                ShapeCastOptions,
+            },
+            Scene, SceneDrawingContext,
+        },
+    };
+    use rapier3d::{
+        dynamics::{BallJoint, JointParams, PrismaticJoint, RigidBodyBuilder},
+        geometry::{ColliderBuilder, InteractionGroups},
+        na::{DMatrix, Isometry3, Point3, Vector3},
+        parry::shape::{Compound, ConvexPolyhedron, SharedShape},
+    };
+
+    #[test]
+    fn test_queries() {
+        let mut physics = Physics::new();
+        let body = physics.add_body(RigidBodyBuilder::new_static().build());
+        let collider = physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(), &body);
+
+        let hit = physics
+            .cast_shape(ShapeCastOptions {
+                shape: ColliderShapeDesc::Ball(BallDesc { radius: 0.5 }),
+                position: Isometry3::translation(0.0, 5.0, 0.0),
+                direction: Vector3::new(0.0, -2.0, 0.0),
+                max_len: 10.0,
+                groups: InteractionGroups::all(),
+            })
+            .unwrap();
+        assert_eq!(hit.collider, collider);
+        assert!((hit.toi - 4.0).abs() < 1.0e-3);
+        assert!((hit.normal - Vector3::y()).norm() < 1.0e-3);
+
+        let projection = physics
+            .project_point(Point3::new(0.0, 5.0, 0.0), true, InteractionGroups::all())
+            .unwrap();
+        assert_eq!(projection.collider, collider);
+        assert!(!projection.is_inside);
+        assert!((projection.position.y - 0.5).abs() < 1.0e-3);
+
+        let mut overlaps = Vec::<ColliderHandle>::new();
+        physics.intersections_with_point(
+            Point3::new(0.1, 0.1, 0.1),
+            InteractionGroups::all(),
+            &mut overlaps,
+        );
+        assert_eq!(overlaps, vec![collider]);
+
+        physics.intersections_with_shape(
+            ColliderShapeDesc::Ball(BallDesc { radius: 0.5 }),
+            Isometry3::translation(0.0, 2.0, 0.0),
+            InteractionGroups::all(),
+            &mut overlaps,
+        );
+        assert!(overlaps.is_empty());
+    }
+
+    #[test]
+    fn test_convex_shape_descs() {
+        let mut cube = Vec::new();
+        for &x in [-0.5, 0.5].iter() {
+            for &y in [-0.5, 0.5].iter() {
+                for &z in [-0.5, 0.5].iter() {
+                    cube.push(Point3::new(x, y, z));
+                }
+            }
+        }
+
+        let hull = SharedShape::convex_hull(&cube).unwrap();
+        let desc = ColliderShapeDesc::from_collider_shape(&*hull);
+        match &desc {
+            ColliderShapeDesc::ConvexHull(hull) => assert_eq!(hull.vertices.len(), 8),
+            _ => unreachable!(),
+        }
+        let restored = desc.into_collider_shape();
+        assert_eq!(
+            restored
+                .downcast_ref::<ConvexPolyhedron>()
+                .unwrap()
+                .points()
+                .len(),
+            8
+        );
+
+        let compound = SharedShape::compound(vec![
+            (Isometry3::translation(-2.0, 0.0, 0.0), hull.clone()),
+            (Isometry3::translation(2.0, 0.0, 0.0), hull),
+        ]);
+        let desc = ColliderShapeDesc::from_collider_shape(&*compound);
+        match &desc {
+            ColliderShapeDesc::ConvexDecomposition(decomposition) => {
+                assert_eq!(decomposition.hulls.len(), 2);
+                // Parts are baked into collider space.
+                assert!(decomposition.hulls[0].vertices.iter().all(|v| v.x < -1.0));
+                assert!(decomposition.hulls[1].vertices.iter().all(|v| v.x > 1.0));
+            }
+            _ => unreachable!(),
+        }
+
+        // Dynamic body with restored decomposition falls under gravity.
+        let mut physics = Physics::new();
+        let body = physics.add_body(RigidBodyBuilder::new_dynamic().build());
+        physics.add_collider(
+            ColliderBuilder::new(desc.into_collider_shape()).build(),
+            &body,
+        );
+        for _ in 0..10 {
+            physics.update(1.0 / 60.0);
+        }
+        assert!(physics.body(&body).unwrap().position().translation.y < 0.0);
+    }
+
+    #[test]
+    fn test_compound_shape_desc() {
+        let compound = SharedShape::compound(vec![
+            (
+                Isometry3::translation(-2.0, 0.0, 0.0),
+                SharedShape::cuboid(0.5, 1.0, 1.5),
+            ),
+            (
+                Isometry3::translation(2.0, 0.0, 0.0),
+                SharedShape::ball(0.25),
+            ),
+        ]);
+        let desc = ColliderShapeDesc::from_collider_shape(&*compound);
+        match &desc {
+            ColliderShapeDesc::Compound(compound) => {
+                assert_eq!(compound.parts.len(), 2);
+                assert_eq!(compound.parts[0].translation, Vector3::new(-2.0, 0.0, 0.0));
+                assert_eq!(compound.parts[1].translation, Vector3::new(2.0, 0.0, 0.0));
+            }
+            _ => unreachable!(),
+        }
+
+        let restored = desc.into_collider_shape();
+        let parts = restored.downcast_ref::<Compound>().unwrap().shapes();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0.translation.vector, Vector3::new(-2.0, 0.0, 0.0));
+        assert_eq!(
+            parts[0].1.as_cuboid().unwrap().half_extents,
+            Vector3::new(0.5, 1.0, 1.5)
+        );
+        assert_eq!(parts[1].0.translation.vector, Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(parts[1].1.as_ball().unwrap().radius, 0.25);
+    }
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut physics = Physics::new();
+        physics.gravity = Vector3::default();
+        physics.integration_parameters.dt = 0.25;
+        physics.fixed_timestep = FixedTimestep {
+            enabled: true,
+            max_substeps: 2,
+            interpolate: true,
+        };
+        let body = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .linvel(1.0, 0.0, 0.0)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.1).build(), &body);
+        let x = |physics: &Physics| physics.body(&body).unwrap().position().translation.x;
+        let approx = |a: f32, b: f32| (a - b).abs() < 1.0e-4;
+
+        // Two steps, remainder is carried over to the next update.
+        physics.update(0.6);
+        assert!(approx(x(&physics), 0.5));
+        assert!(approx(physics.interpolation_factor(), 0.4));
+        assert!(approx(
+            physics
+                .interpolated_body_position(&body)
+                .unwrap()
+                .translation
+                .x,
+            0.35
+        ));
+
+        // Carried over time completes one more step.
+        physics.update(0.2);
+        assert!(approx(x(&physics), 0.75));
+        assert!(approx(physics.interpolation_factor(), 0.2));
+
+        // Amount of steps is clamped, time that can't be simulated is discarded.
+        physics.update(1.6);
+        assert!(approx(x(&physics), 1.25));
+        assert!(approx(physics.interpolation_factor(), 0.6));
+        assert!(approx(
+            physics
+                .interpolated_body_position(&body)
+                .unwrap()
+                .translation
+                .x,
+            1.15
+        ));
+
+        // Zero substeps still advances physics.
+        physics.fixed_timestep.max_substeps = 0;
+        physics.update(0.25);
+        assert!(approx(x(&physics), 1.5));
+
+        // Without interpolation current position is used.
+        physics.fixed_timestep.interpolate = false;
+        assert_eq!(physics.interpolation_factor(), 1.0);
+        assert!(approx(
+            physics
+                .interpolated_body_position(&body)
+                .unwrap()
+                .translation
+                .x,
+            1.5
+        ));
+    }
+
+    #[test]
+    fn test_convex_hull_without_geometry() {
+        let mut graph = Graph::new();
+        let root = BaseBuilder::new().build(&mut graph);
+
+        assert!(Physics::make_convex_hull(root, &graph).is_none());
+        assert!(Physics::make_convex_decomposition(root, &graph, &Default::default()).is_none());
+
+        let mut physics = Physics::new();
+        assert!(physics.mesh_to_convex_hull(root, &graph).is_none());
+        assert!(physics
+            .mesh_to_convex_decomposition(root, &graph, &Default::default())
+            .is_none());
+        assert_eq!(physics.bodies.len(), 0);
+    }
+
+    #[test]
+    fn test_debug_draw() {
+        let mut physics = Physics::new();
+        let ground = physics.add_body(RigidBodyBuilder::new_static().build());
+        physics.add_collider(ColliderBuilder::cuboid(5.0, 0.5, 5.0).build(), &ground);
+        let body = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(0.0, 1.0, 0.0)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.5).build(), &body);
+        physics.add_joint(
+            &ground,
+            &body,
+            BallJoint::new(Point3::new(0.0, 1.0, 0.0), Point3::origin()),
+        );
+        for _ in 0..10 {
+            physics.update(1.0 / 60.0);
+        }
+
+        let options = PhysicsDrawOptions {
+            aabbs: true,
+            ..Default::default()
+        };
+        let mut context = SceneDrawingContext::default();
+        physics.draw_with_options(&mut context, &options);
+        for color in [
+            options.static_color,
+            options.dynamic_color,
+            options.contact_color,
+            options.joint_color,
+            options.aabb_color,
+        ]
+        .iter()
+        {
+            assert!(context.lines.iter().any(|l| l.color == *color));
+        }
+
+        context.clear_lines();
+        physics.draw_with_options(
+            &mut context,
+            &PhysicsDrawOptions {
+                shapes: false,
+                transforms: false,
+                contacts: false,
+                joints: false,
+                ..Default::default()
+            },
+        );
+        assert!(context.lines.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_rollback() {
+        let mut physics = Physics::new();
+        let ground = physics.add_body(RigidBodyBuilder::new_static().build());
+        physics.add_collider(ColliderBuilder::cuboid(5.0, 0.5, 5.0).build(), &ground);
+        let body = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(0.0, 3.0, 0.0)
+                .angvel(Vector3::new(1.0, 2.0, 3.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(), &body);
+        for _ in 0..10 {
+            physics.update(1.0 / 60.0);
+        }
+
+        let snapshot = physics.snapshot();
+        physics.resimulate(40);
+        let expected = *physics.body(&body).unwrap().position();
+
+        let extra = physics.add_body(RigidBodyBuilder::new_dynamic().build());
+        physics.restore(&snapshot);
+        assert!(!physics.contains_body(&extra));
+        assert_eq!(physics.bodies().len(), snapshot.body_count());
+
+        physics.resimulate(40);
+        assert_eq!(*physics.body(&body).unwrap().position(), expected);
+    }
+
+    #[test]
+    fn test_snapshot_rollback_body_with_node() {
+        let mut scene = Scene::new();
+        let node = BaseBuilder::new().build(&mut scene.graph);
+        scene.graph[node].set_physics_binding(PhysicsBinding::BodyWithNode);
+        let body = scene
+            .physics
+            .add_body(RigidBodyBuilder::new_kinematic().build());
+        scene.physics_binder.bind(node, body);
+
+        let snapshot = scene.snapshot_physics();
+
+        // Node drives the body during normal updates.
+        scene.graph[node]
+            .local_transform_mut()
+            .set_position(Vector3::new(5.0, 0.0, 0.0));
+        scene.graph.update_hierarchical_data();
+        scene.update_physics(1.0 / 60.0);
+        assert_eq!(
+            scene.physics.body(&body).unwrap().position().translation.x,
+            5.0
+        );
+
+        // But restored state of the body must not be overwritten by the node.
+        scene.restore_physics(&snapshot);
+        assert_eq!(
+            scene.physics.body(&body).unwrap().position().translation.x,
+            0.0
+        );
+        assert_eq!(scene.graph[node].global_position(), Vector3::default());
+    }
+
+    #[test]
+    fn test_events() {
+        let mut scene = Scene::new();
+        scene.physics.gravity = Vector3::default();
+
+        // Ball flies through a sensor, hits a wall and bounces back.
+        let ball = scene.physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .linvel(10.0, 0.0, 0.0)
+                .build(),
+        );
+        let ball_collider = scene
+            .physics
+            .add_collider(ColliderBuilder::ball(0.5).restitution(1.0).build(), &ball);
+        let sensor = scene.physics.add_body(
+            RigidBodyBuilder::new_static()
+                .translation(2.0, 0.0, 0.0)
+                .build(),
+        );
+        let sensor_collider = scene
+            .physics
+            .add_collider(ColliderBuilder::ball(0.5).sensor(true).build(), &sensor);
+        let wall = scene.physics.add_body(
+            RigidBodyBuilder::new_static()
+                .translation(5.0, 0.0, 0.0)
+                .build(),
+        );
+        let wall_collider = scene.physics.add_collider(
+            ColliderBuilder::cuboid(0.5, 5.0, 5.0)
+                .restitution(1.0)
+                .build(),
+            &wall,
+        );
+
+        let ball_node = BaseBuilder::new().build(&mut scene.graph);
+        scene.physics_binder.bind(ball_node, ball);
+        let sensor_node = BaseBuilder::new().build(&mut scene.graph);
+        scene.physics_binder.bind(sensor_node, sensor);
+        let wall_node = BaseBuilder::new().build(&mut scene.graph);
+        scene.physics_binder.bind(wall_node, wall);
+
+        let mut events = Vec::new();
+        for _ in 0..60 {
+            scene.update_physics(1.0 / 60.0);
+            events.extend(scene.physics.events().iter().cloned());
+        }
+
+        let is_ball_and_wall = |info: &ContactEventInfo| {
+            let colliders = [info.collider1, info.collider2];
+            let nodes = [info.node1, info.node2];
+            colliders.contains(&ball_collider)
+                && colliders.contains(&wall_collider)
+                && nodes.contains(&ball_node)
+                && nodes.contains(&wall_node)
+        };
+        let is_ball_in_sensor = |info: &SensorEventInfo| {
+            info.sensor == sensor_collider
+                && info.other == ball_collider
+                && info.sensor_node == sensor_node
+                && info.other_node == ball_node
+        };
+
+        let sensor_entered = events.iter().position(
+            |e| matches!(e, PhysicsEvent::SensorEntered(info) if is_ball_in_sensor(info)),
+        );
+        let sensor_exited = events
+            .iter()
+            .position(|e| matches!(e, PhysicsEvent::SensorExited(info) if is_ball_in_sensor(info)));
+        let contact_started = events.iter().position(|e| {
+            matches!(e, PhysicsEvent::ContactStarted(info)
+                if is_ball_and_wall(info) && !info.points.is_empty())
+        });
+        let contact_stopped = events.iter().position(|e| {
+            matches!(e, PhysicsEvent::ContactStopped(info)
+                if is_ball_and_wall(info) && info.points.is_empty())
+        });
+        assert!(sensor_entered.unwrap() < sensor_exited.unwrap());
+        assert!(sensor_exited.unwrap() < contact_started.unwrap());
+        assert!(contact_started.unwrap() < contact_stopped.unwrap());
+
+        // Ball is far from everything now and queue is cleared on every update.
+        scene.update_physics(1.0 / 60.0);
+        assert!(scene.physics.events().is_empty());
+    }
+
+    #[test]
+    fn test_desc_round_trip() {
+        let mut physics = Physics::new();
+        let ground = physics.add_body(RigidBodyBuilder::new_static().build());
+        physics.add_collider(
+            ColliderBuilder::trimesh(
+                vec![
+                    Point3::new(-1.0, 0.0, -1.0),
+                    Point3::new(1.0, 0.0, -1.0),
+                    Point3::new(1.0, 0.0, 1.0),
+                    Point3::new(-1.0, 0.0, 1.0),
+                ],
+                vec![[0, 1, 2], [0, 2, 3]],
+            )
+            .build(),
+            &ground,
+        );
+        physics.add_collider(
+            ColliderBuilder::heightfield(
+                DMatrix::from_row_slice(2, 3, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+                Vector3::new(10.0, 1.0, 20.0),
+            )
+            .build(),
+            &ground,
+        );
+        let body = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(0.0, 2.0, 0.0)
+                .linvel(1.0, 2.0, 3.0)
+                .linear_damping(0.25)
+                .angular_damping(0.75)
+                .gravity_scale(0.5)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.5).density(2.0).build(), &body);
+        let ball_joint = physics.add_joint(
+            &ground,
+            &body,
+            BallJoint::new(Point3::origin(), Point3::new(0.0, -2.0, 0.0)),
+        );
+        let slider = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(5.0, 0.0, 0.0)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(), &slider);
+        let mut prismatic = PrismaticJoint::new(
+            Point3::new(5.0, 0.0, 0.0),
+            Vector3::x_axis(),
+            Vector3::y(),
+            Point3::origin(),
+            Vector3::x_axis(),
+            Vector3::y(),
+        );
+        prismatic.limits_enabled = true;
+        prismatic.limits = [-1.0, 2.0];
+        let prismatic_joint = physics.add_joint(&ground, &slider, prismatic);
+        for _ in 0..5 {
+            physics.update(1.0 / 60.0);
+        }
+
+        // Sleeping body is added after simulation, so it won't be woken up.
+        let sleeping = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(-5.0, 0.0, 0.0)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.5).build(), &sleeping);
+        physics.body_mut(&sleeping).unwrap().sleep();
+
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_test_physics_desc_round_trip_{}.bin",
+            std::process::id()
+        ));
+        let mut visitor = Visitor::new();
+        physics.visit("Physics", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut restored = Physics::new();
+        restored.visit("Physics", &mut visitor).unwrap();
+        restored.resolve(&PhysicsBinder::default(), &Graph::new());
+
+        let trimesh = restored
+            .colliders()
+            .iter()
+            .find_map(|(_, c)| c.shape().as_trimesh())
+            .unwrap();
+        assert_eq!(trimesh.vertices().len(), 4);
+        assert_eq!(trimesh.indices(), &[[0, 1, 2], [0, 2, 3]]);
+
+        let heightfield = restored
+            .colliders()
+            .iter()
+            .find_map(|(_, c)| c.shape().as_heightfield())
+            .unwrap();
+        assert_eq!(
+            heightfield.heights(),
+            &DMatrix::from_row_slice(2, 3, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0])
+        );
+        assert_eq!(heightfield.scale(), &Vector3::new(10.0, 1.0, 20.0));
+
+        let (original, restored_body) =
+            (physics.body(&body).unwrap(), restored.body(&body).unwrap());
+        assert_eq!(original.position(), restored_body.position());
+        assert_eq!(original.linvel(), restored_body.linvel());
+        assert_eq!(original.angvel(), restored_body.angvel());
+        assert_eq!(original.mass(), restored_body.mass());
+        assert_eq!(
+            original.mass_properties().local_com,
+            restored_body.mass_properties().local_com
+        );
+        assert_eq!(
+            original.mass_properties().inv_principal_inertia_sqrt,
+            restored_body.mass_properties().inv_principal_inertia_sqrt
+        );
+        assert_eq!(original.gravity_scale(), restored_body.gravity_scale());
+        assert_eq!(original.linear_damping, restored_body.linear_damping);
+        assert_eq!(original.angular_damping, restored_body.angular_damping);
+
+        assert!(!restored.body(&body).unwrap().is_sleeping());
+        assert!(restored.body(&sleeping).unwrap().is_sleeping());
+
+        assert_eq!(restored.joints().len(), 2);
+        let joint_params = |physics: &Physics, handle| {
+            let handle = *physics.joint_handle_map().value_of(handle).unwrap();
+            physics.joints().get(handle).unwrap().params.clone()
+        };
+        match (
+            joint_params(&physics, &ball_joint),
+            joint_params(&restored, &ball_joint),
+        ) {
+            (JointParams::BallJoint(original), JointParams::BallJoint(restored)) => {
+                // Hanging body pulls the joint, so the impulse is not zero.
+                assert_ne!(original.impulse, Default::default());
+                assert_eq!(original.impulse, restored.impulse);
+            }
+            _ => unreachable!(),
+        }
+        match (
+            joint_params(&physics, &prismatic_joint),
+            joint_params(&restored, &prismatic_joint),
+        ) {
+            (JointParams::PrismaticJoint(original), JointParams::PrismaticJoint(restored)) => {
+                assert!(restored.limits_enabled);
+                assert_eq!(restored.limits, [-1.0, 2.0]);
+                assert_eq!(original.impulse, restored.impulse);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
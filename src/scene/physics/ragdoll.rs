@@ -0,0 +1,816 @@
+//! Ragdolls built from skinned skeletons.
+//!
+//! Ragdoll builder takes a skeleton root and a set of bones (limbs) that should be simulated,
+//! and creates a dynamic rigid body with a capsule collider for every limb. Capsules are sized
+//! using vertices of skinned meshes that are mostly influenced by a limb bone, limbs are
+//! connected with ball joints with limited rotation.
+//!
+//! Ragdoll can blend between animation-driven and physics-driven pose of the skeleton. When
+//! blend factor is zero, bodies are kinematic and simply follow animated bones. When blend
+//! factor is above zero, bodies are simulated and bones are interpolated between animated and
+//! simulated pose. This allows to make hit reactions - enable physics, apply an impulse to a
+//! limb and then smoothly blend back to the animation.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, pool::Handle},
+//!     scene::{
+//!         node::Node,
+//!         physics::ragdoll::{LimbDesc, Ragdoll, RagdollBuilder},
+//!         Scene,
+//!     },
+//! };
+//!
+//! fn create_ragdoll(scene: &Scene, skeleton: Handle<Node>) -> Vec<LimbDesc> {
+//!     ["Hips", "Spine", "Head", "LeftUpLeg", "LeftLeg", "RightUpLeg", "RightLeg"]
+//!         .iter()
+//!         .map(|name| LimbDesc::new(scene.graph.find_by_name(skeleton, name)))
+//!         .collect()
+//! }
+//!
+//! fn on_hit(scene: &mut Scene, ragdoll: &mut Ragdoll, bone: Handle<Node>) {
+//!     // Switch to physics immediately and push the limb.
+//!     ragdoll.set_blend(1.0);
+//!     let point = scene.graph[bone].global_position();
+//!     ragdoll.apply_impulse(&mut scene.physics, bone, Vector3::new(0.0, 0.0, 5.0), point);
+//!     // Then smoothly return to the animation in one second.
+//!     ragdoll.blend_to(0.0, 1.0);
+//! }
+//!
+//! fn update(scene: &mut Scene, ragdoll: &mut Ragdoll, dt: f32) {
+//!     // Must be called after scene update, when animations are applied to the skeleton.
+//!     ragdoll.update(&mut scene.graph, &mut scene.physics, dt);
+//! }
+//! ```
+//!
+//! # Limitations
+//!
+//! Joints of current version of physics engine do not support limits, so limits are enforced
+//! by the ragdoll itself in [`Ragdoll::update`] by correcting angular velocities of limbs.
+//!
+//! Adjacent limbs may collide with each other, capsules are shortened by their radius to reduce
+//! overlapping, but it is recommended to put ragdoll into separate collision group that does not
+//! collide with itself (see [`RagdollBuilder::with_collision_groups`]).
+
+use crate::{
+    core::{
+        pool::Handle,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    engine::{ColliderHandle, JointHandle, RigidBodyHandle},
+    scene::{
+        graph::Graph,
+        mesh::buffer::{VertexAttributeKind, VertexReadTrait},
+        node::Node,
+        physics::Physics,
+    },
+    utils::log::{Log, MessageKind},
+};
+use rapier3d::{
+    dynamics::{BallJoint, BodyStatus, RigidBodyBuilder},
+    geometry::{ColliderBuilder, InteractionGroups},
+    na::{Isometry3, Matrix4, Point3, Translation3, UnitQuaternion, Vector3},
+    parry::shape::SharedShape,
+};
+
+const MIN_RADIUS: f32 = 0.02;
+
+/// Describes a bone that should be simulated by a ragdoll.
+#[derive(Clone, Debug)]
+pub struct LimbDesc {
+    /// A bone of the skeleton.
+    pub bone: Handle<Node>,
+
+    /// Maximum angle (in radians) at which the limb can deviate from its initial orientation
+    /// relative to the parent limb.
+    pub angle_limit: f32,
+
+    /// Radius of the capsule of the limb. If none, it will be calculated from vertices of
+    /// skinned meshes.
+    pub radius: Option<f32>,
+}
+
+impl LimbDesc {
+    /// Creates new limb description for given bone with default angle limit of 45 degrees.
+    pub fn new(bone: Handle<Node>) -> Self {
+        Self {
+            bone,
+            angle_limit: 45.0f32.to_radians(),
+            radius: None,
+        }
+    }
+
+    /// Sets maximum angle (in radians) at which the limb can deviate from its initial orientation
+    /// relative to the parent limb.
+    pub fn with_angle_limit(mut self, angle_limit: f32) -> Self {
+        self.angle_limit = angle_limit;
+        self
+    }
+
+    /// Sets radius of the capsule of the limb.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+}
+
+/// A simulated bone of a ragdoll.
+#[derive(Clone, Debug, Default)]
+pub struct Limb {
+    bone: Handle<Node>,
+    parent: Handle<Node>,
+    body: RigidBodyHandle,
+    collider: ColliderHandle,
+    joint: Option<JointHandle>,
+    angle_limit: f32,
+    rest_rotation: UnitQuaternion<f32>,
+}
+
+impl Limb {
+    /// Returns a bone of the limb.
+    pub fn bone(&self) -> Handle<Node> {
+        self.bone
+    }
+
+    /// Returns a bone of the parent limb, `Handle::NONE` for the root limb.
+    pub fn parent_bone(&self) -> Handle<Node> {
+        self.parent
+    }
+
+    /// Returns handle of the rigid body of the limb.
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
+    }
+
+    /// Returns handle of the capsule collider of the limb.
+    pub fn collider(&self) -> ColliderHandle {
+        self.collider
+    }
+
+    /// Returns handle of the joint that connects the limb with its parent, none for the root
+    /// limb.
+    pub fn joint(&self) -> Option<JointHandle> {
+        self.joint
+    }
+
+    /// Returns maximum angle of deviation from initial orientation relative to the parent limb.
+    pub fn angle_limit(&self) -> f32 {
+        self.angle_limit
+    }
+
+    /// Sets maximum angle of deviation from initial orientation relative to the parent limb.
+    pub fn set_angle_limit(&mut self, angle_limit: f32) {
+        self.angle_limit = angle_limit;
+    }
+}
+
+impl Visit for Limb {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.bone.visit("Bone", visitor)?;
+        self.parent.visit("Parent", visitor)?;
+        self.body.visit("Body", visitor)?;
+        self.collider.visit("Collider", visitor)?;
+        self.joint.visit("Joint", visitor)?;
+        self.angle_limit.visit("AngleLimit", visitor)?;
+        self.rest_rotation.visit("RestRotation", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Set of rigid bodies connected with joints that can drive a skeleton.
+#[derive(Clone, Debug, Default)]
+pub struct Ragdoll {
+    root: Handle<Node>,
+    // Sorted so parent limbs are always before their children.
+    limbs: Vec<Limb>,
+    blend: f32,
+    target_blend: f32,
+    blend_speed: f32,
+}
+
+struct PoseSample {
+    animated: Isometry3<f32>,
+    parent: Handle<Node>,
+    parent_animated: Isometry3<f32>,
+    ancestor: Option<usize>,
+}
+
+fn world_isometry(graph: &Graph, node: Handle<Node>) -> Isometry3<f32> {
+    if node.is_none() {
+        return Isometry3::identity();
+    }
+    let (rotation, position) = graph.global_rotation_position_no_scale(node);
+    Isometry3::from_parts(Translation3::from(position), rotation)
+}
+
+fn interpolate(a: &Isometry3<f32>, b: &Isometry3<f32>, t: f32) -> Isometry3<f32> {
+    Isometry3::from_parts(
+        Translation3::from(a.translation.vector.lerp(&b.translation.vector, t)),
+        a.rotation.slerp(&b.rotation, t),
+    )
+}
+
+fn find_limb<I>(graph: &Graph, mut node: Handle<Node>, bones: I) -> Option<usize>
+where
+    I: Iterator<Item = Handle<Node>> + Clone,
+{
+    while node.is_some() {
+        if let Some(index) = bones.clone().position(|bone| bone == node) {
+            return Some(index);
+        }
+        node = graph[node].parent();
+    }
+    None
+}
+
+impl Ragdoll {
+    /// Returns root of the skeleton of the ragdoll.
+    pub fn root(&self) -> Handle<Node> {
+        self.root
+    }
+
+    /// Returns limbs of the ragdoll, parent limbs are always before their children.
+    pub fn limbs(&self) -> &[Limb] {
+        &self.limbs
+    }
+
+    /// Returns mutable reference to limbs of the ragdoll.
+    pub fn limbs_mut(&mut self) -> &mut [Limb] {
+        &mut self.limbs
+    }
+
+    /// Tries to find a limb for given bone.
+    pub fn limb_of(&self, bone: Handle<Node>) -> Option<&Limb> {
+        self.limbs.iter().find(|limb| limb.bone == bone)
+    }
+
+    /// Returns current blend factor, where 0.0 means fully animated skeleton and 1.0 - fully
+    /// simulated.
+    pub fn blend(&self) -> f32 {
+        self.blend
+    }
+
+    /// Sets new blend factor immediately, see [`Self::blend`].
+    pub fn set_blend(&mut self, blend: f32) {
+        self.blend = blend.max(0.0).min(1.0);
+        self.target_blend = self.blend;
+    }
+
+    /// Smoothly changes blend factor to given value in given amount of seconds.
+    pub fn blend_to(&mut self, blend: f32, time: f32) {
+        let blend = blend.max(0.0).min(1.0);
+        if time <= 0.0 {
+            self.set_blend(blend);
+        } else {
+            self.target_blend = blend;
+            self.blend_speed = (blend - self.blend).abs() / time;
+        }
+    }
+
+    /// Returns true if blend factor is changing.
+    pub fn is_blending(&self) -> bool {
+        self.blend != self.target_blend
+    }
+
+    /// Returns true if bodies of the ragdoll are simulated.
+    pub fn is_simulated(&self) -> bool {
+        self.blend > 0.0 || self.target_blend > 0.0
+    }
+
+    /// Applies an impulse at given world point to a body of a limb of given bone. Has effect only
+    /// if the ragdoll is simulated.
+    pub fn apply_impulse(
+        &self,
+        physics: &mut Physics,
+        bone: Handle<Node>,
+        impulse: Vector3<f32>,
+        point: Vector3<f32>,
+    ) {
+        if let Some(limb) = self.limbs.iter().find(|limb| limb.bone == bone) {
+            if let Some(body) = physics.body_mut(&limb.body) {
+                body.apply_impulse_at_point(impulse, Point3::from(point), true);
+            }
+        }
+    }
+
+    /// Synchronizes the ragdoll with the skeleton. When blend factor is zero, bodies are moved to
+    /// animated bones, otherwise bones are interpolated between animated pose and pose of the
+    /// bodies. Must be called after the scene update, so animations are already applied to the
+    /// skeleton.
+    pub fn update(&mut self, graph: &mut Graph, physics: &mut Physics, dt: f32) {
+        if self.blend < self.target_blend {
+            self.blend = (self.blend + self.blend_speed * dt).min(self.target_blend);
+        } else if self.blend > self.target_blend {
+            self.blend = (self.blend - self.blend_speed * dt).max(self.target_blend);
+        }
+
+        // Make sure that global transforms match animated pose.
+        graph.update_hierarchical_data();
+
+        let samples = self
+            .limbs
+            .iter()
+            .map(|limb| {
+                let parent = graph[limb.bone].parent();
+                PoseSample {
+                    animated: world_isometry(graph, limb.bone),
+                    parent,
+                    parent_animated: world_isometry(graph, parent),
+                    ancestor: self
+                        .limbs
+                        .iter()
+                        .position(|other| other.bone == limb.parent),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let simulated = self.is_simulated();
+
+        for (limb, sample) in self.limbs.iter().zip(samples.iter()) {
+            if let Some(body) = physics.body_mut(&limb.body) {
+                if simulated {
+                    if !body.is_dynamic() {
+                        body.set_body_status(BodyStatus::Dynamic);
+                        body.wake_up(true);
+                    }
+                } else if body.is_dynamic() {
+                    // First kinematic frame - body could be far away from animated pose, so
+                    // teleport it instead of moving, otherwise it will sweep through the scene
+                    // with huge velocity.
+                    body.set_body_status(BodyStatus::Kinematic);
+                    body.set_position(sample.animated, true);
+                } else {
+                    body.set_next_kinematic_position(sample.animated);
+                }
+            }
+        }
+
+        if !simulated {
+            return;
+        }
+
+        self.enforce_limits(physics);
+
+        let blended = self
+            .limbs
+            .iter()
+            .zip(samples.iter())
+            .map(|(limb, sample)| {
+                let simulated = physics
+                    .body(&limb.body)
+                    .map(|body| *body.position())
+                    .unwrap_or(sample.animated);
+                interpolate(&sample.animated, &simulated, self.blend)
+            })
+            .collect::<Vec<_>>();
+
+        for (i, (limb, sample)) in self.limbs.iter().zip(samples.iter()).enumerate() {
+            // Bones between a limb and its ancestor limb are not simulated, they're moved
+            // rigidly with the ancestor.
+            let parent_pose = match sample.ancestor {
+                Some(ancestor) if self.limbs[ancestor].bone == sample.parent => blended[ancestor],
+                Some(ancestor) => {
+                    blended[ancestor]
+                        * samples[ancestor].animated.inverse()
+                        * sample.parent_animated
+                }
+                None => sample.parent_animated,
+            };
+            let scale = if sample.parent.is_some() {
+                graph.global_scale(sample.parent)
+            } else {
+                Vector3::new(1.0, 1.0, 1.0)
+            };
+
+            let local = parent_pose.inverse() * blended[i];
+
+            let transform = graph[limb.bone].local_transform_mut();
+            let pre_rotation = **transform.pre_rotation();
+            let post_rotation = **transform.post_rotation();
+            transform
+                .set_position(local.translation.vector.component_div(&scale))
+                .set_rotation(pre_rotation.inverse() * local.rotation * post_rotation);
+        }
+
+        graph.update_hierarchical_data();
+    }
+
+    fn enforce_limits(&self, physics: &mut Physics) {
+        let dt = physics.integration_parameters.dt;
+
+        for limb in self.limbs.iter() {
+            let parent = match self.limbs.iter().find(|other| other.bone == limb.parent) {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let (parent_rotation, parent_angvel) = match physics.body(&parent.body) {
+                Some(body) => (body.position().rotation, *body.angvel()),
+                None => continue,
+            };
+            if let Some(body) = physics.body_mut(&limb.body) {
+                let rotation = body.position().rotation;
+                let rest = parent_rotation * limb.rest_rotation;
+                if let Some((axis, angle)) = (rest.inverse() * rotation).axis_angle() {
+                    if angle > limb.angle_limit {
+                        // Rotate the limb back to the limit during next step.
+                        let clamped =
+                            rest * UnitQuaternion::from_axis_angle(&axis, limb.angle_limit);
+                        let correction = (clamped * rotation.inverse()).scaled_axis();
+                        body.set_angvel(parent_angvel + correction / dt, true);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes every body, collider and joint of the ragdoll from given physics world.
+    pub fn remove(self, physics: &mut Physics) {
+        for limb in self.limbs.iter().rev() {
+            if let Some(joint) = limb.joint.as_ref() {
+                physics.remove_joint(joint, false);
+            }
+        }
+        for limb in self.limbs.iter() {
+            physics.remove_body(&limb.body);
+        }
+    }
+}
+
+impl Visit for Ragdoll {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.root.visit("Root", visitor)?;
+        self.limbs.visit("Limbs", visitor)?;
+        self.blend.visit("Blend", visitor)?;
+        self.target_blend.visit("TargetBlend", visitor)?;
+        self.blend_speed.visit("BlendSpeed", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Ragdoll builder allows you to construct a ragdoll in declarative manner.
+pub struct RagdollBuilder {
+    root: Handle<Node>,
+    limbs: Vec<LimbDesc>,
+    density: f32,
+    collision_groups: InteractionGroups,
+}
+
+impl RagdollBuilder {
+    /// Creates new ragdoll builder for a skeleton with given root.
+    pub fn new(root: Handle<Node>) -> Self {
+        Self {
+            root,
+            limbs: Default::default(),
+            density: 1.0,
+            collision_groups: InteractionGroups::all(),
+        }
+    }
+
+    /// Sets desired limbs of the ragdoll.
+    pub fn with_limbs(mut self, limbs: Vec<LimbDesc>) -> Self {
+        self.limbs = limbs;
+        self
+    }
+
+    /// Adds new limb to the ragdoll.
+    pub fn with_limb(mut self, limb: LimbDesc) -> Self {
+        self.limbs.push(limb);
+        self
+    }
+
+    /// Sets density of colliders of limbs.
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Sets collision groups of colliders of limbs.
+    pub fn with_collision_groups(mut self, groups: InteractionGroups) -> Self {
+        self.collision_groups = groups;
+        self
+    }
+
+    // Collects world-space positions of skinned vertices for every limb, a vertex belongs to a
+    // limb which bone (or its nearest simulated ancestor) has the largest weight for the vertex.
+    fn collect_skin(graph: &Graph, limbs: &[LimbDesc]) -> Vec<Vec<Vector3<f32>>> {
+        let mut result = vec![Vec::new(); limbs.len()];
+        let bones = limbs.iter().map(|limb| limb.bone);
+
+        for node in graph.linear_iter() {
+            if let Node::Mesh(mesh) = node {
+                for surface in mesh.surfaces() {
+                    let surface_limbs = surface
+                        .bones()
+                        .iter()
+                        .map(|&bone| find_limb(graph, bone, bones.clone()))
+                        .collect::<Vec<_>>();
+                    if surface_limbs.iter().all(|limb| limb.is_none()) {
+                        continue;
+                    }
+
+                    let bone_matrices = surface
+                        .bones()
+                        .iter()
+                        .map(|&bone| {
+                            let bone = &graph[bone];
+                            bone.global_transform() * bone.inv_bind_pose_transform()
+                        })
+                        .collect::<Vec<Matrix4<f32>>>();
+
+                    let data = surface.data();
+                    let data = data.read().unwrap();
+                    for view in data.vertex_buffer().iter() {
+                        if let (Ok(position), Ok(indices), Ok(weights)) = (
+                            view.read_3_f32(VertexAttributeKind::Position),
+                            view.read_4_u8(VertexAttributeKind::BoneIndices),
+                            view.read_4_f32(VertexAttributeKind::BoneWeight),
+                        ) {
+                            let mut world_position = Vector3::default();
+                            let mut dominant = None;
+                            let mut max_weight = 0.0;
+                            for k in 0..4 {
+                                let index = indices[k] as usize;
+                                if let Some(matrix) = bone_matrices.get(index) {
+                                    world_position += matrix
+                                        .transform_point(&Point3::from(position))
+                                        .coords
+                                        .scale(weights[k]);
+                                    if weights[k] > max_weight {
+                                        max_weight = weights[k];
+                                        dominant = Some(index);
+                                    }
+                                }
+                            }
+                            if let Some(Some(limb)) = dominant.and_then(|i| surface_limbs.get(i)) {
+                                result[*limb].push(world_position);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Creates bodies, colliders and joints of the ragdoll in given physics world. Bodies are
+    /// created kinematic, see [`Ragdoll::set_blend`] to enable simulation. Limbs that are not
+    /// descendants of the root are ignored.
+    pub fn build(self, graph: &Graph, physics: &mut Physics) -> Ragdoll {
+        // Sort limbs so parents are before their children.
+        let limbs = graph
+            .traverse_handle_iter(self.root)
+            .filter_map(|node| self.limbs.iter().find(|limb| limb.bone == node).cloned())
+            .collect::<Vec<_>>();
+        if limbs.len() != self.limbs.len() {
+            Log::writeln(
+                MessageKind::Warning,
+                format!(
+                    "{} limbs of a ragdoll are not descendants of the root, they're ignored!",
+                    self.limbs.len() - limbs.len()
+                ),
+            );
+        }
+
+        let poses = limbs
+            .iter()
+            .map(|limb| world_isometry(graph, limb.bone))
+            .collect::<Vec<_>>();
+        let parents = limbs
+            .iter()
+            .map(|limb| {
+                find_limb(
+                    graph,
+                    graph[limb.bone].parent(),
+                    limbs.iter().map(|other| other.bone),
+                )
+            })
+            .collect::<Vec<_>>();
+        let skin = Self::collect_skin(graph, &limbs);
+
+        let mut ragdoll = Ragdoll {
+            root: self.root,
+            ..Default::default()
+        };
+
+        for (i, desc) in limbs.iter().enumerate() {
+            let begin = poses[i].translation.vector;
+            let vertices = &skin[i];
+
+            // Capsule goes from the bone to the average position of child limbs, leaf limbs are
+            // extended along direction from the parent limb up to the farthest vertex.
+            let children = parents
+                .iter()
+                .enumerate()
+                .filter(|(_, parent)| **parent == Some(i))
+                .map(|(child, _)| poses[child].translation.vector)
+                .collect::<Vec<_>>();
+            let end = if children.is_empty() {
+                let direction = parents[i]
+                    .and_then(|parent| {
+                        (begin - poses[parent].translation.vector).try_normalize(f32::EPSILON)
+                    })
+                    .unwrap_or_else(Vector3::y);
+                let extent = vertices
+                    .iter()
+                    .map(|v| (v - begin).dot(&direction))
+                    .fold(0.0f32, f32::max);
+                begin + direction.scale(extent)
+            } else {
+                children
+                    .iter()
+                    .sum::<Vector3<f32>>()
+                    .scale(1.0 / children.len() as f32)
+            };
+
+            let length = (end - begin).norm();
+            let radius = desc
+                .radius
+                .or_else(|| {
+                    if vertices.is_empty() {
+                        None
+                    } else {
+                        let distance_sum = vertices
+                            .iter()
+                            .map(|v| distance_to_segment(v, &begin, &end))
+                            .sum::<f32>();
+                        Some(distance_sum / vertices.len() as f32)
+                    }
+                })
+                .unwrap_or(length * 0.25)
+                .max(MIN_RADIUS);
+
+            // Shorten the capsule to reduce overlapping with adjacent limbs.
+            let (a, b) = if length > 2.0 * radius {
+                let axis = (end - begin).scale(1.0 / length);
+                (begin + axis.scale(radius), end - axis.scale(radius))
+            } else {
+                let center = (begin + end).scale(0.5);
+                (center, center)
+            };
+            let inv_pose = poses[i].inverse();
+
+            let body =
+                physics.add_body(RigidBodyBuilder::new_kinematic().position(poses[i]).build());
+            let collider = physics.add_collider(
+                ColliderBuilder::new(SharedShape::capsule(
+                    inv_pose * Point3::from(a),
+                    inv_pose * Point3::from(b),
+                    radius,
+                ))
+                .density(self.density)
+                .collision_groups(self.collision_groups)
+                .build(),
+                &body,
+            );
+
+            let (parent, joint, rest_rotation) = if let Some(parent) = parents[i] {
+                let parent_limb: &Limb = &ragdoll.limbs[parent];
+                let joint = BallJoint::new(
+                    poses[parent].inverse() * Point3::from(begin),
+                    Point3::origin(),
+                );
+                (
+                    parent_limb.bone,
+                    Some(physics.add_joint(&parent_limb.body, &body, joint)),
+                    poses[parent].rotation.inverse() * poses[i].rotation,
+                )
+            } else {
+                (Handle::NONE, None, UnitQuaternion::identity())
+            };
+
+            ragdoll.limbs.push(Limb {
+                bone: desc.bone,
+                parent,
+                body,
+                collider,
+                joint,
+                angle_limit: desc.angle_limit,
+                rest_rotation,
+            });
+        }
+
+        ragdoll
+    }
+}
+
+fn distance_to_segment(point: &Vector3<f32>, begin: &Vector3<f32>, end: &Vector3<f32>) -> f32 {
+    let edge = end - begin;
+    let length_squared = edge.norm_squared();
+    let t = if length_squared > f32::EPSILON {
+        ((point - begin).dot(&edge) / length_squared)
+            .max(0.0)
+            .min(1.0)
+    } else {
+        0.0
+    };
+    (point - (begin + edge.scale(t))).norm()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::pool::Handle,
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            node::Node,
+            physics::{
+                ragdoll::{LimbDesc, RagdollBuilder},
+                Physics,
+            },
+            transform::TransformBuilder,
+        },
+    };
+    use rapier3d::na::Vector3;
+
+    fn add_bone(
+        graph: &mut Graph,
+        position: Vector3<f32>,
+        children: &[Handle<Node>],
+    ) -> Handle<Node> {
+        BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            )
+            .with_children(children)
+            .build(graph)
+    }
+
+    #[test]
+    fn test_ragdoll() {
+        let mut graph = Graph::new();
+        let hand = add_bone(&mut graph, Vector3::new(0.0, -0.5, 0.0), &[]);
+        let arm = add_bone(&mut graph, Vector3::new(0.0, -0.5, 0.0), &[hand]);
+        let shoulder = add_bone(&mut graph, Vector3::new(0.0, 2.0, 0.0), &[arm]);
+        let root = add_bone(&mut graph, Vector3::default(), &[shoulder]);
+        let outsider = add_bone(&mut graph, Vector3::default(), &[]);
+        graph.update_hierarchical_data();
+
+        let mut physics = Physics::new();
+        let mut ragdoll = RagdollBuilder::new(root)
+            .with_limbs(vec![
+                LimbDesc::new(hand),
+                LimbDesc::new(shoulder).with_radius(0.1),
+                LimbDesc::new(arm),
+                LimbDesc::new(outsider),
+            ])
+            .build(&graph, &mut physics);
+
+        assert_eq!(ragdoll.limbs().len(), 3);
+        assert_eq!(ragdoll.limbs()[0].bone(), shoulder);
+        assert!(ragdoll.limbs()[0].joint().is_none());
+        assert_eq!(ragdoll.limb_of(arm).unwrap().parent_bone(), shoulder);
+        assert_eq!(ragdoll.limb_of(hand).unwrap().parent_bone(), arm);
+        assert!(ragdoll.limb_of(hand).unwrap().joint().is_some());
+
+        // Animation-driven: bones are untouched.
+        ragdoll.update(&mut graph, &mut physics, 1.0 / 60.0);
+        physics.update(1.0 / 60.0);
+        assert!(!ragdoll.is_simulated());
+        assert_eq!(graph[hand].global_position(), Vector3::new(0.0, 1.0, 0.0));
+
+        // Physics-driven: the ragdoll falls under gravity, but limbs stay connected.
+        ragdoll.set_blend(1.0);
+        ragdoll.apply_impulse(
+            &mut physics,
+            hand,
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        for _ in 0..30 {
+            ragdoll.update(&mut graph, &mut physics, 1.0 / 60.0);
+            physics.update(1.0 / 60.0);
+        }
+        ragdoll.update(&mut graph, &mut physics, 1.0 / 60.0);
+        assert!(ragdoll.is_simulated());
+        assert_ne!(graph[hand].global_position(), Vector3::new(0.0, 1.0, 0.0));
+        let arm_length = (graph[arm].global_position() - graph[shoulder].global_position()).norm();
+        assert!((arm_length - 0.5).abs() < 0.05);
+
+        // Blending back to animation.
+        ragdoll.blend_to(0.0, 0.5);
+        for _ in 0..31 {
+            ragdoll.update(&mut graph, &mut physics, 1.0 / 60.0);
+        }
+        assert!(!ragdoll.is_simulated());
+        assert!(!ragdoll.is_blending());
+
+        // Bodies are teleported to the pose of bones on the first kinematic frame, without
+        // waiting for the next physics step.
+        let hand_body = physics
+            .body(&ragdoll.limb_of(hand).unwrap().body())
+            .unwrap();
+        assert!(hand_body.is_kinematic());
+        let offset = hand_body.position().translation.vector - graph[hand].global_position();
+        assert!(offset.norm() < 1e-4);
+    }
+}
@@ -0,0 +1,772 @@
+//! Raycast vehicle.
+//!
+//! Raycast vehicle is a rigid body (chassis) which is held above the ground by a set of
+//! suspension springs. Every wheel casts a ray down from its connection point on the chassis,
+//! suspension force is calculated from ray length and applied to the chassis at the contact
+//! point. Tire forces are calculated from slip of the wheel using friction curves and applied
+//! at the contact point too. Wheels themselves are not simulated as rigid bodies, so they're
+//! cheap and stable at high speeds.
+//!
+//! Vehicles are owned by [`Physics`](super::Physics) and updated before each physics step.
+//! Every wheel can be bound to a graph node, which will be moved along suspension axis and
+//! rotated according to steering and wheel spin automatically. Wheel nodes must be children of
+//! a node that is bound to the chassis.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, pool::Handle},
+//!     engine::RigidBodyHandle,
+//!     scene::{
+//!         node::Node,
+//!         physics::vehicle::{RaycastVehicle, VehicleDesc, WheelDesc},
+//!         Scene,
+//!     },
+//! };
+//!
+//! fn create_car(
+//!     scene: &mut Scene,
+//!     chassis: RigidBodyHandle,
+//!     wheels: [Handle<Node>; 4],
+//! ) -> Handle<RaycastVehicle> {
+//!     let positions = [
+//!         Vector3::new(-0.8, 0.0, 1.3),
+//!         Vector3::new(0.8, 0.0, 1.3),
+//!         Vector3::new(-0.8, 0.0, -1.3),
+//!         Vector3::new(0.8, 0.0, -1.3),
+//!     ];
+//!     let mut desc = VehicleDesc::new(chassis);
+//!     for (i, (&node, &position)) in wheels.iter().zip(positions.iter()).enumerate() {
+//!         let front = i < 2;
+//!         desc.wheels.push(
+//!             WheelDesc::new(node, position)
+//!                 .with_steered(front)
+//!                 .with_driven(!front),
+//!         );
+//!     }
+//!     scene.physics.add_vehicle(desc)
+//! }
+//!
+//! fn drive(scene: &mut Scene, car: Handle<RaycastVehicle>) {
+//!     let vehicle = scene.physics.vehicle_mut(car);
+//!     vehicle.set_throttle(1.0);
+//!     vehicle.set_steering(-0.5);
+//! }
+//! ```
+
+use crate::{
+    core::{
+        pool::Handle,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    engine::RigidBodyHandle,
+    scene::{graph::Graph, node::Node, physics::Physics},
+};
+use rapier3d::{
+    geometry::{Collider, InteractionGroups, Ray},
+    na::{Point3, UnitQuaternion, Vector3},
+};
+use std::collections::HashMap;
+
+// Minimal longitudinal speed used in slip calculations to prevent division by zero and jitter
+// at low speeds.
+const MIN_SLIP_SPEED: f32 = 1.0;
+
+/// Friction curve describes how tire friction depends on slip. Friction grows linearly from zero
+/// up to extremum point, then falls to asymptote point and stays constant after it. Friction
+/// values are coefficients, actual force is calculated by multiplying a value by tire load.
+#[derive(Copy, Clone, Debug, Visit)]
+pub struct FrictionCurve {
+    /// Slip at extremum point.
+    pub extremum_slip: f32,
+    /// Friction at extremum point.
+    pub extremum_value: f32,
+    /// Slip at asymptote point.
+    pub asymptote_slip: f32,
+    /// Friction at asymptote point.
+    pub asymptote_value: f32,
+}
+
+impl Default for FrictionCurve {
+    fn default() -> Self {
+        Self::longitudinal()
+    }
+}
+
+impl FrictionCurve {
+    /// Creates default curve for longitudinal (forward) friction, where slip is slip ratio.
+    pub fn longitudinal() -> Self {
+        Self {
+            extremum_slip: 0.2,
+            extremum_value: 1.0,
+            asymptote_slip: 0.8,
+            asymptote_value: 0.75,
+        }
+    }
+
+    /// Creates default curve for lateral (sideways) friction, where slip is slip angle in
+    /// radians.
+    pub fn lateral() -> Self {
+        Self {
+            extremum_slip: 0.15,
+            extremum_value: 1.0,
+            asymptote_slip: 0.5,
+            asymptote_value: 0.8,
+        }
+    }
+
+    /// Returns friction coefficient for given slip.
+    pub fn evaluate(&self, slip: f32) -> f32 {
+        let slip = slip.abs();
+        if slip < self.extremum_slip {
+            self.extremum_value * slip / self.extremum_slip
+        } else if slip < self.asymptote_slip {
+            let t = (slip - self.extremum_slip) / (self.asymptote_slip - self.extremum_slip);
+            self.extremum_value + (self.asymptote_value - self.extremum_value) * t
+        } else {
+            self.asymptote_value
+        }
+    }
+}
+
+/// Wheel descriptor. All vectors are in local coordinates of the chassis.
+#[derive(Clone, Debug, Visit)]
+pub struct WheelDesc {
+    /// A node that will be moved and rotated with the wheel.
+    pub node: Handle<Node>,
+    /// A point on the chassis to which suspension is attached.
+    pub connection_point: Vector3<f32>,
+    /// Direction of suspension, it points from the chassis towards the ground.
+    pub direction: Vector3<f32>,
+    /// Axis of wheel rotation. Forward direction of the wheel is `axle x up`, where `up` is
+    /// opposite to suspension direction.
+    pub axle: Vector3<f32>,
+    /// Radius of the wheel.
+    pub radius: f32,
+    /// Mass of the wheel, it is used to calculate inertia of the wheel.
+    pub mass: f32,
+    /// Length of the suspension when it is not loaded.
+    pub suspension_rest_length: f32,
+    /// Stiffness of the suspension spring (N/m).
+    pub suspension_stiffness: f32,
+    /// Damping of the suspension (N*s/m).
+    pub suspension_damping: f32,
+    /// Friction curve in forward direction of the wheel.
+    pub longitudinal_friction: FrictionCurve,
+    /// Friction curve in sideways direction of the wheel.
+    pub lateral_friction: FrictionCurve,
+    /// Whether the wheel is rotated by steering input.
+    pub steered: bool,
+    /// Whether the wheel receives engine torque.
+    pub driven: bool,
+    /// Whether the wheel is affected by brake input.
+    pub braked: bool,
+}
+
+impl Default for WheelDesc {
+    fn default() -> Self {
+        Self::new(Handle::NONE, Vector3::default())
+    }
+}
+
+impl WheelDesc {
+    /// Creates new wheel attached to given point on the chassis.
+    pub fn new(node: Handle<Node>, connection_point: Vector3<f32>) -> Self {
+        Self {
+            node,
+            connection_point,
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            axle: Vector3::new(1.0, 0.0, 0.0),
+            radius: 0.35,
+            mass: 20.0,
+            suspension_rest_length: 0.3,
+            suspension_stiffness: 30000.0,
+            suspension_damping: 3000.0,
+            longitudinal_friction: FrictionCurve::longitudinal(),
+            lateral_friction: FrictionCurve::lateral(),
+            steered: false,
+            driven: false,
+            braked: true,
+        }
+    }
+
+    /// Sets direction of suspension.
+    pub fn with_direction(mut self, direction: Vector3<f32>) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Sets axis of wheel rotation.
+    pub fn with_axle(mut self, axle: Vector3<f32>) -> Self {
+        self.axle = axle;
+        self
+    }
+
+    /// Sets radius of the wheel.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets mass of the wheel.
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// Sets suspension parameters.
+    pub fn with_suspension(mut self, rest_length: f32, stiffness: f32, damping: f32) -> Self {
+        self.suspension_rest_length = rest_length;
+        self.suspension_stiffness = stiffness;
+        self.suspension_damping = damping;
+        self
+    }
+
+    /// Sets friction curves of the tire.
+    pub fn with_friction(mut self, longitudinal: FrictionCurve, lateral: FrictionCurve) -> Self {
+        self.longitudinal_friction = longitudinal;
+        self.lateral_friction = lateral;
+        self
+    }
+
+    /// Sets whether the wheel is rotated by steering input.
+    pub fn with_steered(mut self, steered: bool) -> Self {
+        self.steered = steered;
+        self
+    }
+
+    /// Sets whether the wheel receives engine torque.
+    pub fn with_driven(mut self, driven: bool) -> Self {
+        self.driven = driven;
+        self
+    }
+
+    /// Sets whether the wheel is affected by brake input.
+    pub fn with_braked(mut self, braked: bool) -> Self {
+        self.braked = braked;
+        self
+    }
+}
+
+/// Vehicle descriptor, it contains every persistent parameter of a vehicle.
+#[derive(Clone, Debug, Visit)]
+pub struct VehicleDesc {
+    /// Rigid body of the chassis, it must be dynamic.
+    pub chassis: RigidBodyHandle,
+    /// Wheels of the vehicle.
+    pub wheels: Vec<WheelDesc>,
+    /// Maximum torque of the engine (N*m), it is evenly distributed between driven wheels.
+    pub max_engine_torque: f32,
+    /// Maximum brake torque (N*m) of each braked wheel.
+    pub max_brake_torque: f32,
+    /// Maximum steering angle in radians.
+    pub max_steering_angle: f32,
+    /// Collision groups used for suspension ray casts.
+    pub collision_groups: u32,
+}
+
+impl Default for VehicleDesc {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl VehicleDesc {
+    /// Creates new vehicle descriptor without wheels.
+    pub fn new(chassis: RigidBodyHandle) -> Self {
+        Self {
+            chassis,
+            wheels: Default::default(),
+            max_engine_torque: 1500.0,
+            max_brake_torque: 3000.0,
+            max_steering_angle: 35.0f32.to_radians(),
+            collision_groups: u32::MAX,
+        }
+    }
+}
+
+/// Wheel of a raycast vehicle.
+#[derive(Clone, Debug)]
+pub struct Wheel {
+    desc: WheelDesc,
+    suspension_length: f32,
+    steering_angle: f32,
+    spin_angle: f32,
+    angular_velocity: f32,
+    in_contact: bool,
+    contact_point: Vector3<f32>,
+    contact_normal: Vector3<f32>,
+}
+
+impl Wheel {
+    fn new(desc: WheelDesc) -> Self {
+        Self {
+            suspension_length: desc.suspension_rest_length,
+            desc,
+            steering_angle: 0.0,
+            spin_angle: 0.0,
+            angular_velocity: 0.0,
+            in_contact: false,
+            contact_point: Default::default(),
+            contact_normal: Vector3::y(),
+        }
+    }
+
+    /// Returns descriptor of the wheel.
+    pub fn desc(&self) -> &WheelDesc {
+        &self.desc
+    }
+
+    /// Returns current length of the suspension.
+    pub fn suspension_length(&self) -> f32 {
+        self.suspension_length
+    }
+
+    /// Returns current steering angle of the wheel in radians.
+    pub fn steering_angle(&self) -> f32 {
+        self.steering_angle
+    }
+
+    /// Returns current rotation angle of the wheel around its axle in radians.
+    pub fn spin_angle(&self) -> f32 {
+        self.spin_angle
+    }
+
+    /// Returns angular velocity of the wheel around its axle (rad/s).
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    /// Returns true if the wheel touches the ground.
+    pub fn is_in_contact(&self) -> bool {
+        self.in_contact
+    }
+
+    /// Returns world-space point of contact with the ground, it is valid only if the wheel is
+    /// in contact.
+    pub fn contact_point(&self) -> Vector3<f32> {
+        self.contact_point
+    }
+
+    /// Returns world-space normal of the ground at contact point, it is valid only if the wheel
+    /// is in contact.
+    pub fn contact_normal(&self) -> Vector3<f32> {
+        self.contact_normal
+    }
+
+    fn up(&self) -> Vector3<f32> {
+        -self
+            .desc
+            .direction
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| Vector3::new(0.0, -1.0, 0.0))
+    }
+
+    fn steered_axle(&self) -> Vector3<f32> {
+        match UnitQuaternion::try_from_axis_angle(&self.up(), self.steering_angle) {
+            Some(rotation) => rotation * self.desc.axle,
+            None => self.desc.axle,
+        }
+    }
+
+    fn inertia(&self) -> f32 {
+        (0.5 * self.desc.mass * self.desc.radius * self.desc.radius).max(f32::EPSILON)
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default)]
+pub struct RaycastVehicle {
+    chassis: RigidBodyHandle,
+    wheels: Vec<Wheel>,
+    max_engine_torque: f32,
+    max_brake_torque: f32,
+    max_steering_angle: f32,
+    collision_groups: u32,
+    throttle: f32,
+    brake: f32,
+    steering: f32,
+}
+
+impl RaycastVehicle {
+    /// Creates new vehicle from given descriptor.
+    pub fn from_desc(desc: VehicleDesc) -> Self {
+        Self {
+            chassis: desc.chassis,
+            wheels: desc.wheels.into_iter().map(Wheel::new).collect(),
+            max_engine_torque: desc.max_engine_torque,
+            max_brake_torque: desc.max_brake_torque,
+            max_steering_angle: desc.max_steering_angle,
+            collision_groups: desc.collision_groups,
+            throttle: 0.0,
+            brake: 0.0,
+            steering: 0.0,
+        }
+    }
+
+    /// Creates descriptor of the vehicle.
+    pub fn to_desc(&self) -> VehicleDesc {
+        VehicleDesc {
+            chassis: self.chassis,
+            wheels: self.wheels.iter().map(|w| w.desc.clone()).collect(),
+            max_engine_torque: self.max_engine_torque,
+            max_brake_torque: self.max_brake_torque,
+            max_steering_angle: self.max_steering_angle,
+            collision_groups: self.collision_groups,
+        }
+    }
+
+    /// Returns handle of the chassis body.
+    pub fn chassis(&self) -> RigidBodyHandle {
+        self.chassis
+    }
+
+    /// Returns wheels of the vehicle.
+    pub fn wheels(&self) -> &[Wheel] {
+        &self.wheels
+    }
+
+    /// Sets throttle input in [-1; 1] range, negative values drive the vehicle backwards.
+    pub fn set_throttle(&mut self, throttle: f32) {
+        self.throttle = throttle.max(-1.0).min(1.0);
+    }
+
+    /// Returns throttle input.
+    pub fn throttle(&self) -> f32 {
+        self.throttle
+    }
+
+    /// Sets brake input in [0; 1] range.
+    pub fn set_brake(&mut self, brake: f32) {
+        self.brake = brake.max(0.0).min(1.0);
+    }
+
+    /// Returns brake input.
+    pub fn brake(&self) -> f32 {
+        self.brake
+    }
+
+    /// Sets steering input in [-1; 1] range, positive values turn steered wheels
+    /// counterclockwise around up axis of the wheels.
+    pub fn set_steering(&mut self, steering: f32) {
+        self.steering = steering.max(-1.0).min(1.0);
+    }
+
+    /// Returns steering input.
+    pub fn steering(&self) -> f32 {
+        self.steering
+    }
+
+    /// Sets maximum torque of the engine.
+    pub fn set_max_engine_torque(&mut self, torque: f32) {
+        self.max_engine_torque = torque;
+    }
+
+    /// Returns maximum torque of the engine.
+    pub fn max_engine_torque(&self) -> f32 {
+        self.max_engine_torque
+    }
+
+    /// Sets maximum brake torque of each braked wheel.
+    pub fn set_max_brake_torque(&mut self, torque: f32) {
+        self.max_brake_torque = torque;
+    }
+
+    /// Returns maximum brake torque of each braked wheel.
+    pub fn max_brake_torque(&self) -> f32 {
+        self.max_brake_torque
+    }
+
+    /// Sets maximum steering angle in radians.
+    pub fn set_max_steering_angle(&mut self, angle: f32) {
+        self.max_steering_angle = angle;
+    }
+
+    /// Returns maximum steering angle in radians.
+    pub fn max_steering_angle(&self) -> f32 {
+        self.max_steering_angle
+    }
+
+    /// Returns speed of the chassis projected on its forward axis, forward axis is defined by
+    /// the first wheel.
+    pub fn forward_speed(&self, physics: &Physics) -> f32 {
+        match (physics.body(&self.chassis), self.wheels.first()) {
+            (Some(body), Some(wheel)) => {
+                let forward = body.position().rotation * wheel.desc.axle.cross(&wheel.up());
+                body.linvel().dot(&forward)
+            }
+            _ => 0.0,
+        }
+    }
+
+    // Calculates suspension and tire forces and applies them to the chassis as impulses.
+    pub(in crate) fn update(&mut self, physics: &mut Physics, dt: f32) {
+        let chassis = match physics.body_handle_map.value_of(&self.chassis) {
+            Some(&chassis) => chassis,
+            None => return,
+        };
+        let (position, mass) = match physics.bodies.get(chassis) {
+            Some(body) => (*body.position(), body.mass()),
+            None => return,
+        };
+
+        let driven_count = self.wheels.iter().filter(|w| w.desc.driven).count().max(1);
+        let drive_torque = self.throttle * self.max_engine_torque / driven_count as f32;
+        let brake_torque = self.brake * self.max_brake_torque;
+        let groups = InteractionGroups(self.collision_groups);
+
+        // Every wheel is responsible for its share of chassis mass when sideways sliding is
+        // stopped.
+        let mass_per_wheel = mass / self.wheels.len().max(1) as f32;
+        let mut impulses = Vec::with_capacity(self.wheels.len());
+        {
+            let query = physics.updated_query();
+            for wheel in self.wheels.iter_mut() {
+                wheel.steering_angle = if wheel.desc.steered {
+                    self.steering * self.max_steering_angle
+                } else {
+                    0.0
+                };
+
+                let origin = position * Point3::from(wheel.desc.connection_point);
+                let down = -(position.rotation * wheel.up());
+                let ray = Ray::new(origin, down);
+                let max_len = wheel.desc.suspension_rest_length + wheel.desc.radius;
+                let filter = |_: rapier3d::geometry::ColliderHandle, collider: &Collider| {
+                    collider.parent() != chassis && !collider.is_sensor()
+                };
+                let hit = query.cast_ray_and_get_normal(
+                    &physics.colliders,
+                    &ray,
+                    max_len,
+                    true,
+                    groups,
+                    Some(&filter),
+                );
+
+                let previous_length = wheel.suspension_length;
+                match hit {
+                    Some((_, intersection)) => {
+                        wheel.in_contact = true;
+                        wheel.suspension_length = (intersection.toi - wheel.desc.radius)
+                            .max(0.0)
+                            .min(wheel.desc.suspension_rest_length);
+                        wheel.contact_point = ray.point_at(intersection.toi).coords;
+                        wheel.contact_normal = intersection.normal;
+                    }
+                    None => {
+                        wheel.in_contact = false;
+                        wheel.suspension_length = wheel.desc.suspension_rest_length;
+                    }
+                }
+
+                let forward_local = wheel.steered_axle().cross(&wheel.up());
+                let forward = position.rotation * forward_local;
+                let axle = position.rotation * wheel.steered_axle();
+
+                // Wheel spin is driven by the engine and brakes, ground friction is applied
+                // later when the load is known.
+                if wheel.desc.driven {
+                    wheel.angular_velocity += drive_torque / wheel.inertia() * dt;
+                }
+                if wheel.desc.braked && brake_torque > 0.0 {
+                    let delta = brake_torque / wheel.inertia() * dt;
+                    if wheel.angular_velocity.abs() <= delta {
+                        wheel.angular_velocity = 0.0;
+                    } else {
+                        wheel.angular_velocity -= delta * wheel.angular_velocity.signum();
+                    }
+                }
+
+                if wheel.in_contact {
+                    let compression = wheel.desc.suspension_rest_length - wheel.suspension_length;
+                    let compression_speed = (previous_length - wheel.suspension_length) / dt;
+                    let load = (wheel.desc.suspension_stiffness * compression
+                        + wheel.desc.suspension_damping * compression_speed)
+                        .max(0.0);
+                    let up = -down;
+                    let normal = wheel.contact_normal;
+
+                    let body = &physics.bodies[chassis];
+                    let velocity = body.velocity_at_point(&Point3::from(wheel.contact_point));
+
+                    // Project wheel axes on the ground plane.
+                    let forward = (forward - normal.scale(forward.dot(&normal)))
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or(forward);
+                    let side = (axle - normal.scale(axle.dot(&normal)))
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or(axle);
+
+                    let longitudinal_speed = velocity.dot(&forward);
+                    let lateral_speed = velocity.dot(&side);
+
+                    let slip_ratio = (wheel.angular_velocity * wheel.desc.radius
+                        - longitudinal_speed)
+                        / longitudinal_speed.abs().max(MIN_SLIP_SPEED);
+                    let slip_angle =
+                        lateral_speed.atan2(longitudinal_speed.abs().max(MIN_SLIP_SPEED));
+
+                    let longitudinal_force = slip_ratio.signum()
+                        * wheel.desc.longitudinal_friction.evaluate(slip_ratio)
+                        * load;
+                    let mut lateral_force = -slip_angle.signum()
+                        * wheel.desc.lateral_friction.evaluate(slip_angle)
+                        * load;
+
+                    // Lateral impulse must not be larger than the one that stops sideways
+                    // sliding, otherwise the chassis will jitter at low speeds.
+                    let max_lateral_force = lateral_speed.abs() * mass_per_wheel / dt;
+                    lateral_force = lateral_force.max(-max_lateral_force).min(max_lateral_force);
+
+                    // Ground reaction torque slows down (or speeds up) the wheel, but it can't
+                    // make the wheel spin past the speed of rolling without slip.
+                    let rolling_velocity = longitudinal_speed / wheel.desc.radius;
+                    let new_velocity = wheel.angular_velocity
+                        - longitudinal_force * wheel.desc.radius / wheel.inertia() * dt;
+                    wheel.angular_velocity = if (wheel.angular_velocity - rolling_velocity)
+                        * (new_velocity - rolling_velocity)
+                        < 0.0
+                    {
+                        rolling_velocity
+                    } else {
+                        new_velocity
+                    };
+
+                    let force = up.scale(load)
+                        + forward.scale(longitudinal_force)
+                        + side.scale(lateral_force);
+                    impulses.push((force.scale(dt), wheel.contact_point));
+                }
+
+                wheel.spin_angle =
+                    (wheel.spin_angle + wheel.angular_velocity * dt) % std::f32::consts::TAU;
+            }
+        }
+
+        if let Some(body) = physics.bodies.get_mut(chassis) {
+            for (impulse, point) in impulses {
+                body.apply_impulse_at_point(impulse, Point3::from(point), true);
+            }
+        }
+    }
+
+    // Moves and rotates wheel nodes.
+    pub(in crate) fn sync_wheel_nodes(&self, graph: &mut Graph) {
+        for wheel in self.wheels.iter() {
+            if !graph.is_valid_handle(wheel.desc.node) {
+                continue;
+            }
+            let up = wheel.up();
+            let position = wheel.desc.connection_point - up.scale(wheel.suspension_length);
+            let steering = UnitQuaternion::try_from_axis_angle(&up, wheel.steering_angle)
+                .unwrap_or_else(UnitQuaternion::identity);
+            let spin = UnitQuaternion::try_from_axis_angle(&wheel.desc.axle, wheel.spin_angle)
+                .unwrap_or_else(UnitQuaternion::identity);
+            graph[wheel.desc.node]
+                .local_transform_mut()
+                .set_position(position)
+                .set_rotation(steering * spin);
+        }
+    }
+
+    pub(in crate) fn remap_nodes(&mut self, old_new_map: &HashMap<Handle<Node>, Handle<Node>>) {
+        for wheel in self.wheels.iter_mut() {
+            wheel.desc.node = old_new_map
+                .get(&wheel.desc.node)
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+}
+
+impl Visit for RaycastVehicle {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        // Only descriptor is saved, state of the vehicle is restored on first update.
+        let mut desc = if visitor.is_reading() {
+            Default::default()
+        } else {
+            self.to_desc()
+        };
+        desc.visit("Desc", visitor)?;
+
+        if visitor.is_reading() {
+            *self = Self::from_desc(desc);
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::pool::Handle,
+        scene::physics::{
+            vehicle::{VehicleDesc, WheelDesc},
+            Physics,
+        },
+    };
+    use rapier3d::{dynamics::RigidBodyBuilder, geometry::ColliderBuilder, na::Vector3};
+
+    #[test]
+    fn test_raycast_vehicle() {
+        let mut physics = Physics::new();
+
+        let ground = physics.add_body(
+            RigidBodyBuilder::new_static()
+                .translation(0.0, -0.5, 0.0)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(100.0, 0.5, 100.0).build(), &ground);
+
+        let chassis = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(0.0, 1.0, 0.0)
+                .build(),
+        );
+        physics.add_collider(
+            ColliderBuilder::cuboid(1.0, 0.25, 2.0)
+                .density(250.0)
+                .build(),
+            &chassis,
+        );
+
+        let mut desc = VehicleDesc::new(chassis);
+        for &(x, z) in [(-0.8, 1.3), (0.8, 1.3), (-0.8, -1.3), (0.8, -1.3)].iter() {
+            desc.wheels.push(
+                WheelDesc::new(Handle::NONE, Vector3::new(x, -0.25, z))
+                    .with_steered(z > 0.0)
+                    .with_driven(z < 0.0),
+            );
+        }
+        let vehicle = physics.add_vehicle(desc);
+
+        // Settle on suspension.
+        for _ in 0..120 {
+            physics.update(1.0 / 60.0);
+        }
+        let vehicle_ref = physics.vehicle(vehicle);
+        assert!(vehicle_ref.wheels().iter().all(|w| w.is_in_contact()));
+        let height = physics.body(&chassis).unwrap().position().translation.y;
+        assert!(height > 0.6 && height < 1.0);
+        assert!(vehicle_ref.forward_speed(&physics).abs() < 0.1);
+
+        // Accelerate.
+        physics.vehicle_mut(vehicle).set_throttle(1.0);
+        for _ in 0..60 {
+            physics.update(1.0 / 60.0);
+        }
+        assert!(physics.vehicle(vehicle).forward_speed(&physics) > 1.0);
+        assert!(physics.vehicle(vehicle).wheels()[2].angular_velocity() > 0.0);
+
+        // Brake.
+        physics.vehicle_mut(vehicle).set_throttle(0.0);
+        physics.vehicle_mut(vehicle).set_brake(1.0);
+        for _ in 0..180 {
+            physics.update(1.0 / 60.0);
+        }
+        assert!(physics.vehicle(vehicle).forward_speed(&physics).abs() < 0.5);
+    }
+}
@@ -7,6 +7,11 @@
 //!
 //! Huge amount of sprites may cause performance issues, also uou should
 //! not use sprites to make particle systems, use ParticleSystem instead.
+//!
+//! # Animation
+//!
+//! Sprite can play sprite sheet (flipbook) animation, in this case its texture
+//! must be an atlas of frames. See [`SpriteSheetAnimation`] docs for more info.
 
 use crate::core::pool::Handle;
 use crate::scene::graph::Graph;
@@ -14,10 +19,14 @@ use crate::scene::node::Node;
 use crate::{
     core::{
         color::Color,
+        math::Rect,
         visitor::{Visit, VisitResult, Visitor},
     },
     resource::texture::Texture,
-    scene::base::{Base, BaseBuilder},
+    scene::{
+        base::{Base, BaseBuilder},
+        sprite_sheet::SpriteSheetAnimation,
+    },
 };
 use std::ops::{Deref, DerefMut};
 
@@ -29,6 +38,7 @@ pub struct Sprite {
     color: Color,
     size: f32,
     rotation: f32,
+    animation: Option<SpriteSheetAnimation>,
 }
 
 impl Deref for Sprite {
@@ -60,6 +70,7 @@ impl Sprite {
             color: self.color,
             size: self.size,
             rotation: self.rotation,
+            animation: self.animation.clone(),
         }
     }
 
@@ -110,6 +121,37 @@ impl Sprite {
     pub fn texture_ref(&self) -> Option<&Texture> {
         self.texture.as_ref()
     }
+
+    /// Sets new sprite sheet animation. `None` means that whole texture will be used.
+    pub fn set_animation(&mut self, animation: Option<SpriteSheetAnimation>) {
+        self.animation = animation;
+    }
+
+    /// Returns shared reference to current sprite sheet animation.
+    pub fn animation(&self) -> Option<&SpriteSheetAnimation> {
+        self.animation.as_ref()
+    }
+
+    /// Returns mutable reference to current sprite sheet animation.
+    pub fn animation_mut(&mut self) -> Option<&mut SpriteSheetAnimation> {
+        self.animation.as_mut()
+    }
+
+    /// Returns rectangle of texture coordinates which should be used to render the sprite.
+    pub fn frame_rect(&self) -> Rect<f32> {
+        self.animation.as_ref().map_or(
+            Rect::new(0.0, 0.0, 1.0, 1.0),
+            SpriteSheetAnimation::current_frame_rect,
+        )
+    }
+
+    /// Updates sprite sheet animation of the sprite. This method should not be used
+    /// directly, it will be automatically called by scene update.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(animation) = self.animation.as_mut() {
+            animation.update(dt);
+        }
+    }
 }
 
 impl Visit for Sprite {
@@ -121,6 +163,14 @@ impl Visit for Sprite {
         self.size.visit("Size", visitor)?;
         self.rotation.visit("Rotation", visitor)?;
         self.base.visit("Base", visitor)?;
+        // Backward compatibility - animation may be missing in old files.
+        if let Err(e) = self.animation.visit("Animation", visitor) {
+            if visitor.is_reading() {
+                self.animation = None;
+            } else {
+                return Err(e);
+            }
+        }
 
         visitor.leave_region()
     }
@@ -134,6 +184,7 @@ pub struct SpriteBuilder {
     color: Color,
     size: f32,
     rotation: f32,
+    animation: Option<SpriteSheetAnimation>,
 }
 
 impl SpriteBuilder {
@@ -145,6 +196,7 @@ impl SpriteBuilder {
             color: Color::WHITE,
             size: 0.2,
             rotation: 0.0,
+            animation: None,
         }
     }
 
@@ -178,6 +230,12 @@ impl SpriteBuilder {
         self
     }
 
+    /// Sets desired sprite sheet animation.
+    pub fn with_animation(mut self, animation: SpriteSheetAnimation) -> Self {
+        self.animation = Some(animation);
+        self
+    }
+
     fn build_sprite(self) -> Sprite {
         Sprite {
             base: self.base_builder.build_base(),
@@ -185,6 +243,7 @@ impl SpriteBuilder {
             color: self.color,
             size: self.size,
             rotation: self.rotation,
+            animation: self.animation,
         }
     }
 
@@ -0,0 +1,308 @@
+//! Sprite sheet (flipbook) animation.
+//!
+//! Sprite sheet is a texture atlas that contains frames of an animation placed in a grid.
+//! Frames are enumerated from left to right, from top to bottom. Sprite sheets are used
+//! by particle systems and sprites (both 3D and 2D) to make animated effects like
+//! explosions, smoke, fire, etc.
+
+use crate::core::{algebra::Vector2, math::Rect, numeric_range::NumericRange, visitor::prelude::*};
+
+/// Layout of frames in a texture atlas.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteSheet {
+    columns: u32,
+    rows: u32,
+}
+
+impl Default for SpriteSheet {
+    fn default() -> Self {
+        Self {
+            columns: 1,
+            rows: 1,
+        }
+    }
+}
+
+impl SpriteSheet {
+    /// Creates new sprite sheet layout with given amount of columns and rows. Each value
+    /// will be clamped to at least one.
+    pub fn new(columns: u32, rows: u32) -> Self {
+        Self {
+            columns: columns.max(1),
+            rows: rows.max(1),
+        }
+    }
+
+    /// Returns amount of columns of the sheet.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Sets amount of columns of the sheet, it will be clamped to at least one.
+    pub fn set_columns(&mut self, columns: u32) {
+        self.columns = columns.max(1);
+    }
+
+    /// Returns amount of rows of the sheet.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Sets amount of rows of the sheet, it will be clamped to at least one.
+    pub fn set_rows(&mut self, rows: u32) {
+        self.rows = rows.max(1);
+    }
+
+    /// Returns total amount of frames in the sheet.
+    pub fn frame_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Returns random frame index.
+    pub fn random_frame(&self) -> u32 {
+        (NumericRange::new(0.0, self.frame_count() as f32).random() as u32)
+            .min(self.frame_count() - 1)
+    }
+
+    /// Returns rectangle of a frame in texture coordinates. Frame index wraps around
+    /// total amount of frames.
+    pub fn frame_rect(&self, frame: u32) -> Rect<f32> {
+        let frame = frame % self.frame_count();
+        let size = Vector2::new(1.0 / self.columns as f32, 1.0 / self.rows as f32);
+        Rect {
+            position: Vector2::new(
+                (frame % self.columns) as f32 * size.x,
+                (frame / self.columns) as f32 * size.y,
+            ),
+            size,
+        }
+    }
+}
+
+impl Visit for SpriteSheet {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.columns.visit("Columns", visitor)?;
+        self.rows.visit("Rows", visitor)?;
+
+        // Saved data could be corrupted, zero would cause division by zero.
+        self.columns = self.columns.max(1);
+        self.rows = self.rows.max(1);
+
+        visitor.leave_region()
+    }
+}
+
+/// Sprite sheet animation player, it switches frames of a sprite sheet with fixed rate.
+#[derive(Clone, Debug, Visit)]
+pub struct SpriteSheetAnimation {
+    sheet: SpriteSheet,
+    frame_rate: f32,
+    looped: bool,
+    playing: bool,
+    start_frame: u32,
+    /// Current playback position in frames.
+    time: f32,
+}
+
+impl Default for SpriteSheetAnimation {
+    fn default() -> Self {
+        Self {
+            sheet: Default::default(),
+            frame_rate: 30.0,
+            looped: true,
+            playing: true,
+            start_frame: 0,
+            time: 0.0,
+        }
+    }
+}
+
+impl SpriteSheetAnimation {
+    /// Creates new looped animation with given layout and frame rate (frames per second).
+    pub fn new(sheet: SpriteSheet, frame_rate: f32) -> Self {
+        Self {
+            sheet,
+            frame_rate,
+            ..Default::default()
+        }
+    }
+
+    /// Returns layout of the sprite sheet.
+    pub fn sheet(&self) -> SpriteSheet {
+        self.sheet
+    }
+
+    /// Sets new layout of the sprite sheet.
+    pub fn set_sheet(&mut self, sheet: SpriteSheet) {
+        self.sheet = sheet;
+    }
+
+    /// Returns frame rate in frames per second.
+    pub fn frame_rate(&self) -> f32 {
+        self.frame_rate
+    }
+
+    /// Sets new frame rate in frames per second.
+    pub fn set_frame_rate(&mut self, frame_rate: f32) {
+        self.frame_rate = frame_rate.max(0.0);
+    }
+
+    /// Returns true if animation is looped.
+    pub fn is_looped(&self) -> bool {
+        self.looped
+    }
+
+    /// Sets whether animation should start over when it reaches last frame or hold it.
+    pub fn set_looped(&mut self, looped: bool) {
+        self.looped = looped;
+    }
+
+    /// Returns index of a frame from which playback starts.
+    pub fn start_frame(&self) -> u32 {
+        self.start_frame
+    }
+
+    /// Sets index of a frame from which playback starts.
+    pub fn set_start_frame(&mut self, frame: u32) {
+        self.start_frame = frame;
+    }
+
+    /// Sets random start frame, useful to prevent synchronous animation of many sprites.
+    pub fn randomize_start_frame(&mut self) {
+        self.start_frame = self.sheet.random_frame();
+    }
+
+    /// Returns true if animation is playing.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Resumes playback.
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Pauses playback, current frame will be held.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Moves playback position to the start frame.
+    pub fn rewind(&mut self) {
+        self.time = 0.0;
+    }
+
+    /// Advances animation by given time in seconds. There is no need to call it manually,
+    /// it will be automatically called by scene update call.
+    pub fn update(&mut self, dt: f32) {
+        if !self.playing {
+            return;
+        }
+
+        self.time += self.frame_rate * dt;
+
+        let frame_count = self.sheet.frame_count() as f32;
+        if self.time >= frame_count {
+            if self.looped {
+                self.time %= frame_count;
+            } else {
+                self.time = frame_count - 1.0;
+                self.playing = false;
+            }
+        }
+    }
+
+    /// Returns index of current frame.
+    pub fn current_frame(&self) -> u32 {
+        (self.start_frame + self.time as u32) % self.sheet.frame_count()
+    }
+
+    /// Returns rectangle of current frame in texture coordinates.
+    pub fn current_frame_rect(&self) -> Rect<f32> {
+        self.sheet.frame_rect(self.current_frame())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector2, math::Rect},
+        scene::sprite_sheet::{SpriteSheet, SpriteSheetAnimation},
+    };
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect<f32> {
+        Rect {
+            position: Vector2::new(x, y),
+            size: Vector2::new(w, h),
+        }
+    }
+
+    #[test]
+    fn test_frame_rect() {
+        let sheet = SpriteSheet::new(4, 2);
+
+        assert_eq!(sheet.frame_count(), 8);
+        assert_eq!(sheet.frame_rect(0), rect(0.0, 0.0, 0.25, 0.5));
+        assert_eq!(sheet.frame_rect(3), rect(0.75, 0.0, 0.25, 0.5));
+        assert_eq!(sheet.frame_rect(4), rect(0.0, 0.5, 0.25, 0.5));
+        assert_eq!(sheet.frame_rect(6), rect(0.5, 0.5, 0.25, 0.5));
+        // Index wraps around frame count.
+        assert_eq!(sheet.frame_rect(9), sheet.frame_rect(1));
+
+        // Zero is clamped to one, so whole texture is a single frame.
+        let single = SpriteSheet::new(0, 0);
+        assert_eq!(single.frame_count(), 1);
+        assert_eq!(single.frame_rect(5), rect(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_looped_animation() {
+        let mut animation = SpriteSheetAnimation::new(SpriteSheet::new(2, 2), 8.0);
+
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), 2);
+        assert_eq!(animation.current_frame_rect(), rect(0.0, 0.5, 0.5, 0.5));
+
+        // 2 + 3 frames passes the end of 4-frame sheet and starts over.
+        animation.update(0.375);
+        assert_eq!(animation.current_frame(), 1);
+        assert!(animation.is_playing());
+    }
+
+    #[test]
+    fn test_non_looped_animation() {
+        let mut animation = SpriteSheetAnimation::new(SpriteSheet::new(2, 2), 8.0);
+        animation.set_looped(false);
+
+        animation.update(0.375);
+        assert_eq!(animation.current_frame(), 3);
+        assert!(animation.is_playing());
+
+        // Last frame is held and playback stops.
+        animation.update(1.0);
+        assert_eq!(animation.current_frame(), 3);
+        assert!(!animation.is_playing());
+
+        animation.update(1.0);
+        assert_eq!(animation.current_frame(), 3);
+    }
+
+    #[test]
+    fn test_start_frame_wraps_around() {
+        let mut animation = SpriteSheetAnimation::new(SpriteSheet::new(2, 2), 8.0);
+        animation.set_start_frame(3);
+        assert_eq!(animation.current_frame(), 3);
+
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), 1);
+
+        animation.pause();
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), 1);
+
+        animation.rewind();
+        assert_eq!(animation.current_frame(), 3);
+    }
+}
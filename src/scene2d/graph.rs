@@ -183,12 +183,14 @@ impl Graph {
         self.pool.forget_ticket(ticket)
     }
 
-    pub fn update(&mut self, render_target_size: Vector2<f32>, _dt: f32) {
+    pub fn update(&mut self, render_target_size: Vector2<f32>, dt: f32) {
         self.update_hierarchical_data();
 
         for node in self.pool.iter_mut() {
-            if let Node::Camera(camera) = node {
-                camera.update(render_target_size);
+            match node {
+                Node::Camera(camera) => camera.update(render_target_size),
+                Node::Sprite(sprite) => sprite.update(dt),
+                _ => (),
             }
         }
     }
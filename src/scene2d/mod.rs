@@ -8,7 +8,7 @@ use crate::{
     },
     engine::PhysicsBinder,
     resource::texture::Texture,
-    scene::base::PhysicsBinding,
+    scene::{base::PhysicsBinding, SceneDrawingContext},
     scene2d::{graph::Graph, node::Node, physics::Physics, physics::PhysicsPerformanceStatistics},
     sound::{context::SoundContext, engine::SoundEngine},
 };
@@ -57,6 +57,11 @@ pub struct Scene2d {
 
     pub physics_binder: PhysicsBinder<Node>,
 
+    /// Drawing context for simple graphics, it is drawn on top of the scene by every camera.
+    /// Lines must be in XY plane, it can be used to draw physics world for example.
+    #[visit(skip)]
+    pub drawing_context: SceneDrawingContext,
+
     #[visit(skip)]
     pub performance_statistics: PerformanceStatistics,
 }
@@ -71,6 +76,7 @@ impl Scene2d {
             sound_context: SoundContext::new(),
             physics: Default::default(),
             physics_binder: Default::default(),
+            drawing_context: Default::default(),
             performance_statistics: Default::default(),
         }
     }
@@ -160,6 +166,7 @@ impl Scene2d {
                 // will redraw frame completely.
                 render_target: Default::default(),
                 sound_context: self.sound_context.deep_clone(),
+                drawing_context: self.drawing_context.clone(),
                 performance_statistics: Default::default(),
                 ambient_light_color: self.ambient_light_color,
                 enabled: self.enabled,
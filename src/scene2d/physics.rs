@@ -4,10 +4,11 @@ use crate::core::algebra::{Const, DVector};
 use crate::{
     core::{
         algebra::{
-            Dynamic, Isometry2, Point2, Translation, Translation2, Unit, UnitComplex, VecStorage,
-            Vector2,
+            Dynamic, Isometry2, Isometry3, Matrix4, Point2, Point3, Translation, Translation2,
+            Unit, UnitComplex, VecStorage, Vector2, Vector3,
         },
         arrayvec::ArrayVec,
+        color::Color,
         instant,
         math::ray::Ray,
         pool::ErasedHandle,
@@ -16,6 +17,7 @@ use crate::{
         BiDirHashMap,
     },
     engine::{ColliderHandle, JointHandle, RigidBodyHandle},
+    scene::{physics::PhysicsDrawOptions, Line, SceneDrawingContext},
 };
 use rapier2d::{
     dynamics::{
@@ -26,11 +28,11 @@ use rapier2d::{
         BroadPhase, Collider, ColliderBuilder, ColliderSet, InteractionGroups, NarrowPhase,
         Segment, Shape,
     },
-    parry::shape::{FeatureId, SharedShape},
+    parry::shape::{Compound, FeatureId, SharedShape},
     pipeline::{EventHandler, PhysicsPipeline, QueryPipeline},
 };
 use std::{
-    cell::{Cell, RefCell},
+    cell::{Cell, RefCell, RefMut},
     cmp::Ordering,
     collections::HashMap,
     fmt::{Debug, Display, Formatter},
@@ -73,6 +75,53 @@ pub struct RayCastOptions {
     pub sort_results: bool,
 }
 
+/// A set of options for the shape cast.
+pub struct ShapeCastOptions {
+    /// A shape to cast.
+    pub shape: ColliderShapeDesc,
+
+    /// Initial position and orientation of the shape.
+    pub position: Isometry2<f32>,
+
+    /// Direction of the cast, it does not need to be normalized.
+    pub direction: Vector2<f32>,
+
+    /// Maximum distance of cast.
+    pub max_len: f32,
+
+    /// Groups to check.
+    pub groups: InteractionGroups,
+}
+
+/// A shape cast result.
+#[derive(Debug, Clone)]
+pub struct ShapeCastResult {
+    /// A handle of the first collider hit by the shape.
+    pub collider: ColliderHandle,
+
+    /// Distance that the shape has travelled before the hit.
+    pub toi: f32,
+
+    /// A contact point on the collider in world coordinates.
+    pub position: Point2<f32>,
+
+    /// A normal of the collider at the contact point in world coordinates.
+    pub normal: Vector2<f32>,
+}
+
+/// A point projection result.
+#[derive(Debug, Clone)]
+pub struct PointProjectionResult {
+    /// A handle of the collider closest to the point.
+    pub collider: ColliderHandle,
+
+    /// A closest point on the collider in world coordinates.
+    pub position: Point2<f32>,
+
+    /// Whether the point is inside the collider or not.
+    pub is_inside: bool,
+}
+
 /// Physics world.
 pub struct Physics {
     /// Current physics pipeline.
@@ -132,23 +181,37 @@ impl Default for Physics {
     }
 }
 
-/// A trait for ray cast results storage. It has two implementations: Vec and ArrayVec.
+// Debug drawing context is 3D, so 2D entities are placed in XY plane.
+fn isometry_to_matrix(isometry: &Isometry2<f32>) -> Matrix4<f32> {
+    Isometry3::new(
+        Vector3::new(isometry.translation.x, isometry.translation.y, 0.0),
+        Vector3::new(0.0, 0.0, isometry.rotation.angle()),
+    )
+    .to_homogeneous()
+}
+
+fn point_to_3d(point: &Point2<f32>) -> Vector3<f32> {
+    Vector3::new(point.x, point.y, 0.0)
+}
+
+/// A trait for query results storage. It has two implementations: Vec and ArrayVec.
 /// Latter is needed for the cases where you need to avoid runtime memory allocations
-/// and do everything on stack.
-pub trait QueryResultsStorage {
+/// and do everything on stack. Ray casts store [`Intersection`]s, overlap tests store
+/// handles of colliders.
+pub trait QueryResultsStorage<T = Intersection> {
     /// Pushes new intersection in the storage. Returns true if intersection was
     /// successfully inserted, false otherwise.
-    fn push(&mut self, intersection: Intersection) -> bool;
+    fn push(&mut self, intersection: T) -> bool;
 
     /// Clears the storage.
     fn clear(&mut self);
 
     /// Sorts intersections by given compare function.
-    fn sort_intersections_by<C: FnMut(&Intersection, &Intersection) -> Ordering>(&mut self, cmp: C);
+    fn sort_intersections_by<C: FnMut(&T, &T) -> Ordering>(&mut self, cmp: C);
 }
 
-impl QueryResultsStorage for Vec<Intersection> {
-    fn push(&mut self, intersection: Intersection) -> bool {
+impl<T> QueryResultsStorage<T> for Vec<T> {
+    fn push(&mut self, intersection: T) -> bool {
         self.push(intersection);
         true
     }
@@ -159,14 +222,14 @@ impl QueryResultsStorage for Vec<Intersection> {
 
     fn sort_intersections_by<C>(&mut self, cmp: C)
     where
-        C: FnMut(&Intersection, &Intersection) -> Ordering,
+        C: FnMut(&T, &T) -> Ordering,
     {
         self.sort_by(cmp);
     }
 }
 
-impl<const CAP: usize> QueryResultsStorage for ArrayVec<Intersection, CAP> {
-    fn push(&mut self, intersection: Intersection) -> bool {
+impl<T, const CAP: usize> QueryResultsStorage<T> for ArrayVec<T, CAP> {
+    fn push(&mut self, intersection: T) -> bool {
         self.try_push(intersection).is_ok()
     }
 
@@ -176,7 +239,7 @@ impl<const CAP: usize> QueryResultsStorage for ArrayVec<Intersection, CAP> {
 
     fn sort_intersections_by<C>(&mut self, cmp: C)
     where
-        C: FnMut(&Intersection, &Intersection) -> Ordering,
+        C: FnMut(&T, &T) -> Ordering,
     {
         self.sort_by(cmp);
     }
@@ -273,6 +336,231 @@ impl Physics {
         phys
     }
 
+    /// Draws physics world. Very useful for debugging, it allows you to see where are
+    /// rigid bodies, which colliders they have and so on. Everything is drawn in XY plane.
+    pub fn draw(&self, context: &mut SceneDrawingContext) {
+        self.draw_with_options(context, &Default::default())
+    }
+
+    /// Draws physics world using given options. Drawing is not immediate, it only pushes lines
+    /// into the context, they will be rendered later on by 2D renderer.
+    pub fn draw_with_options(
+        &self,
+        context: &mut SceneDrawingContext,
+        options: &PhysicsDrawOptions,
+    ) {
+        if options.transforms {
+            for (_, body) in self.bodies.iter() {
+                context.draw_transform(isometry_to_matrix(body.position()));
+            }
+        }
+
+        for (_, collider) in self.colliders.iter() {
+            let body = self.bodies.get(collider.parent()).unwrap();
+
+            if options.shapes {
+                let color = if collider.is_sensor() {
+                    options.sensor_color
+                } else if body.is_sleeping() {
+                    options.sleeping_color
+                } else {
+                    match body.body_status() {
+                        BodyStatus::Static => options.static_color,
+                        BodyStatus::Dynamic => options.dynamic_color,
+                        BodyStatus::Kinematic => options.kinematic_color,
+                    }
+                };
+                Self::draw_shape(
+                    context,
+                    collider.shape(),
+                    isometry_to_matrix(collider.position()),
+                    color,
+                );
+            }
+
+            if options.aabbs {
+                let aabb = collider.compute_aabb();
+                let center = aabb.center();
+                context.draw_rectangle(
+                    aabb.half_extents(),
+                    Matrix4::new_translation(&Vector3::new(center.x, center.y, 0.0)),
+                    options.aabb_color,
+                );
+            }
+        }
+
+        if options.contacts {
+            for pair in self.narrow_phase.contact_pairs() {
+                if let Some(collider1) = self.colliders.get(pair.pair.collider1) {
+                    for manifold in pair.manifolds.iter() {
+                        for point in manifold.points.iter() {
+                            let position = point_to_3d(&(collider1.position() * point.local_p1));
+                            let normal = manifold.data.normal * options.normal_length;
+                            context.draw_circle(
+                                position,
+                                0.025,
+                                6,
+                                Matrix4::identity(),
+                                options.contact_color,
+                            );
+                            context.add_line(Line {
+                                begin: position,
+                                end: position + Vector3::new(normal.x, normal.y, 0.0),
+                                color: options.contact_color,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if options.joints {
+            for (_, joint) in self.joints.iter() {
+                if let (Some(body1), Some(body2)) =
+                    (self.bodies.get(joint.body1), self.bodies.get(joint.body2))
+                {
+                    Self::draw_joint(context, joint, body1, body2, options);
+                }
+            }
+        }
+    }
+
+    fn draw_shape(
+        context: &mut SceneDrawingContext,
+        shape: &dyn Shape,
+        transform: Matrix4<f32>,
+        color: Color,
+    ) {
+        let transform_point = |p: &Point2<f32>| {
+            transform
+                .transform_point(&Point3::new(p.x, p.y, 0.0))
+                .coords
+        };
+
+        if let Some(ball) = shape.as_ball() {
+            context.draw_circle(Default::default(), ball.radius, 16, transform, color);
+        } else if let Some(cuboid) = shape.as_cuboid() {
+            context.draw_rectangle(cuboid.half_extents, transform, color);
+        } else if let Some(capsule) = shape.as_capsule() {
+            let (a, b) = (capsule.segment.a, capsule.segment.b);
+            let side = (b - a)
+                .try_normalize(f32::EPSILON)
+                .map(|d| Vector2::new(-d.y, d.x) * capsule.radius)
+                .unwrap_or_default();
+            for offset in [side, -side].iter() {
+                context.add_line(Line {
+                    begin: transform_point(&(a + offset)),
+                    end: transform_point(&(b + offset)),
+                    color,
+                });
+            }
+            for end in [a, b].iter() {
+                context.draw_circle(point_to_3d(end), capsule.radius, 16, transform, color);
+            }
+        } else if let Some(segment) = shape.downcast_ref::<Segment>() {
+            context.add_line(Line {
+                begin: transform_point(&segment.a),
+                end: transform_point(&segment.b),
+                color,
+            });
+        } else if let Some(triangle) = shape.as_triangle() {
+            context.draw_triangle(
+                transform_point(&triangle.a),
+                transform_point(&triangle.b),
+                transform_point(&triangle.c),
+                color,
+            );
+        } else if let Some(trimesh) = shape.as_trimesh() {
+            for triangle in trimesh.triangles() {
+                context.draw_triangle(
+                    transform_point(&triangle.a),
+                    transform_point(&triangle.b),
+                    transform_point(&triangle.c),
+                    color,
+                );
+            }
+        } else if let Some(heightfield) = shape.as_heightfield() {
+            for segment in heightfield.segments() {
+                context.add_line(Line {
+                    begin: transform_point(&segment.a),
+                    end: transform_point(&segment.b),
+                    color,
+                });
+            }
+        } else if let Some(polygon) = shape.as_convex_polygon() {
+            let points = polygon.points();
+            for (i, point) in points.iter().enumerate() {
+                context.add_line(Line {
+                    begin: transform_point(point),
+                    end: transform_point(&points[(i + 1) % points.len()]),
+                    color,
+                });
+            }
+        } else if let Some(compound) = shape.downcast_ref::<Compound>() {
+            for (position, part) in compound.shapes() {
+                Self::draw_shape(
+                    context,
+                    &**part,
+                    transform * isometry_to_matrix(position),
+                    color,
+                );
+            }
+        }
+    }
+
+    fn draw_joint(
+        context: &mut SceneDrawingContext,
+        joint: &Joint,
+        body1: &RigidBody,
+        body2: &RigidBody,
+        options: &PhysicsDrawOptions,
+    ) {
+        let color = options.joint_color;
+        let (anchor1, anchor2) = match &joint.params {
+            JointParams::BallJoint(v) => (
+                body1.position() * v.local_anchor1,
+                body2.position() * v.local_anchor2,
+            ),
+            JointParams::FixedJoint(v) => (
+                body1.position() * Point2::from(v.local_anchor1.translation.vector),
+                body2.position() * Point2::from(v.local_anchor2.translation.vector),
+            ),
+            JointParams::PrismaticJoint(v) => {
+                let anchor1 = body1.position() * v.local_anchor1;
+                let axis = body1.position() * v.local_axis1().into_inner();
+                let (min, max) = if v.limits_enabled {
+                    (v.limits[0], v.limits[1])
+                } else {
+                    (-options.normal_length, options.normal_length)
+                };
+                context.add_line(Line {
+                    begin: point_to_3d(&(anchor1 + axis * min)),
+                    end: point_to_3d(&(anchor1 + axis * max)),
+                    color,
+                });
+                if v.limits_enabled {
+                    // Mark limits with small circles.
+                    for limit in [min, max].iter() {
+                        let position = point_to_3d(&(anchor1 + axis * *limit));
+                        context.draw_circle(position, 0.025, 6, Matrix4::identity(), color);
+                    }
+                }
+                (anchor1, body2.position() * v.local_anchor2)
+            }
+        };
+
+        // Connect bodies with their anchors, anchors itself are drawn as small circles.
+        for (body, anchor) in [(body1, anchor1), (body2, anchor2)].iter() {
+            let anchor = point_to_3d(anchor);
+            context.add_line(Line {
+                begin: point_to_3d(&Point2::from(body.position().translation.vector)),
+                end: anchor,
+                color,
+            });
+            context.draw_circle(anchor, 0.05, 8, Matrix4::identity(), color);
+        }
+    }
+
     /// TODO
     pub fn bodies(&self) -> &RigidBodySet {
         &self.bodies
@@ -531,6 +819,98 @@ impl Physics {
         );
     }
 
+    fn updated_query(&self) -> RefMut<QueryPipeline> {
+        let mut query = self.query.borrow_mut();
+        // See comment in `cast_ray` about why the pipeline is updated on every query.
+        query.update(&self.bodies, &self.colliders);
+        query
+    }
+
+    /// Sweeps a shape along given direction and returns the first collider hit by the shape.
+    pub fn cast_shape(&self, opts: ShapeCastOptions) -> Option<ShapeCastResult> {
+        let query = self.updated_query();
+
+        let shape = opts.shape.into_collider_shape();
+        let direction = opts
+            .direction
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_default();
+        query
+            .cast_shape(
+                &self.colliders,
+                &opts.position,
+                &direction,
+                &*shape,
+                opts.max_len,
+                opts.groups,
+                None,
+            )
+            .map(|(handle, toi)| ShapeCastResult {
+                collider: self.collider_handle_map.key_of(&handle).cloned().unwrap(),
+                toi: toi.toi,
+                // Witness and normal on the first shape are in world coordinates, because the
+                // first shape is the query pipeline itself.
+                position: toi.witness1,
+                normal: toi.normal1.into_inner(),
+            })
+    }
+
+    /// Finds a collider that is closest to given point. If `solid` is true, points inside
+    /// colliders will be projected on themselves, otherwise on boundaries of colliders.
+    pub fn project_point(
+        &self,
+        point: Point2<f32>,
+        solid: bool,
+        groups: InteractionGroups,
+    ) -> Option<PointProjectionResult> {
+        let query = self.updated_query();
+
+        query
+            .project_point(&self.colliders, &point, solid, groups, None)
+            .map(|(handle, projection)| PointProjectionResult {
+                collider: self.collider_handle_map.key_of(&handle).cloned().unwrap(),
+                position: projection.point,
+                is_inside: projection.is_inside,
+            })
+    }
+
+    /// Collects every collider that intersects with given shape.
+    pub fn intersections_with_shape<S: QueryResultsStorage<ColliderHandle>>(
+        &self,
+        shape: ColliderShapeDesc,
+        position: Isometry2<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        let shape = shape.into_collider_shape();
+        query.intersections_with_shape(
+            &self.colliders,
+            &position,
+            &*shape,
+            groups,
+            None,
+            |handle| query_buffer.push(self.collider_handle_map.key_of(&handle).cloned().unwrap()),
+        );
+    }
+
+    /// Collects every collider that contains given point.
+    pub fn intersections_with_point<S: QueryResultsStorage<ColliderHandle>>(
+        &self,
+        point: Point2<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        query.intersections_with_point(&self.colliders, &point, groups, None, |handle| {
+            query_buffer.push(self.collider_handle_map.key_of(&handle).cloned().unwrap())
+        });
+    }
+
     pub(in crate) fn resolve(&mut self) {
         assert_eq!(self.bodies.len(), 0);
         assert_eq!(self.colliders.len(), 0);
@@ -1247,3 +1627,91 @@ pub struct PhysicsDesc {
     pub collider_handle_map: BiDirHashMap<ColliderHandle, ErasedHandle>,
     pub joint_handle_map: BiDirHashMap<JointHandle, ErasedHandle>,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Isometry2, Point2, Vector2},
+        engine::ColliderHandle,
+        scene2d::physics::{BallDesc, ColliderShapeDesc, Intersection, Physics, ShapeCastOptions},
+    };
+    use rapier2d::{
+        dynamics::RigidBodyBuilder,
+        geometry::{ColliderBuilder, InteractionGroups},
+    };
+
+    #[test]
+    fn test_queries() {
+        let mut physics = Physics::new();
+        let body = physics.add_body(RigidBodyBuilder::new_static().build());
+        let collider = physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5).build(), &body);
+
+        let mut intersections = Vec::<Intersection>::new();
+        physics.cast_ray(
+            Vector2::new(0.0, 5.0),
+            Vector2::new(0.0, -2.0),
+            10.0,
+            InteractionGroups::all(),
+            true,
+            &mut intersections,
+        );
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].collider, collider);
+        assert!((intersections[0].toi - 4.5).abs() < 1.0e-3);
+
+        // Ray is filtered out by interaction groups.
+        physics.cast_ray(
+            Vector2::new(0.0, 5.0),
+            Vector2::new(0.0, -1.0),
+            10.0,
+            InteractionGroups::none(),
+            true,
+            &mut intersections,
+        );
+        assert!(intersections.is_empty());
+
+        let hit = physics
+            .cast_shape(ShapeCastOptions {
+                shape: ColliderShapeDesc::Ball(BallDesc { radius: 0.5 }),
+                position: Isometry2::translation(0.0, 5.0),
+                direction: Vector2::new(0.0, -2.0),
+                max_len: 10.0,
+                groups: InteractionGroups::all(),
+            })
+            .unwrap();
+        assert_eq!(hit.collider, collider);
+        assert!((hit.toi - 4.0).abs() < 1.0e-3);
+        assert!((hit.normal - Vector2::y()).norm() < 1.0e-3);
+
+        let projection = physics
+            .project_point(Point2::new(0.0, 5.0), true, InteractionGroups::all())
+            .unwrap();
+        assert_eq!(projection.collider, collider);
+        assert!(!projection.is_inside);
+        assert!((projection.position.y - 0.5).abs() < 1.0e-3);
+
+        let mut overlaps = Vec::<ColliderHandle>::new();
+        physics.intersections_with_point(
+            Point2::new(0.1, 0.1),
+            InteractionGroups::all(),
+            &mut overlaps,
+        );
+        assert_eq!(overlaps, vec![collider]);
+
+        physics.intersections_with_shape(
+            ColliderShapeDesc::Ball(BallDesc { radius: 0.5 }),
+            Isometry2::translation(0.0, 0.8),
+            InteractionGroups::all(),
+            &mut overlaps,
+        );
+        assert_eq!(overlaps, vec![collider]);
+
+        physics.intersections_with_shape(
+            ColliderShapeDesc::Ball(BallDesc { radius: 0.5 }),
+            Isometry2::translation(0.0, 2.0),
+            InteractionGroups::all(),
+            &mut overlaps,
+        );
+        assert!(overlaps.is_empty());
+    }
+}
@@ -7,6 +7,7 @@ use crate::{
         visitor::prelude::*,
     },
     resource::texture::Texture,
+    scene::sprite_sheet::SpriteSheetAnimation,
     scene2d::{
         base::{Base, BaseBuilder},
         graph::Graph,
@@ -15,12 +16,33 @@ use crate::{
 };
 use std::ops::{Deref, DerefMut};
 
-#[derive(Visit)]
 pub struct Sprite {
     base: Base,
     texture: Option<Texture>,
     color: Color,
     size: f32,
+    animation: Option<SpriteSheetAnimation>,
+}
+
+impl Visit for Sprite {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.base.visit("Base", visitor)?;
+        self.texture.visit("Texture", visitor)?;
+        self.color.visit("Color", visitor)?;
+        self.size.visit("Size", visitor)?;
+        // Backward compatibility - animation may be missing in old files.
+        if let Err(e) = self.animation.visit("Animation", visitor) {
+            if visitor.is_reading() {
+                self.animation = None;
+            } else {
+                return Err(e);
+            }
+        }
+
+        visitor.leave_region()
+    }
 }
 
 impl Default for Sprite {
@@ -30,6 +52,7 @@ impl Default for Sprite {
             texture: None,
             color: Default::default(),
             size: 16.0,
+            animation: None,
         }
     }
 }
@@ -73,6 +96,31 @@ impl Sprite {
         self.size = size;
     }
 
+    pub fn animation(&self) -> Option<&SpriteSheetAnimation> {
+        self.animation.as_ref()
+    }
+
+    pub fn animation_mut(&mut self) -> Option<&mut SpriteSheetAnimation> {
+        self.animation.as_mut()
+    }
+
+    pub fn set_animation(&mut self, animation: Option<SpriteSheetAnimation>) {
+        self.animation = animation;
+    }
+
+    pub fn frame_rect(&self) -> Rect<f32> {
+        self.animation.as_ref().map_or(
+            Rect::new(0.0, 0.0, 1.0, 1.0),
+            SpriteSheetAnimation::current_frame_rect,
+        )
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if let Some(animation) = self.animation.as_mut() {
+            animation.update(dt);
+        }
+    }
+
     pub fn local_bounds(&self) -> Rect<f32> {
         Rect {
             position: self.local_transform().position(),
@@ -107,6 +155,7 @@ impl Sprite {
             texture: self.texture.clone(),
             color: self.color,
             size: self.size,
+            animation: self.animation.clone(),
         }
     }
 }
@@ -116,6 +165,7 @@ pub struct SpriteBuilder {
     texture: Option<Texture>,
     color: Color,
     size: f32,
+    animation: Option<SpriteSheetAnimation>,
 }
 
 impl SpriteBuilder {
@@ -125,6 +175,7 @@ impl SpriteBuilder {
             texture: None,
             color: Color::WHITE,
             size: 16.0,
+            animation: None,
         }
     }
 
@@ -143,12 +194,18 @@ impl SpriteBuilder {
         self
     }
 
+    pub fn with_animation(mut self, animation: SpriteSheetAnimation) -> Self {
+        self.animation = Some(animation);
+        self
+    }
+
     pub fn build(self, graph: &mut Graph) -> Handle<Node> {
         graph.add_node(Node::Sprite(Sprite {
             base: self.base_builder.build_base(),
             texture: self.texture,
             color: self.color,
             size: self.size,
+            animation: self.animation,
         }))
     }
 }